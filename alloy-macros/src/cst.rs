@@ -144,16 +144,17 @@ fn try_extract_single_generic_arg(args: AngleBracketedGenericArguments) -> Resul
     } else {
         return Err(());
     };
-    if let Some(_) = args.next() {
+    if args.next().is_some() {
         return Err(());
     }
     if let GenericArgument::Type(t) = arg {
         Ok(t)
     } else {
-        return Err(());
+        Err(())
     }
 }
 
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 enum FieldType {
     CST,
@@ -246,10 +247,10 @@ fn impl_tuple_struct(
                 let ident = Ident::new(&format!("var{i}"), Span::call_site());
                 let ty = try_extract_generic(field.ty.clone()).unwrap();
                 assign_vars.extend(quote! {
-                    let ident: #ty = (*cst.#idx).into();
+                    let #ident: #ty = (*cst.#idx).into();
                 });
                 assign_fields.push(quote! {
-                    #ident
+                    std::boxed::Box::from(#ident)
                 });
             }
             FieldType::Space => continue,
@@ -265,13 +266,12 @@ fn impl_tuple_struct(
             }
             FieldType::SpannedCST => {
                 let ident = Ident::new(&format!("var{i}"), Span::call_site());
-
                 let ty = try_extract_generic(field.ty.clone()).unwrap();
                 assign_vars.extend(quote! {
-                    let ident: #ty = (*cst.#idx.ast).into();
+                    let #ident: #ty = (*cst.#idx.ast).into();
                 });
                 assign_fields.push(quote! {
-                    #ident
+                    std::boxed::Box::from(#ident)
                 });
             }
         };
@@ -26,7 +26,11 @@ fn expand_binary(expr: &ExprBinary) -> TokenStream {
         alloy::ast::expression::binary::BinaryExpression {
             left: std::boxed::Box::from(#left),
             right: std::boxed::Box::from(#right),
-            operator: alloy::ast::expression::binary::BinaryOperator::#op
+            operator: alloy::parser::Spanned {
+                ast: alloy::ast::expression::binary::BinaryOperator::#op,
+                start: 0,
+                end: 0,
+            }
         }
     };
     quote! {alloy::ast::expression::Expression::Binary(#binary)}
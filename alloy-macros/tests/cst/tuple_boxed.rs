@@ -0,0 +1,41 @@
+use alloy::parser::Spanned;
+use alloy_macros::AST;
+
+#[derive(AST)]
+pub enum ExprCST {
+    Num(NumCST),
+    Negate(NegateCST),
+    Pair(PairCST),
+}
+
+#[derive(AST)]
+pub struct NumCST(i64);
+
+#[derive(AST)]
+pub struct NegateCST(Spanned<char>, Box<ExprCST>);
+
+#[derive(AST)]
+pub struct PairCST(Spanned<Box<ExprCST>>, i64, Box<ExprCST>);
+
+fn spanned<T>(ast: T) -> Spanned<T> {
+    Spanned {
+        ast,
+        start: 0,
+        end: 1,
+    }
+}
+
+fn main() {
+    let num = |n| ExprCST::Num(NumCST(n));
+
+    let negate: Negate = NegateCST(spanned('-'), Box::from(num(1))).into();
+    let Negate(op, expr) = negate;
+    assert_eq!(op, '-');
+    assert!(matches!(*expr, Expr::Num(Num(1))));
+
+    let pair: Pair = PairCST(spanned(Box::from(num(2))), 3, Box::from(num(4))).into();
+    let Pair(lhs, middle, rhs) = pair;
+    assert!(matches!(*lhs, Expr::Num(Num(2))));
+    assert_eq!(middle, 3);
+    assert!(matches!(*rhs, Expr::Num(Num(4))));
+}
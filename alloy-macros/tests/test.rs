@@ -15,4 +15,5 @@ fn test_cst_ast() {
     t.pass("tests/cst/boxed_cst.rs");
     t.pass("tests/cst/complete.rs");
     t.pass("tests/cst/into.rs");
+    t.pass("tests/cst/tuple_boxed.rs");
 }
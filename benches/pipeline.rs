@@ -0,0 +1,117 @@
+//! Parser and compiler benchmarks over a generated program.
+//!
+//! Run with `cargo bench`, every benchmark reports the mean time per iteration.
+//! The compute loop benchmark tracks VM dispatch cost such as fused
+//! compare-and-branch instructions in loop conditions and the function call
+//! benchmark tracks the cost of frames, the array equality benchmarks compare
+//! an array to itself and to an equal copy.
+
+use std::{
+    fmt::Write,
+    hint::black_box,
+    time::{Duration, Instant},
+};
+
+use alloy::{ast::value::Value, compiler::compile_program, parser, vm::VM};
+
+/// Generate a representative program made of `sections` repetitions of
+/// functions and declarations with nested arithmetic, conditionals and loops.
+fn generate_program(sections: usize) -> String {
+    let mut source = String::new();
+    for i in 0..sections {
+        writeln!(
+            source,
+            "fn f{i}(a, b) {{ var c = a * (b + {i}); return c ** 2 - a / (b + 1); }}"
+        )
+        .unwrap();
+        writeln!(source, "var x{i} = ({i} + 3) * (7 - {i} * 2) / 4;").unwrap();
+        writeln!(source, "const y{i} = x{i} ** 2 + {i}.5 * (x{i} - 1);").unwrap();
+        writeln!(source, "var n{i} = 0;").unwrap();
+        writeln!(source, "while n{i} < 100 and x{i} != y{i} {{").unwrap();
+        writeln!(source, "    n{i} = n{i} + 1;").unwrap();
+        writeln!(
+            source,
+            "    if n{i} > 50 {{ x{i} = x{i} * 2; }} else {{ x{i} = x{i} - (n{i} + y{i}) / 3; }}"
+        )
+        .unwrap();
+        writeln!(source, "}}").unwrap();
+        writeln!(source, "print x{i} + y{i} * n{i};").unwrap();
+    }
+    source
+}
+
+/// Loop dominated by comparisons in conditions and integer arithmetic.
+const COMPUTE_LOOP: &str = "
+var i = 0;
+var total = 0;
+while i < 100000 {
+    if i >= 50000 { total = total + i * 2; } else { total = total - i; }
+    if total != 0 { total = total / 2; }
+    i = i + 1;
+}
+print total;
+";
+
+/// Loop dominated by calls of a small recursive function.
+const FUNCTION_CALLS: &str = "
+fn fib(n) {
+    if n < 2 { return n; }
+    return fib(n - 1) + fib(n - 2);
+}
+print fib(20);
+";
+
+fn bench<T>(name: &str, mut f: impl FnMut() -> T) {
+    // Warm up and estimate how many iterations fit into the measurement window
+    let warm_up = Instant::now();
+    let mut iterations = 0u32;
+    while warm_up.elapsed() < Duration::from_millis(500) {
+        black_box(f());
+        iterations += 1;
+    }
+    let iterations = (iterations * 4).max(1);
+
+    let start = Instant::now();
+    for _ in 0..iterations {
+        black_box(f());
+    }
+    let mean = start.elapsed() / iterations;
+    println!("{name:<24} {mean:>12?}/iter ({iterations} iterations)");
+}
+
+fn main() {
+    let source = generate_program(200);
+    bench("parse", || parser::parse(black_box(&source)).unwrap());
+
+    let statements = parser::parse(&source).unwrap();
+    bench("compile", || {
+        compile_program(black_box(&statements)).unwrap()
+    });
+    bench("parse + compile", || {
+        let statements = parser::parse(black_box(&source)).unwrap();
+        compile_program(&statements).unwrap()
+    });
+
+    let statements = parser::parse(COMPUTE_LOOP).unwrap();
+    let code_block = compile_program(&statements).unwrap();
+    let mut output = Vec::new();
+    bench("run compute loop", || {
+        output.clear();
+        VM::new_with_output(&mut output).run(&code_block).unwrap()
+    });
+
+    let statements = parser::parse(FUNCTION_CALLS).unwrap();
+    let code_block = compile_program(&statements).unwrap();
+    bench("run function calls", || {
+        output.clear();
+        VM::new_with_output(&mut output).run(&code_block).unwrap()
+    });
+
+    // Comparing an array to itself is a pointer comparison, while equal but
+    // distinct arrays are compared element by element
+    let array = Value::array((0..100_000).map(Value::Integer).collect());
+    let alias = array.clone();
+    bench("array self equality", || array.equal(black_box(&alias)));
+    let copy = Value::array((0..100_000).map(Value::Integer).collect());
+    bench("array deep equality", || array.equal(black_box(&copy)));
+}
@@ -0,0 +1,100 @@
+use alloc::string::{String, ToString};
+use core::fmt;
+
+use pest::iterators::Pair;
+
+use crate::{
+    compiler::types::Type,
+    parser::{expect_rule, Parse, ParserError, Rule},
+};
+
+use super::value::Value;
+
+/// Explicit type of a declaration or function parameter such as `: int`,
+/// names other than the builtin types are kept as `Named`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TypeAnnotation {
+    Int,
+    Float,
+    Bool,
+    String,
+    Named(String),
+}
+
+impl TypeAnnotation {
+    /// Static type checked against values of the annotated identifier, named
+    /// types aren't known to the type checker.
+    pub fn ty(&self) -> Type {
+        match self {
+            TypeAnnotation::Int => Type::Int,
+            TypeAnnotation::Float => Type::Float,
+            TypeAnnotation::Bool => Type::Bool,
+            TypeAnnotation::String => Type::String,
+            TypeAnnotation::Named(_) => Type::Unknown,
+        }
+    }
+
+    /// Value of an annotated variable declared without an initializer.
+    pub fn zero_value(&self) -> Value {
+        match self {
+            TypeAnnotation::Int => Value::Integer(0),
+            TypeAnnotation::Float => Value::Float(0.0),
+            TypeAnnotation::Bool => Value::False,
+            TypeAnnotation::String => Value::String(String::new()),
+            TypeAnnotation::Named(_) => Value::Null,
+        }
+    }
+}
+
+impl Parse<'_> for TypeAnnotation {
+    fn parse(pair: Pair<'_, Rule>) -> Result<Self, ParserError> {
+        expect_rule(&pair, Rule::type_annotation)?;
+        let name = pair.into_inner().next().unwrap().as_str();
+        let annotation = match name {
+            "int" => TypeAnnotation::Int,
+            "float" => TypeAnnotation::Float,
+            "bool" => TypeAnnotation::Bool,
+            "string" => TypeAnnotation::String,
+            _ => TypeAnnotation::Named(name.to_string()),
+        };
+        Ok(annotation)
+    }
+}
+
+impl fmt::Display for TypeAnnotation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TypeAnnotation::Int => write!(f, "int"),
+            TypeAnnotation::Float => write!(f, "float"),
+            TypeAnnotation::Bool => write!(f, "bool"),
+            TypeAnnotation::String => write!(f, "string"),
+            TypeAnnotation::Named(name) => write!(f, "{name}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::{parse_rule, ParseResult, Rule};
+
+    use super::TypeAnnotation;
+
+    fn parse_annotation(input: &str) -> ParseResult<TypeAnnotation> {
+        parse_rule::<TypeAnnotation>(Rule::type_annotation, input)
+    }
+
+    #[test]
+    fn test_type_annotation() -> ParseResult<()> {
+        assert_eq!(parse_annotation(": int")?, TypeAnnotation::Int);
+        assert_eq!(parse_annotation(":float")?, TypeAnnotation::Float);
+        assert_eq!(parse_annotation(": bool")?, TypeAnnotation::Bool);
+        assert_eq!(parse_annotation(": string")?, TypeAnnotation::String);
+        assert_eq!(
+            parse_annotation(": Point")?,
+            TypeAnnotation::Named("Point".into())
+        );
+        parse_annotation(":").unwrap_err();
+        parse_annotation(": 1").unwrap_err();
+        Ok(())
+    }
+}
@@ -0,0 +1,433 @@
+use alloc::{vec, vec::Vec};
+use core::{
+    cmp::Ordering,
+    convert::TryFrom,
+    fmt,
+    ops::{Add, Mul, Neg, Sub},
+};
+
+use crate::math;
+
+/// Largest power of ten that fits in a limb, numbers are converted to and
+/// from decimal nine digits at a time.
+const DECIMAL_CHUNK: u32 = 1_000_000_000;
+const DECIMAL_CHUNK_DIGITS: usize = 9;
+
+/// Signed integer of arbitrary size used once integer arithmetic overflows
+/// `i64`. The magnitude is stored as little endian base 2^32 limbs without
+/// trailing zero limbs and zero isn't negative, so every number has a single
+/// representation and derived equality is numeric equality.
+#[derive(Clone, PartialEq, Eq, Hash, Default)]
+pub struct BigInt {
+    negative: bool,
+    magnitude: Vec<u32>,
+}
+
+impl BigInt {
+    fn from_parts(negative: bool, mut magnitude: Vec<u32>) -> Self {
+        while magnitude.last() == Some(&0) {
+            magnitude.pop();
+        }
+        let negative = negative && !magnitude.is_empty();
+        Self {
+            negative,
+            magnitude,
+        }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.magnitude.is_empty()
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.negative
+    }
+
+    /// Parse unsigned decimal `digits`, `None` if `digits` is empty or
+    /// contains anything other than ASCII digits.
+    pub fn parse_decimal(digits: &str) -> Option<Self> {
+        if digits.is_empty() || !digits.bytes().all(|byte| byte.is_ascii_digit()) {
+            return None;
+        }
+        let mut magnitude = Vec::new();
+        // Leading chunk is shorter so the rest are whole chunks
+        let leading = match digits.len() % DECIMAL_CHUNK_DIGITS {
+            0 => DECIMAL_CHUNK_DIGITS,
+            len => len,
+        };
+        let (first, rest) = digits.split_at(leading);
+        let chunks = core::iter::once(first).chain(
+            rest.as_bytes()
+                .chunks(DECIMAL_CHUNK_DIGITS)
+                .map(|chunk| core::str::from_utf8(chunk).unwrap()),
+        );
+        for chunk in chunks {
+            let scale = 10u32.pow(chunk.len() as u32);
+            mul_small_add(&mut magnitude, scale, chunk.parse().unwrap());
+        }
+        Some(Self::from_parts(false, magnitude))
+    }
+
+    /// Convert back to `i64`, `None` if the number is out of range.
+    pub fn to_i64(&self) -> Option<i64> {
+        if self.magnitude.len() > 2 {
+            return None;
+        }
+        let abs = self
+            .magnitude
+            .iter()
+            .rev()
+            .fold(0u64, |abs, limb| (abs << 32) | u64::from(*limb));
+        if self.negative {
+            // `i64::MIN` has no positive counterpart
+            match abs.cmp(&(1 << 63)) {
+                Ordering::Less => Some(-(abs as i64)),
+                Ordering::Equal => Some(i64::MIN),
+                Ordering::Greater => None,
+            }
+        } else {
+            i64::try_from(abs).ok()
+        }
+    }
+
+    /// Nearest float, numbers too large for `f64` are infinite.
+    pub fn to_f64(&self) -> f64 {
+        let abs = self
+            .magnitude
+            .iter()
+            .rev()
+            .fold(0.0, |abs, limb| abs * 4_294_967_296.0 + f64::from(*limb));
+        if self.negative {
+            -abs
+        } else {
+            abs
+        }
+    }
+
+    /// Compare with `float` exactly, `None` if `float` is NaN.
+    pub fn cmp_f64(&self, float: f64) -> Option<Ordering> {
+        if float.is_nan() {
+            return None;
+        }
+        if float.is_infinite() {
+            return Some(if float > 0.0 {
+                Ordering::Less
+            } else {
+                Ordering::Greater
+            });
+        }
+        // Fractional part decides if the integer parts are equal
+        let trunc = math::trunc(float);
+        Some(
+            self.cmp(&Self::from_integral(trunc))
+                .then(trunc.partial_cmp(&float)?),
+        )
+    }
+
+    /// Exact value of finite `float` that has no fractional part.
+    fn from_integral(float: f64) -> Self {
+        let bits = float.to_bits();
+        let exponent = ((bits >> 52) & 0x7ff) as usize;
+        // Zero, integral floats other than zero aren't subnormal
+        if exponent == 0 {
+            return Self::default();
+        }
+        // Value is `mantissa * 2^(exponent - 1075)`
+        let mantissa = (bits & ((1 << 52) - 1)) | (1 << 52);
+        let magnitude = if exponent >= 1075 {
+            let shift = exponent - 1075;
+            let shifted = u128::from(mantissa) << (shift % 32);
+            let mut magnitude = vec![0; shift / 32];
+            magnitude.extend([
+                shifted as u32,
+                (shifted >> 32) as u32,
+                (shifted >> 64) as u32,
+            ]);
+            magnitude
+        } else {
+            let abs = mantissa >> (1075 - exponent);
+            vec![abs as u32, (abs >> 32) as u32]
+        };
+        Self::from_parts(float < 0.0, magnitude)
+    }
+}
+
+impl From<i64> for BigInt {
+    fn from(int: i64) -> Self {
+        let abs = int.unsigned_abs();
+        Self::from_parts(int < 0, vec![abs as u32, (abs >> 32) as u32])
+    }
+}
+
+/// Multiply `magnitude` by `factor` and add `addend` in place.
+fn mul_small_add(magnitude: &mut Vec<u32>, factor: u32, addend: u32) {
+    let mut carry = u64::from(addend);
+    for limb in magnitude.iter_mut() {
+        let product = u64::from(*limb) * u64::from(factor) + carry;
+        *limb = product as u32;
+        carry = product >> 32;
+    }
+    if carry > 0 {
+        magnitude.push(carry as u32);
+    }
+}
+
+/// Divide `magnitude` by `divisor` in place and return the remainder.
+fn div_small(magnitude: &mut [u32], divisor: u32) -> u32 {
+    let mut remainder = 0u64;
+    for limb in magnitude.iter_mut().rev() {
+        let dividend = (remainder << 32) | u64::from(*limb);
+        *limb = (dividend / u64::from(divisor)) as u32;
+        remainder = dividend % u64::from(divisor);
+    }
+    remainder as u32
+}
+
+fn cmp_magnitude(lhs: &[u32], rhs: &[u32]) -> Ordering {
+    lhs.len()
+        .cmp(&rhs.len())
+        .then_with(|| lhs.iter().rev().cmp(rhs.iter().rev()))
+}
+
+fn add_magnitude(lhs: &[u32], rhs: &[u32]) -> Vec<u32> {
+    let (long, short) = if lhs.len() >= rhs.len() {
+        (lhs, rhs)
+    } else {
+        (rhs, lhs)
+    };
+    let mut sum = Vec::with_capacity(long.len() + 1);
+    let mut carry = 0u64;
+    for (i, limb) in long.iter().enumerate() {
+        let total = u64::from(*limb) + u64::from(short.get(i).copied().unwrap_or(0)) + carry;
+        sum.push(total as u32);
+        carry = total >> 32;
+    }
+    if carry > 0 {
+        sum.push(carry as u32);
+    }
+    sum
+}
+
+/// Subtract magnitudes, `lhs` must not be less than `rhs`.
+fn sub_magnitude(lhs: &[u32], rhs: &[u32]) -> Vec<u32> {
+    let mut difference = Vec::with_capacity(lhs.len());
+    let mut borrow = 0i64;
+    for (i, limb) in lhs.iter().enumerate() {
+        let mut total = i64::from(*limb) - i64::from(rhs.get(i).copied().unwrap_or(0)) - borrow;
+        borrow = 0;
+        if total < 0 {
+            total += 1 << 32;
+            borrow = 1;
+        }
+        difference.push(total as u32);
+    }
+    debug_assert_eq!(borrow, 0);
+    difference
+}
+
+fn mul_magnitude(lhs: &[u32], rhs: &[u32]) -> Vec<u32> {
+    let mut product = vec![0u32; lhs.len() + rhs.len()];
+    for (i, l) in lhs.iter().enumerate() {
+        let mut carry = 0u64;
+        for (j, r) in rhs.iter().enumerate() {
+            let total = u64::from(product[i + j]) + u64::from(*l) * u64::from(*r) + carry;
+            product[i + j] = total as u32;
+            carry = total >> 32;
+        }
+        product[i + rhs.len()] = carry as u32;
+    }
+    product
+}
+
+impl Add for &BigInt {
+    type Output = BigInt;
+
+    fn add(self, rhs: &BigInt) -> BigInt {
+        if self.negative == rhs.negative {
+            return BigInt::from_parts(
+                self.negative,
+                add_magnitude(&self.magnitude, &rhs.magnitude),
+            );
+        }
+        match cmp_magnitude(&self.magnitude, &rhs.magnitude) {
+            Ordering::Less => {
+                BigInt::from_parts(rhs.negative, sub_magnitude(&rhs.magnitude, &self.magnitude))
+            }
+            _ => BigInt::from_parts(
+                self.negative,
+                sub_magnitude(&self.magnitude, &rhs.magnitude),
+            ),
+        }
+    }
+}
+
+impl Sub for &BigInt {
+    type Output = BigInt;
+
+    fn sub(self, rhs: &BigInt) -> BigInt {
+        self + &-rhs
+    }
+}
+
+impl Mul for &BigInt {
+    type Output = BigInt;
+
+    fn mul(self, rhs: &BigInt) -> BigInt {
+        BigInt::from_parts(
+            self.negative != rhs.negative,
+            mul_magnitude(&self.magnitude, &rhs.magnitude),
+        )
+    }
+}
+
+impl Neg for &BigInt {
+    type Output = BigInt;
+
+    fn neg(self) -> BigInt {
+        BigInt::from_parts(!self.negative, self.magnitude.clone())
+    }
+}
+
+impl PartialOrd for BigInt {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for BigInt {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self.negative, other.negative) {
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+            (false, false) => cmp_magnitude(&self.magnitude, &other.magnitude),
+            (true, true) => cmp_magnitude(&other.magnitude, &self.magnitude),
+        }
+    }
+}
+
+impl fmt::Display for BigInt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_zero() {
+            return write!(f, "0");
+        }
+        let mut magnitude = self.magnitude.clone();
+        let mut chunks = Vec::new();
+        while !magnitude.is_empty() {
+            chunks.push(div_small(&mut magnitude, DECIMAL_CHUNK));
+            while magnitude.last() == Some(&0) {
+                magnitude.pop();
+            }
+        }
+        if self.negative {
+            write!(f, "-")?;
+        }
+        let mut chunks = chunks.iter().rev();
+        write!(f, "{}", chunks.next().unwrap())?;
+        for chunk in chunks {
+            write!(f, "{chunk:09}")?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for BigInt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{self}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BigInt;
+
+    fn big(digits: &str) -> BigInt {
+        match digits.strip_prefix('-') {
+            Some(digits) => -&BigInt::parse_decimal(digits).unwrap(),
+            None => BigInt::parse_decimal(digits).unwrap(),
+        }
+    }
+
+    #[test]
+    fn decimal_round_trip() {
+        for digits in [
+            "0",
+            "1",
+            "4294967296",
+            "99999999999999999999999",
+            "-123456789012345678901234567890",
+        ] {
+            assert_eq!(big(digits).to_string(), digits);
+        }
+        assert_eq!(big("000123").to_string(), "123");
+        assert!(BigInt::parse_decimal("").is_none());
+        assert!(BigInt::parse_decimal("12a").is_none());
+    }
+
+    #[test]
+    fn i64_conversion() {
+        for int in [0, 1, -1, i64::MAX, i64::MIN, 1 << 32] {
+            assert_eq!(BigInt::from(int).to_i64(), Some(int));
+            assert_eq!(BigInt::from(int).to_string(), int.to_string());
+        }
+        assert_eq!(big("9223372036854775808").to_i64(), None);
+        assert_eq!(big("-9223372036854775809").to_i64(), None);
+        assert_eq!(big("18446744073709551616").to_f64(), 18446744073709551616.0);
+    }
+
+    #[test]
+    fn arithmetic() {
+        assert_eq!(
+            &big("99999999999999999999999") + &BigInt::from(1),
+            big("100000000000000000000000")
+        );
+        assert_eq!(
+            &big("-99999999999999999999999") + &BigInt::from(1),
+            big("-99999999999999999999998")
+        );
+        assert_eq!(
+            &big("5") - &big("100000000000000000000"),
+            big("-99999999999999999995")
+        );
+        assert_eq!(
+            &BigInt::from(i64::MAX) * &BigInt::from(i64::MIN),
+            big("-85070591730234615856620279821087277056")
+        );
+        assert_eq!(&big("123456789") - &big("123456789"), BigInt::default());
+        assert!(big("-100000000000000000000") < BigInt::from(-1));
+        assert!(big("100000000000000000000") > BigInt::from(i64::MAX));
+    }
+
+    #[test]
+    fn float_comparison() {
+        use std::cmp::Ordering;
+
+        // 2^64 + 1 rounds to 2^64 as a float
+        let big_float = 18446744073709551616.0;
+        assert_eq!(
+            big("18446744073709551617").cmp_f64(big_float),
+            Some(Ordering::Greater)
+        );
+        assert_eq!(
+            big("18446744073709551616").cmp_f64(big_float),
+            Some(Ordering::Equal)
+        );
+        assert_eq!(
+            big("-18446744073709551617").cmp_f64(-big_float),
+            Some(Ordering::Less)
+        );
+        assert_eq!(
+            big("10000000000000000000").cmp_f64(1e19),
+            Some(Ordering::Equal)
+        );
+        assert_eq!(big("1").cmp_f64(1.5), Some(Ordering::Less));
+        assert_eq!(big("-1").cmp_f64(-1.5), Some(Ordering::Greater));
+        assert_eq!(big("0").cmp_f64(-0.0), Some(Ordering::Equal));
+        assert_eq!(big("1").cmp_f64(0.25), Some(Ordering::Greater));
+        assert_eq!(
+            big(&"9".repeat(400)).cmp_f64(f64::MAX),
+            Some(Ordering::Greater)
+        );
+        assert_eq!(big("123").cmp_f64(f64::INFINITY), Some(Ordering::Less));
+        assert_eq!(big("123").cmp_f64(f64::NAN), None);
+    }
+}
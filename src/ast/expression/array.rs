@@ -0,0 +1,101 @@
+use alloc::vec::Vec;
+use core::{convert::TryInto, fmt};
+
+use pest::iterators::Pair;
+
+use crate::{
+    compiler::{
+        types::{Infer, Type, TypeChecker},
+        Compile, Compiler, CompilerError, CompilerResult, Instruction,
+    },
+    parser::{expect_rule, Parse, ParserError, Rule},
+};
+
+use super::Expression;
+
+/// Array literal such as `[1, 2, 3]`.
+#[derive(PartialEq)]
+pub struct ArrayExpression {
+    pub elements: Vec<Expression>,
+}
+
+impl Compile for ArrayExpression {
+    fn compile(&self, compiler: &mut Compiler) -> CompilerResult<()> {
+        let count = self
+            .elements
+            .len()
+            .try_into()
+            .map_err(|_| CompilerError::CollectionLimitReached)?;
+        for element in &self.elements {
+            element.compile(compiler)?;
+        }
+        compiler.emit(Instruction::BuildArray(count));
+        Ok(())
+    }
+}
+
+impl Infer for ArrayExpression {
+    fn infer(&self, checker: &mut TypeChecker) -> Type {
+        for element in &self.elements {
+            element.infer(checker);
+        }
+        Type::Unknown
+    }
+}
+
+impl Parse<'_> for ArrayExpression {
+    fn parse(pair: Pair<'_, Rule>) -> Result<Self, ParserError> {
+        expect_rule(&pair, Rule::array_literal)?;
+        let elements = pair
+            .into_inner()
+            .map(Expression::parse)
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self { elements })
+    }
+}
+
+impl fmt::Debug for ArrayExpression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(&self.elements).finish()
+    }
+}
+
+impl fmt::Display for ArrayExpression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[")?;
+        for (i, element) in self.elements.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{element}")?;
+        }
+        write!(f, "]")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::{parse_rule, ParseResult, Rule};
+
+    use super::ArrayExpression;
+
+    fn parse_array(input: &str) -> ParseResult<ArrayExpression> {
+        parse_rule::<ArrayExpression>(Rule::array_literal, input)
+    }
+
+    #[test]
+    fn test_array_literal() -> ParseResult<()> {
+        assert!(parse_array("[]")?.elements.is_empty());
+        assert_eq!(parse_array("[1]")?.elements.len(), 1);
+        assert_eq!(parse_array("[1, x, 2 + 3,]")?.elements.len(), 3);
+        assert_eq!(parse_array("[ 1,[2, []] ]")?.to_string(), "[1, [2, []]]");
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_array_literal() {
+        parse_array("[,]").unwrap_err();
+        parse_array("[1 2]").unwrap_err();
+        parse_array("[1, 2").unwrap_err();
+    }
+}
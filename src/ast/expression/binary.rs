@@ -1,44 +1,65 @@
-use std::fmt;
+use alloc::{boxed::Box, vec};
+use core::fmt;
 
+use once_cell::race::OnceBox;
 use pest::{
     iterators::Pair,
-    prec_climber::{Assoc, Operator, PrecClimber},
+    pratt_parser::{Assoc, Op, PrattParser},
 };
 
 use crate::{
     ast::value::Value,
-    compiler::{Compile, Compiler, CompilerResult, Instruction},
-    parser::{Parse, ParserError, Rule},
+    compiler::{
+        types::{Infer, Type, TypeChecker},
+        Compile, Compiler, CompilerResult, Instruction,
+    },
+    parser::{unexpected_rule, Parse, ParserError, Rule, Spanned},
 };
 
-use super::{identifier::IdentifierExpression, Expression};
-
-lazy_static! {
-    static ref PREC_CLIMBER: PrecClimber<Rule> = {
-        PrecClimber::new(vec![
-            Operator::new(Rule::logical_xor, Assoc::Left),
-            Operator::new(Rule::logical_or, Assoc::Left),
-            Operator::new(Rule::logical_and, Assoc::Left),
-            Operator::new(Rule::equal_to, Assoc::Left)
-                | Operator::new(Rule::not_equal_to, Assoc::Left),
-            Operator::new(Rule::less_than, Assoc::Left)
-                | Operator::new(Rule::greater_than, Assoc::Left)
-                | Operator::new(Rule::less_than_eq, Assoc::Left)
-                | Operator::new(Rule::greater_than_eq, Assoc::Left),
-            Operator::new(Rule::add, Assoc::Left) | Operator::new(Rule::subtract, Assoc::Left),
-            Operator::new(Rule::multiply, Assoc::Left) | Operator::new(Rule::divide, Assoc::Left),
-            Operator::new(Rule::power, Assoc::Right),
-        ])
-    };
+use super::{
+    array::ArrayExpression, call::CallExpression, comparison::ComparisonExpression,
+    identifier::IdentifierExpression, if_expression::IfExpression, index, set::SetExpression,
+    unary::UnaryOperator, Expression,
+};
+
+static PRATT_PARSER: OnceBox<PrattParser<Rule>> = OnceBox::new();
+
+fn pratt_parser() -> &'static PrattParser<Rule> {
+    PRATT_PARSER.get_or_init(|| {
+        let parser = PrattParser::new()
+            .op(Op::infix(Rule::logical_xor, Assoc::Left))
+            .op(Op::infix(Rule::logical_or, Assoc::Left))
+            .op(Op::infix(Rule::logical_and, Assoc::Left))
+            .op(Op::infix(Rule::equal_to, Assoc::Left) | Op::infix(Rule::not_equal_to, Assoc::Left))
+            .op(Op::infix(Rule::less_than, Assoc::Left)
+                | Op::infix(Rule::greater_than, Assoc::Left)
+                | Op::infix(Rule::less_than_eq, Assoc::Left)
+                | Op::infix(Rule::greater_than_eq, Assoc::Left)
+                | Op::infix(Rule::contains, Assoc::Left))
+            .op(Op::infix(Rule::add, Assoc::Left) | Op::infix(Rule::subtract, Assoc::Left))
+            .op(Op::infix(Rule::multiply, Assoc::Left)
+                | Op::infix(Rule::divide, Assoc::Left)
+                | Op::infix(Rule::reminder, Assoc::Left))
+            .op(Op::infix(Rule::power, Assoc::Right));
+        Box::new(parser)
+    })
 }
 
-#[derive(PartialEq)]
 pub struct BinaryExpression {
     pub left: Box<Expression>,
-    pub operator: BinaryOperator,
+    /// Operator with its span in the source, runtime errors of the operation
+    /// point at it.
+    pub operator: Spanned<BinaryOperator>,
     pub right: Box<Expression>,
 }
 
+/// Expressions are equal regardless of where their operators are in the source.
+impl PartialEq for BinaryExpression {
+    fn eq(&self, other: &Self) -> bool {
+        self.left == other.left && *self.operator == *other.operator && self.right == other.right
+    }
+}
+
 impl fmt::Debug for BinaryExpression {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "({:?} {} {:?})", self.left, self.operator, self.right)
@@ -46,8 +67,47 @@ impl fmt::Debug for BinaryExpression {
 }
 
 impl fmt::Display for BinaryExpression {
-    fn fmt(&self, _f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        todo!();
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let precedence = self.operator.precedence();
+        // Operands binding looser than the operator need parentheses, for the
+        // side opposite to the operator's associativity equal precedence does too.
+        // Comparisons on either side of a relational operator would chain.
+        let (left, right) = if self.operator.is_right_associative() {
+            (precedence + 1, precedence)
+        } else if ComparisonExpression::is_chained(*self.operator) {
+            (precedence + 1, precedence + 1)
+        } else {
+            (precedence, precedence + 1)
+        };
+        write_operand(f, &self.left, left)?;
+        write!(f, " {} ", self.operator)?;
+        write_operand(f, &self.right, right)
+    }
+}
+
+/// Write `operand` wrapping it in parentheses if it binds looser than `min_precedence`.
+pub(super) fn write_operand(
+    f: &mut fmt::Formatter<'_>,
+    operand: &Expression,
+    min_precedence: u8,
+) -> fmt::Result {
+    let parenthesize = match operand {
+        Expression::Binary(binary) => binary.operator.precedence() < min_precedence,
+        Expression::Comparison(comparison) => comparison.precedence() < min_precedence,
+        Expression::Unary(unary) => unary.operator == UnaryOperator::Not,
+        Expression::Value(_)
+        | Expression::Identifier(_)
+        | Expression::Set(_)
+        | Expression::Array(_)
+        | Expression::Call(_)
+        | Expression::If(_)
+        | Expression::Index(_)
+        | Expression::Slice(_) => false,
+    };
+    if parenthesize {
+        write!(f, "({operand})")
+    } else {
+        write!(f, "{operand}")
     }
 }
 
@@ -55,7 +115,15 @@ impl Compile for BinaryExpression {
     fn compile(&self, compiler: &mut Compiler) -> CompilerResult<()> {
         self.left.compile(compiler)?;
         self.right.compile(compiler)?;
-        let instruction = match self.operator {
+        compiler.emit_spanned(self.operator.instruction(), &self.operator);
+        Ok(())
+    }
+}
+
+impl BinaryOperator {
+    /// Instruction applying the operator to the two values on top of the stack.
+    pub fn instruction(&self) -> Instruction {
+        match self {
             BinaryOperator::Add => Instruction::BinaryAdd,
             BinaryOperator::Subtract => Instruction::BinarySubtract,
             BinaryOperator::Multiply => Instruction::BinaryMultiply,
@@ -71,34 +139,69 @@ impl Compile for BinaryExpression {
             BinaryOperator::LogicalAnd => Instruction::BinaryLogicalAnd,
             BinaryOperator::LogicalOr => Instruction::BinaryLogicalOr,
             BinaryOperator::LogicalXor => Instruction::BinaryLogicalXor,
-        };
-        compiler.emit(instruction);
-        Ok(())
+            BinaryOperator::In => Instruction::Contains,
+        }
+    }
+}
+
+impl Infer for BinaryExpression {
+    fn infer(&self, checker: &mut TypeChecker) -> Type {
+        let left = self.left.infer(checker);
+        let right = self.right.infer(checker);
+        checker.infer_binary(*self.operator, left, right)
     }
 }
 
 impl Parse<'_> for BinaryExpression {
     fn parse(rule: Pair<'_, Rule>) -> Result<Self, ParserError> {
-        let expression = match rule.as_rule() {
-            Rule::binary_expression => rule.into_inner(),
-            _ => unreachable!(),
-        };
-        let result = PREC_CLIMBER.climb(
-            expression,
-            |pair: Pair<Rule>| -> Expression {
-                match pair.as_rule() {
-                    Rule::value => Value::parse(pair).unwrap().into(),
-                    Rule::expression => Expression::parse(pair).unwrap(),
-                    Rule::identifier => IdentifierExpression::parse(pair).unwrap().into(),
-                    _ => unreachable!("{}", pair),
-                }
+        match parse_binary(rule.clone())? {
+            Expression::Binary(binary) => Ok(binary),
+            _ => Err(unexpected_rule(&rule)),
+        }
+    }
+}
+
+/// Parse a `binary_expression` into a binary expression, or a comparison if
+/// the outermost operators are chained relational operators.
+pub(super) fn parse_binary(rule: Pair<'_, Rule>) -> Result<Expression, ParserError> {
+    let expression = match rule.as_rule() {
+        Rule::binary_expression => rule.into_inner(),
+        _ => return Err(unexpected_rule(&rule)),
+    };
+    // Expressions are paired with whether they are a comparison produced by
+    // the parser, parenthesized comparisons aren't extended to a chain. The
+    // first operand that fails to parse is carried up to the result.
+    let (result, _) = pratt_parser()
+        .map_primary(
+            |pair: Pair<Rule>| -> Result<(Expression, bool), ParserError> {
+                let expression = match pair.as_rule() {
+                    Rule::value => Value::parse(pair)?.into(),
+                    Rule::expression => Expression::parse(pair)?,
+                    Rule::identifier => IdentifierExpression::parse(pair)?.into(),
+                    Rule::set_literal => SetExpression::parse(pair)?.into(),
+                    Rule::array_literal => ArrayExpression::parse(pair)?.into(),
+                    Rule::call_expression => CallExpression::parse(pair)?.into(),
+                    Rule::if_expression => IfExpression::parse(pair)?.into(),
+                    Rule::index_expression => index::parse_index(pair)?,
+                    _ => return Err(unexpected_rule(&pair)),
+                };
+                Ok((expression, false))
             },
-            |left: Expression, op: Pair<Rule>, right: Expression| -> Expression {
+        )
+        .map_infix(
+            |left: Result<(Expression, bool), ParserError>,
+             op: Pair<Rule>,
+             right: Result<(Expression, bool), ParserError>|
+             -> Result<(Expression, bool), ParserError> {
+                let (left, comparison) = left?;
+                let (right, _) = right?;
+                let span = op.as_span();
                 let operator = match op.as_rule() {
                     Rule::add => BinaryOperator::Add,
                     Rule::subtract => BinaryOperator::Subtract,
                     Rule::multiply => BinaryOperator::Multiply,
                     Rule::divide => BinaryOperator::Divide,
+                    Rule::reminder => BinaryOperator::Reminder,
                     Rule::power => BinaryOperator::Power,
                     Rule::less_than => BinaryOperator::LessThan,
                     Rule::less_than_eq => BinaryOperator::LessThanEqual,
@@ -109,21 +212,38 @@ impl Parse<'_> for BinaryExpression {
                     Rule::logical_and => BinaryOperator::LogicalAnd,
                     Rule::logical_or => BinaryOperator::LogicalOr,
                     Rule::logical_xor => BinaryOperator::LogicalXor,
-                    _ => unreachable!(),
+                    Rule::contains => BinaryOperator::In,
+                    _ => return Err(unexpected_rule(&op)),
+                };
+                let operator = Spanned {
+                    ast: operator,
+                    start: span.start(),
+                    end: span.end(),
                 };
-                Expression::Binary(BinaryExpression {
-                    left: Box::from(left),
-                    right: Box::from(right),
-                    operator,
-                })
+                let chained = ComparisonExpression::is_chained(*operator);
+                let expression = match left {
+                    Expression::Comparison(mut chain) if chained && comparison => {
+                        chain.operands.push(right);
+                        chain.operators.push(operator);
+                        Expression::Comparison(chain)
+                    }
+                    Expression::Binary(binary) if chained && comparison => {
+                        Expression::Comparison(ComparisonExpression {
+                            operands: vec![*binary.left, *binary.right, right],
+                            operators: vec![binary.operator, operator],
+                        })
+                    }
+                    left => Expression::Binary(BinaryExpression {
+                        left: Box::from(left),
+                        right: Box::from(right),
+                        operator,
+                    }),
+                };
+                Ok((expression, chained))
             },
-        );
-        if let Expression::Binary(binary) = result {
-            Ok(binary)
-        } else {
-            unreachable!()
-        }
-    }
+        )
+        .parse(expression)?;
+    Ok(result)
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -143,6 +263,88 @@ pub enum BinaryOperator {
     LogicalAnd,
     LogicalOr,
     LogicalXor,
+    In,
+}
+
+impl BinaryOperator {
+    /// Fused compare-and-branch instruction jumping when the comparison is
+    /// false, `None` for operators that aren't comparisons.
+    pub fn jump_unless(&self) -> Option<Instruction> {
+        let jump = match self {
+            BinaryOperator::LessThan => Instruction::JumpUnlessLessThan(0),
+            BinaryOperator::LessThanEqual => Instruction::JumpUnlessLessThanEqual(0),
+            BinaryOperator::GreaterThan => Instruction::JumpUnlessGreaterThan(0),
+            BinaryOperator::GreaterThanEqual => Instruction::JumpUnlessGreaterThanEqual(0),
+            BinaryOperator::Equal => Instruction::JumpUnlessEqual(0),
+            BinaryOperator::NotEqual => Instruction::JumpUnlessNotEqual(0),
+            _ => return None,
+        };
+        Some(jump)
+    }
+
+    /// Precedence of the operator matching the order in `PRATT_PARSER`, higher
+    /// values bind tighter.
+    pub fn precedence(&self) -> u8 {
+        match self {
+            BinaryOperator::LogicalXor => 0,
+            BinaryOperator::LogicalOr => 1,
+            BinaryOperator::LogicalAnd => 2,
+            BinaryOperator::Equal | BinaryOperator::NotEqual => 3,
+            BinaryOperator::LessThan
+            | BinaryOperator::LessThanEqual
+            | BinaryOperator::GreaterThan
+            | BinaryOperator::GreaterThanEqual
+            | BinaryOperator::In => 4,
+            BinaryOperator::Add | BinaryOperator::Subtract => 5,
+            BinaryOperator::Multiply | BinaryOperator::Divide | BinaryOperator::Reminder => 6,
+            BinaryOperator::Power => 7,
+        }
+    }
+
+    /// Type of the result when applied to operands of type `left` and
+    /// `right`, `None` if the operands aren't supported. Logical operators
+    /// only accept booleans even though any value has a truthiness at runtime.
+    pub fn result_type(&self, left: Type, right: Type) -> Option<Type> {
+        use Type::*;
+        match self {
+            BinaryOperator::Equal | BinaryOperator::NotEqual => Some(Bool),
+            BinaryOperator::LogicalAnd | BinaryOperator::LogicalOr | BinaryOperator::LogicalXor => {
+                match (left, right) {
+                    (Bool | Unknown, Bool | Unknown) => Some(Bool),
+                    _ => None,
+                }
+            }
+            BinaryOperator::LessThan
+            | BinaryOperator::LessThanEqual
+            | BinaryOperator::GreaterThan
+            | BinaryOperator::GreaterThanEqual => match (left, right) {
+                (Int | Float | Unknown, Int | Float | Unknown) => Some(Bool),
+                (String | Unknown, String) | (String, Unknown) => Some(Bool),
+                _ => None,
+            },
+            // Sets don't have a static type so only strings are checked
+            BinaryOperator::In => match (left, right) {
+                (String | Unknown, String) | (_, Unknown) => Some(Bool),
+                _ => None,
+            },
+            BinaryOperator::Add if (left, right) == (String, String) => Some(String),
+            // Integers raised to negative powers are floats
+            BinaryOperator::Power if (left, right) == (Int, Int) => Some(Unknown),
+            _ => match (left, right) {
+                (Int, Int) => Some(Int),
+                (Int | Float, Int | Float) => Some(Float),
+                (Int | Float | Unknown, Int | Float | Unknown) => Some(Unknown),
+                (String | Unknown, String | Unknown) if *self == BinaryOperator::Add => {
+                    Some(Unknown)
+                }
+                _ => None,
+            },
+        }
+    }
+
+    pub fn is_right_associative(&self) -> bool {
+        *self == BinaryOperator::Power
+    }
 }
 
 impl fmt::Display for BinaryOperator {
@@ -163,13 +365,17 @@ impl fmt::Display for BinaryOperator {
             BinaryOperator::LogicalAnd => write!(f, "and"),
             BinaryOperator::LogicalOr => write!(f, "or"),
             BinaryOperator::LogicalXor => write!(f, "xor"),
+            BinaryOperator::In => write!(f, "in"),
         }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::parser::{parse_rule, ParserError, Rule};
+    use crate::{
+        ast::expression::UnaryExpression,
+        parser::{parse_rule, ParserError, Rule},
+    };
 
     use super::BinaryExpression;
 
@@ -185,6 +391,29 @@ mod tests {
         parse_binary("1 - 1")?;
         parse_binary("1 + 2 + 3")?;
         parse_binary("(1 + 2) / 3")?;
+        assert_eq!(parse_binary("1 + 2 % 3 * 4")?.to_string(), "1 + 2 % 3 * 4");
+        assert_eq!(parse_binary("(1 + 2) % 3")?.to_string(), "(1 + 2) % 3");
         Ok(())
     }
+
+    #[test]
+    fn test_operand_errors() {
+        // Operands that fail to parse are returned as errors, hexadecimal
+        // literals don't fit in an integer even with `bigint`
+        let literal = "0x1_0000_0000_0000_0000";
+        parse_binary(&format!("1 + {literal}")).unwrap_err();
+        parse_binary(&format!("{literal} * 2 - 1")).unwrap_err();
+        parse_binary(&format!("1 + (2 * {literal})")).unwrap_err();
+        parse_binary(&format!("1 + set{{{literal}}}")).unwrap_err();
+        parse_binary(&format!("1 + [{literal}]")).unwrap_err();
+        parse_binary(&format!("1 + f({literal})")).unwrap_err();
+        parse_binary(&format!("1 + if x {{ {literal} }} else {{ 2 }}")).unwrap_err();
+        parse_binary(&format!("1 + a[{literal}]")).unwrap_err();
+    }
+
+    #[test]
+    fn test_mismatched_binary_rule() {
+        parse_rule::<BinaryExpression>(Rule::value, "1").unwrap_err();
+        parse_rule::<UnaryExpression>(Rule::binary_expression, "1 + 1").unwrap_err();
+    }
 }
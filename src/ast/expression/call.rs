@@ -0,0 +1,183 @@
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::{convert::TryInto, fmt};
+
+use pest::iterators::Pair;
+
+use crate::{
+    compiler::{
+        types::{Infer, Type, TypeChecker},
+        Compile, Compiler, CompilerError, CompilerResult, Instruction,
+    },
+    parser::{expect_rule, Parse, ParserError, Rule},
+    vm::native,
+};
+
+use super::Expression;
+
+/// Call of a declared or host function such as `add(1, 2)`.
+#[derive(PartialEq)]
+pub struct CallExpression {
+    pub callee: String,
+    pub args: Vec<Expression>,
+}
+
+/// Builtins compiled to a single instruction and their arity, declared
+/// functions shadow them. Other builtins are called with `CallNative`.
+fn intrinsic(name: &str) -> Option<(Instruction, u8)> {
+    match name {
+        "append" => Some((Instruction::Push, 2)),
+        _ => None,
+    }
+}
+
+impl Compile for CallExpression {
+    fn compile(&self, compiler: &mut Compiler) -> CompilerResult<()> {
+        // Host functions check their own arguments when called
+        if let Some(index) = compiler.get_host_function(&self.callee) {
+            let argc = self
+                .args
+                .len()
+                .try_into()
+                .map_err(|_| CompilerError::ParameterLimitReached)?;
+            for arg in &self.args {
+                arg.compile(compiler)?;
+            }
+            compiler.emit(Instruction::CallHost(index, argc));
+            return Ok(());
+        }
+        let (instruction, arity) = match compiler.get_function(&self.callee) {
+            Some((index, arity)) => (Instruction::Call(index, arity), arity),
+            None => match intrinsic(&self.callee) {
+                Some(intrinsic) => intrinsic,
+                None => return self.compile_native(compiler),
+            },
+        };
+        if self.args.len() != usize::from(arity) {
+            return Err(CompilerError::ArityMismatch {
+                name: self.callee.clone(),
+                expected: arity,
+                found: self.args.len(),
+            });
+        }
+        for arg in &self.args {
+            arg.compile(compiler)?;
+        }
+        compiler.emit(instruction);
+        Ok(())
+    }
+}
+
+impl CallExpression {
+    /// Check whether this calls the function whose body is being compiled.
+    pub fn is_recursive(&self, compiler: &Compiler) -> bool {
+        let callee = compiler.get_function(&self.callee);
+        matches!((compiler.current_function(), callee), (Some(current), Some((index, _))) if current == index)
+    }
+
+    /// Compile `return` of this call as a `TailCall` reusing the current
+    /// frame, must only be used if the call `is_recursive`.
+    pub fn compile_tail_call(&self, compiler: &mut Compiler) -> CompilerResult<()> {
+        let (index, arity) = compiler
+            .get_function(&self.callee)
+            .ok_or_else(|| CompilerError::UndefinedIdentifer(self.callee.clone()))?;
+        if self.args.len() != usize::from(arity) {
+            return Err(CompilerError::ArityMismatch {
+                name: self.callee.clone(),
+                expected: arity,
+                found: self.args.len(),
+            });
+        }
+        for arg in &self.args {
+            arg.compile(compiler)?;
+        }
+        compiler.emit(Instruction::TailCall(index, arity));
+        Ok(())
+    }
+
+    /// Compile call of a native function, natives check their own arguments
+    /// when called.
+    fn compile_native(&self, compiler: &mut Compiler) -> CompilerResult<()> {
+        let index = native::index(&self.callee)
+            .ok_or_else(|| CompilerError::UndefinedIdentifer(self.callee.clone()))?;
+        let argc = self
+            .args
+            .len()
+            .try_into()
+            .map_err(|_| CompilerError::ParameterLimitReached)?;
+        for arg in &self.args {
+            arg.compile(compiler)?;
+        }
+        compiler.emit(Instruction::CallNative(index, argc));
+        Ok(())
+    }
+}
+
+impl Infer for CallExpression {
+    fn infer(&self, checker: &mut TypeChecker) -> Type {
+        for arg in &self.args {
+            arg.infer(checker);
+        }
+        Type::Unknown
+    }
+}
+
+impl Parse<'_> for CallExpression {
+    fn parse(pair: Pair<'_, Rule>) -> Result<Self, ParserError> {
+        expect_rule(&pair, Rule::call_expression)?;
+        let mut inner = pair.into_inner();
+        let callee = inner.next().unwrap().as_str().to_string();
+        let args = inner.map(Expression::parse).collect::<Result<_, _>>()?;
+        Ok(Self { callee, args })
+    }
+}
+
+impl fmt::Debug for CallExpression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CallExpression")
+            .field("callee", &self.callee)
+            .field("args", &self.args)
+            .finish()
+    }
+}
+
+impl fmt::Display for CallExpression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}(", self.callee)?;
+        for (i, arg) in self.args.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{arg}")?;
+        }
+        write!(f, ")")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::{parse_rule, ParseResult, Rule};
+
+    use super::CallExpression;
+
+    fn parse_call(input: &str) -> ParseResult<CallExpression> {
+        parse_rule::<CallExpression>(Rule::call_expression, input)
+    }
+
+    #[test]
+    fn test_call_expression() -> ParseResult<()> {
+        assert!(parse_call("f()")?.args.is_empty());
+        assert_eq!(parse_call("add(1, x)")?.args.len(), 2);
+        assert_eq!(parse_call("f( g(1), [2], )")?.to_string(), "f(g(1), [2])");
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_call_expression() {
+        parse_call("f(,)").unwrap_err();
+        parse_call("f(1 2)").unwrap_err();
+        parse_call("print(1)").unwrap_err();
+    }
+}
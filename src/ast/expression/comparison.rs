@@ -0,0 +1,162 @@
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::{
+    compiler::{
+        types::{Infer, Type, TypeChecker},
+        Compile, Compiler, CompilerResult, Instruction,
+    },
+    parser::Spanned,
+};
+
+use super::{
+    binary::{write_operand, BinaryOperator},
+    Expression,
+};
+
+/// Name of the local holding the middle operand of a comparison, it isn't a
+/// valid identifier so it can't clash with user variables.
+const MIDDLE_OPERAND: &str = "<comparison>";
+
+/// Chain of relational comparisons such as `a < b <= c`, which is `a < b and
+/// b <= c` with `b` evaluated once. Like `and` every operand is evaluated
+/// even if an earlier comparison is false.
+pub struct ComparisonExpression {
+    pub operands: Vec<Expression>,
+    /// Operator between each pair of adjacent operands with its span in the
+    /// source.
+    pub operators: Vec<Spanned<BinaryOperator>>,
+}
+
+/// Expressions are equal regardless of where their operators are in the source.
+impl PartialEq for ComparisonExpression {
+    fn eq(&self, other: &Self) -> bool {
+        self.operands == other.operands
+            && self
+                .operators
+                .iter()
+                .map(|operator| **operator)
+                .eq(other.operators.iter().map(|operator| **operator))
+    }
+}
+
+impl Compile for ComparisonExpression {
+    fn compile(&self, compiler: &mut Compiler) -> CompilerResult<()> {
+        compiler.enter_scope();
+        let result = self.compile_chain(compiler);
+        compiler.exit_scope();
+        result
+    }
+}
+
+impl ComparisonExpression {
+    /// Compile comparisons left to right, each middle operand is duplicated
+    /// and kept in a local until it's the left operand of the next one.
+    fn compile_chain(&self, compiler: &mut Compiler) -> CompilerResult<()> {
+        self.operands[0].compile(compiler)?;
+        let middle = compiler.register_var(MIDDLE_OPERAND)?;
+        let last = self.operators.len() - 1;
+        for (i, operator) in self.operators.iter().enumerate() {
+            self.operands[i + 1].compile(compiler)?;
+            if i < last {
+                compiler.emit(Instruction::Dup);
+                compiler.emit(middle.store());
+            }
+            compiler.emit_spanned(operator.instruction(), operator);
+            if i > 0 {
+                compiler.emit(Instruction::BinaryLogicalAnd);
+            }
+            if i < last {
+                compiler.emit(middle.load());
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether `operator` forms a chain with an adjacent comparison instead
+    /// of comparing its result.
+    pub fn is_chained(operator: BinaryOperator) -> bool {
+        matches!(
+            operator,
+            BinaryOperator::LessThan
+                | BinaryOperator::LessThanEqual
+                | BinaryOperator::GreaterThan
+                | BinaryOperator::GreaterThanEqual
+        )
+    }
+
+    /// Precedence of the chained operators.
+    pub fn precedence(&self) -> u8 {
+        self.operators[0].precedence()
+    }
+}
+
+impl Infer for ComparisonExpression {
+    fn infer(&self, checker: &mut TypeChecker) -> Type {
+        let types: Vec<_> = self
+            .operands
+            .iter()
+            .map(|operand| operand.infer(checker))
+            .collect();
+        for (operator, pair) in self.operators.iter().zip(types.windows(2)) {
+            checker.infer_binary(**operator, pair[0], pair[1]);
+        }
+        Type::Bool
+    }
+}
+
+impl fmt::Debug for ComparisonExpression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "({:?}", self.operands[0])?;
+        for (operator, operand) in self.operators.iter().zip(&self.operands[1..]) {
+            write!(f, " {} {:?}", **operator, operand)?;
+        }
+        write!(f, ")")
+    }
+}
+
+impl fmt::Display for ComparisonExpression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Operands that are comparisons themselves need parentheses
+        let precedence = self.precedence() + 1;
+        write_operand(f, &self.operands[0], precedence)?;
+        for (operator, operand) in self.operators.iter().zip(&self.operands[1..]) {
+            write!(f, " {} ", **operator)?;
+            write_operand(f, operand, precedence)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        ast::expression::Expression,
+        parser::{parse_expression_str, ParseResult},
+    };
+
+    #[test]
+    fn test_comparison_chains() -> ParseResult<()> {
+        let Expression::Comparison(chain) = parse_expression_str("a < b + 1 <= c > 2")? else {
+            panic!("expected comparison")
+        };
+        assert_eq!(chain.operands.len(), 4);
+        assert_eq!(chain.to_string(), "a < b + 1 <= c > 2");
+        // Parenthesized comparisons and other operators don't chain
+        for input in [
+            "(1 < 2) < 3",
+            "1 < (2 < 3)",
+            "1 < 2 == true",
+            "1 < 2 and 2 < 3",
+        ] {
+            let expression = parse_expression_str(input)?;
+            assert!(matches!(expression, Expression::Binary(_)), "{input}");
+            assert_eq!(expression.to_string(), input);
+        }
+        assert_eq!(
+            parse_expression_str("(1 < 2 < 3) < 4")?.to_string(),
+            "(1 < 2 < 3) < 4"
+        );
+        Ok(())
+    }
+}
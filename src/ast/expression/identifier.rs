@@ -1,10 +1,14 @@
-use std::fmt;
+use alloc::{borrow::ToOwned, string::String};
+use core::fmt;
 
 use pest::iterators::Pair;
 
 use crate::{
-    compiler::{Compile, Compiler, CompilerError, CompilerResult, Instruction},
-    parser::{Parse, ParserError, Rule},
+    compiler::{
+        types::{Infer, Type, TypeChecker},
+        Compile, Compiler, CompilerError, CompilerResult,
+    },
+    parser::{expect_rule, Parse, ParserError, Rule},
 };
 
 #[derive(PartialEq, Eq)]
@@ -20,8 +24,11 @@ impl From<String> for IdentifierExpression {
 
 impl Compile for IdentifierExpression {
     fn compile(&self, compiler: &mut Compiler) -> CompilerResult<()> {
+        if let Some(value) = compiler.get_inlined(&self.ident).cloned() {
+            return value.compile(compiler);
+        }
         let instruction = match compiler.get_identifier(&self.ident) {
-            Some((_, idx)) => Instruction::LoadSymbol(idx),
+            Some((_, slot)) => slot.load(),
             None => return Err(CompilerError::UndefinedIdentifer(self.ident.to_owned())),
         };
         compiler.emit(instruction);
@@ -29,9 +36,15 @@ impl Compile for IdentifierExpression {
     }
 }
 
+impl Infer for IdentifierExpression {
+    fn infer(&self, checker: &mut TypeChecker) -> Type {
+        checker.lookup(&self.ident)
+    }
+}
+
 impl Parse<'_> for IdentifierExpression {
     fn parse(pair: Pair<'_, Rule>) -> Result<Self, ParserError> {
-        matches!(pair.as_rule(), Rule::identifier);
+        expect_rule(&pair, Rule::identifier)?;
         let ident = String::from(pair.as_str());
         Ok(IdentifierExpression { ident })
     }
@@ -82,10 +95,16 @@ mod tests {
         parse_identifer("1_2abc").unwrap_err();
     }
 
+    #[test]
+    fn test_mismatched_identifier_rule() {
+        parse_rule::<IdentifierExpression>(Rule::value, "12").unwrap_err();
+    }
+
     #[test]
     fn test_keywords_as_identifiers() {
         parse_identifer("if").unwrap_err();
         parse_identifer("else").unwrap_err();
+        parse_identifer("elif").unwrap_err();
         parse_identifer("print").unwrap_err();
         parse_identifer("while").unwrap_err();
         parse_identifer("for").unwrap_err();
@@ -0,0 +1,185 @@
+use alloc::{boxed::Box, vec::Vec};
+use core::fmt;
+
+use pest::iterators::{Pair, Pairs};
+
+use crate::{
+    ast::statement::BlockStatement,
+    compiler::{
+        types::{Infer, Type, TypeChecker},
+        BlockType, Compile, Compiler, CompilerError, CompilerResult,
+    },
+    parser::{expect_rule, unexpected_rule, Parse, ParseResult, ParserError, Rule},
+};
+
+use super::Expression;
+
+/// Conditional expression such as `if c { 1 } else { 2 }`, its value is the
+/// value of the taken branch. Unlike `if` statements the `else` branch is
+/// required so the value is always defined.
+#[derive(PartialEq)]
+pub struct IfExpression {
+    branches: Vec<(Expression, BlockStatement)>,
+    else_branch: Option<Box<BlockStatement>>,
+}
+
+impl Compile for IfExpression {
+    fn compile(&self, compiler: &mut Compiler) -> CompilerResult<()> {
+        let else_branch = self
+            .else_branch
+            .as_ref()
+            .ok_or(CompilerError::MissingElse)?;
+        compiler.enter_if_expression();
+        for (condition, branch) in &self.branches {
+            let condition_failed = condition.compile_condition(compiler)?;
+            branch.compile_value(compiler)?;
+            let exit = compiler.emit_untargeted_jump();
+            compiler.target_jump_on_exit(BlockType::IfExpression, exit);
+            compiler.target_jump(condition_failed)?;
+        }
+        else_branch.compile_value(compiler)?;
+        compiler.exit_if_expression()
+    }
+}
+
+impl Infer for IfExpression {
+    fn infer(&self, checker: &mut TypeChecker) -> Type {
+        let mut types = Vec::new();
+        for (condition, branch) in &self.branches {
+            let condition = condition.infer(checker);
+            checker.check_condition(condition);
+            types.push(branch.infer_value(checker));
+        }
+        if let Some(else_branch) = &self.else_branch {
+            types.push(else_branch.infer_value(checker));
+        }
+        match types.split_first() {
+            Some((first, rest)) if rest.iter().all(|ty| ty == first) => *first,
+            _ => Type::Unknown,
+        }
+    }
+}
+
+impl Parse<'_> for IfExpression {
+    fn parse(pair: Pair<'_, Rule>) -> Result<Self, ParserError> {
+        expect_rule(&pair, Rule::if_expression)?;
+        let mut branches = Vec::new();
+        let mut else_branch = None;
+        for pair in pair.into_inner() {
+            match pair.as_rule() {
+                Rule::if_branch => {
+                    let mut inner = pair.into_inner();
+                    expect_rule(&inner.next().unwrap(), Rule::k_if)?;
+                    branches.push(parse_branch(inner)?);
+                }
+                Rule::else_if_branch => {
+                    let mut inner = pair.into_inner();
+                    let keyword = inner.next().unwrap();
+                    if keyword.as_rule() != Rule::k_elif {
+                        expect_rule(&keyword, Rule::k_else)?;
+                        expect_rule(&inner.next().unwrap(), Rule::k_if)?;
+                    }
+                    branches.push(parse_branch(inner)?);
+                }
+                Rule::else_branch => {
+                    let mut inner = pair.into_inner();
+                    expect_rule(&inner.next().unwrap(), Rule::k_else)?;
+                    let branch = BlockStatement::parse(inner.next().unwrap())?;
+                    else_branch = Some(Box::from(branch));
+                }
+                _ => return Err(unexpected_rule(&pair)),
+            }
+        }
+        Ok(Self {
+            branches,
+            else_branch,
+        })
+    }
+}
+
+/// Parse the condition and the block of a branch following its keywords.
+fn parse_branch(mut inner: Pairs<'_, Rule>) -> ParseResult<(Expression, BlockStatement)> {
+    let condition = Expression::parse(inner.next().unwrap())?;
+    let branch = BlockStatement::parse(inner.next().unwrap())?;
+    Ok((condition, branch))
+}
+
+impl fmt::Debug for IfExpression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("IfExpression")
+            .field("branches", &self.branches)
+            .field("else_branch", &self.else_branch)
+            .finish()
+    }
+}
+
+impl fmt::Display for IfExpression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, (condition, branch)) in self.branches.iter().enumerate() {
+            if i > 0 {
+                write!(f, " else ")?;
+            }
+            write!(f, "if {condition} {branch}")?;
+        }
+        if let Some(else_branch) = &self.else_branch {
+            write!(f, " else {else_branch}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::{parse_rule, ParseResult, Rule};
+
+    use super::IfExpression;
+
+    fn parse_if(input: &str) -> ParseResult<IfExpression> {
+        parse_rule::<IfExpression>(Rule::if_expression, input)
+    }
+
+    #[test]
+    fn test_if_expression() -> ParseResult<()> {
+        let expression = parse_if("if c { 1 } else { 2 }")?;
+        assert_eq!(expression.branches.len(), 1);
+        assert!(expression.else_branch.is_some());
+        let expression = parse_if("if a { 1 } else if b { var x = 2; x } elif c { 3 } else { 4 }")?;
+        assert_eq!(expression.branches.len(), 3);
+        assert!(parse_if("if c { 1 }")?.else_branch.is_none());
+        assert_eq!(
+            parse_if("if c {1} else {2}")?.to_string(),
+            "if c {\n    1\n} else {\n    2\n}"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_if_expression() {
+        parse_if("if { 1 } else { 2 }").unwrap_err();
+        parse_if("if c 1 else 2").unwrap_err();
+        parse_if("else { 2 }").unwrap_err();
+        parse_rule::<IfExpression>(Rule::if_statement, "if c { 1 } else { 2 }").unwrap_err();
+    }
+
+    #[test]
+    fn test_if_expression_equality() -> ParseResult<()> {
+        assert_eq!(
+            parse_if("if c { 1 } else { 2 }")?,
+            parse_if("if c {1} else {2}")?
+        );
+        assert_eq!(
+            parse_if("if a { 1 } elif b { 2 } else { 3 }")?,
+            parse_if("if a { 1 } else if b { 2 } else { 3 }")?
+        );
+        assert_ne!(
+            parse_if("if c { 1 } else { 2 }")?,
+            parse_if("if c { 1 } else { 3 }")?
+        );
+        assert_ne!(
+            parse_if("if c { 1 } else { 2 }")?,
+            parse_if("if c { 1; } else { 2 }")?
+        );
+        assert_ne!(parse_if("if c { 1 } else { 2 }")?, parse_if("if c { 1 }")?);
+        Ok(())
+    }
+}
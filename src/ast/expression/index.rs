@@ -0,0 +1,133 @@
+use alloc::boxed::Box;
+use core::fmt;
+
+use pest::iterators::Pair;
+
+use crate::{
+    compiler::{
+        types::{Infer, Type, TypeChecker},
+        Compile, Compiler, CompilerResult, Instruction,
+    },
+    parser::{expect_rule, unexpected_rule, Parse, ParserError, Rule},
+};
+
+use super::{ArrayExpression, CallExpression, Expression, IdentifierExpression, SliceExpression};
+
+/// Element of an array such as `arr[0]`, negative indices count from the end
+/// so `arr[-1]` is the last element. Indices out of bounds either way are a
+/// runtime error.
+#[derive(PartialEq)]
+pub struct IndexExpression {
+    pub target: Box<Expression>,
+    pub index: Box<Expression>,
+}
+
+impl Compile for IndexExpression {
+    fn compile(&self, compiler: &mut Compiler) -> CompilerResult<()> {
+        self.target.compile(compiler)?;
+        self.index.compile(compiler)?;
+        compiler.emit(Instruction::GetIndex);
+        Ok(())
+    }
+}
+
+impl Infer for IndexExpression {
+    fn infer(&self, checker: &mut TypeChecker) -> Type {
+        self.target.infer(checker);
+        self.index.infer(checker);
+        Type::Unknown
+    }
+}
+
+/// Parse an `index_expression` into the index or slice applied last.
+pub(super) fn parse_index(pair: Pair<'_, Rule>) -> Result<Expression, ParserError> {
+    expect_rule(&pair, Rule::index_expression)?;
+    let mut inner = pair.into_inner();
+    let target = inner.next().unwrap();
+    let mut target = match target.as_rule() {
+        Rule::array_literal => ArrayExpression::parse(target)?.into(),
+        Rule::call_expression => CallExpression::parse(target)?.into(),
+        Rule::identifier => IdentifierExpression::parse(target)?.into(),
+        Rule::expression => Expression::parse(target)?,
+        _ => return Err(unexpected_rule(&target)),
+    };
+    // Chained indices apply left to right, `a[0][1]` is `(a[0])[1]`
+    for pair in inner {
+        let target_box = Box::new(target);
+        target = match pair.as_rule() {
+            Rule::index => IndexExpression {
+                target: target_box,
+                index: Box::new(Expression::parse(pair.into_inner().next().unwrap())?),
+            }
+            .into(),
+            Rule::slice => SliceExpression::parse_bounds(target_box, pair)?.into(),
+            _ => return Err(unexpected_rule(&pair)),
+        };
+    }
+    Ok(target)
+}
+
+/// Write the target of an index or slice, parenthesized unless it's a
+/// postfix expression itself.
+pub(super) fn fmt_target(f: &mut fmt::Formatter<'_>, target: &Expression) -> fmt::Result {
+    match target {
+        Expression::Array(_)
+        | Expression::Call(_)
+        | Expression::Identifier(_)
+        | Expression::Index(_)
+        | Expression::Slice(_) => write!(f, "{target}"),
+        _ => write!(f, "({target})"),
+    }
+}
+
+impl Parse<'_> for IndexExpression {
+    fn parse(pair: Pair<'_, Rule>) -> Result<Self, ParserError> {
+        let rule = pair.clone();
+        match parse_index(pair)? {
+            Expression::Index(index) => Ok(index),
+            _ => Err(unexpected_rule(&rule)),
+        }
+    }
+}
+
+impl fmt::Debug for IndexExpression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "({:?}[{:?}])", self.target, self.index)
+    }
+}
+
+impl fmt::Display for IndexExpression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt_target(f, &self.target)?;
+        write!(f, "[{}]", self.index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::{parse_rule, ParseResult, Rule};
+
+    use super::IndexExpression;
+
+    fn parse_index(input: &str) -> ParseResult<IndexExpression> {
+        parse_rule::<IndexExpression>(Rule::index_expression, input)
+    }
+
+    #[test]
+    fn test_index_expression() -> ParseResult<()> {
+        assert_eq!(parse_index("a[0]")?.to_string(), "a[0]");
+        assert_eq!(parse_index("a[ -1 ]")?.to_string(), "a[-1]");
+        assert_eq!(parse_index("[1, 2][i + 1]")?.to_string(), "[1, 2][i + 1]");
+        assert_eq!(parse_index("f(x)[0][1]")?.to_string(), "f(x)[0][1]");
+        assert_eq!(parse_index("(a + b)[0]")?.to_string(), "(a + b)[0]");
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_index_expression() {
+        parse_index("a[]").unwrap_err();
+        parse_index("a[0").unwrap_err();
+        parse_index("1[0]").unwrap_err();
+        parse_index("a[1:]").unwrap_err();
+    }
+}
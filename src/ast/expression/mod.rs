@@ -1,28 +1,48 @@
-use std::fmt;
+use core::fmt;
 
 use pest::iterators::Pair;
 
 use crate::{
-    compiler::{Compile, Compiler, CompilerResult},
-    parser::{Parse, ParserError, Rule},
+    compiler::{
+        types::{Infer, Type, TypeChecker},
+        Compile, Compiler, CompilerResult, JumpRef,
+    },
+    parser::{expect_rule, unexpected_rule, Parse, ParserError, Rule},
 };
 
 pub use self::{
-    binary::BinaryExpression, identifier::IdentifierExpression, unary::UnaryExpression,
+    array::ArrayExpression, binary::BinaryExpression, call::CallExpression,
+    comparison::ComparisonExpression, identifier::IdentifierExpression,
+    if_expression::IfExpression, index::IndexExpression, set::SetExpression,
+    slice::SliceExpression, unary::UnaryExpression,
 };
 
 use super::value::Value;
 
+pub mod array;
 pub mod binary;
+pub mod call;
+pub mod comparison;
 pub mod identifier;
+pub mod if_expression;
+pub mod index;
+pub mod set;
+pub mod slice;
 pub mod unary;
 
 #[derive(Debug, PartialEq)]
 pub enum Expression {
     Value(Value),
     Binary(BinaryExpression),
+    Comparison(ComparisonExpression),
     Unary(UnaryExpression),
     Identifier(IdentifierExpression),
+    Set(SetExpression),
+    Array(ArrayExpression),
+    Call(CallExpression),
+    If(IfExpression),
+    Index(IndexExpression),
+    Slice(SliceExpression),
 }
 
 impl Compile for Expression {
@@ -30,12 +50,54 @@ impl Compile for Expression {
         match self {
             Expression::Value(expr) => expr.compile(compiler),
             Expression::Binary(expr) => expr.compile(compiler),
+            Expression::Comparison(expr) => expr.compile(compiler),
             Expression::Unary(expr) => expr.compile(compiler),
             Expression::Identifier(expr) => expr.compile(compiler),
+            Expression::Set(expr) => expr.compile(compiler),
+            Expression::Array(expr) => expr.compile(compiler),
+            Expression::Call(expr) => expr.compile(compiler),
+            Expression::If(expr) => expr.compile(compiler),
+            Expression::Index(expr) => expr.compile(compiler),
+            Expression::Slice(expr) => expr.compile(compiler),
         }
     }
 }
 
+impl Infer for Expression {
+    fn infer(&self, checker: &mut TypeChecker) -> Type {
+        match self {
+            Expression::Value(expr) => expr.infer(checker),
+            Expression::Binary(expr) => expr.infer(checker),
+            Expression::Comparison(expr) => expr.infer(checker),
+            Expression::Unary(expr) => expr.infer(checker),
+            Expression::Identifier(expr) => expr.infer(checker),
+            Expression::Set(expr) => expr.infer(checker),
+            Expression::Array(expr) => expr.infer(checker),
+            Expression::Call(expr) => expr.infer(checker),
+            Expression::If(expr) => expr.infer(checker),
+            Expression::Index(expr) => expr.infer(checker),
+            Expression::Slice(expr) => expr.infer(checker),
+        }
+    }
+}
+
+impl Expression {
+    /// Compile expression as a branch condition, the returned jump is taken
+    /// when the condition is false. Comparisons are fused into a single
+    /// compare-and-branch instruction instead of producing a boolean first.
+    pub fn compile_condition(&self, compiler: &mut Compiler) -> CompilerResult<JumpRef> {
+        if let Expression::Binary(binary) = self {
+            if let Some(jump) = binary.operator.jump_unless() {
+                binary.left.compile(compiler)?;
+                binary.right.compile(compiler)?;
+                return Ok(compiler.emit_spanned_jump(jump, &binary.operator));
+            }
+        }
+        self.compile(compiler)?;
+        Ok(compiler.emit_untargeted_jump_if_false())
+    }
+}
+
 impl From<Value> for Expression {
     fn from(value: Value) -> Self {
         Self::Value(value)
@@ -54,6 +116,42 @@ impl From<UnaryExpression> for Expression {
     }
 }
 
+impl From<SetExpression> for Expression {
+    fn from(set: SetExpression) -> Self {
+        Self::Set(set)
+    }
+}
+
+impl From<ArrayExpression> for Expression {
+    fn from(array: ArrayExpression) -> Self {
+        Self::Array(array)
+    }
+}
+
+impl From<CallExpression> for Expression {
+    fn from(call: CallExpression) -> Self {
+        Self::Call(call)
+    }
+}
+
+impl From<IfExpression> for Expression {
+    fn from(expression: IfExpression) -> Self {
+        Self::If(expression)
+    }
+}
+
+impl From<IndexExpression> for Expression {
+    fn from(index: IndexExpression) -> Self {
+        Self::Index(index)
+    }
+}
+
+impl From<SliceExpression> for Expression {
+    fn from(slice: SliceExpression) -> Self {
+        Self::Slice(slice)
+    }
+}
+
 impl From<IdentifierExpression> for Expression {
     fn from(identifier: IdentifierExpression) -> Self {
         Self::Identifier(identifier)
@@ -62,16 +160,22 @@ impl From<IdentifierExpression> for Expression {
 
 impl Parse<'_> for Expression {
     fn parse(pair: Pair<'_, Rule>) -> Result<Self, ParserError> {
-        matches!(pair.as_rule(), Rule::expression);
+        expect_rule(&pair, Rule::expression)?;
         let inner_pair = pair.into_inner().next().unwrap();
         let expression: Expression = match inner_pair.as_rule() {
-            Rule::binary_expression => BinaryExpression::parse(inner_pair)?.into(),
+            Rule::binary_expression => binary::parse_binary(inner_pair)?,
             Rule::unprecedent_unary_expression | Rule::precedent_unary_expression => {
                 UnaryExpression::parse(inner_pair)?.into()
             }
             Rule::identifier => IdentifierExpression::parse(inner_pair)?.into(),
             Rule::value => Value::parse(inner_pair)?.into(),
-            _ => unreachable!(),
+            Rule::set_literal => SetExpression::parse(inner_pair)?.into(),
+            Rule::array_literal => ArrayExpression::parse(inner_pair)?.into(),
+            Rule::call_expression => CallExpression::parse(inner_pair)?.into(),
+            Rule::if_expression => IfExpression::parse(inner_pair)?.into(),
+            Rule::index_expression => index::parse_index(inner_pair)?,
+            Rule::expression => Expression::parse(inner_pair)?,
+            _ => return Err(unexpected_rule(&inner_pair)),
         };
         Ok(expression)
     }
@@ -82,8 +186,15 @@ impl fmt::Display for Expression {
         match self {
             Expression::Value(value) => write!(f, "{value}"),
             Expression::Binary(binary) => write!(f, "{binary}"),
+            Expression::Comparison(comparison) => write!(f, "{comparison}"),
             Expression::Unary(unary) => write!(f, "{unary}"),
             Expression::Identifier(identifier) => write!(f, "{identifier}"),
+            Expression::Set(set) => write!(f, "{set}"),
+            Expression::Array(array) => write!(f, "{array}"),
+            Expression::Call(call) => write!(f, "{call}"),
+            Expression::If(expression) => write!(f, "{expression}"),
+            Expression::Index(index) => write!(f, "{index}"),
+            Expression::Slice(slice) => write!(f, "{slice}"),
         }
     }
 }
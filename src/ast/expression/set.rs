@@ -0,0 +1,105 @@
+use alloc::vec::Vec;
+use core::{convert::TryInto, fmt};
+
+use pest::iterators::Pair;
+
+use crate::{
+    compiler::{
+        types::{Infer, Type, TypeChecker},
+        Compile, Compiler, CompilerError, CompilerResult, Instruction,
+    },
+    parser::{expect_rule, Parse, ParserError, Rule},
+};
+
+use super::Expression;
+
+/// Set literal such as `set{1, 2, 3}`, the `set` prefix tells it apart from
+/// blocks.
+#[derive(PartialEq)]
+pub struct SetExpression {
+    pub elements: Vec<Expression>,
+}
+
+impl Compile for SetExpression {
+    fn compile(&self, compiler: &mut Compiler) -> CompilerResult<()> {
+        let count = self
+            .elements
+            .len()
+            .try_into()
+            .map_err(|_| CompilerError::CollectionLimitReached)?;
+        for element in &self.elements {
+            element.compile(compiler)?;
+        }
+        compiler.emit(Instruction::BuildSet(count));
+        Ok(())
+    }
+}
+
+impl Infer for SetExpression {
+    fn infer(&self, checker: &mut TypeChecker) -> Type {
+        for element in &self.elements {
+            element.infer(checker);
+        }
+        Type::Unknown
+    }
+}
+
+impl Parse<'_> for SetExpression {
+    fn parse(pair: Pair<'_, Rule>) -> Result<Self, ParserError> {
+        expect_rule(&pair, Rule::set_literal)?;
+        let mut inner = pair.into_inner();
+
+        expect_rule(&inner.next().unwrap(), Rule::set_open)?;
+        let elements = inner
+            .map(Expression::parse)
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self { elements })
+    }
+}
+
+impl fmt::Debug for SetExpression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(&self.elements).finish()
+    }
+}
+
+impl fmt::Display for SetExpression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "set{{")?;
+        for (i, element) in self.elements.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{element}")?;
+        }
+        write!(f, "}}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::{parse_rule, ParseResult, Rule};
+
+    use super::SetExpression;
+
+    fn parse_set(input: &str) -> ParseResult<SetExpression> {
+        parse_rule::<SetExpression>(Rule::set_literal, input)
+    }
+
+    #[test]
+    fn test_set_literal() -> ParseResult<()> {
+        assert!(parse_set("set{}")?.elements.is_empty());
+        assert_eq!(parse_set("set{1}")?.elements.len(), 1);
+        assert_eq!(parse_set("set{1, x, 2 + 3,}")?.elements.len(), 3);
+        assert_eq!(parse_set("set{ 1,2 }")?.to_string(), "set{1, 2}");
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_set_literal() {
+        parse_set("set {1}").unwrap_err();
+        parse_set("set{,}").unwrap_err();
+        parse_set("set{1 2}").unwrap_err();
+        parse_set("{1, 2}").unwrap_err();
+    }
+}
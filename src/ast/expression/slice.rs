@@ -0,0 +1,141 @@
+use alloc::boxed::Box;
+use core::fmt;
+
+use pest::iterators::Pair;
+
+use crate::{
+    ast::value::Value,
+    compiler::{
+        types::{Infer, Type, TypeChecker},
+        Compile, Compiler, CompilerResult, Instruction,
+    },
+    parser::{expect_rule, unexpected_rule, Parse, ParserError, Rule},
+};
+
+use super::{index, Expression};
+
+/// Copy of a range of an array or string such as `arr[1:3]`, omitted bounds
+/// are the start and end. See `Value::slice` for how bounds are clamped.
+#[derive(PartialEq)]
+pub struct SliceExpression {
+    pub target: Box<Expression>,
+    pub start: Option<Box<Expression>>,
+    pub end: Option<Box<Expression>>,
+}
+
+impl SliceExpression {
+    /// Parse the bounds of a `slice` applied to `target`.
+    pub(super) fn parse_bounds(
+        target: Box<Expression>,
+        pair: Pair<'_, Rule>,
+    ) -> Result<Self, ParserError> {
+        expect_rule(&pair, Rule::slice)?;
+        let mut start = None;
+        let mut end = None;
+        for bound in pair.into_inner() {
+            let slot = match bound.as_rule() {
+                Rule::slice_start => &mut start,
+                Rule::slice_end => &mut end,
+                _ => return Err(unexpected_rule(&bound)),
+            };
+            let expression = Expression::parse(bound.into_inner().next().unwrap())?;
+            *slot = Some(Box::new(expression));
+        }
+        Ok(Self { target, start, end })
+    }
+}
+
+impl Compile for SliceExpression {
+    fn compile(&self, compiler: &mut Compiler) -> CompilerResult<()> {
+        self.target.compile(compiler)?;
+        for bound in [&self.start, &self.end] {
+            match bound {
+                Some(bound) => bound.compile(compiler)?,
+                None => Value::Null.compile(compiler)?,
+            }
+        }
+        compiler.emit(Instruction::Slice);
+        Ok(())
+    }
+}
+
+impl Infer for SliceExpression {
+    fn infer(&self, checker: &mut TypeChecker) -> Type {
+        self.target.infer(checker);
+        for bound in self.start.iter().chain(&self.end) {
+            bound.infer(checker);
+        }
+        Type::Unknown
+    }
+}
+
+impl Parse<'_> for SliceExpression {
+    fn parse(pair: Pair<'_, Rule>) -> Result<Self, ParserError> {
+        let rule = pair.clone();
+        match index::parse_index(pair)? {
+            Expression::Slice(slice) => Ok(slice),
+            _ => Err(unexpected_rule(&rule)),
+        }
+    }
+}
+
+impl fmt::Debug for SliceExpression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "({:?}[", self.target)?;
+        if let Some(start) = &self.start {
+            write!(f, "{start:?}")?;
+        }
+        write!(f, ":")?;
+        if let Some(end) = &self.end {
+            write!(f, "{end:?}")?;
+        }
+        write!(f, "])")
+    }
+}
+
+impl fmt::Display for SliceExpression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        index::fmt_target(f, &self.target)?;
+        write!(f, "[")?;
+        if let Some(start) = &self.start {
+            write!(f, "{start}")?;
+        }
+        write!(f, ":")?;
+        if let Some(end) = &self.end {
+            write!(f, "{end}")?;
+        }
+        write!(f, "]")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::{parse_rule, ParseResult, Rule};
+
+    use super::SliceExpression;
+
+    fn parse_slice(input: &str) -> ParseResult<SliceExpression> {
+        parse_rule::<SliceExpression>(Rule::index_expression, input)
+    }
+
+    #[test]
+    fn test_slice_expression() -> ParseResult<()> {
+        let slice = parse_slice("a[1:3]")?;
+        assert!(slice.start.is_some() && slice.end.is_some());
+        assert_eq!(slice.to_string(), "a[1:3]");
+        let slice = parse_slice("a[:2]")?;
+        assert!(slice.start.is_none() && slice.end.is_some());
+        let slice = parse_slice("a[ 1 : ]")?;
+        assert!(slice.start.is_some() && slice.end.is_none());
+        assert_eq!(slice.to_string(), "a[1:]");
+        assert_eq!(parse_slice("a[:]")?.to_string(), "a[:]");
+        assert_eq!(parse_slice("a[0][-2:]")?.to_string(), "a[0][-2:]");
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_slice_expression() {
+        parse_slice("a[1:2:3]").unwrap_err();
+        parse_slice("a[0]").unwrap_err();
+    }
+}
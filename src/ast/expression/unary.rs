@@ -1,10 +1,14 @@
-use std::fmt;
+use alloc::boxed::Box;
+use core::fmt;
 
 use pest::iterators::Pair;
 
 use crate::{
-    compiler::{Compile, Compiler, CompilerResult, Instruction},
-    parser::{Parse, ParserError, Rule},
+    compiler::{
+        types::{Infer, Type, TypeChecker, TypeError},
+        Compile, Compiler, CompilerResult, Instruction,
+    },
+    parser::{unexpected_rule, Parse, ParserError, Rule},
 };
 
 use super::Expression;
@@ -27,13 +31,28 @@ impl Compile for UnaryExpression {
     }
 }
 
+impl Infer for UnaryExpression {
+    fn infer(&self, checker: &mut TypeChecker) -> Type {
+        let operand = self.expression.infer(checker);
+        match (self.operator, operand) {
+            (_, Type::Unknown) if self.operator != UnaryOperator::Not => Type::Unknown,
+            (UnaryOperator::Not, Type::Bool | Type::Unknown) => Type::Bool,
+            (UnaryOperator::Plus | UnaryOperator::Minus, Type::Int | Type::Float) => operand,
+            (operator, _) => {
+                checker.report(TypeError::UnsupportedOperand(operator, operand));
+                Type::Unknown
+            }
+        }
+    }
+}
+
 impl Parse<'_> for UnaryExpression {
     fn parse(pair: Pair<'_, Rule>) -> Result<Self, ParserError> {
         let mut inner = match pair.as_rule() {
             Rule::unprecedent_unary_expression | Rule::precedent_unary_expression => {
                 pair.into_inner()
             }
-            _ => unreachable!(),
+            _ => return Err(unexpected_rule(&pair)),
         };
         let operator = match inner.next().unwrap().as_rule() {
             Rule::not => UnaryOperator::Not,
@@ -62,8 +81,14 @@ impl fmt::Debug for UnaryExpression {
 }
 
 impl fmt::Display for UnaryExpression {
-    fn fmt(&self, _f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        todo!()
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.operator, self.expression.as_ref()) {
+            (UnaryOperator::Not, expression) => write!(f, "not {expression}"),
+            (operator, expression @ (Expression::Binary(_) | Expression::Unary(_))) => {
+                write!(f, "{operator}({expression})")
+            }
+            (operator, expression) => write!(f, "{operator}{expression}"),
+        }
     }
 }
 
@@ -89,3 +114,33 @@ impl fmt::Display for UnaryOperator {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        ast::expression::{binary::BinaryOperator, Expression},
+        parser::{parse_expression_str, ParseResult},
+    };
+
+    use super::UnaryOperator;
+
+    #[test]
+    fn test_not_binds_looser_than_comparison() -> ParseResult<()> {
+        for (input, expected) in [
+            ("not a == b", BinaryOperator::Equal),
+            ("not a != b", BinaryOperator::NotEqual),
+            ("not a < b", BinaryOperator::LessThan),
+            ("not a + b", BinaryOperator::Add),
+        ] {
+            let Expression::Unary(unary) = parse_expression_str(input)? else {
+                panic!("{input} isn't a unary expression");
+            };
+            assert_eq!(unary.operator, UnaryOperator::Not, "{input}");
+            assert!(
+                matches!(*unary.expression, Expression::Binary(ref binary) if binary.operator == expected),
+                "{input}"
+            );
+        }
+        Ok(())
+    }
+}
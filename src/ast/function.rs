@@ -1,14 +1,28 @@
-use std::fmt;
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::fmt;
 
 use pest::iterators::{Pair, Pairs};
 
 use crate::{
-    compiler::{Compile, Compiler, CompilerResult},
-    parser::{parse_pairs, Parse, ParseResult, ParserError, Rule},
+    collections::HashSet,
+    compiler::{
+        types::{Check, Infer, Type, TypeChecker},
+        Compile, Compiler, CompilerError, CompilerResult, Instruction,
+    },
+    parser::{expect_rule, parse_pairs, Parse, ParseResult, ParserError, ParserErrorKind, Rule},
 };
 
-use super::{expression::Expression, statement::Statement};
+use super::{
+    annotation::TypeAnnotation,
+    expression::Expression,
+    statement::{self, Statement},
+    value::Value,
+};
 
+#[derive(PartialEq)]
 pub struct ReturnStatement {
     expression: Option<Expression>,
 }
@@ -24,17 +38,20 @@ impl fmt::Debug for ReturnStatement {
 }
 
 impl fmt::Display for ReturnStatement {
-    fn fmt(&self, _f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        todo!()
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.expression {
+            Some(expression) => write!(f, "return {expression};"),
+            None => write!(f, "return;"),
+        }
     }
 }
 
 impl Parse<'_> for ReturnStatement {
     fn parse(pair: Pair<'_, Rule>) -> Result<Self, ParserError> {
-        matches!(pair.as_rule(), Rule::return_statement);
+        expect_rule(&pair, Rule::return_statement)?;
         let mut inner = pair.into_inner();
 
-        matches!(inner.next().unwrap().as_rule(), Rule::k_return);
+        expect_rule(&inner.next().unwrap(), Rule::k_return)?;
         if let Some(expr) = inner.next() {
             Ok(Self {
                 expression: Some(Expression::parse(expr)?),
@@ -45,15 +62,38 @@ impl Parse<'_> for ReturnStatement {
     }
 }
 
+impl Check for ReturnStatement {
+    fn check(&self, checker: &mut TypeChecker) {
+        if let Some(expression) = &self.expression {
+            expression.infer(checker);
+        }
+    }
+}
+
 impl Compile for ReturnStatement {
-    fn compile(&self, _compiler: &mut Compiler) -> CompilerResult<()> {
-        todo!()
+    fn compile(&self, compiler: &mut Compiler) -> CompilerResult<()> {
+        if !compiler.in_function() {
+            return Err(CompilerError::ReturnOutsideFunction);
+        }
+        match &self.expression {
+            // Returning the result of calling the function itself doesn't
+            // need a new frame
+            Some(Expression::Call(call)) if call.is_recursive(compiler) => {
+                return call.compile_tail_call(compiler);
+            }
+            Some(expression) => expression.compile(compiler)?,
+            None => Value::Null.compile(compiler)?,
+        }
+        compiler.emit(Instruction::Return);
+        Ok(())
     }
 }
 
+#[derive(PartialEq)]
 pub struct FunctionStatement {
     name: String,
     args: Vec<String>,
+    annotations: Vec<Option<TypeAnnotation>>,
     body: Vec<Statement>,
 }
 
@@ -64,13 +104,27 @@ impl fmt::Debug for FunctionStatement {
         if !self.args.is_empty() {
             debug.field("args", &self.args);
         }
+        if self.annotations.iter().any(Option::is_some) {
+            debug.field("annotations", &self.annotations);
+        }
         debug.field("body", &self.body).finish()
     }
 }
 
 impl fmt::Display for FunctionStatement {
-    fn fmt(&self, _f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        todo!()
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "fn {}(", self.name)?;
+        for (i, (arg, annotation)) in self.args.iter().zip(&self.annotations).enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{arg}")?;
+            if let Some(annotation) = annotation {
+                write!(f, ": {annotation}")?;
+            }
+        }
+        write!(f, ") ")?;
+        statement::write_block(f, &self.body)
     }
 }
 
@@ -90,37 +144,80 @@ where
     Ok(out)
 }
 
+/// Reject parameter lists that name the same parameter more than once.
+fn check_duplicate_args(pairs: Pairs<Rule>) -> ParseResult<()> {
+    let mut seen = HashSet::new();
+    for parameter in pairs {
+        let pair = parameter.into_inner().next().unwrap();
+        if !seen.insert(pair.as_str()) {
+            let kind = ParserErrorKind::DuplicateParameter(pair.as_str().to_string());
+            return Err(ParserError::for_pair(pair, kind));
+        }
+    }
+    Ok(())
+}
+
 impl<'a> Parse<'a> for FunctionStatement {
     fn parse(pair: Pair<'a, Rule>) -> Result<Self, ParserError> {
-        matches!(pair.as_rule(), Rule::function_statement);
+        expect_rule(&pair, Rule::function_statement)?;
         let mut inner = pair.into_inner();
 
-        matches!(inner.next().unwrap().as_rule(), Rule::k_fn);
+        expect_rule(&inner.next().unwrap(), Rule::k_fn)?;
 
         let name_pair = inner.next().unwrap();
         let name = name_pair.as_str().to_string();
 
         let args_pairs = inner.next().unwrap().into_inner();
-        let args = pairs_to_boxed_slice(args_pairs, |s| Ok(s.as_str().to_string()))?;
+        check_duplicate_args(args_pairs.clone())?;
+        let parameters = pairs_to_boxed_slice(args_pairs, |parameter| {
+            let mut inner = parameter.into_inner();
+            let arg = inner.next().unwrap().as_str().to_string();
+            let annotation = inner.next().map(TypeAnnotation::parse).transpose()?;
+            Ok((arg, annotation))
+        })?;
+        let (args, annotations) = parameters.into_iter().unzip();
 
         let body_pairs = inner.next().unwrap().into_inner();
         let body = parse_pairs(body_pairs)?;
 
-        Ok(Self { name, args, body })
+        Ok(Self {
+            name,
+            args,
+            annotations,
+            body,
+        })
+    }
+}
+
+impl Check for FunctionStatement {
+    fn check(&self, checker: &mut TypeChecker) {
+        checker.enter_scope();
+        for (arg, annotation) in self.args.iter().zip(&self.annotations) {
+            match annotation {
+                Some(annotation) => checker.declare_annotated(arg, annotation.ty(), None),
+                None => checker.declare(arg, Type::Unknown),
+            }
+        }
+        checker.check_block(&self.body);
+        checker.exit_scope();
     }
 }
 
 impl Compile for FunctionStatement {
-    fn compile(&self, _compiler: &mut Compiler) -> CompilerResult<()> {
-        todo!()
+    fn compile(&self, compiler: &mut Compiler) -> CompilerResult<()> {
+        compiler.compile_function(&self.name, &self.args, &self.body)
     }
 }
 
 #[cfg(test)]
 mod test {
-    use crate::parser::{self, ParseResult};
+    use crate::parser::{self, ParseResult, ParserErrorKind, Rule};
+
+    use crate::ast::annotation::TypeAnnotation;
 
-    use super::FunctionStatement;
+    use crate::ast::statement::Statement;
+
+    use super::{FunctionStatement, ReturnStatement};
 
     fn parse_function(input: &str) -> ParseResult<()> {
         parser::parse_statement::<FunctionStatement>(input)?;
@@ -136,6 +233,50 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn test_multiline_return() -> ParseResult<()> {
+        // Statements end at `;` rather than at line breaks, so a line break
+        // after `return` doesn't end the statement
+        let returned = |input: &str| -> ParseResult<Vec<Option<String>>> {
+            let function = parser::parse_statement::<FunctionStatement>(input)?;
+            let returned = function.body.iter().map(|statement| match statement {
+                Statement::Return(ret) => ret.expression.as_ref().map(ToString::to_string),
+                statement => panic!("unexpected {statement}"),
+            });
+            Ok(returned.collect())
+        };
+        assert_eq!(returned("fn f() { return\n5; }")?, [Some("5".to_string())]);
+        assert_eq!(
+            returned("fn f(x) {\n    return\n\n        x + 1\n    ;\n}")?,
+            [Some("x + 1".to_string())]
+        );
+        assert_eq!(
+            returned("fn f() { return // nothing\n x; }")?,
+            [Some("x".to_string())]
+        );
+        assert_eq!(returned("fn f() { return; return\n; }")?, [None, None]);
+        parse_function("fn f() { return\n5 }").unwrap_err();
+        parse_function("fn f() { return 5\n6; }").unwrap_err();
+        Ok(())
+    }
+
+    #[test]
+    fn test_annotated_parameters() -> ParseResult<()> {
+        let function =
+            parser::parse_statement::<FunctionStatement>("fn scale(x: float, n) { return x; }")?;
+        assert_eq!(
+            function.annotations,
+            vec![Some(TypeAnnotation::Float), None]
+        );
+        assert_eq!(
+            function.to_string().lines().next(),
+            Some("fn scale(x: float, n) {")
+        );
+        parse_function("fn f(x: int, x: float) {}").unwrap_err();
+        parse_function("fn f(x:) {}").unwrap_err();
+        Ok(())
+    }
+
     #[test]
     fn test_wrong_function_statements() {
         parse_function("fn print(x) { print x; }").unwrap_err();
@@ -143,4 +284,22 @@ mod test {
         parse_function("fn add(x x x) {}").unwrap_err();
         parse_function("fn add(x, x x) {}").unwrap_err();
     }
+
+    #[test]
+    fn test_duplicate_function_parameters() {
+        for input in ["fn f(x, x) {}", "fn f(x, y, x) { return x; }"] {
+            let error = parse_function(input).unwrap_err();
+            assert!(
+                matches!(error.kind(), ParserErrorKind::DuplicateParameter(name) if name == "x"),
+                "{input}"
+            );
+        }
+        parse_function("fn f(x, xx, x_) {}").unwrap();
+    }
+
+    #[test]
+    fn test_mismatched_function_rule() {
+        parser::parse_statement::<FunctionStatement>("print 1;").unwrap_err();
+        parser::parse_rule::<ReturnStatement>(Rule::print_statement, "print 1;").unwrap_err();
+    }
 }
@@ -1,3 +1,5 @@
+use alloc::string::String;
+
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum IdentifierKind {
     Constant,
@@ -21,7 +23,7 @@ impl Identifier {
     pub fn new_var(ident: String) -> Self {
         Self {
             ident,
-            kind: IdentifierKind::Constant,
+            kind: IdentifierKind::Variable,
         }
     }
 
@@ -33,3 +35,19 @@ impl Identifier {
         self.kind == IdentifierKind::Variable
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::Identifier;
+
+    #[test]
+    fn test_identifier_kind() {
+        let var = Identifier::new_var("x".into());
+        assert!(var.is_var());
+        assert!(!var.is_const());
+
+        let constant = Identifier::new_const("x".into());
+        assert!(constant.is_const());
+        assert!(!constant.is_var());
+    }
+}
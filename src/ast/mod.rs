@@ -1,3 +1,6 @@
+pub mod annotation;
+#[cfg(feature = "bigint")]
+pub mod bigint;
 pub mod expression;
 pub mod function;
 pub mod identifier;
@@ -1,37 +1,109 @@
-use std::fmt;
+use alloc::{borrow::ToOwned, string::String, vec::Vec};
+use core::fmt;
 
 use pest::iterators::Pair;
 
 use crate::{
     ast::{
-        expression::Expression,
+        annotation::TypeAnnotation,
+        expression::{binary::BinaryOperator, Expression},
         identifier::{Identifier, IdentifierKind},
+        value::Value,
     },
-    compiler::{Compile, Compiler, CompilerError, CompilerResult, Instruction},
-    parser::{Parse, ParserError, Rule},
+    compiler::{
+        symbol_table::Slot,
+        types::{Check, Infer, Type, TypeChecker},
+        Compile, Compiler, CompilerError, CompilerResult, Instruction,
+    },
+    parser::{expect_rule, Parse, ParserError, Rule},
 };
 
+/// Declaration of one or more identifiers sharing a `var` or `const`
+/// keyword, such as `var a = 1, b: int;`.
+#[derive(PartialEq)]
 pub struct DeclarationStatement {
+    declarations: Vec<Declarator>,
+}
+
+/// Single identifier of a declaration with its optional type annotation
+/// and initializer.
+#[derive(Debug, PartialEq)]
+struct Declarator {
     identifier: Identifier,
+    annotation: Option<TypeAnnotation>,
     initial_value: Option<Expression>,
 }
 
-impl Compile for DeclarationStatement {
+impl Declarator {
+    /// Compile the declaration, initializer is compiled before the identifier
+    /// is registered so it can't refer to itself, if registration fails its
+    /// instructions are discarded.
     fn compile(&self, compiler: &mut Compiler) -> CompilerResult<()> {
+        let start = compiler.place_label();
         if let Some(expr) = &self.initial_value {
             expr.compile(compiler)?;
         }
-        let idx = compiler.register(self.identifier.clone())?;
-        if self.initial_value.is_some() {
-            compiler.emit(Instruction::StoreSymbol(idx));
+        let slot = match compiler.register(self.identifier.clone()) {
+            Ok(slot) => slot,
+            Err(err) => {
+                compiler.discard_from(start);
+                return Err(err);
+            }
+        };
+        match (&self.initial_value, &self.annotation, slot) {
+            (Some(_), _, _) => compiler.emit(slot.store()),
+            // Annotated variables start with the zero value of their type
+            (None, Some(annotation), _) => {
+                annotation.zero_value().compile(compiler)?;
+                compiler.emit(slot.store());
+            }
+            // Local slots are reused so they must be reset to `null` explicitly
+            (None, None, Slot::Local(_)) => {
+                Value::Null.compile(compiler)?;
+                compiler.emit(slot.store());
+            }
+            (None, None, Slot::Global(_)) => {}
+        }
+        // Constants bound to literals are loaded as the literal instead
+        if let (true, Some(Expression::Value(value))) =
+            (self.identifier.is_const(), &self.initial_value)
+        {
+            compiler.inline_constant(&self.identifier.ident, value.clone());
+        }
+        Ok(())
+    }
+}
+
+impl Compile for DeclarationStatement {
+    fn compile(&self, compiler: &mut Compiler) -> CompilerResult<()> {
+        // Declarations are compiled in order so later initializers can refer
+        // to earlier identifiers
+        for declarator in &self.declarations {
+            declarator.compile(compiler)?;
         }
         Ok(())
     }
 }
 
+impl Check for DeclarationStatement {
+    fn check(&self, checker: &mut TypeChecker) {
+        for declarator in &self.declarations {
+            let ty = declarator
+                .initial_value
+                .as_ref()
+                .map(|expr| expr.infer(checker));
+            let ident = &declarator.identifier.ident;
+            match &declarator.annotation {
+                Some(annotation) => checker.declare_annotated(ident, annotation.ty(), ty),
+                None => checker.declare(ident, ty.unwrap_or(Type::Unknown)),
+            }
+        }
+    }
+}
+
 impl Parse<'_> for DeclarationStatement {
     fn parse(pair: Pair<'_, Rule>) -> Result<Self, ParserError> {
-        matches!(pair.as_rule(), Rule::declaration_statement);
+        expect_rule(&pair, Rule::declaration_statement)?;
         let mut inner = pair.into_inner();
 
         let kind_keyword = inner.next().unwrap();
@@ -41,93 +113,292 @@ impl Parse<'_> for DeclarationStatement {
             _ => unreachable!(),
         };
 
-        let ident_token = inner.next().unwrap();
-        let identifier = match ident_token.as_rule() {
-            Rule::identifier => {
-                let ident = String::from(ident_token.as_str());
-                Identifier { ident, kind }
-            }
-            _ => unreachable!(),
-        };
+        let mut declarations = Vec::new();
+        for declarator in inner {
+            let mut declarator = declarator.into_inner();
 
-        let initial_value = match inner.next() {
-            Some(token) => Some(Expression::parse(token)?),
-            None => None,
-        };
+            let ident_token = declarator.next().unwrap();
+            expect_rule(&ident_token, Rule::identifier)?;
+            let ident = String::from(ident_token.as_str());
 
-        Ok(DeclarationStatement {
-            identifier,
-            initial_value,
-        })
+            let mut token = declarator.next();
+            let annotation = match token.clone() {
+                Some(pair) if pair.as_rule() == Rule::type_annotation => {
+                    token = declarator.next();
+                    Some(TypeAnnotation::parse(pair)?)
+                }
+                _ => None,
+            };
+            let initial_value = match token {
+                Some(token) => Some(Expression::parse(token)?),
+                None => None,
+            };
+            declarations.push(Declarator {
+                identifier: Identifier { ident, kind },
+                annotation,
+                initial_value,
+            });
+        }
+
+        Ok(DeclarationStatement { declarations })
     }
 }
 
 impl fmt::Debug for DeclarationStatement {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let mut debug = f.debug_struct("DeclarationStatement");
-        debug.field("identifier", &self.identifier);
-        if let Some(initial) = &self.initial_value {
-            debug.field("initial_value", initial);
+        let mut debug = f.debug_list();
+        for declarator in &self.declarations {
+            match (&declarator.annotation, &declarator.initial_value) {
+                (None, Some(initial)) => debug.entry(&(&declarator.identifier, initial)),
+                (None, None) => debug.entry(&declarator.identifier),
+                (Some(_), _) => debug.entry(declarator),
+            };
         }
         debug.finish()
     }
 }
 
 impl fmt::Display for DeclarationStatement {
-    fn fmt(&self, _f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        todo!()
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let keyword = match self.declarations[0].identifier.kind {
+            IdentifierKind::Constant => "const",
+            IdentifierKind::Variable => "var",
+        };
+        write!(f, "{keyword} ")?;
+        for (i, declarator) in self.declarations.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}", declarator.identifier.ident)?;
+            if let Some(annotation) = &declarator.annotation {
+                write!(f, ": {annotation}")?;
+            }
+            if let Some(initial) = &declarator.initial_value {
+                write!(f, " = {initial}")?;
+            }
+        }
+        write!(f, ";")
+    }
+}
+
+/// Left-hand side of an assignment.
+#[derive(Debug, PartialEq)]
+pub enum AssignmentTarget {
+    Identifier(String),
+    /// Element of an array such as `arr[i]`, the array is mutated in place.
+    Index {
+        identifier: String,
+        index: Expression,
+    },
+}
+
+impl AssignmentTarget {
+    fn identifier(&self) -> &str {
+        match self {
+            AssignmentTarget::Identifier(identifier)
+            | AssignmentTarget::Index { identifier, .. } => identifier,
+        }
+    }
+}
+
+impl fmt::Display for AssignmentTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AssignmentTarget::Identifier(identifier) => write!(f, "{identifier}"),
+            AssignmentTarget::Index { identifier, index } => write!(f, "{identifier}[{index}]"),
+        }
     }
 }
 
-#[derive(Debug)]
+/// Assignment to a variable such as `x = 1;` or to an array element such as
+/// `arr[0] = 1;`, compound assignments such as `x += 1;` apply `operator` to
+/// the current value and `value`.
+#[derive(Debug, PartialEq)]
 pub struct AssignmentStatement {
-    identifier: String,
+    target: AssignmentTarget,
+    operator: Option<BinaryOperator>,
     value: Expression,
 }
 
 impl Compile for AssignmentStatement {
     fn compile(&self, compiler: &mut Compiler) -> CompilerResult<()> {
-        match compiler.get_identifier(&self.identifier) {
-            Some((IdentifierKind::Variable, idx)) => {
-                self.value.compile(compiler)?;
-                compiler.emit(Instruction::StoreSymbol(idx));
+        let identifier = self.target.identifier();
+        if let AssignmentTarget::Index { index, .. } = &self.target {
+            // Elements of arrays bound to constants can still be replaced
+            let (_, slot) = compiler
+                .get_identifier(identifier)
+                .ok_or_else(|| CompilerError::UndefinedIdentifer(identifier.to_owned()))?;
+            compiler.emit(slot.load());
+            index.compile(compiler)?;
+            self.value.compile(compiler)?;
+            compiler.emit(Instruction::SetIndex);
+            return Ok(());
+        }
+        match compiler.get_identifier(identifier) {
+            Some((IdentifierKind::Variable, slot)) => {
+                if let Some(operator) = self.operator {
+                    compiler.emit(slot.load());
+                    self.value.compile(compiler)?;
+                    compiler.emit(operator.instruction());
+                } else {
+                    self.value.compile(compiler)?;
+                }
+                compiler.emit(slot.store());
                 Ok(())
             }
             Some((IdentifierKind::Constant, _)) => Err(CompilerError::AssignmentToConst),
-            None => Err(CompilerError::UndefinedIdentifer(
-                self.identifier.to_owned(),
-            )),
+            None if compiler.implicit_var() && self.operator.is_none() => {
+                // Declared after the value like `var`, so it can't refer to itself
+                self.value.compile(compiler)?;
+                let slot = compiler.register_var(identifier)?;
+                compiler.emit(slot.store());
+                Ok(())
+            }
+            None => Err(CompilerError::UndefinedIdentifer(identifier.to_owned())),
         }
     }
 }
 
+impl Check for AssignmentStatement {
+    fn check(&self, checker: &mut TypeChecker) {
+        let identifier = match &self.target {
+            AssignmentTarget::Identifier(identifier) => identifier,
+            AssignmentTarget::Index { index, .. } => {
+                index.infer(checker);
+                self.value.infer(checker);
+                return;
+            }
+        };
+        let mut ty = self.value.infer(checker);
+        if let Some(operator) = self.operator {
+            let current = checker.lookup(identifier);
+            ty = checker.infer_binary(operator, current, ty);
+        }
+        checker.assign(identifier, ty);
+    }
+}
+
+/// Parses both `assignment_statement` and the bare `assignment` used by the
+/// step of a C-style `for`.
 impl Parse<'_> for AssignmentStatement {
     fn parse(pair: Pair<'_, Rule>) -> Result<Self, ParserError> {
-        matches!(pair.as_rule(), Rule::assignment_statement);
+        let pair = match pair.as_rule() {
+            Rule::assignment_statement => pair.into_inner().next().unwrap(),
+            _ => pair,
+        };
+        expect_rule(&pair, Rule::assignment)?;
         let mut inner = pair.into_inner();
 
-        let identifier_token = inner.next().unwrap();
-        matches!(identifier_token.as_rule(), Rule::identifier);
-        let identifier = String::from(identifier_token.as_str());
+        let target_token = inner.next().unwrap();
+        let target = match target_token.as_rule() {
+            Rule::index_target => {
+                let mut target_inner = target_token.into_inner();
+                let identifier = String::from(target_inner.next().unwrap().as_str());
+                let index = Expression::parse(target_inner.next().unwrap())?;
+                AssignmentTarget::Index { identifier, index }
+            }
+            _ => {
+                expect_rule(&target_token, Rule::identifier)?;
+                AssignmentTarget::Identifier(String::from(target_token.as_str()))
+            }
+        };
 
-        let expression = inner.next().unwrap();
-        let value = Expression::parse(expression)?;
+        let mut token = inner.next().unwrap();
+        let operator = match token.as_rule() {
+            Rule::assign_add => Some(BinaryOperator::Add),
+            Rule::assign_subtract => Some(BinaryOperator::Subtract),
+            Rule::assign_multiply => Some(BinaryOperator::Multiply),
+            Rule::assign_divide => Some(BinaryOperator::Divide),
+            _ => None,
+        };
+        if operator.is_some() {
+            token = inner.next().unwrap();
+        }
+        let value = Expression::parse(token)?;
 
-        Ok(AssignmentStatement { identifier, value })
+        Ok(AssignmentStatement {
+            target,
+            operator,
+            value,
+        })
     }
 }
 
 impl fmt::Display for AssignmentStatement {
-    fn fmt(&self, _f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        todo!()
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.operator {
+            Some(operator) => write!(f, "{} {operator}= {};", self.target, self.value),
+            None => write!(f, "{} = {};", self.target, self.value),
+        }
+    }
+}
+
+/// Postfix increment or decrement of a variable such as `i++;`, which is
+/// `i += 1;` or `i -= 1;` for `i--;`.
+#[derive(Debug, PartialEq)]
+pub struct IncrementStatement {
+    identifier: String,
+    delta: i64,
+}
+
+impl Compile for IncrementStatement {
+    fn compile(&self, compiler: &mut Compiler) -> CompilerResult<()> {
+        match compiler.get_identifier(&self.identifier) {
+            Some((IdentifierKind::Variable, slot)) => {
+                compiler.emit(slot.load());
+                Value::Integer(self.delta).compile(compiler)?;
+                compiler.emit(Instruction::BinaryAdd);
+                compiler.emit(slot.store());
+                Ok(())
+            }
+            Some((IdentifierKind::Constant, _)) => Err(CompilerError::AssignmentToConst),
+            None => Err(CompilerError::UndefinedIdentifer(self.identifier.clone())),
+        }
+    }
+}
+
+impl Check for IncrementStatement {
+    fn check(&self, checker: &mut TypeChecker) {
+        let current = checker.lookup(&self.identifier);
+        let ty = checker.infer_binary(BinaryOperator::Add, current, Type::Int);
+        checker.assign(&self.identifier, ty);
+    }
+}
+
+/// Parses both `increment_statement` and the bare `increment` used by the
+/// step of a C-style `for`.
+impl Parse<'_> for IncrementStatement {
+    fn parse(pair: Pair<'_, Rule>) -> Result<Self, ParserError> {
+        let pair = match pair.as_rule() {
+            Rule::increment_statement => pair.into_inner().next().unwrap(),
+            _ => pair,
+        };
+        expect_rule(&pair, Rule::increment)?;
+        let mut inner = pair.into_inner();
+        let identifier = String::from(inner.next().unwrap().as_str());
+        let delta = match inner.next().unwrap().as_rule() {
+            Rule::increment_op => 1,
+            _ => -1,
+        };
+        Ok(IncrementStatement { identifier, delta })
+    }
+}
+
+impl fmt::Display for IncrementStatement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let operator = if self.delta > 0 { "++" } else { "--" };
+        write!(f, "{}{operator};", self.identifier)
     }
 }
 
 #[cfg(test)]
 mod test {
-    use crate::parser::{self, ParseResult};
+    use crate::{
+        compiler::{Compile, Compiler, CompilerError, CompilerResult},
+        parser::{self, ParseResult},
+    };
 
-    use super::{AssignmentStatement, DeclarationStatement};
+    use super::{AssignmentStatement, DeclarationStatement, IncrementStatement};
 
     fn parse_declaration(input: &str) -> ParseResult<()> {
         parser::parse_statement::<DeclarationStatement>(input)?;
@@ -144,6 +415,20 @@ mod test {
         parse_declaration("var myVar;")?;
         parse_declaration("var myVar = 2;")?;
         parse_declaration("const myConst = 2;")?;
+        parse_declaration("var a = 1, b, c = a + 1;")?;
+        parse_declaration("const a = 1, b = 2;")?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_annotated_declaration() -> ParseResult<()> {
+        let declaration =
+            parser::parse_statement::<DeclarationStatement>("var x: int = 5, y, z:float;")?;
+        assert_eq!(declaration.to_string(), "var x: int = 5, y, z: float;");
+        parse_declaration("var x: int;")?;
+        parse_declaration("const s: string = x;")?;
+        parse_declaration("var x: = 5;").unwrap_err();
+        parse_declaration("const x: int;").unwrap_err();
         Ok(())
     }
 
@@ -152,6 +437,100 @@ mod test {
         parse_assignment("myVar = 120;")?;
         parse_assignment("myVar = true;")?;
         parse_assignment("myVar = 12 * 12 - 12;")?;
+        parse_assignment("myVar += 1;")?;
+        parse_assignment("myVar -= 2 * 3;")?;
+        parse_assignment("myVar *= 2;")?;
+        parse_assignment("myVar /= 2;")?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_index_assignment() -> ParseResult<()> {
+        let statement = parser::parse_statement::<AssignmentStatement>("arr [i+1]=[2];")?;
+        assert_eq!(statement.to_string(), "arr[i + 1] = [2];");
+        parse_assignment("arr[0] = arr;")?;
+        parse_assignment("arr[0] += 1;").unwrap_err();
+        parse_assignment("arr[] = 1;").unwrap_err();
+        parse_assignment("[1][0] = 1;").unwrap_err();
+        Ok(())
+    }
+
+    #[test]
+    fn test_compound_assignment() -> CompilerResult<()> {
+        let statement = parser::parse_statement::<AssignmentStatement>("x+=2;").unwrap();
+        assert_eq!(statement.to_string(), "x += 2;");
+        parse_assignment("x + = 2;").unwrap_err();
+        parse_assignment("x %= 2;").unwrap_err();
+
+        let mut compiler = Compiler::new();
+        compiler.register_var("x")?;
+        statement.compile(&mut compiler)?;
+        let (code_block, _) = compiler.finish()?;
+        let instructions = code_block
+            .instructions
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            instructions,
+            [
+                "LoadSymbol(0)",
+                "LoadValue(0)",
+                "BinaryAdd",
+                "StoreSymbol(0)"
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_increment_statement() -> CompilerResult<()> {
+        let increment = parser::parse_statement::<IncrementStatement>("i ++;").unwrap();
+        assert_eq!(increment.to_string(), "i++;");
+        let decrement = parser::parse_statement::<IncrementStatement>("i--;").unwrap();
+        assert_eq!(decrement.to_string(), "i--;");
+        for input in ["i++", "++i;", "1++;", "i + +;", "a[0]++;", "i++ + 1;"] {
+            parser::parse_statement::<IncrementStatement>(input).unwrap_err();
+        }
+        // Increments aren't expressions
+        parser::parse("var x = i++;").unwrap_err();
+        assert_eq!(parser::parse("i--1;").unwrap()[0].to_string(), "i - -1;");
+
+        let mut compiler = Compiler::new();
+        for statement in parser::parse("var i = 0; i++; i--;").unwrap() {
+            statement.compile(&mut compiler)?;
+        }
+        let (code_block, _) = compiler.finish()?;
+        let instructions = code_block
+            .instructions
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            instructions[2..],
+            [
+                "LoadSymbol(0)",
+                "LoadValue(1)",
+                "BinaryAdd",
+                "StoreSymbol(0)",
+                "LoadSymbol(0)",
+                "LoadValue(2)",
+                "BinaryAdd",
+                "StoreSymbol(0)"
+            ]
+        );
+
+        let mut compiler = Compiler::new();
+        let statements = parser::parse("const c = 0; c++;").unwrap();
+        statements[0].compile(&mut compiler)?;
+        assert!(matches!(
+            statements[1].compile(&mut compiler),
+            Err(CompilerError::AssignmentToConst)
+        ));
+        assert!(matches!(
+            Compiler::new().compile_block(&[decrement.into()]),
+            Err(CompilerError::UndefinedIdentifer(_))
+        ));
         Ok(())
     }
 
@@ -164,5 +543,41 @@ mod test {
         parse_declaration("const const = 2;").unwrap_err();
         parse_declaration("const var = 2;").unwrap_err();
         parse_declaration("const if = 2;").unwrap_err();
+        parse_declaration("const a, b;").unwrap_err();
+        parse_declaration("const a = 1, b;").unwrap_err();
+        parse_declaration("var a = 1,;").unwrap_err();
+        parse_declaration("var a = 1 b = 2;").unwrap_err();
+    }
+
+    #[test]
+    fn test_multiple_declarations() -> CompilerResult<()> {
+        let statement =
+            parser::parse_statement::<DeclarationStatement>("var a=1,b , c=a;").unwrap();
+        assert_eq!(statement.to_string(), "var a = 1, b, c = a;");
+
+        let mut compiler = Compiler::new();
+        statement.compile(&mut compiler)?;
+        let (code_block, _) = compiler.finish()?;
+        let instructions = code_block
+            .instructions
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            instructions,
+            [
+                "LoadValue(0)",
+                "StoreSymbol(0)",
+                "LoadSymbol(0)",
+                "StoreSymbol(2)"
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_mismatched_declaration_rule() {
+        parse_declaration("myVar = 2;").unwrap_err();
+        parse_assignment("var myVar = 2;").unwrap_err();
     }
 }
@@ -1,34 +1,83 @@
-use std::fmt;
+use alloc::{
+    boxed::Box,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::fmt;
 
 use pest::iterators::Pair;
 
 use crate::{
     ast::expression::Expression,
-    compiler::{Compile, Compiler, CompilerResult},
+    compiler::{
+        types::{Check, Infer, Type, TypeChecker},
+        BlockType, Compile, Compiler, CompilerResult, Instruction,
+    },
     parser::{self, Parse, ParserError, Rule},
 };
 
-use super::Statement;
+use super::{
+    declare_assign_statement::{AssignmentStatement, IncrementStatement},
+    write_block, ExpressionStatement, Statement,
+};
 
-#[derive(Debug)]
+/// Loop over the elements of an iterable such as `for x in [1, 2] { }`.
+#[derive(Debug, PartialEq)]
 pub struct ForStatement {
     identifier: String,
     iterator: Expression,
     body: Vec<Statement>,
 }
 
+impl ForStatement {
+    fn compile_loop(&self, compiler: &mut Compiler) -> CompilerResult<()> {
+        compiler.enter_for();
+
+        let next_label = compiler.place_label();
+        let exit = compiler.emit_jump(Instruction::IterNext(0));
+        compiler.target_jump_on_exit(BlockType::For, exit);
+        let slot = compiler.register_var(&self.identifier)?;
+        compiler.emit(slot.store());
+
+        compiler.compile_block(&self.body)?;
+        compiler.target_continue_jumps()?;
+        compiler.emit(Instruction::Jump(next_label.target()?));
+        compiler.exit_for()
+    }
+}
+
 impl Compile for ForStatement {
-    fn compile(&self, _compiler: &mut Compiler) -> CompilerResult<()> {
-        todo!()
+    fn compile(&self, compiler: &mut Compiler) -> CompilerResult<()> {
+        self.iterator.compile(compiler)?;
+        compiler.emit(Instruction::GetIter);
+        // Loop variable is scoped to the loop
+        compiler.enter_scope();
+        let result = self.compile_loop(compiler);
+        compiler.exit_scope();
+        result?;
+        // Both exhausting the iterator and `break` exit here, the iterated
+        // array and the index are discarded
+        compiler.emit(Instruction::PopN(2));
+        Ok(())
+    }
+}
+
+impl Check for ForStatement {
+    fn check(&self, checker: &mut TypeChecker) {
+        self.iterator.infer(checker);
+        checker.enter_scope();
+        checker.declare(&self.identifier, Type::Unknown);
+        checker.check_block(&self.body);
+        checker.exit_scope();
     }
 }
 
 impl Parse<'_> for ForStatement {
     fn parse(pair: Pair<'_, Rule>) -> Result<Self, ParserError> {
-        matches!(pair.as_rule(), Rule::for_statement);
+        parser::expect_rule(&pair, Rule::for_statement)?;
         let mut inner = pair.into_inner();
 
-        matches!(inner.next().unwrap().as_rule(), Rule::k_for);
+        parser::expect_rule(&inner.next().unwrap(), Rule::k_for)?;
 
         let identifier_token = inner.next().unwrap();
         let identifier = match identifier_token.as_rule() {
@@ -36,7 +85,7 @@ impl Parse<'_> for ForStatement {
             _ => unreachable!(),
         };
 
-        matches!(inner.next().unwrap().as_rule(), Rule::k_in);
+        parser::expect_rule(&inner.next().unwrap(), Rule::k_in)?;
         let expression = inner.next().unwrap();
         let iterator = Expression::parse(expression)?;
 
@@ -52,8 +101,134 @@ impl Parse<'_> for ForStatement {
 }
 
 impl fmt::Display for ForStatement {
-    fn fmt(&self, _f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        todo!()
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "for {} in {} ", self.identifier, self.iterator)?;
+        write_block(f, &self.body)
+    }
+}
+
+/// Loop with explicit `init`, `condition` and `step` parts such as
+/// `for (var i = 0; i < 3; i += 1) { }`, a missing condition is `true`.
+#[derive(Debug, PartialEq)]
+pub struct CStyleForStatement {
+    init: Option<Box<Statement>>,
+    condition: Option<Expression>,
+    step: Option<Box<Statement>>,
+    body: Vec<Statement>,
+}
+
+impl CStyleForStatement {
+    fn compile_loop(&self, compiler: &mut Compiler) -> CompilerResult<()> {
+        if let Some(init) = &self.init {
+            init.compile(compiler)?;
+        }
+        compiler.enter_for();
+
+        let condition_label = compiler.place_label();
+        if let Some(condition) = &self.condition {
+            let exit = condition.compile_condition(compiler)?;
+            compiler.target_jump_on_exit(BlockType::For, exit);
+        }
+
+        compiler.compile_block(&self.body)?;
+        // `continue` skips the rest of the body but not the step
+        compiler.target_continue_jumps()?;
+        if let Some(step) = &self.step {
+            step.compile(compiler)?;
+        }
+        compiler.emit(Instruction::Jump(condition_label.target()?));
+        compiler.exit_for()
+    }
+}
+
+impl Compile for CStyleForStatement {
+    fn compile(&self, compiler: &mut Compiler) -> CompilerResult<()> {
+        // Identifiers declared by `init` are scoped to the loop
+        compiler.enter_scope();
+        let result = self.compile_loop(compiler);
+        compiler.exit_scope();
+        result
+    }
+}
+
+impl Check for CStyleForStatement {
+    fn check(&self, checker: &mut TypeChecker) {
+        checker.enter_scope();
+        if let Some(init) = &self.init {
+            init.check(checker);
+        }
+        if let Some(condition) = &self.condition {
+            let condition = condition.infer(checker);
+            checker.check_condition(condition);
+        }
+        checker.check_block(&self.body);
+        if let Some(step) = &self.step {
+            step.check(checker);
+        }
+        checker.exit_scope();
+    }
+}
+
+impl Parse<'_> for CStyleForStatement {
+    fn parse(pair: Pair<'_, Rule>) -> Result<Self, ParserError> {
+        parser::expect_rule(&pair, Rule::c_for_statement)?;
+        let mut inner = pair.into_inner();
+
+        parser::expect_rule(&inner.next().unwrap(), Rule::k_for)?;
+
+        let init = match inner.next().unwrap().into_inner().next() {
+            Some(statement) => Some(Box::new(Statement::parse(statement)?)),
+            None => None,
+        };
+
+        let condition = match inner.next().unwrap().into_inner().next() {
+            Some(expression) => Some(Expression::parse(expression)?),
+            None => None,
+        };
+
+        let step = match inner.next().unwrap().into_inner().next() {
+            Some(step) if step.as_rule() == Rule::assignment => {
+                Some(Box::new(AssignmentStatement::parse(step)?.into()))
+            }
+            Some(step) if step.as_rule() == Rule::increment => {
+                Some(Box::new(IncrementStatement::parse(step)?.into()))
+            }
+            Some(step) => {
+                let expression = Expression::parse(step)?;
+                Some(Box::new(ExpressionStatement::from(expression).into()))
+            }
+            None => None,
+        };
+
+        let statement_pairs = inner.next().unwrap().into_inner();
+        let body = parser::parse_pairs(statement_pairs)?;
+
+        Ok(CStyleForStatement {
+            init,
+            condition,
+            step,
+            body,
+        })
+    }
+}
+
+impl fmt::Display for CStyleForStatement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "for (")?;
+        match &self.init {
+            Some(init) => write!(f, "{init}")?,
+            None => write!(f, ";")?,
+        }
+        if let Some(condition) = &self.condition {
+            write!(f, " {condition}")?;
+        }
+        write!(f, ";")?;
+        // Step is a statement but isn't terminated by `;`
+        if let Some(step) = &self.step {
+            write!(f, " {}", step.to_string().trim_end_matches(';'))?;
+        }
+        write!(f, ") ")?;
+        write_block(f, &self.body)
     }
 }
 
@@ -61,13 +236,38 @@ impl fmt::Display for ForStatement {
 mod test {
     use crate::parser::{self, ParseResult};
 
-    use super::ForStatement;
+    use super::{CStyleForStatement, ForStatement};
 
     fn parse_for(input: &str) -> ParseResult<()> {
         parser::parse_statement::<ForStatement>(input)?;
         Ok(())
     }
 
+    fn parse_c_for(input: &str) -> ParseResult<String> {
+        let statement = parser::parse_statement::<CStyleForStatement>(input)?;
+        Ok(statement.to_string())
+    }
+
+    #[test]
+    fn test_c_style_for_statement() -> ParseResult<()> {
+        assert_eq!(
+            parse_c_for("for (var i = 0; i < 3; i += 1) { print i; }")?,
+            "for (var i = 0; i < 3; i += 1) {\n    print i;\n}"
+        );
+        assert_eq!(
+            parse_c_for("for (;;) { break; }")?,
+            "for (;;) {\n    break;\n}"
+        );
+        assert_eq!(parse_c_for("for (i = 0;;i) {}")?, "for (i = 0;; i) {}");
+        assert_eq!(parse_c_for("for (; true ;) {}")?, "for (; true;) {}");
+        assert_eq!(parse_c_for("for (;; i ++) {}")?, "for (;; i++) {}");
+        parse_c_for("for (;) {}").unwrap_err();
+        parse_c_for("for (;;;) {}").unwrap_err();
+        parse_c_for("for (;; i += 1;) {}").unwrap_err();
+        parse_c_for("for ;; {}").unwrap_err();
+        Ok(())
+    }
+
     #[test]
     fn test_for_statement() -> ParseResult<()> {
         parse_for("for i in 2 {}")?;
@@ -87,4 +287,9 @@ mod test {
         parse_for("for i in 2 }").unwrap_err();
         parse_for("for i in 2 {").unwrap_err();
     }
+
+    #[test]
+    fn test_mismatched_for_rule() {
+        parse_for("while true {}").unwrap_err();
+    }
 }
@@ -1,15 +1,20 @@
-use std::fmt;
+use alloc::vec::Vec;
+use core::fmt;
 
 use pest::iterators::Pair;
 
 use crate::{
     ast::expression::Expression,
-    compiler::{BlockType, Compile, Compiler, CompilerResult},
+    compiler::{
+        types::{Check, Infer, TypeChecker},
+        BlockType, Compile, Compiler, CompilerResult,
+    },
     parser::{self, Parse, ParserError, Rule},
 };
 
-use super::Statement;
+use super::{write_block, Statement};
 
+#[derive(Debug, PartialEq)]
 pub struct ConditionalStatement {
     condition: Expression,
     statements: Vec<Statement>,
@@ -17,18 +22,30 @@ pub struct ConditionalStatement {
 
 impl Compile for ConditionalStatement {
     fn compile(&self, compiler: &mut Compiler) -> CompilerResult<()> {
-        self.condition.compile(compiler)?;
-        let condition_failed = compiler.emit_untargeted_jump();
-        for statement in &self.statements {
-            statement.compile(compiler)?;
-        }
+        let condition_failed = self.condition.compile_condition(compiler)?;
+        compiler.compile_block(&self.statements)?;
         let exit = compiler.emit_untargeted_jump();
         compiler.target_jump_on_exit(BlockType::If, exit);
-        compiler.target_jump(condition_failed);
-        Ok(())
+        compiler.target_jump(condition_failed)
+    }
+}
+
+impl Check for ConditionalStatement {
+    fn check(&self, checker: &mut TypeChecker) {
+        let condition = self.condition.infer(checker);
+        checker.check_condition(condition);
+        checker.check_block(&self.statements);
+    }
+}
+
+impl fmt::Display for ConditionalStatement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ", self.condition)?;
+        write_block(f, &self.statements)
     }
 }
 
+#[derive(PartialEq)]
 pub struct IfStatement {
     if_statement: ConditionalStatement,
     else_if_statements: Vec<ElseIfStatement>,
@@ -52,19 +69,26 @@ impl fmt::Debug for IfStatement {
 }
 
 impl fmt::Display for IfStatement {
-    fn fmt(&self, _f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        todo!()
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "if {}", self.if_statement)?;
+        for else_if_statement in &self.else_if_statements {
+            write!(f, " {else_if_statement}")?;
+        }
+        if let Some(else_statement) = &self.else_statement {
+            write!(f, " {else_statement}")?;
+        }
+        Ok(())
     }
 }
 
 impl Parse<'_> for IfStatement {
     fn parse(pair: Pair<'_, Rule>) -> Result<Self, ParserError> {
-        matches!(pair.as_rule(), Rule::if_statement);
+        parser::expect_rule(&pair, Rule::if_statement)?;
         let mut inner = pair.into_inner();
 
         // If body
         let mut if_body = inner.next().unwrap().into_inner();
-        matches!(if_body.next().unwrap().as_rule(), Rule::k_if);
+        parser::expect_rule(&if_body.next().unwrap(), Rule::k_if)?;
 
         let expression = if_body.next().unwrap();
         let condition = Expression::parse(expression)?;
@@ -106,8 +130,19 @@ impl Compile for IfStatement {
         if let Some(ref else_statement) = self.else_statement {
             else_statement.compile(compiler)?;
         }
-        compiler.exit_if();
-        Ok(())
+        compiler.exit_if()
+    }
+}
+
+impl Check for IfStatement {
+    fn check(&self, checker: &mut TypeChecker) {
+        self.if_statement.check(checker);
+        for else_if_statement in &self.else_if_statements {
+            else_if_statement.0.check(checker);
+        }
+        if let Some(else_statement) = &self.else_statement {
+            checker.check_block(&else_statement.statements);
+        }
     }
 }
 
@@ -121,6 +156,7 @@ impl IfStatement {
     }
 }
 
+#[derive(PartialEq)]
 pub struct ElseIfStatement(ConditionalStatement);
 
 impl fmt::Debug for ElseIfStatement {
@@ -133,21 +169,24 @@ impl fmt::Debug for ElseIfStatement {
 }
 
 impl fmt::Display for ElseIfStatement {
-    fn fmt(&self, _f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        todo!()
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "else if {}", self.0)
     }
 }
 
 impl Parse<'_> for ElseIfStatement {
     fn parse(pair: Pair<'_, Rule>) -> Result<Self, ParserError> {
-        matches!(pair.as_rule(), Rule::else_if_body);
+        parser::expect_rule(&pair, Rule::else_if_body)?;
         let mut inner = pair.into_inner();
 
-        matches!(inner.next().unwrap().as_rule(), Rule::k_else);
-        matches!(inner.next().unwrap().as_rule(), Rule::k_if);
+        let keyword = inner.next().unwrap();
+        if keyword.as_rule() != Rule::k_elif {
+            parser::expect_rule(&keyword, Rule::k_else)?;
+            parser::expect_rule(&inner.next().unwrap(), Rule::k_if)?;
+        }
 
         let expression = inner.next().unwrap();
-        let condition = Expression::parse(expression).unwrap();
+        let condition = Expression::parse(expression)?;
 
         let statement_pairs = inner.next().unwrap().into_inner();
         let statements = parser::parse_pairs(statement_pairs)?;
@@ -165,22 +204,24 @@ impl Compile for ElseIfStatement {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub struct ElseStatement {
     statements: Vec<Statement>,
 }
 
 impl fmt::Display for ElseStatement {
-    fn fmt(&self, _f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        todo!()
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "else ")?;
+        write_block(f, &self.statements)
     }
 }
 
 impl Parse<'_> for ElseStatement {
     fn parse(pair: Pair<'_, Rule>) -> Result<Self, ParserError> {
+        parser::expect_rule(&pair, Rule::else_body)?;
         let mut inner = pair.into_inner();
 
-        matches!(inner.next().unwrap().as_rule(), Rule::k_else);
+        parser::expect_rule(&inner.next().unwrap(), Rule::k_else)?;
 
         let statement_pairs = inner.next().unwrap().into_inner();
         let statements = parser::parse_pairs(statement_pairs)?;
@@ -191,16 +232,13 @@ impl Parse<'_> for ElseStatement {
 
 impl Compile for ElseStatement {
     fn compile(&self, compiler: &mut Compiler) -> CompilerResult<()> {
-        for statement in &self.statements {
-            statement.compile(compiler)?;
-        }
-        Ok(())
+        compiler.compile_block(&self.statements)
     }
 }
 
 #[cfg(test)]
 mod test {
-    use crate::parser::{self, ParseResult};
+    use crate::parser::{self, ParseResult, Rule};
 
     use super::IfStatement;
 
@@ -218,6 +256,21 @@ mod test {
         parse_if("if false {} else if true {} else if true {} else if true {} ")?;
         parse_if("if false {} else if true {} else {}")?;
         parse_if("if false {} else {}")?;
+        parse_if("if false {} elif true {} elif true {} else {}")?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_elif_alias() -> ParseResult<()> {
+        let elif = parser::parse_statement::<IfStatement>("if c { print 1; } elif d {} else {}")?;
+        let else_if =
+            parser::parse_statement::<IfStatement>("if c { print 1; } else if d {} else {}")?;
+        assert_eq!(format!("{elif:?}"), format!("{else_if:?}"));
+        assert_eq!(elif.to_string(), else_if.to_string());
+
+        parse_if("if c {} elif {}").unwrap_err();
+        parse_if("if c {} elifd {}").unwrap_err();
+        parser::parse_rule::<IfStatement>(Rule::identifier, "elif").unwrap_err();
         Ok(())
     }
 
@@ -226,5 +279,13 @@ mod test {
         parse_if("if {}").unwrap_err();
         parse_if("if true print 2; }").unwrap_err();
         parse_if("if true { print 2;").unwrap_err();
+        // Conditions that fail to parse are errors in every branch
+        parse_if("if true {} else if 0x1_0000_0000_0000_0000 {}").unwrap_err();
+        parse_if("if true {} elif 0x1_0000_0000_0000_0000 {}").unwrap_err();
+    }
+
+    #[test]
+    fn test_mismatched_if_rule() {
+        parser::parse_rule::<IfStatement>(Rule::while_statement, "while true {}").unwrap_err();
     }
 }
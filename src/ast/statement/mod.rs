@@ -1,22 +1,27 @@
-use std::fmt;
+use alloc::{string::ToString, vec::Vec};
+use core::fmt;
 
 use pest::iterators::Pair;
 
 use crate::{
-    compiler::{Compile, Compiler, CompilerError, CompilerResult, Instruction},
+    compiler::{
+        types::{Check, Infer, Type, TypeChecker},
+        Compile, Compiler, CompilerError, CompilerResult, Instruction,
+    },
     parser::{self, Parse, ParserError, Rule},
 };
 
 use self::{
-    declare_assign_statement::{AssignmentStatement, DeclarationStatement},
-    for_statement::ForStatement,
+    declare_assign_statement::{AssignmentStatement, DeclarationStatement, IncrementStatement},
+    for_statement::{CStyleForStatement, ForStatement},
     if_statement::IfStatement,
-    while_statement::WhileStatement,
+    while_statement::{DoWhileStatement, WhileStatement},
 };
 
 use super::{
     expression::Expression,
     function::{FunctionStatement, ReturnStatement},
+    value::Value,
 };
 
 pub mod declare_assign_statement;
@@ -24,13 +29,17 @@ pub mod for_statement;
 pub mod if_statement;
 pub mod while_statement;
 
+#[derive(PartialEq)]
 pub enum Statement {
     Print(PrintStatement),
     If(IfStatement),
     Declaration(DeclarationStatement),
     Assignment(AssignmentStatement),
+    Increment(IncrementStatement),
     While(WhileStatement),
+    DoWhile(DoWhileStatement),
     For(ForStatement),
+    CStyleFor(CStyleForStatement),
     Block(BlockStatement),
     Continue(ContinueStatement),
     Break(BreakStatement),
@@ -63,18 +72,36 @@ impl From<AssignmentStatement> for Statement {
     }
 }
 
+impl From<IncrementStatement> for Statement {
+    fn from(s: IncrementStatement) -> Self {
+        Self::Increment(s)
+    }
+}
+
 impl From<WhileStatement> for Statement {
     fn from(s: WhileStatement) -> Self {
         Self::While(s)
     }
 }
 
+impl From<DoWhileStatement> for Statement {
+    fn from(s: DoWhileStatement) -> Self {
+        Self::DoWhile(s)
+    }
+}
+
 impl From<ForStatement> for Statement {
     fn from(s: ForStatement) -> Self {
         Self::For(s)
     }
 }
 
+impl From<CStyleForStatement> for Statement {
+    fn from(s: CStyleForStatement) -> Self {
+        Self::CStyleFor(s)
+    }
+}
+
 impl From<BlockStatement> for Statement {
     fn from(s: BlockStatement) -> Self {
         Self::Block(s)
@@ -113,14 +140,18 @@ impl From<ReturnStatement> for Statement {
 
 impl Compile for Statement {
     fn compile(&self, compiler: &mut Compiler) -> CompilerResult<()> {
+        compiler.check_strict(self)?;
         match self {
             Statement::Print(s) => s.compile(compiler),
             Statement::Block(s) => s.compile(compiler),
             Statement::If(s) => s.compile(compiler),
             Statement::Declaration(s) => s.compile(compiler),
             Statement::Assignment(s) => s.compile(compiler),
+            Statement::Increment(s) => s.compile(compiler),
             Statement::While(s) => s.compile(compiler),
+            Statement::DoWhile(s) => s.compile(compiler),
             Statement::For(s) => s.compile(compiler),
+            Statement::CStyleFor(s) => s.compile(compiler),
             Statement::Continue(s) => s.compile(compiler),
             Statement::Break(s) => s.compile(compiler),
             Statement::Expression(s) => s.compile(compiler),
@@ -130,6 +161,27 @@ impl Compile for Statement {
     }
 }
 
+impl Check for Statement {
+    fn check(&self, checker: &mut TypeChecker) {
+        match self {
+            Statement::Print(s) => s.check(checker),
+            Statement::Block(s) => s.check(checker),
+            Statement::If(s) => s.check(checker),
+            Statement::Declaration(s) => s.check(checker),
+            Statement::Assignment(s) => s.check(checker),
+            Statement::Increment(s) => s.check(checker),
+            Statement::While(s) => s.check(checker),
+            Statement::DoWhile(s) => s.check(checker),
+            Statement::For(s) => s.check(checker),
+            Statement::CStyleFor(s) => s.check(checker),
+            Statement::Expression(s) => s.check(checker),
+            Statement::Function(s) => s.check(checker),
+            Statement::Return(s) => s.check(checker),
+            Statement::Continue(_) | Statement::Break(_) => {}
+        }
+    }
+}
+
 impl Parse<'_> for Statement {
     fn parse(pair: Pair<'_, Rule>) -> Result<Self, ParserError> {
         let statement = match pair.as_rule() {
@@ -137,15 +189,18 @@ impl Parse<'_> for Statement {
             Rule::if_statement => IfStatement::parse(pair)?.into(),
             Rule::declaration_statement => DeclarationStatement::parse(pair)?.into(),
             Rule::assignment_statement => AssignmentStatement::parse(pair)?.into(),
+            Rule::increment_statement => IncrementStatement::parse(pair)?.into(),
             Rule::while_statement => WhileStatement::parse(pair)?.into(),
+            Rule::do_while_statement => DoWhileStatement::parse(pair)?.into(),
             Rule::for_statement => ForStatement::parse(pair)?.into(),
+            Rule::c_for_statement => CStyleForStatement::parse(pair)?.into(),
             Rule::block_statement => BlockStatement::parse(pair)?.into(),
             Rule::continue_statement => ContinueStatement::parse(pair)?.into(),
             Rule::break_statement => BreakStatement::parse(pair)?.into(),
             Rule::expression_statement => ExpressionStatement::parse(pair)?.into(),
             Rule::function_statement => FunctionStatement::parse(pair)?.into(),
             Rule::return_statement => ReturnStatement::parse(pair)?.into(),
-            _ => unreachable!(),
+            _ => return Err(parser::unexpected_rule(&pair)),
         };
         Ok(statement)
     }
@@ -159,8 +214,11 @@ impl fmt::Debug for Statement {
             Statement::If(s) => write!(f, "{s:?}"),
             Statement::Declaration(s) => write!(f, "{s:?}"),
             Statement::Assignment(s) => write!(f, "{s:?}"),
+            Statement::Increment(s) => write!(f, "{s:?}"),
             Statement::While(s) => write!(f, "{s:?}"),
+            Statement::DoWhile(s) => write!(f, "{s:?}"),
             Statement::For(s) => write!(f, "{s:?}"),
+            Statement::CStyleFor(s) => write!(f, "{s:?}"),
             Statement::Continue(s) => write!(f, "{s:?}"),
             Statement::Break(s) => write!(f, "{s:?}"),
             Statement::Expression(s) => write!(f, "{s:?}"),
@@ -178,8 +236,11 @@ impl fmt::Display for Statement {
             Statement::If(s) => write!(f, "{}", s),
             Statement::Declaration(s) => write!(f, "{}", s),
             Statement::Assignment(s) => write!(f, "{}", s),
+            Statement::Increment(s) => write!(f, "{}", s),
             Statement::While(s) => write!(f, "{}", s),
+            Statement::DoWhile(s) => write!(f, "{}", s),
             Statement::For(s) => write!(f, "{}", s),
+            Statement::CStyleFor(s) => write!(f, "{}", s),
             Statement::Continue(s) => write!(f, "{}", s),
             Statement::Break(s) => write!(f, "{}", s),
             Statement::Expression(s) => write!(f, "{}", s),
@@ -189,7 +250,22 @@ impl fmt::Display for Statement {
     }
 }
 
-#[derive(Debug)]
+/// Write `statements` as a brace delimited block, each statement is written on
+/// its own line and indented by four spaces.
+pub(crate) fn write_block(f: &mut fmt::Formatter<'_>, statements: &[Statement]) -> fmt::Result {
+    if statements.is_empty() {
+        return write!(f, "{{}}");
+    }
+    writeln!(f, "{{")?;
+    for statement in statements {
+        for line in statement.to_string().lines() {
+            writeln!(f, "    {line}")?;
+        }
+    }
+    write!(f, "}}")
+}
+
+#[derive(Debug, PartialEq)]
 pub struct PrintStatement {
     expression: Expression,
 }
@@ -202,12 +278,18 @@ impl Compile for PrintStatement {
     }
 }
 
+impl Check for PrintStatement {
+    fn check(&self, checker: &mut TypeChecker) {
+        self.expression.infer(checker);
+    }
+}
+
 impl Parse<'_> for PrintStatement {
     fn parse(pair: Pair<'_, Rule>) -> Result<Self, ParserError> {
-        matches!(pair.as_rule(), Rule::print_statement);
+        parser::expect_rule(&pair, Rule::print_statement)?;
 
         let mut inner = pair.into_inner();
-        matches!(inner.next().unwrap().as_rule(), Rule::k_print);
+        parser::expect_rule(&inner.next().unwrap(), Rule::k_print)?;
 
         let expression = Expression::parse(inner.next().unwrap())?;
         Ok(PrintStatement { expression })
@@ -215,40 +297,111 @@ impl Parse<'_> for PrintStatement {
 }
 
 impl fmt::Display for PrintStatement {
-    fn fmt(&self, _f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        todo!()
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "print {};", self.expression)
     }
 }
 
-#[derive(Debug)]
+/// Block of statements in its own scope such as `{ var x = 1; print x; }`,
+/// a trailing expression without `;` like `{ 1; 2; 3 }` is the block's value.
+#[derive(Debug, PartialEq)]
 pub struct BlockStatement {
     body: Vec<Statement>,
+    value: Option<Expression>,
 }
 
 impl Compile for BlockStatement {
     fn compile(&self, compiler: &mut Compiler) -> CompilerResult<()> {
-        for statement in &self.body {
-            statement.compile(compiler)?;
+        let start = compiler.place_label();
+        self.compile_scope(compiler)?;
+        // Value of a top-level block is kept in REPL mode like the value of
+        // a top-level expression statement
+        compiler.pop_unused_value(start);
+        Ok(())
+    }
+}
+
+impl Check for BlockStatement {
+    fn check(&self, checker: &mut TypeChecker) {
+        self.infer_value(checker);
+    }
+}
+
+impl BlockStatement {
+    /// Compile the block leaving its value on the stack, blocks without a
+    /// trailing expression have the value `null`.
+    pub fn compile_value(&self, compiler: &mut Compiler) -> CompilerResult<()> {
+        self.compile_scope(compiler)?;
+        if self.value.is_none() {
+            Value::Null.compile(compiler)?;
         }
         Ok(())
     }
+
+    /// Compile body and trailing expression in a new scope, the scope is
+    /// exited even if compilation fails.
+    fn compile_scope(&self, compiler: &mut Compiler) -> CompilerResult<()> {
+        compiler.enter_scope();
+        let result = self
+            .body
+            .iter()
+            .try_for_each(|statement| statement.compile(compiler));
+        let result = match &self.value {
+            Some(value) => result.and_then(|()| value.compile(compiler)),
+            None => result,
+        };
+        compiler.exit_scope();
+        result
+    }
+
+    /// Check the block and infer type of its value, blocks without a
+    /// trailing expression are `Unknown`.
+    pub fn infer_value(&self, checker: &mut TypeChecker) -> Type {
+        checker.enter_scope();
+        for statement in &self.body {
+            statement.check(checker);
+        }
+        let ty = match &self.value {
+            Some(value) => value.infer(checker),
+            None => Type::Unknown,
+        };
+        checker.exit_scope();
+        ty
+    }
 }
 
 impl Parse<'_> for BlockStatement {
     fn parse(pair: Pair<'_, Rule>) -> Result<Self, ParserError> {
-        matches!(pair.as_rule(), Rule::block_statement);
-        let body = parser::parse_pairs(pair.into_inner())?;
-        Ok(BlockStatement { body })
+        parser::expect_rule(&pair, Rule::block_statement)?;
+        let mut body = Vec::new();
+        let mut value = None;
+        for pair in pair.into_inner() {
+            match pair.as_rule() {
+                Rule::expression => value = Some(Expression::parse(pair)?),
+                _ => body.push(Statement::parse(pair)?),
+            }
+        }
+        Ok(BlockStatement { body, value })
     }
 }
 
 impl fmt::Display for BlockStatement {
-    fn fmt(&self, _f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        todo!()
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Some(value) = &self.value else {
+            return write_block(f, &self.body);
+        };
+        writeln!(f, "{{")?;
+        for statement in &self.body {
+            for line in statement.to_string().lines() {
+                writeln!(f, "    {line}")?;
+            }
+        }
+        writeln!(f, "    {value}")?;
+        write!(f, "}}")
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub struct BreakStatement;
 
 impl Compile for BreakStatement {
@@ -263,33 +416,46 @@ impl Compile for BreakStatement {
 
 impl Parse<'_> for BreakStatement {
     fn parse(pair: Pair<'_, Rule>) -> Result<Self, ParserError> {
-        matches!(pair.as_rule(), Rule::break_statement);
+        parser::expect_rule(&pair, Rule::break_statement)?;
         Ok(Self {})
     }
 }
 
 impl fmt::Display for BreakStatement {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "BreakStatement")
+        write!(f, "break;")
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub struct ExpressionStatement {
     expression: Expression,
 }
 
+impl From<Expression> for ExpressionStatement {
+    fn from(expression: Expression) -> Self {
+        Self { expression }
+    }
+}
+
 impl Compile for ExpressionStatement {
     fn compile(&self, compiler: &mut Compiler) -> CompilerResult<()> {
+        let start = compiler.place_label();
         self.expression.compile(compiler)?;
-        compiler.emit(Instruction::Pop);
+        compiler.pop_unused_value(start);
         Ok(())
     }
 }
 
+impl Check for ExpressionStatement {
+    fn check(&self, checker: &mut TypeChecker) {
+        self.expression.infer(checker);
+    }
+}
+
 impl Parse<'_> for ExpressionStatement {
     fn parse(pair: Pair<'_, Rule>) -> Result<Self, ParserError> {
-        matches!(pair.as_rule(), Rule::expression_statement);
+        parser::expect_rule(&pair, Rule::expression_statement)?;
 
         let expression_pair = pair.into_inner().next().unwrap();
         let expression = Expression::parse(expression_pair)?;
@@ -298,18 +464,18 @@ impl Parse<'_> for ExpressionStatement {
 }
 
 impl fmt::Display for ExpressionStatement {
-    fn fmt(&self, _f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        todo!()
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{};", self.expression)
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub struct ContinueStatement;
 
 impl Compile for ContinueStatement {
     fn compile(&self, compiler: &mut Compiler) -> CompilerResult<()> {
         let jump = compiler.emit_untargeted_jump();
-        match compiler.target_jump_on_loop_exit(jump) {
+        match compiler.target_jump_on_loop_continue(jump) {
             Some(_) => Ok(()),
             None => Err(CompilerError::ContinueOutsideLoop),
         }
@@ -318,7 +484,7 @@ impl Compile for ContinueStatement {
 
 impl Parse<'_> for ContinueStatement {
     fn parse(pair: Pair<'_, Rule>) -> Result<Self, ParserError> {
-        matches!(pair.as_rule(), Rule::continue_statement);
+        parser::expect_rule(&pair, Rule::continue_statement)?;
         Ok(Self {})
     }
 }
@@ -331,7 +497,7 @@ impl fmt::Display for ContinueStatement {
 
 #[cfg(test)]
 mod test {
-    use crate::parser::{self, ParseResult};
+    use crate::parser::{self, ParseResult, ParserErrorKind, Rule};
 
     use super::{BlockStatement, PrintStatement};
 
@@ -361,12 +527,35 @@ mod test {
         parse_print("print;").unwrap_err();
     }
 
+    #[test]
+    fn test_mismatched_print_rule() {
+        let err = parser::parse_rule::<PrintStatement>(Rule::expression, "1 + 2").unwrap_err();
+        assert!(matches!(
+            err.kind(),
+            ParserErrorKind::UnexpectedRule(Rule::expression)
+        ));
+    }
+
     #[test]
     fn test_block_statement() -> ParseResult<()> {
         parse_block("{}")?;
         parse_block("{ print 24; }")?;
         parse_block("{ print 24; print 24; }")?;
         parse_block("{ print 24; print 24; print 24; }")?;
+        parse_block("{ 1; 2; 3 }")?;
+        parse_block("{ x + 1 }")?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_block_value() -> ParseResult<()> {
+        let block = parser::parse_statement::<BlockStatement>("{ 1; 2; 3 }")?;
+        assert_eq!(block.body.len(), 2);
+        assert!(block.value.is_some());
+        assert_eq!(block.to_string(), "{\n    1;\n    2;\n    3\n}");
+        let block = parser::parse_statement::<BlockStatement>("{ 1; 2; 3; }")?;
+        assert_eq!(block.body.len(), 3);
+        assert!(block.value.is_none());
         Ok(())
     }
 
@@ -374,5 +563,7 @@ mod test {
     fn test_wrong_block_statements() {
         parse_block("{ print 24; ").unwrap_err();
         parse_block("print 24; }").unwrap_err();
+        parse_block("{ 1 2 }").unwrap_err();
+        parse_block("{ 1 print 2; }").unwrap_err();
     }
 }
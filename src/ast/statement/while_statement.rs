@@ -1,16 +1,20 @@
-use std::fmt;
+use alloc::vec::Vec;
+use core::fmt;
 
 use pest::iterators::Pair;
 
 use crate::{
     ast::expression::Expression,
-    compiler::{BlockType, Compile, Compiler, CompilerResult, Instruction},
+    compiler::{
+        types::{Check, Infer, TypeChecker},
+        BlockType, Compile, Compiler, CompilerResult, Instruction,
+    },
     parser::{self, Parse, ParserError, Rule},
 };
 
-use super::Statement;
+use super::{write_block, Statement};
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub struct WhileStatement {
     condition: Expression,
     body: Vec<Statement>,
@@ -21,25 +25,30 @@ impl Compile for WhileStatement {
         compiler.enter_while();
 
         let condition_label = compiler.place_label();
-        self.condition.compile(compiler)?;
-        let exit = compiler.emit_untargeted_jump_if_false();
+        let exit = self.condition.compile_condition(compiler)?;
         compiler.target_jump_on_exit(BlockType::While, exit);
 
-        for statement in &self.body {
-            statement.compile(compiler)?;
-        }
+        compiler.compile_block(&self.body)?;
+        compiler.target_continue_jumps()?;
         compiler.emit(Instruction::Jump(condition_label.target()?));
-        compiler.exit_while();
-        Ok(())
+        compiler.exit_while()
+    }
+}
+
+impl Check for WhileStatement {
+    fn check(&self, checker: &mut TypeChecker) {
+        let condition = self.condition.infer(checker);
+        checker.check_condition(condition);
+        checker.check_block(&self.body);
     }
 }
 
 impl Parse<'_> for WhileStatement {
     fn parse(pair: Pair<'_, Rule>) -> Result<Self, ParserError> {
-        matches!(pair.as_rule(), Rule::while_statement);
+        parser::expect_rule(&pair, Rule::while_statement)?;
         let mut inner = pair.into_inner();
 
-        matches!(inner.next().unwrap().as_rule(), Rule::k_while);
+        parser::expect_rule(&inner.next().unwrap(), Rule::k_while)?;
         let expression = inner.next().unwrap();
         let condition = Expression::parse(expression)?;
 
@@ -51,8 +60,62 @@ impl Parse<'_> for WhileStatement {
 }
 
 impl fmt::Display for WhileStatement {
-    fn fmt(&self, _f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        todo!()
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "while {} ", self.condition)?;
+        write_block(f, &self.body)
+    }
+}
+
+/// Post-test loop such as `do { } while x;` whose body runs at least once.
+#[derive(Debug, PartialEq)]
+pub struct DoWhileStatement {
+    body: Vec<Statement>,
+    condition: Expression,
+}
+
+impl Compile for DoWhileStatement {
+    fn compile(&self, compiler: &mut Compiler) -> CompilerResult<()> {
+        compiler.enter_while();
+
+        let body_label = compiler.place_label();
+        compiler.compile_block(&self.body)?;
+        compiler.target_continue_jumps()?;
+        self.condition.compile(compiler)?;
+        compiler.emit(Instruction::JumpIfTrue(body_label.target()?));
+        compiler.exit_while()
+    }
+}
+
+impl Check for DoWhileStatement {
+    fn check(&self, checker: &mut TypeChecker) {
+        checker.check_block(&self.body);
+        let condition = self.condition.infer(checker);
+        checker.check_condition(condition);
+    }
+}
+
+impl Parse<'_> for DoWhileStatement {
+    fn parse(pair: Pair<'_, Rule>) -> Result<Self, ParserError> {
+        parser::expect_rule(&pair, Rule::do_while_statement)?;
+        let mut inner = pair.into_inner();
+
+        parser::expect_rule(&inner.next().unwrap(), Rule::k_do)?;
+        let statement_pairs = inner.next().unwrap().into_inner();
+        let body = parser::parse_pairs(statement_pairs)?;
+
+        parser::expect_rule(&inner.next().unwrap(), Rule::k_while)?;
+        let expression = inner.next().unwrap();
+        let condition = Expression::parse(expression)?;
+
+        Ok(DoWhileStatement { body, condition })
+    }
+}
+
+impl fmt::Display for DoWhileStatement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "do ")?;
+        write_block(f, &self.body)?;
+        write!(f, " while {};", self.condition)
     }
 }
 
@@ -60,7 +123,7 @@ impl fmt::Display for WhileStatement {
 mod test {
     use crate::parser::{self, ParseResult, ParserError};
 
-    use super::WhileStatement;
+    use super::{DoWhileStatement, WhileStatement};
 
     fn parse_while(input: &str) -> ParseResult<()> {
         parser::parse_statement::<WhileStatement>(input)?;
@@ -82,4 +145,21 @@ mod test {
         parse_while("while true }").unwrap_err();
         parse_while("while true {").unwrap_err();
     }
+
+    #[test]
+    fn test_do_while_statement() -> ParseResult<()> {
+        let statement =
+            parser::parse_statement::<DoWhileStatement>("do { print 1; } while x < 3;")?;
+        assert_eq!(statement.to_string(), "do {\n    print 1;\n} while x < 3;");
+        parser::parse_statement::<DoWhileStatement>("do {} while true;")?;
+        parser::parse_statement::<DoWhileStatement>("do {} while true").unwrap_err();
+        parser::parse_statement::<DoWhileStatement>("do while true;").unwrap_err();
+        parser::parse_statement::<DoWhileStatement>("do {}").unwrap_err();
+        Ok(())
+    }
+
+    #[test]
+    fn test_mismatched_while_rule() {
+        parse_while("for i in 2 {}").unwrap_err();
+    }
 }
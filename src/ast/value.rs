@@ -1,37 +1,366 @@
-use std::{fmt, num::ParseIntError};
+use alloc::{
+    format,
+    rc::Rc,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use core::{
+    cell::RefCell,
+    cmp::Ordering,
+    convert::TryFrom,
+    fmt,
+    hash::{Hash, Hasher},
+    num::{IntErrorKind, ParseIntError},
+};
 
 use crate::{
-    compiler::{Compile, Compiler, CompilerResult, Instruction},
-    parser::{Parse, ParseResult, ParserError, Rule},
+    collections::{HashMap, HashSet},
+    compiler::{
+        types::{Infer, Type, TypeChecker},
+        Compile, Compiler, CompilerResult, Instruction,
+    },
+    math,
+    parser::{
+        expect_rule,
+        literal::{
+            parse_binary, parse_bool, parse_decimal, parse_float, parse_hexadecimal, parse_null,
+            parse_octal,
+        },
+        Parse, ParseResult, ParserError, ParserErrorKind, Rule,
+    },
 };
 
-use pest::iterators::Pair;
+#[cfg(feature = "bigint")]
+use super::bigint::BigInt;
+use nom::{branch::alt, combinator::all_consuming};
+use pest::{iterators::Pair, Span};
+use thiserror::Error;
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum ValueError {
+    #[error("`{0}` and `{1}` can't be compared")]
+    Incomparable(&'static str, &'static str),
+    #[error("unsupported operand types for `{0}`: `{1}` and `{2}`")]
+    UnsupportedOperands(&'static str, &'static str, &'static str),
+    #[error("unsupported operand type for `{0}`: `{1}`")]
+    UnsupportedOperand(&'static str, &'static str),
+    #[error("division by zero")]
+    DivisionByZero,
+    #[error("integer overflow")]
+    Overflow,
+    #[error("`{0}` isn't hashable")]
+    Unhashable(&'static str),
+    #[error("index `{index}` is out of bounds for length {len}")]
+    IndexOutOfBounds { index: i64, len: usize },
+    #[error("`{0}` isn't iterable")]
+    NotIterable(&'static str),
+}
+
+pub type ValueResult<T> = Result<T, ValueError>;
+
+/// Explicit conversion of a value that can't be performed without losing
+/// information.
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum ConversionError {
+    #[error("`{0}` isn't finite")]
+    NonFinite(f64),
+    #[error("`{0}` is out of range")]
+    OutOfRange(f64),
+    #[error("`{0}` has a fractional part")]
+    Fractional(f64),
+    #[error("`{0}` isn't an integer literal")]
+    InvalidLiteral(String),
+    #[error("`{0}` can't be converted")]
+    Unsupported(&'static str),
+}
+
+/// How `Value::try_to_int` treats floats with a fractional part.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FloatToInt {
+    /// Truncate towards zero so `3.5` becomes `3`.
+    Truncate,
+    /// Reject floats with a fractional part.
+    Exact,
+}
+
+/// How addition, subtraction and multiplication of integers treat results
+/// outside of the range of `i64`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum IntOverflow {
+    /// Fail with `ValueError::Overflow`.
+    #[default]
+    Error,
+    /// Compute the result as a float instead. Floats only have 53 bits of
+    /// precision so the result is approximate, `i64::MAX + 1` and
+    /// `i64::MAX + 2` are the same float. With the `bigint` feature results
+    /// are big integers instead so integer arithmetic never overflows.
+    Promote,
+}
+
+/// `PartialEq` for `Value` is exact, floats are compared with `==` so `NaN`
+/// is never equal to itself, use `Value::approx_eq` for a tolerant comparison.
+/// Arrays are equal if they are the same array or their elements are equal
+/// pairwise, arrays inside of themselves are only equal to themselves.
+///
+/// Arrays are the only mutable values and have reference semantics, `var b =
+/// a;` and passing `a` to a function share the same array. Strings and sets
+/// can't be modified in place so copying or sharing them is
+/// indistinguishable. `Value::deep_clone`, exposed as the `clone` native,
+/// copies arrays instead of sharing them.
+#[derive(Clone, Default)]
 pub enum Value {
     Integer(i64),
     Float(f64),
     True,
     False,
+    #[default]
     Null,
     String(String),
+    Set(HashSet<HashableValue>),
+    /// Arrays live on the heap, copies of the value refer to the same array
+    /// so mutation is visible through each of them.
+    Array(Rc<RefCell<Vec<Value>>>),
+    /// Integer outside of the range of `i64`, results that fit in `i64` are
+    /// always stored as `Value::Integer`.
+    #[cfg(feature = "bigint")]
+    BigInt(BigInt),
 }
 
-impl Default for Value {
-    fn default() -> Self {
-        Self::Null
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        self.eq_in(other, &mut Vec::new())
     }
 }
 
-impl fmt::Display for Value {
+/// Pattern of a `match` arm, the wildcard `_` matches every value and a value
+/// pattern is matched with `Value::matches`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Pattern {
+    Wildcard,
+    Value(Value),
+}
+
+impl From<Value> for Pattern {
+    fn from(value: Value) -> Self {
+        Self::Value(value)
+    }
+}
+
+/// Value that can be stored in a hash based collection. Values are compared
+/// exactly by type, so `1` and `1.0` are distinct elements and floats are
+/// compared by their canonical bits, so `-0.0` is the same element as `0.0`
+/// and every `NaN` is the same element.
+#[derive(Clone)]
+pub struct HashableValue(Value);
+
+impl HashableValue {
+    pub fn value(&self) -> &Value {
+        &self.0
+    }
+
+    pub fn into_value(self) -> Value {
+        self.0
+    }
+}
+
+impl TryFrom<Value> for HashableValue {
+    type Error = ValueError;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Set(_) | Value::Array(_) => Err(ValueError::Unhashable(value.type_name())),
+            _ => Ok(Self(value)),
+        }
+    }
+}
+
+impl PartialEq for HashableValue {
+    fn eq(&self, other: &Self) -> bool {
+        match (&self.0, &other.0) {
+            (Value::Float(lhs), Value::Float(rhs)) => canonical_bits(*lhs) == canonical_bits(*rhs),
+            (lhs, rhs) => lhs == rhs,
+        }
+    }
+}
+
+/// Bits of `float` with `-0.0` mapped to `0.0` and every `NaN` mapped to
+/// the same payload.
+fn canonical_bits(float: f64) -> u64 {
+    if float == 0.0 {
+        0.0f64.to_bits()
+    } else if float.is_nan() {
+        f64::NAN.to_bits()
+    } else {
+        float.to_bits()
+    }
+}
+
+impl Eq for HashableValue {}
+
+impl Hash for HashableValue {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        core::mem::discriminant(&self.0).hash(state);
+        match &self.0 {
+            Value::Integer(int) => int.hash(state),
+            Value::Float(float) => canonical_bits(*float).hash(state),
+            Value::String(string) => string.hash(state),
+            #[cfg(feature = "bigint")]
+            Value::BigInt(big) => big.hash(state),
+            Value::True | Value::False | Value::Null => {}
+            Value::Set(_) | Value::Array(_) => unreachable!("collections aren't hashable"),
+        }
+    }
+}
+
+impl fmt::Debug for HashableValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// Unlike `Display` the type of the value is always visible, so `5` and
+/// `5.0` are written as `Int(5)` and `Float(5.0)`.
+impl fmt::Debug for Value {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Self::Integer(int) => write!(f, "{int}"),
-            Self::Float(float) => write!(f, "{float}"),
-            Self::True => write!(f, "true"),
-            Self::False => write!(f, "false"),
+            Self::Integer(int) => f.debug_tuple("Int").field(int).finish(),
+            Self::Float(float) => f.debug_tuple("Float").field(float).finish(),
+            Self::True => f.debug_tuple("Bool").field(&true).finish(),
+            Self::False => f.debug_tuple("Bool").field(&false).finish(),
+            Self::Null => write!(f, "Null"),
+            Self::String(string) => f.debug_tuple("Str").field(string).finish(),
+            Self::Set(set) => f.debug_tuple("Set").field(set).finish(),
+            Self::Array(_) => {
+                let enclosing = RefCell::default();
+                fmt::Debug::fmt(&Enclosed::new(self, &enclosing), f)
+            }
+            #[cfg(feature = "bigint")]
+            Self::BigInt(big) => f.debug_tuple("BigInt").field(big).finish(),
+        }
+    }
+}
+
+/// Collections are written like their literals so output can be read back.
+/// Strings are written as is at the top level but quoted and escaped inside
+/// collections, so `["a, b"]` isn't mistaken for two elements. An array
+/// inside of itself is written as `[...]`.
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let enclosing = RefCell::default();
+        fmt::Display::fmt(&Enclosed::new(self, &enclosing), f)
+    }
+}
+
+/// Address of an array, arrays that contain themselves are found by
+/// comparing it with the addresses of the arrays enclosing an element.
+type ArrayPtr = *const RefCell<Vec<Value>>;
+
+/// Value being formatted as an element of the arrays in `enclosing`.
+struct Enclosed<'a> {
+    value: &'a Value,
+    /// Strings are quoted inside of collections.
+    nested: bool,
+    enclosing: &'a RefCell<Vec<ArrayPtr>>,
+}
+
+impl<'a> Enclosed<'a> {
+    fn new(value: &'a Value, enclosing: &'a RefCell<Vec<ArrayPtr>>) -> Self {
+        Self {
+            value,
+            nested: false,
+            enclosing,
+        }
+    }
+
+    fn element(&self, value: &'a Value) -> Self {
+        Self {
+            value,
+            nested: true,
+            enclosing: self.enclosing,
+        }
+    }
+
+    /// Write the elements of `array` with `write`, or `[...]` if the array
+    /// encloses itself.
+    fn write_array(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+        array: &RefCell<Vec<Value>>,
+        write: impl FnOnce(&mut fmt::Formatter<'_>, &[Value]) -> fmt::Result,
+    ) -> fmt::Result {
+        let ptr: ArrayPtr = array;
+        if self.enclosing.borrow().contains(&ptr) {
+            return write!(f, "[...]");
+        }
+        self.enclosing.borrow_mut().push(ptr);
+        let result = write(f, &array.borrow());
+        self.enclosing.borrow_mut().pop();
+        result
+    }
+}
+
+impl fmt::Display for Enclosed<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.value {
+            Value::Integer(int) => write!(f, "{int}"),
+            // Always write the decimal point so floats aren't read back as integers
+            Value::Float(float) if float.is_finite() && math::fract(*float) == 0.0 => {
+                write!(f, "{float:.1}")
+            }
+            Value::Float(float) => write!(f, "{float}"),
+            Value::True => write!(f, "true"),
+            Value::False => write!(f, "false"),
             Value::Null => write!(f, "null"),
+            Value::String(string) if self.nested => write!(f, "{string:?}"),
             Value::String(string) => write!(f, "{string}"),
+            #[cfg(feature = "bigint")]
+            Value::BigInt(big) => write!(f, "{big}"),
+            Value::Set(set) => {
+                // Sort elements so sets are displayed deterministically
+                let mut elements = set
+                    .iter()
+                    .map(|element| self.element(element.value()).to_string())
+                    .collect::<Vec<_>>();
+                elements.sort();
+                write!(f, "set{{{}}}", elements.join(", "))
+            }
+            Value::Array(array) => self.write_array(f, array, |f, elements| {
+                write!(f, "[")?;
+                for (i, element) in elements.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", self.element(element))?;
+                }
+                write!(f, "]")
+            }),
+        }
+    }
+}
+
+impl fmt::Debug for Enclosed<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.value {
+            Value::Array(array) => self.write_array(f, array, |f, elements| {
+                write!(f, "Array(")?;
+                f.debug_list()
+                    .entries(elements.iter().map(|element| self.element(element)))
+                    .finish()?;
+                write!(f, ")")
+            }),
+            value => fmt::Debug::fmt(value, f),
+        }
+    }
+}
+
+/// Big integers that fit in `i64` are stored as `Value::Integer`.
+#[cfg(feature = "bigint")]
+impl From<BigInt> for Value {
+    fn from(big: BigInt) -> Self {
+        match big.to_i64() {
+            Some(int) => Value::Integer(int),
+            None => Value::BigInt(big),
         }
     }
 }
@@ -64,6 +393,20 @@ impl From<f64> for Value {
     }
 }
 
+impl Infer for Value {
+    fn infer(&self, _checker: &mut TypeChecker) -> Type {
+        match self {
+            Value::Integer(_) => Type::Int,
+            Value::Float(_) => Type::Float,
+            Value::True | Value::False => Type::Bool,
+            Value::String(_) => Type::String,
+            #[cfg(feature = "bigint")]
+            Value::BigInt(_) => Type::Int,
+            Value::Null | Value::Set(_) | Value::Array(_) => Type::Unknown,
+        }
+    }
+}
+
 impl Compile for Value {
     fn compile(&self, compiler: &mut Compiler) -> CompilerResult<()> {
         let index = compiler.register_value(self.clone())?;
@@ -74,7 +417,7 @@ impl Compile for Value {
 
 impl Parse<'_> for Value {
     fn parse(rule: Pair<'_, Rule>) -> Result<Self, ParserError> {
-        matches!(rule.as_rule(), Rule::value);
+        expect_rule(&rule, Rule::value)?;
         let value = rule.into_inner().next().unwrap();
         let result = match value.as_rule() {
             Rule::integer => Value::parse_integer(value)?,
@@ -95,23 +438,791 @@ impl Parse<'_> for Value {
     }
 }
 
+/// Whether either array is already being compared, so it is inside of itself.
+fn is_enclosing(
+    enclosing: &[(ArrayPtr, ArrayPtr)],
+    lhs: &Rc<RefCell<Vec<Value>>>,
+    rhs: &Rc<RefCell<Vec<Value>>>,
+) -> bool {
+    let (lhs, rhs) = (Rc::as_ptr(lhs), Rc::as_ptr(rhs));
+    enclosing.iter().any(|&(l, r)| l == lhs || r == rhs)
+}
+
+/// Compare arrays lexicographically by their elements, an array that is a
+/// prefix of the other is less than it.
+fn compare_elements(
+    lhs: &[Value],
+    rhs: &[Value],
+    enclosing: &mut Vec<(ArrayPtr, ArrayPtr)>,
+) -> ValueResult<Option<Ordering>> {
+    for (lhs, rhs) in lhs.iter().zip(rhs.iter()) {
+        if !lhs.strict_eq_in(rhs, enclosing) {
+            return lhs.compare_in(rhs, enclosing);
+        }
+    }
+    Ok(Some(lhs.len().cmp(&rhs.len())))
+}
+
+/// Compare `int` with `float` without converting `int` to a float, which
+/// would round integers larger than 2^53.
+fn compare_int_float(int: i64, float: f64) -> Option<Ordering> {
+    // Bounds of `i64` are powers of two so they are exact as floats
+    const MIN: f64 = i64::MIN as f64;
+    if float.is_nan() {
+        return None;
+    }
+    if float >= -MIN {
+        return Some(Ordering::Less);
+    }
+    if float < MIN {
+        return Some(Ordering::Greater);
+    }
+    // Integer part is in range of `i64` so the conversion is exact, the
+    // fractional part decides if the integer parts are equal
+    let trunc = math::trunc(float);
+    Some(int.cmp(&(trunc as i64)).then(trunc.partial_cmp(&float)?))
+}
+
 impl Value {
+    /// Name of the value's type as used in error messages.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Integer(_) => "int",
+            Value::Float(_) => "float",
+            Value::True | Value::False => "bool",
+            Value::Null => "null",
+            Value::String(_) => "string",
+            Value::Set(_) => "set",
+            Value::Array(_) => "array",
+            #[cfg(feature = "bigint")]
+            Value::BigInt(_) => "bigint",
+        }
+    }
+
+    /// Allocate a new array holding `elements`.
+    pub fn array(elements: Vec<Value>) -> Value {
+        Value::Array(Rc::new(RefCell::new(elements)))
+    }
+
+    /// Copy the value and every array it refers to, unlike `clone` which
+    /// shares arrays. Arrays referred to more than once, including arrays
+    /// containing themselves, are copied once and stay shared in the copy.
+    pub fn deep_clone(&self) -> Value {
+        self.deep_clone_with(&mut HashMap::new())
+    }
+
+    fn deep_clone_with(&self, copies: &mut HashMap<ArrayPtr, Value>) -> Value {
+        let Value::Array(array) = self else {
+            return self.clone();
+        };
+        if let Some(copy) = copies.get(&Rc::as_ptr(array)) {
+            return copy.clone();
+        }
+        // Register the copy before copying elements so cycles terminate
+        let copy = Rc::new(RefCell::new(Vec::new()));
+        copies.insert(Rc::as_ptr(array), Value::Array(Rc::clone(&copy)));
+        let elements = array
+            .borrow()
+            .iter()
+            .map(|element| element.deep_clone_with(copies))
+            .collect();
+        *copy.borrow_mut() = elements;
+        Value::Array(copy)
+    }
+
+    /// Element at `index` of an array. Negative indices count from the end,
+    /// `-1` is the last element and `-len` the first.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if `self` isn't an array, `index`
+    /// isn't an integer or `index` is out of bounds.
+    pub fn get_index(&self, index: &Value) -> ValueResult<Value> {
+        let (Value::Array(array), Value::Integer(index)) = (self, index) else {
+            return Err(ValueError::UnsupportedOperands(
+                "[]",
+                self.type_name(),
+                index.type_name(),
+            ));
+        };
+        let array = array.borrow();
+        let position = resolve_index(*index, array.len())?;
+        Ok(array[position].clone())
+    }
+
+    /// Copy of the elements of an array or the characters of a string from
+    /// `start` up to but excluding `end`. Bounds are integers or `null` for
+    /// the start and end of the value, negative bounds count from the end
+    /// like indices do. Bounds out of range are clamped rather than an error,
+    /// so a `start` past `end` gives an empty slice.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if `self` isn't an array or a
+    /// string or a bound isn't an integer or `null`.
+    pub fn slice(&self, start: &Value, end: &Value) -> ValueResult<Value> {
+        let bound = |bound: &Value, default: usize, len: usize| match bound {
+            Value::Null => Ok(default),
+            Value::Integer(bound) => Ok(clamp_bound(*bound, len)),
+            _ => Err(ValueError::UnsupportedOperands(
+                "[:]",
+                self.type_name(),
+                bound.type_name(),
+            )),
+        };
+        match self {
+            Value::Array(array) => {
+                let array = array.borrow();
+                let start = bound(start, 0, array.len())?;
+                let end = bound(end, array.len(), array.len())?.max(start);
+                Ok(Value::array(array[start..end].to_vec()))
+            }
+            Value::String(string) => {
+                let len = string.chars().count();
+                let start = bound(start, 0, len)?;
+                let end = bound(end, len, len)?.max(start);
+                Ok(Value::String(
+                    string.chars().skip(start).take(end - start).collect(),
+                ))
+            }
+            _ => Err(ValueError::UnsupportedOperand("[:]", self.type_name())),
+        }
+    }
+
+    /// Replace the element at `index` of an array in place, negative indices
+    /// count from the end like in [`Value::get_index`].
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if `self` isn't an array, `index`
+    /// isn't an integer or `index` is out of bounds.
+    pub fn set_index(&self, index: &Value, value: Value) -> ValueResult<()> {
+        let (Value::Array(array), Value::Integer(index)) = (self, index) else {
+            return Err(ValueError::UnsupportedOperands(
+                "[]",
+                self.type_name(),
+                index.type_name(),
+            ));
+        };
+        let mut array = array.borrow_mut();
+        let position = resolve_index(*index, array.len())?;
+        array[position] = value;
+        Ok(())
+    }
+
+    /// Append `value` to the end of an array in place.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if `self` isn't an array.
+    pub fn push(&self, value: Value) -> ValueResult<()> {
+        match self {
+            Value::Array(array) => {
+                array.borrow_mut().push(value);
+                Ok(())
+            }
+            _ => Err(ValueError::UnsupportedOperand("append", self.type_name())),
+        }
+    }
+
+    /// Array `for` loops iterate over in index order. Arrays are iterated in
+    /// place, so elements appended by the loop body are visited as well.
+    /// Strings are iterated by character and sets in an unspecified order.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the value isn't an array, string
+    /// or set.
+    pub fn iterable(&self) -> ValueResult<Value> {
+        match self {
+            Value::Array(_) => Ok(self.clone()),
+            Value::String(string) => Ok(Value::array(
+                string.chars().map(|c| Value::String(c.into())).collect(),
+            )),
+            Value::Set(set) => Ok(Value::array(
+                set.iter().map(|item| item.value().clone()).collect(),
+            )),
+            _ => Err(ValueError::NotIterable(self.type_name())),
+        }
+    }
+
+    /// Truthiness of the value, `false`, `null`, zero and empty strings are
+    /// falsy while everything else is truthy.
+    pub fn is_truthy(&self) -> bool {
+        match self {
+            Value::Integer(int) => *int != 0,
+            Value::Float(float) => *float != 0.0,
+            Value::True => true,
+            Value::False | Value::Null => false,
+            Value::String(string) => !string.is_empty(),
+            Value::Set(set) => !set.is_empty(),
+            Value::Array(array) => !array.borrow().is_empty(),
+            #[cfg(feature = "bigint")]
+            Value::BigInt(big) => !big.is_zero(),
+        }
+    }
+
+    /// Membership test used by `in`, `self` is the container and `item` is
+    /// the element searched for. Strings contain their substrings.
+    pub fn contains(&self, item: &Value) -> ValueResult<Value> {
+        match (self, item) {
+            (Value::Set(set), _) => {
+                let item = HashableValue::try_from(item.clone())?;
+                Ok(set.contains(&item).into())
+            }
+            (Value::String(string), Value::String(substring)) => {
+                Ok(string.contains(substring.as_str()).into())
+            }
+            _ => Err(ValueError::UnsupportedOperands(
+                "in",
+                item.type_name(),
+                self.type_name(),
+            )),
+        }
+    }
+
+    /// Compare two numeric values, mixed integer and float comparisons are
+    /// exact even for integers that can't be represented as floats and
+    /// `None` is returned if either side is NaN. Strings are compared lexicographically
+    /// by Unicode scalar values and arrays are compared lexicographically by
+    /// their elements, a string or an array that is a prefix of the other is
+    /// less than it.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if both values aren't numbers,
+    /// strings or arrays, or if the first elements that differ can't be
+    /// compared. Arrays inside of themselves are compared by identity, they
+    /// are equal if they are the same array and unordered otherwise.
+    pub fn compare(&self, other: &Value) -> ValueResult<Option<Ordering>> {
+        self.compare_in(other, &mut Vec::new())
+    }
+
+    /// `compare` of elements of the pairs of arrays in `enclosing`.
+    fn compare_in(
+        &self,
+        other: &Value,
+        enclosing: &mut Vec<(ArrayPtr, ArrayPtr)>,
+    ) -> ValueResult<Option<Ordering>> {
+        let ordering = match (self, other) {
+            (Value::Array(lhs), Value::Array(rhs)) => {
+                if self.is_identical(other) {
+                    return Ok(Some(Ordering::Equal));
+                }
+                if is_enclosing(enclosing, lhs, rhs) {
+                    return Ok(None);
+                }
+                enclosing.push((Rc::as_ptr(lhs), Rc::as_ptr(rhs)));
+                let ordering = compare_elements(&lhs.borrow(), &rhs.borrow(), enclosing);
+                enclosing.pop();
+                return ordering;
+            }
+            // Byte order of UTF-8 is the order of the scalar values it encodes
+            (Value::String(lhs), Value::String(rhs)) => Some(lhs.cmp(rhs)),
+            (Value::Integer(lhs), Value::Integer(rhs)) => Some(lhs.cmp(rhs)),
+            (Value::Integer(lhs), Value::Float(rhs)) => compare_int_float(*lhs, *rhs),
+            (Value::Float(lhs), Value::Integer(rhs)) => {
+                compare_int_float(*rhs, *lhs).map(Ordering::reverse)
+            }
+            (Value::Float(lhs), Value::Float(rhs)) => lhs.partial_cmp(rhs),
+            #[cfg(feature = "bigint")]
+            (Value::BigInt(_), Value::Integer(_) | Value::BigInt(_))
+            | (Value::Integer(_), Value::BigInt(_)) => {
+                Some(self.to_bigint().unwrap().cmp(&other.to_bigint().unwrap()))
+            }
+            #[cfg(feature = "bigint")]
+            (Value::BigInt(lhs), Value::Float(rhs)) => lhs.cmp_f64(*rhs),
+            #[cfg(feature = "bigint")]
+            (Value::Float(lhs), Value::BigInt(rhs)) => rhs.cmp_f64(*lhs).map(Ordering::reverse),
+            _ => {
+                return Err(ValueError::Incomparable(
+                    self.type_name(),
+                    other.type_name(),
+                ))
+            }
+        };
+        Ok(ordering)
+    }
+
+    /// `PartialEq` of elements of the pairs of arrays in `enclosing`.
+    fn eq_in(&self, other: &Value, enclosing: &mut Vec<(ArrayPtr, ArrayPtr)>) -> bool {
+        match (self, other) {
+            (Value::Integer(lhs), Value::Integer(rhs)) => lhs == rhs,
+            (Value::Float(lhs), Value::Float(rhs)) => lhs == rhs,
+            (Value::True, Value::True)
+            | (Value::False, Value::False)
+            | (Value::Null, Value::Null) => true,
+            (Value::String(lhs), Value::String(rhs)) => lhs == rhs,
+            (Value::Set(lhs), Value::Set(rhs)) => lhs == rhs,
+            (Value::Array(lhs), Value::Array(rhs)) => {
+                if Rc::ptr_eq(lhs, rhs) {
+                    return true;
+                }
+                if is_enclosing(enclosing, lhs, rhs) {
+                    return false;
+                }
+                enclosing.push((Rc::as_ptr(lhs), Rc::as_ptr(rhs)));
+                let (lhs, rhs) = (lhs.borrow(), rhs.borrow());
+                let equal = lhs.len() == rhs.len()
+                    && lhs
+                        .iter()
+                        .zip(rhs.iter())
+                        .all(|(l, r)| l.eq_in(r, enclosing));
+                enclosing.pop();
+                equal
+            }
+            #[cfg(feature = "bigint")]
+            (Value::BigInt(lhs), Value::BigInt(rhs)) => lhs == rhs,
+            _ => false,
+        }
+    }
+
+    /// Compare floats using a tolerance relative to their magnitude, any other
+    /// pair of values are compared with `PartialEq`.
+    pub fn approx_eq(&self, other: &Value) -> bool {
+        match (self, other) {
+            (Value::Float(lhs), Value::Float(rhs)) => {
+                let largest = lhs.abs().max(rhs.abs());
+                lhs == rhs || (lhs - rhs).abs() <= largest * f64::EPSILON
+            }
+            _ => self == other,
+        }
+    }
+
+    pub fn less_than(&self, other: &Value) -> ValueResult<Value> {
+        let ordering = self.compare(other)?;
+        Ok(matches!(ordering, Some(Ordering::Less)).into())
+    }
+
+    pub fn less_than_equal(&self, other: &Value) -> ValueResult<Value> {
+        let ordering = self.compare(other)?;
+        Ok(matches!(ordering, Some(Ordering::Less | Ordering::Equal)).into())
+    }
+
+    pub fn greater_than(&self, other: &Value) -> ValueResult<Value> {
+        let ordering = self.compare(other)?;
+        Ok(matches!(ordering, Some(Ordering::Greater)).into())
+    }
+
+    pub fn greater_than_equal(&self, other: &Value) -> ValueResult<Value> {
+        let ordering = self.compare(other)?;
+        Ok(matches!(ordering, Some(Ordering::Greater | Ordering::Equal)).into())
+    }
+
+    /// Check whether both values refer to the same heap object, values that
+    /// aren't heap objects are never identical.
+    pub fn is_identical(&self, other: &Value) -> bool {
+        match (self, other) {
+            (Value::Array(lhs), Value::Array(rhs)) => Rc::ptr_eq(lhs, rhs),
+            _ => false,
+        }
+    }
+
+    /// Type-aware equality used by the language's `==`, integers and floats
+    /// are compared numerically but values of any other differing types are
+    /// never equal, so `1 == true` is `false`. Arrays are equal if they have
+    /// the same length and their elements are equal pairwise, an array is
+    /// always equal to itself without comparing its elements. Arrays inside
+    /// of themselves are only equal if they are the same array.
+    pub fn strict_eq(&self, other: &Value) -> bool {
+        self.strict_eq_in(other, &mut Vec::new())
+    }
+
+    /// `strict_eq` of elements of the pairs of arrays in `enclosing`.
+    fn strict_eq_in(&self, other: &Value, enclosing: &mut Vec<(ArrayPtr, ArrayPtr)>) -> bool {
+        if self.is_identical(other) {
+            return true;
+        }
+        if let (Value::Array(lhs), Value::Array(rhs)) = (self, other) {
+            if is_enclosing(enclosing, lhs, rhs) {
+                return false;
+            }
+            enclosing.push((Rc::as_ptr(lhs), Rc::as_ptr(rhs)));
+            let (lhs, rhs) = (lhs.borrow(), rhs.borrow());
+            let equal = lhs.len() == rhs.len()
+                && lhs
+                    .iter()
+                    .zip(rhs.iter())
+                    .all(|(l, r)| l.strict_eq_in(r, enclosing));
+            enclosing.pop();
+            return equal;
+        }
+        match self.compare(other) {
+            Ok(ordering) => ordering == Some(Ordering::Equal),
+            Err(_) => self == other,
+        }
+    }
+
+    /// Check whether `self` matches the arm `pattern` of a `match`, the
+    /// wildcard always matches. Unlike `strict_eq` values of different types
+    /// never match, so neither `1` and `true` nor `1` and `1.0` match. Arrays
+    /// match if their elements match pairwise, arrays inside of themselves
+    /// only match themselves.
+    pub fn matches(&self, pattern: &Pattern) -> bool {
+        match pattern {
+            Pattern::Wildcard => true,
+            Pattern::Value(pattern) => self.matches_in(pattern, &mut Vec::new()),
+        }
+    }
+
+    /// `matches` of elements of the pairs of arrays in `enclosing`.
+    fn matches_in(&self, pattern: &Value, enclosing: &mut Vec<(ArrayPtr, ArrayPtr)>) -> bool {
+        if self.is_identical(pattern) {
+            return true;
+        }
+        match (self, pattern) {
+            (Value::Array(values), Value::Array(patterns)) => {
+                if is_enclosing(enclosing, values, patterns) {
+                    return false;
+                }
+                enclosing.push((Rc::as_ptr(values), Rc::as_ptr(patterns)));
+                let (values, patterns) = (values.borrow(), patterns.borrow());
+                let matches = values.len() == patterns.len()
+                    && values
+                        .iter()
+                        .zip(patterns.iter())
+                        .all(|(v, p)| v.matches_in(p, enclosing));
+                enclosing.pop();
+                matches
+            }
+            _ => self.type_name() == pattern.type_name() && self.strict_eq(pattern),
+        }
+    }
+
+    /// `strict_eq` as a boolean value, the language's `==`.
+    pub fn equal(&self, other: &Value) -> Value {
+        self.strict_eq(other).into()
+    }
+
+    /// Values of different types that can't be compared are never equal, so
+    /// unlike the ordering comparisons this never fails.
+    pub fn not_equal(&self, other: &Value) -> Value {
+        (!self.strict_eq(other)).into()
+    }
+
+    /// Apply numeric `operator` to both values, integer operations that
+    /// overflow are errors and mixed integer and float operands are computed
+    /// in floating point.
+    fn arithmetic(
+        &self,
+        other: &Value,
+        operator: &'static str,
+        int: fn(i64, i64) -> Option<i64>,
+        float: fn(f64, f64) -> f64,
+    ) -> ValueResult<Value> {
+        match (self, other) {
+            (Value::Integer(lhs), Value::Integer(rhs)) => match int(*lhs, *rhs) {
+                Some(result) => Ok(Value::Integer(result)),
+                None => Err(ValueError::Overflow),
+            },
+            (Value::Integer(lhs), Value::Float(rhs)) => Ok(Value::Float(float(*lhs as f64, *rhs))),
+            (Value::Float(lhs), Value::Integer(rhs)) => Ok(Value::Float(float(*lhs, *rhs as f64))),
+            (Value::Float(lhs), Value::Float(rhs)) => Ok(Value::Float(float(*lhs, *rhs))),
+            _ => Err(ValueError::UnsupportedOperands(
+                operator,
+                self.type_name(),
+                other.type_name(),
+            )),
+        }
+    }
+
+    /// Integer operand as a big integer, `None` for any other value.
+    #[cfg(feature = "bigint")]
+    fn to_bigint(&self) -> Option<BigInt> {
+        match self {
+            Value::Integer(int) => Some(BigInt::from(*int)),
+            Value::BigInt(big) => Some(big.clone()),
+            _ => None,
+        }
+    }
+
+    /// Apply `big` in arbitrary precision if either operand is a big integer
+    /// or `int` overflows, `None` if the operation isn't done in arbitrary
+    /// precision.
+    #[cfg(feature = "bigint")]
+    fn big_arithmetic(
+        &self,
+        other: &Value,
+        int: fn(i64, i64) -> Option<i64>,
+        big: fn(&BigInt, &BigInt) -> BigInt,
+    ) -> Option<Value> {
+        if let (Value::Integer(lhs), Value::Integer(rhs)) = (self, other) {
+            if int(*lhs, *rhs).is_some() {
+                return None;
+            }
+        }
+        let (lhs, rhs) = (self.to_bigint()?, other.to_bigint()?);
+        Some(big(&lhs, &rhs).into())
+    }
+
+    /// Apply `operation` with booleans converted to `0` and `1` if either
+    /// operand is a boolean and both are numbers or booleans, `None` if the
+    /// operands don't need converting or can't be converted.
+    fn bools_as_ints(
+        &self,
+        other: &Value,
+        operation: fn(&Value, &Value) -> ValueResult<Value>,
+    ) -> Option<ValueResult<Value>> {
+        let is_bool = |value: &Value| matches!(value, Value::True | Value::False);
+        if !is_bool(self) && !is_bool(other) {
+            return None;
+        }
+        let number = |value: &Value| match value {
+            Value::True => Some(Value::Integer(1)),
+            Value::False => Some(Value::Integer(0)),
+            Value::Integer(_) | Value::Float(_) => Some(value.clone()),
+            #[cfg(feature = "bigint")]
+            Value::BigInt(_) => Some(value.clone()),
+            _ => None,
+        };
+        Some(operation(&number(self)?, &number(other)?))
+    }
+
+    /// Adds numbers or concatenates strings, booleans are added as `0` and `1`.
+    pub fn add(&self, other: &Value) -> ValueResult<Value> {
+        if let (Value::String(lhs), Value::String(rhs)) = (self, other) {
+            return Ok(Value::String(format!("{lhs}{rhs}")));
+        }
+        if let Some(result) = self.bools_as_ints(other, Value::add) {
+            return result;
+        }
+        #[cfg(feature = "bigint")]
+        if let Some(result) = self.big_arithmetic(other, i64::checked_add, |lhs, rhs| lhs + rhs) {
+            return Ok(result);
+        }
+        self.arithmetic(other, "+", i64::checked_add, |lhs, rhs| lhs + rhs)
+    }
+
+    /// Booleans are subtracted as `0` and `1` like in `add`.
+    pub fn subtract(&self, other: &Value) -> ValueResult<Value> {
+        if let Some(result) = self.bools_as_ints(other, Value::subtract) {
+            return result;
+        }
+        #[cfg(feature = "bigint")]
+        if let Some(result) = self.big_arithmetic(other, i64::checked_sub, |lhs, rhs| lhs - rhs) {
+            return Ok(result);
+        }
+        self.arithmetic(other, "-", i64::checked_sub, |lhs, rhs| lhs - rhs)
+    }
+
+    /// Booleans are multiplied as `0` and `1` like in `add`.
+    pub fn multiply(&self, other: &Value) -> ValueResult<Value> {
+        if let Some(result) = self.bools_as_ints(other, Value::multiply) {
+            return result;
+        }
+        #[cfg(feature = "bigint")]
+        if let Some(result) = self.big_arithmetic(other, i64::checked_mul, |lhs, rhs| lhs * rhs) {
+            return Ok(result);
+        }
+        self.arithmetic(other, "*", i64::checked_mul, |lhs, rhs| lhs * rhs)
+    }
+
+    /// `add` that computes the result as a float if integer addition
+    /// overflows, see `IntOverflow::Promote`.
+    pub fn add_promote(&self, other: &Value) -> ValueResult<Value> {
+        self.promote_overflow(other, self.add(other), |lhs, rhs| lhs + rhs)
+    }
+
+    /// `subtract` that computes the result as a float if integer subtraction
+    /// overflows, see `IntOverflow::Promote`.
+    pub fn subtract_promote(&self, other: &Value) -> ValueResult<Value> {
+        self.promote_overflow(other, self.subtract(other), |lhs, rhs| lhs - rhs)
+    }
+
+    /// `multiply` that computes the result as a float if integer
+    /// multiplication overflows, see `IntOverflow::Promote`.
+    pub fn multiply_promote(&self, other: &Value) -> ValueResult<Value> {
+        self.promote_overflow(other, self.multiply(other), |lhs, rhs| lhs * rhs)
+    }
+
+    /// Replace an overflow of integer `result` with `float` applied to the
+    /// operands converted to floats.
+    fn promote_overflow(
+        &self,
+        other: &Value,
+        result: ValueResult<Value>,
+        float: fn(f64, f64) -> f64,
+    ) -> ValueResult<Value> {
+        match (result, self, other) {
+            (Err(ValueError::Overflow), Value::Integer(lhs), Value::Integer(rhs)) => {
+                Ok(Value::Float(float(*lhs as f64, *rhs as f64)))
+            }
+            (result, _, _) => result,
+        }
+    }
+
+    /// Integer division truncates towards zero and fails on zero divisor,
+    /// float division follows IEEE semantics.
+    pub fn divide(&self, other: &Value) -> ValueResult<Value> {
+        if let (Value::Integer(_), Value::Integer(0)) = (self, other) {
+            return Err(ValueError::DivisionByZero);
+        }
+        self.arithmetic(other, "/", i64::checked_div, |lhs, rhs| lhs / rhs)
+    }
+
+    /// Remainder has the sign of the dividend like Rust's `%`, booleans are
+    /// `0` and `1` like in `add`.
+    pub fn reminder(&self, other: &Value) -> ValueResult<Value> {
+        if let Some(result) = self.bools_as_ints(other, Value::reminder) {
+            return result;
+        }
+        if let (Value::Integer(_), Value::Integer(0)) = (self, other) {
+            return Err(ValueError::DivisionByZero);
+        }
+        self.arithmetic(other, "%", i64::checked_rem, |lhs, rhs| lhs % rhs)
+    }
+
+    /// Integers raised to negative powers produce floats.
+    pub fn power(&self, other: &Value) -> ValueResult<Value> {
+        match (self, other) {
+            (Value::Integer(lhs), Value::Integer(rhs)) if *rhs < 0 => {
+                Ok(Value::Float(math::powf(*lhs as f64, *rhs as f64)))
+            }
+            _ => self.arithmetic(
+                other,
+                "**",
+                |lhs, rhs| lhs.checked_pow(rhs.try_into().ok()?),
+                math::powf,
+            ),
+        }
+    }
+
+    pub fn negate(&self) -> ValueResult<Value> {
+        #[cfg(feature = "bigint")]
+        match self {
+            Value::Integer(i64::MIN) => return Ok((-&BigInt::from(i64::MIN)).into()),
+            Value::BigInt(big) => return Ok((-big).into()),
+            _ => {}
+        }
+        match self {
+            Value::Integer(int) => int
+                .checked_neg()
+                .map(Value::Integer)
+                .ok_or(ValueError::Overflow),
+            Value::Float(float) => Ok(Value::Float(-float)),
+            _ => Err(ValueError::UnsupportedOperand("-", self.type_name())),
+        }
+    }
+
+    pub fn not(&self) -> Value {
+        (!self.is_truthy()).into()
+    }
+
+    /// Logical operators always return a boolean rather than one of their
+    /// operands, so `1 and 2` is `true` and not `2`. This matches the type
+    /// checker which infers `bool` for every logical expression.
+    pub fn and(&self, other: &Value) -> Value {
+        self.logical(other, |lhs, rhs| lhs && rhs)
+    }
+
+    pub fn or(&self, other: &Value) -> Value {
+        self.logical(other, |lhs, rhs| lhs || rhs)
+    }
+
+    pub fn xor(&self, other: &Value) -> Value {
+        self.logical(other, |lhs, rhs| lhs != rhs)
+    }
+
+    /// Apply logical `operator` to truthiness of both values.
+    fn logical(&self, other: &Value, operator: fn(bool, bool) -> bool) -> Value {
+        operator(self.is_truthy(), other.is_truthy()).into()
+    }
+
+    /// Convert to an integer without silently wrapping, floats must be finite
+    /// and in range of `i64` and `mode` decides whether a fractional part is
+    /// truncated or an error. Strings are parsed as integer literals.
+    pub fn try_to_int(&self, mode: FloatToInt) -> Result<i64, ConversionError> {
+        match self {
+            Value::Integer(int) => Ok(*int),
+            Value::Float(float) if !float.is_finite() => Err(ConversionError::NonFinite(*float)),
+            // `i64::MAX` rounds up to 2^63 as a float so the bound is exclusive
+            Value::Float(float) if math::trunc(*float).abs() >= i64::MAX as f64 => {
+                Err(ConversionError::OutOfRange(*float))
+            }
+            Value::Float(float) if mode == FloatToInt::Exact && math::fract(*float) != 0.0 => {
+                Err(ConversionError::Fractional(*float))
+            }
+            Value::Float(float) => Ok(*float as i64),
+            Value::True => Ok(1),
+            Value::False => Ok(0),
+            Value::String(string) => match Value::from_str_radix_aware(string) {
+                Ok(Value::Integer(int)) => Ok(int),
+                _ => Err(ConversionError::InvalidLiteral(string.clone())),
+            },
+            #[cfg(feature = "bigint")]
+            Value::BigInt(big) => Err(ConversionError::OutOfRange(big.to_f64())),
+            Value::Null | Value::Set(_) | Value::Array(_) => {
+                Err(ConversionError::Unsupported(self.type_name()))
+            }
+        }
+    }
+
+    /// Parse a literal from a string such as `"0b101"`, `"1_000"`, `"1.5e3"`
+    /// or `"true"` using the same rules as literals in source code, leading
+    /// and trailing whitespace is ignored.
+    pub fn from_str_radix_aware(input: &str) -> ParseResult<Value> {
+        let literal = input.trim();
+        // Radix prefixes are tried before decimals since `0b101` starts with
+        // the decimal `0`, likewise floats are tried before decimals
+        let result = alt((
+            all_consuming(parse_hexadecimal),
+            all_consuming(parse_octal),
+            all_consuming(parse_binary),
+            all_consuming(parse_float),
+            all_consuming(parse_decimal),
+            all_consuming(parse_bool),
+            all_consuming(parse_null),
+        ))(literal.into());
+        match result {
+            Ok((_, value)) => Ok(value.ast),
+            Err(_) => {
+                let start = input.len() - input.trim_start().len();
+                let span = Span::new(input, start, start + literal.len()).unwrap();
+                let expected = vec![Rule::value];
+                let kind = if literal.is_empty() {
+                    ParserErrorKind::UnexpectedEof { expected }
+                } else {
+                    ParserErrorKind::UnexpectedToken {
+                        expected,
+                        found: literal.to_string(),
+                    }
+                };
+                Err(ParserError::for_span(span, kind))
+            }
+        }
+    }
+
+    /// Whitespace may only follow the sign, which is a pair of its own, so
+    /// the digits are parsed without it.
     fn parse_float(pair: Pair<Rule>) -> ParseResult<Self> {
-        matches!(pair.as_rule(), Rule::float);
-        let float = pair.as_str();
-        let replaced = float.replace(|ch| ch == ' ' || ch == '_', "");
-        match replaced.parse::<f64>() {
+        expect_rule(&pair, Rule::float)?;
+        let span = pair.as_span();
+        let mut negative = false;
+        let mut digits = "";
+        for inner in pair.into_inner() {
+            match inner.as_rule() {
+                Rule::minus => negative = true,
+                Rule::plus => {}
+                _ => digits = inner.as_str(),
+            }
+        }
+        match digits.replace('_', "").parse::<f64>() {
+            Ok(float) if negative => Ok(Value::Float(-float)),
             Ok(float) => Ok(Value::Float(float)),
-            Err(e) => Err(ParserError::for_pair(pair, e)),
+            Err(e) => Err(ParserError::for_span(span, e)),
         }
     }
 
     fn parse_integer(pair: Pair<Rule>) -> ParseResult<Self> {
-        matches!(pair.as_rule(), Rule::integer);
+        expect_rule(&pair, Rule::integer)?;
         let span = pair.as_span();
 
         let mut inner = pair.into_inner();
         let first = inner.next().unwrap();
+        #[cfg(feature = "bigint")]
+        if let Some(big) = Value::parse_big_integer(first.clone(), inner.clone().next()) {
+            return Ok(big);
+        }
         match inner.next() {
             Some(rule) => match Value::parse_unsigned_integer(rule) {
                 Ok(unsigned) => match first.as_rule() {
@@ -119,15 +1230,45 @@ impl Value {
                     Rule::minus => Ok(Value::Integer(-unsigned)),
                     _ => unreachable!(),
                 },
-                Err(e) => Err(ParserError::for_span(span, e)),
+                Err(e) => Err(Value::integer_error(span, e)),
             },
             None => match Value::parse_unsigned_integer(first) {
                 Ok(int) => Ok(Value::Integer(int)),
-                Err(e) => Err(ParserError::for_span(span, e)),
+                Err(e) => Err(Value::integer_error(span, e)),
             },
         }
     }
 
+    /// Error for integer literal at `span` that failed to parse, overflows
+    /// are reported with the literal's text.
+    fn integer_error(span: Span, error: ParseIntError) -> ParserError {
+        match error.kind() {
+            IntErrorKind::PosOverflow | IntErrorKind::NegOverflow => {
+                let literal = span.as_str().to_string();
+                ParserError::for_span(span, ParserErrorKind::IntegerLiteralOverflow { literal })
+            }
+            _ => ParserError::for_span(span, error),
+        }
+    }
+
+    /// Parse a decimal literal that overflows `i64` as a big integer, `None`
+    /// for any other literal. `sign` is the first pair of the literal and
+    /// `digits` the second one if the literal has a sign.
+    #[cfg(feature = "bigint")]
+    fn parse_big_integer(sign: Pair<Rule>, digits: Option<Pair<Rule>>) -> Option<Value> {
+        let (negative, digits) = match digits {
+            Some(digits) => (sign.as_rule() == Rule::minus, digits),
+            None => (false, sign),
+        };
+        if digits.as_rule() != Rule::decimal
+            || Value::parse_integer_with_radix(digits.as_str(), 10).is_ok()
+        {
+            return None;
+        }
+        let big = BigInt::parse_decimal(&digits.as_str().replace('_', ""))?;
+        Some(if negative { (-&big).into() } else { big.into() })
+    }
+
     fn parse_unsigned_integer(pair: Pair<Rule>) -> Result<i64, ParseIntError> {
         match pair.as_rule() {
             Rule::binary => Value::parse_integer_with_radix(pair.as_str(), 2),
@@ -144,16 +1285,46 @@ impl Value {
             10 => input,
             _ => unreachable!(),
         };
-        let input = input.replace(|ch| ch == ' ' || ch == '_', "");
-        i64::from_str_radix(&input, radix)
+        // The grammar only allows underscores between digits
+        i64::from_str_radix(&input.replace('_', ""), radix)
     }
 }
 
+/// Position of slice bound `bound` in a collection of `len` elements,
+/// clamped to `0..=len` after negative bounds have `len` added.
+fn clamp_bound(bound: i64, len: usize) -> usize {
+    let bound = if bound < 0 {
+        let back = usize::try_from(bound.unsigned_abs()).unwrap_or(usize::MAX);
+        len.saturating_sub(back)
+    } else {
+        usize::try_from(bound).unwrap_or(usize::MAX)
+    };
+    bound.min(len)
+}
+
+/// Position of `index` in a collection of `len` elements, negative indices
+/// have `len` added so they count from the end.
+fn resolve_index(index: i64, len: usize) -> ValueResult<usize> {
+    let position = if index < 0 {
+        i64::try_from(len)
+            .ok()
+            .and_then(|len| index.checked_add(len))
+    } else {
+        Some(index)
+    };
+    position
+        .and_then(|position| usize::try_from(position).ok())
+        .filter(|&position| position < len)
+        .ok_or(ValueError::IndexOutOfBounds { index, len })
+}
+
 #[cfg(test)]
 mod test {
-    use crate::parser::{self, ParseResult, Rule};
+    use std::{cmp::Ordering, collections::HashSet, convert::TryFrom, rc::Rc};
 
-    use super::Value;
+    use crate::parser::{self, ParseResult, ParserErrorKind, Rule};
+
+    use super::{HashableValue, Pattern, Value, ValueError, ValueResult};
 
     fn parse_value(input: &str) -> ParseResult<Value> {
         parser::parse_rule::<Value>(Rule::value, input)
@@ -203,6 +1374,28 @@ mod test {
     }
 
     #[test]
+    fn integer_literal_overflow() {
+        // Decimal literals that overflow are big integers instead
+        #[cfg(not(feature = "bigint"))]
+        {
+            let error = parse_value("99999999999999999999").unwrap_err();
+            assert_eq!(
+                error.kind().to_string(),
+                "integer literal `99999999999999999999` is too large"
+            );
+        }
+        let error = parse_value("-0x1_0000_0000_0000_0000").unwrap_err();
+        match error.kind() {
+            ParserErrorKind::IntegerLiteralOverflow { literal } => {
+                assert_eq!(literal, "-0x1_0000_0000_0000_0000");
+            }
+            kind => panic!("unexpected {kind:?}"),
+        }
+        parse_value("0x7FFF_FFFF_FFFF_FFFF").unwrap();
+    }
+
+    #[test]
+    #[cfg(not(feature = "bigint"))]
     fn overflow_test() {
         let overflow = "1_000_000_000_000_000_000_000_000_000_000";
         assert!(parse_value(overflow).is_err());
@@ -210,6 +1403,707 @@ mod test {
         assert!(parse_value(underflow).is_err());
     }
 
+    #[test]
+    fn from_str_radix_aware() {
+        assert_eq!(Value::from_str_radix_aware("0b101").unwrap(), 5.into());
+        assert_eq!(Value::from_str_radix_aware("-0xFF").unwrap(), (-255).into());
+        assert_eq!(Value::from_str_radix_aware(" 1_000 ").unwrap(), 1000.into());
+        assert_eq!(Value::from_str_radix_aware("2.75").unwrap(), 2.75.into());
+        assert_eq!(Value::from_str_radix_aware("3.05").unwrap(), 3.05.into());
+        assert_eq!(Value::from_str_radix_aware("1.5e3").unwrap(), 1500.0.into());
+        assert_eq!(Value::from_str_radix_aware("true").unwrap(), Value::True);
+        assert_eq!(Value::from_str_radix_aware("null").unwrap(), Value::Null);
+
+        let err = Value::from_str_radix_aware("12x").unwrap_err();
+        assert!(matches!(
+            err.kind(),
+            ParserErrorKind::UnexpectedToken { found, .. } if found == "12x"
+        ));
+        let err = Value::from_str_radix_aware("  ").unwrap_err();
+        assert!(matches!(err.kind(), ParserErrorKind::UnexpectedEof { .. }));
+        let overflow = "1_000_000_000_000_000_000_000_000_000_000";
+        Value::from_str_radix_aware(overflow).unwrap_err();
+        Value::from_str_radix_aware("_1").unwrap_err();
+    }
+
+    #[test]
+    fn float_canonicalization() {
+        assert!(Value::Float(-0.0).strict_eq(&Value::Float(0.0)));
+
+        let hashable = |float: f64| HashableValue::try_from(Value::Float(float)).unwrap();
+        let set = HashSet::from([hashable(0.0)]);
+        assert!(set.contains(&hashable(-0.0)));
+
+        let other_nan = f64::from_bits(f64::NAN.to_bits() ^ 1);
+        assert!(other_nan.is_nan());
+        let set = HashSet::from([hashable(f64::NAN), hashable(other_nan), hashable(-f64::NAN)]);
+        assert_eq!(set.len(), 1);
+    }
+
+    #[test]
+    fn try_to_int() {
+        use super::{ConversionError, FloatToInt::*};
+
+        let int = |value: Value, mode| value.try_to_int(mode);
+        assert_eq!(
+            int(1e30.into(), Truncate),
+            Err(ConversionError::OutOfRange(1e30))
+        );
+        assert_eq!(
+            int((-1e30).into(), Exact),
+            Err(ConversionError::OutOfRange(-1e30))
+        );
+        assert_eq!(int(3.0.into(), Exact), Ok(3));
+        assert_eq!(int(3.5.into(), Truncate), Ok(3));
+        assert_eq!(int((-3.5).into(), Truncate), Ok(-3));
+        assert_eq!(
+            int(3.5.into(), Exact),
+            Err(ConversionError::Fractional(3.5))
+        );
+        assert!(matches!(
+            int(f64::NAN.into(), Truncate),
+            Err(ConversionError::NonFinite(_))
+        ));
+        assert_eq!(
+            int(f64::INFINITY.into(), Truncate),
+            Err(ConversionError::NonFinite(f64::INFINITY))
+        );
+        assert_eq!(int(true.into(), Exact), Ok(1));
+        assert_eq!(int(Value::String("0x10".into()), Exact), Ok(16));
+        assert_eq!(
+            int(Value::String("1.5".into()), Truncate),
+            Err(ConversionError::InvalidLiteral("1.5".into()))
+        );
+        assert_eq!(
+            int(Value::Null, Truncate),
+            Err(ConversionError::Unsupported("null"))
+        );
+    }
+
+    #[test]
+    fn debug_shows_types() {
+        assert_eq!(format!("{:?}", Value::Integer(5)), "Int(5)");
+        assert_eq!(format!("{:?}", Value::Float(5.0)), "Float(5.0)");
+        assert_eq!(format!("{:?}", Value::True), "Bool(true)");
+        assert_eq!(format!("{:?}", Value::False), "Bool(false)");
+        assert_eq!(format!("{:?}", Value::String("x".into())), r#"Str("x")"#);
+        assert_eq!(format!("{:?}", Value::Null), "Null");
+        let set = HashSet::from([HashableValue::try_from(Value::Integer(1)).unwrap()]);
+        assert_eq!(format!("{:?}", Value::Set(set)), "Set({Int(1)})");
+        let array = vec![Value::Integer(1), Value::Float(1.0)];
+        assert_eq!(
+            format!("{:?}", Value::array(array)),
+            "Array([Int(1), Float(1.0)])"
+        );
+    }
+
+    #[test]
+    fn mismatched_rule() {
+        let err = parser::parse_rule::<Value>(Rule::identifier, "abc").unwrap_err();
+        assert!(matches!(
+            err.kind(),
+            ParserErrorKind::UnexpectedRule(Rule::identifier)
+        ));
+    }
+
+    #[test]
+    fn numeric_comparisons() {
+        let one = [Value::Integer(1), Value::Float(1.0)];
+        let two = [Value::Integer(2), Value::Float(2.0)];
+        for lhs in &one {
+            for rhs in &two {
+                assert_eq!(lhs.less_than(rhs), Ok(Value::True));
+                assert_eq!(lhs.less_than_equal(rhs), Ok(Value::True));
+                assert_eq!(lhs.greater_than(rhs), Ok(Value::False));
+                assert_eq!(lhs.greater_than_equal(rhs), Ok(Value::False));
+                assert_eq!(lhs.not_equal(rhs), Value::True);
+
+                assert_eq!(rhs.less_than(lhs), Ok(Value::False));
+                assert_eq!(rhs.greater_than(lhs), Ok(Value::True));
+            }
+            for rhs in &one {
+                assert_eq!(lhs.less_than(rhs), Ok(Value::False));
+                assert_eq!(lhs.less_than_equal(rhs), Ok(Value::True));
+                assert_eq!(lhs.greater_than_equal(rhs), Ok(Value::True));
+                assert_eq!(lhs.not_equal(rhs), Value::False);
+            }
+        }
+    }
+
+    #[test]
+    fn nan_comparisons() {
+        let nan = Value::Float(f64::NAN);
+        for other in [Value::Integer(1), Value::Float(1.0), nan.clone()] {
+            assert_eq!(nan.less_than(&other), Ok(Value::False));
+            assert_eq!(nan.less_than_equal(&other), Ok(Value::False));
+            assert_eq!(nan.greater_than(&other), Ok(Value::False));
+            assert_eq!(nan.greater_than_equal(&other), Ok(Value::False));
+            assert_eq!(nan.not_equal(&other), Value::True);
+        }
+    }
+
+    #[test]
+    fn float_equality() {
+        assert_ne!(Value::Float(1e20), Value::Float(1e20 + 1e5));
+        assert_ne!(Value::Float(1e-20), Value::Float(2e-20));
+        assert_ne!(Value::Float(f64::NAN), Value::Float(f64::NAN));
+        assert_ne!(Value::Float(0.1 + 0.2), Value::Float(0.3));
+        assert_eq!(Value::Float(1e20), Value::Float(1e20));
+
+        assert!(Value::Float(0.1 + 0.2).approx_eq(&Value::Float(0.3)));
+        assert!(Value::Float(1e20).approx_eq(&Value::Float(1e20 + 1.0)));
+        assert!(!Value::Float(1e20).approx_eq(&Value::Float(1e20 + 1e5)));
+        assert!(!Value::Float(1e-20).approx_eq(&Value::Float(2e-20)));
+        assert!(!Value::Float(f64::NAN).approx_eq(&Value::Float(f64::NAN)));
+    }
+
+    #[test]
+    fn incomparable_values() {
+        let err = Value::True.less_than(&Value::Integer(1)).unwrap_err();
+        assert_eq!(err, ValueError::Incomparable("bool", "int"));
+        assert!(Value::Null.greater_than(&Value::Float(1.0)).is_err());
+        assert_eq!(Value::True.not_equal(&Value::Integer(1)), Value::True);
+        assert_eq!(Value::Null.not_equal(&Value::Null), Value::False);
+    }
+
+    #[test]
+    fn strict_equality() {
+        assert!(!Value::Integer(1).strict_eq(&Value::True));
+        assert!(!Value::Integer(0).strict_eq(&Value::False));
+        assert!(!Value::Float(1.0).strict_eq(&Value::True));
+        assert!(!Value::Integer(0).strict_eq(&Value::Null));
+        assert!(Value::Integer(1).strict_eq(&Value::Float(1.0)));
+        assert!(Value::True.strict_eq(&Value::True));
+        assert!(!Value::Float(f64::NAN).strict_eq(&Value::Float(f64::NAN)));
+        assert_eq!(Value::Integer(1).equal(&Value::True), Value::False);
+        assert_eq!(Value::Integer(1).not_equal(&Value::True), Value::True);
+        assert_eq!(Value::Integer(2).equal(&Value::Float(2.0)), Value::True);
+    }
+
+    #[test]
+    fn display() {
+        let string = |string: &str| Value::String(string.to_owned());
+        // Top level strings are written as is
+        assert_eq!(string("a \"b\"").to_string(), "a \"b\"");
+
+        let nested = Value::array(vec![
+            Value::Integer(1),
+            Value::array(vec![Value::Float(2.0), Value::array(Vec::new())]),
+            Value::Null,
+        ]);
+        assert_eq!(nested.to_string(), "[1, [2.0, []], null]");
+
+        let strings = Value::array(vec![
+            string("a, b"),
+            string("say \"hi\"\n"),
+            Value::array(vec![string("\\")]),
+        ]);
+        assert_eq!(strings.to_string(), r#"["a, b", "say \"hi\"\n", ["\\"]]"#);
+
+        let set = [string("b"), string("a"), Value::Integer(1)]
+            .into_iter()
+            .map(|value| HashableValue::try_from(value).unwrap())
+            .collect::<HashSet<_>>();
+        assert_eq!(Value::Set(set).to_string(), r#"set{"a", "b", 1}"#);
+
+        // Strings stay quoted next to an array written inside of itself, and
+        // in a set nested in such an array
+        let set = HashSet::from([HashableValue::try_from(string("c")).unwrap()]);
+        let cyclic = Value::array(vec![string("a"), Value::Null, Value::Set(set)]);
+        cyclic.set_index(&1.into(), cyclic.clone()).unwrap();
+        assert_eq!(cyclic.to_string(), r#"["a", [...], set{"c"}]"#);
+        assert_eq!(
+            Value::array(vec![cyclic.clone()]).to_string(),
+            r#"[["a", [...], set{"c"}]]"#
+        );
+    }
+
+    #[test]
+    fn slice() {
+        let array = Value::array((1..=4).map(Value::Integer).collect());
+        let slice = |start: Value, end: Value| array.slice(&start, &end).unwrap().to_string();
+        assert_eq!(slice(Value::Integer(1), Value::Integer(3)), "[2, 3]");
+        assert_eq!(slice(Value::Null, Value::Integer(2)), "[1, 2]");
+        assert_eq!(slice(Value::Integer(1), Value::Null), "[2, 3, 4]");
+        assert_eq!(slice(Value::Integer(-2), Value::Null), "[3, 4]");
+        // Bounds out of range are clamped
+        assert_eq!(
+            slice(Value::Integer(-10), Value::Integer(10)),
+            "[1, 2, 3, 4]"
+        );
+        assert_eq!(slice(Value::Integer(3), Value::Integer(1)), "[]");
+        assert_eq!(
+            slice(Value::Integer(i64::MIN), Value::Integer(i64::MAX)),
+            "[1, 2, 3, 4]"
+        );
+
+        // Strings are sliced by characters
+        let string = Value::String("héllo".to_owned());
+        assert_eq!(
+            string.slice(&Value::Integer(1), &Value::Integer(-1)),
+            Ok(Value::String("éll".to_owned()))
+        );
+
+        // Slices are copies
+        let copy = array.slice(&Value::Null, &Value::Null).unwrap();
+        copy.set_index(&Value::Integer(0), Value::Integer(0))
+            .unwrap();
+        assert_eq!(array.to_string(), "[1, 2, 3, 4]");
+
+        assert_eq!(
+            array.slice(&Value::True, &Value::Null),
+            Err(ValueError::UnsupportedOperands("[:]", "array", "bool"))
+        );
+        assert_eq!(
+            Value::Integer(1).slice(&Value::Null, &Value::Null),
+            Err(ValueError::UnsupportedOperand("[:]", "int"))
+        );
+    }
+
+    #[test]
+    fn match_patterns() {
+        let matches = |value: &Value, pattern: &Value| value.matches(&pattern.clone().into());
+        // The first arm whose pattern matches is selected, so `match 1 {
+        // true => ...; 1 => ...; _ => ... }` selects the `1` arm
+        let arms = [
+            Pattern::Value(Value::True),
+            Pattern::Value(Value::Float(1.0)),
+            Pattern::Value(Value::Integer(1)),
+            Pattern::Wildcard,
+        ];
+        let arm = arms.iter().position(|arm| Value::Integer(1).matches(arm));
+        assert_eq!(arm, Some(2));
+        let arm = arms.iter().position(|arm| Value::Integer(2).matches(arm));
+        assert_eq!(arm, Some(3));
+
+        assert!(matches(&Value::True, &Value::True));
+        assert!(!matches(&Value::True, &Value::False));
+        assert!(!matches(&Value::Integer(0), &Value::False));
+        assert!(!matches(&Value::Integer(0), &Value::Null));
+        assert!(matches(&Value::Null, &Value::Null));
+        assert!(matches(&Value::Float(-0.0), &Value::Float(0.0)));
+        assert!(!matches(&Value::Float(f64::NAN), &Value::Float(f64::NAN)));
+        assert!(matches(
+            &Value::from("a".to_string()),
+            &Value::from("a".to_string())
+        ));
+
+        let array = Value::array(vec![Value::Integer(1), Value::True]);
+        assert!(matches(&array, &array));
+        assert!(matches(
+            &array,
+            &Value::array(vec![Value::Integer(1), Value::True])
+        ));
+        assert!(!matches(
+            &array,
+            &Value::array(vec![Value::Float(1.0), Value::True])
+        ));
+        assert!(!matches(&array, &Value::array(vec![Value::Integer(1)])));
+    }
+
+    #[test]
+    fn wildcard_pattern() {
+        let cyclic = Value::array(vec![Value::Null]);
+        cyclic.set_index(&0.into(), cyclic.clone()).unwrap();
+        let set = HashSet::from([HashableValue::try_from(Value::Integer(1)).unwrap()]);
+        let values = [
+            Value::Integer(1),
+            Value::Float(1.5),
+            Value::Float(f64::NAN),
+            Value::True,
+            Value::False,
+            Value::Null,
+            Value::from("a".to_string()),
+            Value::Set(set),
+            Value::array(vec![]),
+            Value::array(vec![Value::Integer(1), Value::Null]),
+            cyclic,
+        ];
+        for value in values {
+            assert!(value.matches(&Pattern::Wildcard), "{value:?}");
+        }
+    }
+
+    #[test]
+    fn array_equality() {
+        let ints =
+            |ints: &[i64]| Value::array(ints.iter().map(|int| Value::Integer(*int)).collect());
+
+        assert!(ints(&[1, 2]).strict_eq(&ints(&[1, 2])));
+        assert!(!ints(&[1, 2]).strict_eq(&ints(&[1, 3])));
+        assert!(!ints(&[1, 2]).strict_eq(&ints(&[1, 2, 3])));
+        assert!(ints(&[]).strict_eq(&ints(&[])));
+        // Elements are compared with `strict_eq`
+        assert!(ints(&[1]).strict_eq(&Value::array(vec![Value::Float(1.0)])));
+        assert!(!ints(&[1]).strict_eq(&Value::array(vec![Value::True])));
+        assert!(!ints(&[]).strict_eq(&Value::Null));
+
+        // Distinct arrays are compared element by element
+        let (lhs, rhs) = (ints(&[1, 2]), ints(&[1, 2]));
+        assert!(!lhs.is_identical(&rhs));
+        assert!(lhs.strict_eq(&rhs));
+        assert!(lhs.is_identical(&lhs.clone()));
+        assert!(!Value::Integer(1).is_identical(&Value::Integer(1)));
+        // Identity is checked first, so an array holding NaN equals itself
+        let nan = Value::array(vec![Value::Float(f64::NAN)]);
+        assert!(nan.strict_eq(&nan.clone()));
+        assert!(!nan.strict_eq(&Value::array(vec![Value::Float(f64::NAN)])));
+
+        let nested = |inner: &[i64]| Value::array(vec![Value::True, ints(inner), ints(&[])]);
+        assert!(nested(&[1, 2]).strict_eq(&nested(&[1, 2])));
+        assert!(!nested(&[1, 2]).strict_eq(&nested(&[2, 1])));
+        assert_eq!(nested(&[1]).not_equal(&nested(&[1, 1])), Value::True);
+    }
+
+    #[test]
+    fn array_ordering() {
+        let ints =
+            |ints: &[i64]| Value::array(ints.iter().map(|int| Value::Integer(*int)).collect());
+
+        assert_eq!(ints(&[1, 2]).less_than(&ints(&[1, 3])), Ok(Value::True));
+        assert_eq!(ints(&[2]).greater_than(&ints(&[1, 9])), Ok(Value::True));
+        assert_eq!(ints(&[1]).less_than(&ints(&[1, 0])), Ok(Value::True));
+        assert_eq!(
+            ints(&[1, 2]).less_than_equal(&ints(&[1, 2])),
+            Ok(Value::True)
+        );
+        assert_eq!(
+            Value::array(vec![ints(&[1]), Value::Integer(5)])
+                .greater_than(&Value::array(vec![ints(&[0, 9])])),
+            Ok(Value::True)
+        );
+        // Equal prefixes are skipped even if they aren't ordered
+        let prefixed = |int| Value::array(vec![Value::True, Value::Integer(int)]);
+        assert_eq!(prefixed(1).less_than(&prefixed(2)), Ok(Value::True));
+        assert_eq!(
+            Value::array(vec![Value::True]).less_than(&Value::array(vec![Value::False])),
+            Err(ValueError::Incomparable("bool", "bool"))
+        );
+        assert_eq!(
+            ints(&[1]).less_than(&Value::Integer(1)),
+            Err(ValueError::Incomparable("array", "int"))
+        );
+    }
+
+    #[test]
+    fn string_ordering() {
+        let string = |string: &str| Value::from(string.to_string());
+
+        assert_eq!(string("a").less_than(&string("b")), Ok(Value::True));
+        assert_eq!(
+            string("apple").less_than(&string("banana")),
+            Ok(Value::True)
+        );
+        assert_eq!(string("ab").less_than(&string("abc")), Ok(Value::True));
+        assert_eq!(
+            string("b").greater_than_equal(&string("abc")),
+            Ok(Value::True)
+        );
+        assert_eq!(string("Z").less_than(&string("a")), Ok(Value::True));
+        assert_eq!(string("z").less_than(&string("é")), Ok(Value::True));
+        assert_eq!(string("é").less_than(&string("😀")), Ok(Value::True));
+        assert_eq!(string("abc").equal(&string("abc")), Value::True);
+        assert_eq!(string("abc").not_equal(&string("abd")), Value::True);
+        assert_eq!(
+            string("1").less_than(&Value::Integer(2)),
+            Err(ValueError::Incomparable("string", "int"))
+        );
+        assert_eq!(
+            Value::Float(1.0).greater_than(&string("1")),
+            Err(ValueError::Incomparable("float", "string"))
+        );
+        assert_eq!(string("1").equal(&Value::Integer(1)), Value::False);
+    }
+
+    #[test]
+    fn arithmetic() {
+        assert_eq!(
+            Value::Integer(2).add(&Value::Integer(3)),
+            Ok(Value::Integer(5))
+        );
+        assert_eq!(
+            Value::Integer(2).add(&Value::Float(0.5)),
+            Ok(Value::Float(2.5))
+        );
+        assert_eq!(
+            Value::Float(2.5).subtract(&Value::Integer(1)),
+            Ok(Value::Float(1.5))
+        );
+        assert_eq!(
+            Value::Integer(-7).divide(&Value::Integer(2)),
+            Ok(Value::Integer(-3))
+        );
+        assert_eq!(
+            Value::Integer(-7).reminder(&Value::Integer(2)),
+            Ok(Value::Integer(-1))
+        );
+        assert_eq!(
+            Value::Integer(2).power(&Value::Integer(10)),
+            Ok(Value::Integer(1024))
+        );
+        assert_eq!(
+            Value::Integer(2).power(&Value::Integer(-1)),
+            Ok(Value::Float(0.5))
+        );
+        assert_eq!(Value::Integer(3).negate(), Ok(Value::Integer(-3)));
+        assert_eq!(
+            Value::String("al".to_string()).add(&Value::String("loy".to_string())),
+            Ok(Value::String("alloy".to_string()))
+        );
+        assert_eq!(Value::Integer(0).or(&Value::Float(1.0)), Value::True);
+        assert_eq!(Value::True.xor(&Value::Integer(1)), Value::False);
+        assert_eq!(Value::Null.not(), Value::True);
+    }
+
+    #[test]
+    #[cfg(not(feature = "bigint"))]
+    fn overflow_promotes_to_float() {
+        let max = Value::Integer(i64::MAX);
+        assert_eq!(max.add(&1.into()), Err(ValueError::Overflow));
+        let Ok(Value::Float(sum)) = max.add_promote(&1.into()) else {
+            panic!("expected float")
+        };
+        assert!((sum - 9.223_372_036_854_776e18).abs() < 1.0);
+        assert_eq!(
+            Value::Integer(i64::MIN).subtract_promote(&1.into()),
+            Ok(Value::Float(i64::MIN as f64 - 1.0))
+        );
+        assert_eq!(
+            max.multiply_promote(&2.into()),
+            Ok(Value::Float(i64::MAX as f64 * 2.0))
+        );
+        // Results in range and other errors are unchanged
+        assert_eq!(
+            Value::Integer(1).add_promote(&2.into()),
+            Ok(Value::Integer(3))
+        );
+        assert!(Value::Null.add_promote(&1.into()).is_err());
+    }
+
+    #[test]
+    fn deep_clone() {
+        let inner = Value::array(vec![1.into()]);
+        let outer = Value::array(vec![inner.clone(), inner.clone()]);
+        let copy = outer.deep_clone();
+        assert_eq!(copy, outer);
+        inner.set_index(&0.into(), 2.into()).unwrap();
+        assert_eq!(copy.to_string(), "[[1], [1]]");
+        // Shared arrays stay shared in the copy
+        let Value::Array(elements) = &copy else {
+            panic!("expected array")
+        };
+        elements.borrow()[0].set_index(&0.into(), 3.into()).unwrap();
+        assert_eq!(copy.to_string(), "[[3], [3]]");
+
+        // Cycles are copied without recursing forever
+        let cyclic = Value::array(vec![Value::Null]);
+        cyclic.set_index(&0.into(), cyclic.clone()).unwrap();
+        let Value::Array(copy) = cyclic.deep_clone() else {
+            panic!("expected array")
+        };
+        let Value::Array(element) = &copy.borrow()[0] else {
+            panic!("expected array")
+        };
+        assert!(Rc::ptr_eq(element, &copy));
+        assert_eq!(Value::Integer(5).deep_clone(), Value::Integer(5));
+    }
+
+    #[test]
+    fn cyclic_arrays() {
+        let cyclic = || {
+            let array = Value::array(vec![1.into(), Value::Null]);
+            array.set_index(&1.into(), array.clone()).unwrap();
+            array
+        };
+        let (lhs, rhs) = (cyclic(), cyclic());
+        // Arrays inside of themselves are written as `[...]`
+        assert_eq!(lhs.to_string(), "[1, [...]]");
+        assert_eq!(format!("{lhs:?}"), "Array([Int(1), [...]])");
+        let outer = Value::array(vec![lhs.clone(), lhs.clone()]);
+        assert_eq!(outer.to_string(), "[[1, [...]], [1, [...]]]");
+
+        // and are compared by identity
+        assert!(lhs.strict_eq(&lhs));
+        assert!(!lhs.strict_eq(&rhs));
+        assert_eq!(lhs.compare(&lhs), Ok(Some(Ordering::Equal)));
+        assert_eq!(lhs.compare(&rhs), Ok(None));
+        assert!(lhs.matches(&lhs.clone().into()));
+        assert!(!lhs.matches(&rhs.clone().into()));
+        let shared = Value::array(vec![1.into(), lhs.clone()]);
+        assert!(shared.strict_eq(&lhs));
+        assert!(shared.matches(&lhs.clone().into()));
+        assert_eq!(shared.compare(&lhs), Ok(Some(Ordering::Equal)));
+        assert_eq!(lhs, lhs);
+        assert_ne!(lhs, rhs);
+        assert_eq!(shared, lhs);
+        assert!(lhs.approx_eq(&lhs));
+        assert!(!lhs.approx_eq(&rhs));
+    }
+
+    #[test]
+    fn logical_truth_tables() {
+        type Operator = fn(&Value, &Value) -> Value;
+        let tables: [(Operator, [bool; 4]); 3] = [
+            (Value::and, [false, false, false, true]),
+            (Value::or, [false, true, true, true]),
+            (Value::xor, [false, true, true, false]),
+        ];
+        // Falsy and truthy operands of different types, results are always
+        // booleans rather than one of the operands
+        let falsy = [
+            Value::False,
+            Value::Null,
+            Value::Integer(0),
+            Value::Float(0.0),
+        ];
+        let truthy = [Value::True, Value::Integer(2), Value::Float(0.5)];
+        for (operator, expected) in tables {
+            for lhs in falsy.iter().chain(&truthy) {
+                for rhs in falsy.iter().chain(&truthy) {
+                    let row = usize::from(lhs.is_truthy()) * 2 + usize::from(rhs.is_truthy());
+                    assert_eq!(operator(lhs, rhs), expected[row].into(), "{lhs} {rhs}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn arithmetic_errors() {
+        assert_eq!(
+            Value::Integer(1).add(&Value::Null),
+            Err(ValueError::UnsupportedOperands("+", "int", "null"))
+        );
+        assert_eq!(
+            Value::True.negate(),
+            Err(ValueError::UnsupportedOperand("-", "bool"))
+        );
+        assert_eq!(
+            Value::Integer(1).divide(&Value::Integer(0)),
+            Err(ValueError::DivisionByZero)
+        );
+        assert_eq!(
+            Value::Integer(1).reminder(&Value::Integer(0)),
+            Err(ValueError::DivisionByZero)
+        );
+        #[cfg(not(feature = "bigint"))]
+        assert_eq!(
+            Value::Integer(i64::MAX).add(&Value::Integer(1)),
+            Err(ValueError::Overflow)
+        );
+        #[cfg(not(feature = "bigint"))]
+        assert_eq!(Value::Integer(i64::MIN).negate(), Err(ValueError::Overflow));
+        assert_eq!(
+            Value::Integer(2).power(&Value::Integer(64)),
+            Err(ValueError::Overflow)
+        );
+        assert_eq!(
+            Value::Float(1.0).divide(&Value::Integer(0)),
+            Ok(Value::Float(f64::INFINITY))
+        );
+    }
+
+    #[test]
+    fn bool_arithmetic() -> ValueResult<()> {
+        assert_eq!(
+            Value::False.subtract(&Value::Integer(5))?,
+            Value::Integer(-5)
+        );
+        assert_eq!(Value::Integer(5).subtract(&Value::True)?, Value::Integer(4));
+        assert_eq!(Value::True.add(&Value::True)?, Value::Integer(2));
+        assert_eq!(Value::True.multiply(&Value::Float(2.5))?, Value::Float(2.5));
+        assert_eq!(
+            Value::False.reminder(&Value::Integer(3))?,
+            Value::Integer(0)
+        );
+        assert_eq!(
+            Value::Integer(5).reminder(&Value::False),
+            Err(ValueError::DivisionByZero)
+        );
+
+        type Operation = fn(&Value, &Value) -> ValueResult<Value>;
+        let operations: [Operation; 4] = [
+            Value::add,
+            Value::subtract,
+            Value::multiply,
+            Value::reminder,
+        ];
+        // Booleans act as 0 and 1 on either side of every operator
+        for operation in operations {
+            for (boolean, int) in [
+                (Value::True, Value::Integer(1)),
+                (Value::False, Value::Integer(0)),
+            ] {
+                for number in [
+                    Value::Integer(5),
+                    Value::Float(5.0),
+                    Value::Integer(0),
+                    Value::True,
+                ] {
+                    let number_int = match number {
+                        Value::True => Value::Integer(1),
+                        _ => number.clone(),
+                    };
+                    // Compared by `Debug` so `5.0 % 0` is equal to itself
+                    assert_eq!(
+                        format!("{:?}", operation(&boolean, &number)),
+                        format!("{:?}", operation(&int, &number_int)),
+                        "{boolean} {number}"
+                    );
+                    assert_eq!(
+                        format!("{:?}", operation(&number, &boolean)),
+                        format!("{:?}", operation(&number_int, &int)),
+                        "{number} {boolean}"
+                    );
+                }
+            }
+        }
+        // Only numbers are converted, booleans don't turn strings into numbers
+        assert_eq!(
+            Value::True.add(&Value::String("a".into())),
+            Err(ValueError::UnsupportedOperands("+", "bool", "string"))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn hashable_values() {
+        use std::{collections::HashSet, convert::TryFrom};
+
+        use super::HashableValue;
+
+        let set = [
+            Value::Integer(1),
+            Value::Float(1.0),
+            Value::True,
+            Value::Float(f64::NAN),
+            Value::Float(f64::NAN),
+            Value::Integer(1),
+        ]
+        .into_iter()
+        .map(|value| HashableValue::try_from(value).unwrap())
+        .collect::<HashSet<_>>();
+        assert_eq!(set.len(), 4);
+
+        let set = Value::Set(set);
+        assert_eq!(set.type_name(), "set");
+        assert_eq!(set.to_string(), "set{1, 1.0, NaN, true}");
+        assert_eq!(set.contains(&Value::Float(1.0)), Ok(Value::True));
+        assert_eq!(set.contains(&Value::False), Ok(Value::False));
+        assert_eq!(
+            HashableValue::try_from(set.clone()).unwrap_err(),
+            ValueError::Unhashable("set")
+        );
+        assert_eq!(set.contains(&set), Err(ValueError::Unhashable("set")));
+        assert_eq!(
+            Value::Integer(1).contains(&Value::Integer(1)),
+            Err(ValueError::UnsupportedOperands("in", "int", "int"))
+        );
+        assert_eq!(
+            Value::String("alloy".to_string()).contains(&Value::String("llo".to_string())),
+            Ok(Value::True)
+        );
+    }
+
     #[test]
     fn parse_float() {
         test_float("1.0", 1.);
@@ -218,5 +2112,80 @@ mod test {
         test_float("1.", 1.0);
         test_float("-1.", -1.0);
         test_float("-.2", -0.2);
+        test_float("- 1_000.000_5", -1000.0005);
+        test_float("+ .5", 0.5);
+        assert!(parse_value("-0.0").unwrap().strict_eq(&Value::Float(-0.0)));
+    }
+
+    #[test]
+    fn digit_separators() {
+        test_integer("1_000", 1_000);
+        test_integer("0b1_0", 2);
+        test_integer("0xF_F", 255);
+        test_float("1_0.0_1", 10.01);
+        // Whitespace and underscores that don't separate two digits
+        for input in [
+            "1 000", "1__0", "1_", "1_.5", "1._5", "0x_F", "0xF__F", "0b1_", "- 1 0",
+        ] {
+            assert!(parser::parse_expression_str(input).is_err(), "{input}");
+        }
+    }
+
+    #[test]
+    fn truthiness() {
+        assert!(Value::Integer(-1).is_truthy());
+        assert!(!Value::Integer(0).is_truthy());
+        assert!(Value::Float(f64::NAN).is_truthy());
+        assert!(!Value::Float(-0.0).is_truthy());
+        assert!(Value::True.is_truthy());
+        assert!(!Value::False.is_truthy());
+        assert!(!Value::Null.is_truthy());
+        assert!(Value::String("false".to_string()).is_truthy());
+        assert!(!Value::String(String::new()).is_truthy());
+    }
+
+    #[test]
+    fn exact_mixed_comparisons() {
+        // 2^53 + 1 rounds to 2^53 as a float
+        let big = Value::Integer((1 << 53) + 1);
+        let float = Value::Float(9007199254740992.0);
+        assert_eq!(big.greater_than(&float), Ok(Value::True));
+        assert_eq!(float.less_than(&big), Ok(Value::True));
+        assert_eq!(big.equal(&float), Value::False);
+        assert_eq!(Value::Integer(1 << 53).equal(&float), Value::True);
+        assert_eq!(
+            Value::Integer(-(1 << 53) - 1).less_than(&Value::Float(-9007199254740992.0)),
+            Ok(Value::True)
+        );
+
+        // Fractional parts decide between equal integer parts
+        assert_eq!(
+            Value::Integer(2).less_than(&Value::Float(2.5)),
+            Ok(Value::True)
+        );
+        assert_eq!(
+            Value::Integer(-2).greater_than(&Value::Float(-2.5)),
+            Ok(Value::True)
+        );
+        assert_eq!(Value::Integer(0).equal(&Value::Float(-0.0)), Value::True);
+
+        // Floats outside of the range of `i64`
+        let max = Value::Integer(i64::MAX);
+        assert_eq!(
+            max.less_than(&Value::Float(9223372036854775808.0)),
+            Ok(Value::True)
+        );
+        assert_eq!(max.less_than(&Value::Float(f64::INFINITY)), Ok(Value::True));
+        let min = Value::Integer(i64::MIN);
+        assert_eq!(
+            min.equal(&Value::Float(-9223372036854775808.0)),
+            Value::True
+        );
+        assert_eq!(min.greater_than(&Value::Float(-1e19)), Ok(Value::True));
+        assert_eq!(
+            min.greater_than(&Value::Float(f64::NEG_INFINITY)),
+            Ok(Value::True)
+        );
+        assert_eq!(min.compare(&Value::Float(f64::NAN)), Ok(None));
     }
 }
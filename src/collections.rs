@@ -0,0 +1,6 @@
+//! Hash collections of `std`, or of `hashbrown` when building without it.
+
+#[cfg(not(feature = "std"))]
+pub use hashbrown::{HashMap, HashSet};
+#[cfg(feature = "std")]
+pub use std::collections::{HashMap, HashSet};
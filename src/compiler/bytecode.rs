@@ -0,0 +1,633 @@
+//! Binary encoding of compiled programs used by `.alloyc` files. Files are
+//! loaded from untrusted sources, so decoding checks every length and operand
+//! against the input and the decoded program instead of trusting the file.
+//!
+//! All integers are little endian and lengths are `u32` prefixes. A file is
+//! the magic bytes and format version followed by the main code block:
+//!
+//! - instructions, each an opcode followed by its operands
+//! - constants, each a tag followed by its payload
+//! - functions, each its name, arity and code block without constants or
+//!   functions of its own
+//! - spans, each an instruction index with the start and end of its source
+//! - locals, each a name, slot and the scope of the local
+
+use alloc::{
+    rc::Rc,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::{convert::TryFrom, str};
+
+use thiserror::Error;
+
+#[cfg(feature = "bigint")]
+use crate::ast::bigint::BigInt;
+use crate::{ast::value::Value, vm::native};
+
+use super::{
+    code_block::{CodeBlock, Function, LocalInfo},
+    Instruction,
+};
+
+const MAGIC: &[u8; 4] = b"ALYC";
+const VERSION: u8 = 1;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum DecodeError {
+    #[error("not a compiled alloy program")]
+    BadMagic,
+    #[error("unsupported bytecode version {0}")]
+    UnsupportedVersion(u8),
+    #[error("unexpected end of input at byte {0}")]
+    UnexpectedEof(usize),
+    #[error("length {len} at byte {offset} exceeds the rest of the input")]
+    LengthOutOfBounds { len: usize, offset: usize },
+    #[error("unknown opcode {opcode} at byte {offset}")]
+    UnknownOpcode { opcode: u8, offset: usize },
+    #[error("unknown value tag {tag} at byte {offset}")]
+    UnknownValueTag { tag: u8, offset: usize },
+    #[error("invalid UTF-8 string at byte {0}")]
+    InvalidUtf8(usize),
+    #[error("invalid integer at byte {0}")]
+    InvalidInteger(usize),
+    #[error("invalid operand of `{instruction}` at instruction {pc}")]
+    InvalidOperand { instruction: String, pc: usize },
+    #[error("{0} unexpected bytes after the end of the program")]
+    TrailingBytes(usize),
+}
+
+pub type DecodeResult<T> = Result<T, DecodeError>;
+
+impl CodeBlock {
+    /// Encode the code block together with its functions.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a constant is a set or an array, the compiler only
+    /// registers literals as constants.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = MAGIC.to_vec();
+        out.push(VERSION);
+        write_block(&mut out, self, true);
+        out
+    }
+
+    /// Decode a code block encoded by `CodeBlock::to_bytes`. Malformed input
+    /// is rejected with an error, and so are operands that index past the
+    /// constants, functions or instructions of the decoded program.
+    pub fn from_bytes(bytes: &[u8]) -> DecodeResult<CodeBlock> {
+        let mut reader = Reader::new(bytes);
+        if reader.take(MAGIC.len()).ok() != Some(MAGIC) {
+            return Err(DecodeError::BadMagic);
+        }
+        let version = reader.u8()?;
+        if version != VERSION {
+            return Err(DecodeError::UnsupportedVersion(version));
+        }
+        let code = reader.block(true)?;
+        if reader.remaining() > 0 {
+            return Err(DecodeError::TrailingBytes(reader.remaining()));
+        }
+        validate(&code, &code)?;
+        for function in &code.functions {
+            validate(&code, &function.code)?;
+        }
+        Ok(code)
+    }
+}
+
+/// Check operands of `block`'s instructions against the constants and
+/// functions of `main`, jumps may target one past the last instruction.
+fn validate(main: &CodeBlock, block: &CodeBlock) -> DecodeResult<()> {
+    for (pc, instruction) in block.instructions.iter().enumerate() {
+        let valid = match *instruction {
+            Instruction::LoadValue(idx) => usize::from(idx) < main.values.len(),
+            Instruction::Call(idx, argc) | Instruction::TailCall(idx, argc) => main
+                .functions
+                .get(usize::from(idx))
+                .is_some_and(|function| function.arity == argc),
+            Instruction::CallNative(idx, _) => native::get_by_index(idx).is_some(),
+            _ => match instruction.jump_target() {
+                Some(target) => usize::from(target) <= block.instructions.len(),
+                None => true,
+            },
+        };
+        if !valid {
+            return Err(DecodeError::InvalidOperand {
+                instruction: instruction.to_string(),
+                pc,
+            });
+        }
+    }
+    Ok(())
+}
+
+fn write_block(out: &mut Vec<u8>, block: &CodeBlock, is_main: bool) {
+    write_len(out, block.instructions.len());
+    for instruction in &block.instructions {
+        write_instruction(out, *instruction);
+    }
+    if is_main {
+        write_len(out, block.values.len());
+        for value in block.values.iter() {
+            write_value(out, value);
+        }
+        write_len(out, block.functions.len());
+        for function in &block.functions {
+            write_str(out, &function.name);
+            out.push(function.arity);
+            write_block(out, &function.code, false);
+        }
+    }
+    write_len(out, block.spans.len());
+    for (&pc, &(start, end)) in &block.spans {
+        write_len(out, pc);
+        write_len(out, start);
+        write_len(out, end);
+    }
+    write_len(out, block.locals.len());
+    for local in &block.locals {
+        write_str(out, &local.name);
+        out.extend_from_slice(&local.slot.to_le_bytes());
+        write_len(out, local.scope_start);
+        write_len(out, local.scope_end);
+    }
+}
+
+fn write_len(out: &mut Vec<u8>, len: usize) {
+    let len = u32::try_from(len).expect("lengths and offsets fit in `u32`");
+    out.extend_from_slice(&len.to_le_bytes());
+}
+
+fn write_str(out: &mut Vec<u8>, string: &str) {
+    write_len(out, string.len());
+    out.extend_from_slice(string.as_bytes());
+}
+
+fn write_value(out: &mut Vec<u8>, value: &Value) {
+    match value {
+        Value::Integer(int) => {
+            out.push(0);
+            out.extend_from_slice(&int.to_le_bytes());
+        }
+        Value::Float(float) => {
+            out.push(1);
+            out.extend_from_slice(&float.to_bits().to_le_bytes());
+        }
+        Value::True => out.push(2),
+        Value::False => out.push(3),
+        Value::Null => out.push(4),
+        Value::String(string) => {
+            out.push(5);
+            write_str(out, string);
+        }
+        #[cfg(feature = "bigint")]
+        Value::BigInt(big) => {
+            out.push(6);
+            write_str(out, &big.to_string());
+        }
+        Value::Set(_) | Value::Array(_) => {
+            panic!("`{}` can't be a constant", value.type_name())
+        }
+    }
+}
+
+/// Opcodes follow the order of `Instruction`'s variants.
+fn write_instruction(out: &mut Vec<u8>, instruction: Instruction) {
+    let (opcode, operand, argc) = match instruction {
+        Instruction::StoreSymbol(idx) => (0, Some(idx), None),
+        Instruction::LoadSymbol(idx) => (1, Some(idx), None),
+        Instruction::StoreLocal(idx) => (2, Some(idx), None),
+        Instruction::LoadLocal(idx) => (3, Some(idx), None),
+        Instruction::LoadValue(idx) => (4, Some(idx), None),
+        Instruction::Pop => (5, None, None),
+        Instruction::PopN(count) => (6, Some(count), None),
+        Instruction::Dup => (7, None, None),
+        Instruction::Display => (8, None, None),
+        Instruction::Jump(target) => (9, Some(target), None),
+        Instruction::JumpIfTrue(target) => (10, Some(target), None),
+        Instruction::JumpIfFalse(target) => (11, Some(target), None),
+        Instruction::JumpUnlessLessThan(target) => (12, Some(target), None),
+        Instruction::JumpUnlessLessThanEqual(target) => (13, Some(target), None),
+        Instruction::JumpUnlessGreaterThan(target) => (14, Some(target), None),
+        Instruction::JumpUnlessGreaterThanEqual(target) => (15, Some(target), None),
+        Instruction::JumpUnlessEqual(target) => (16, Some(target), None),
+        Instruction::JumpUnlessNotEqual(target) => (17, Some(target), None),
+        Instruction::BinaryAdd => (18, None, None),
+        Instruction::BinarySubtract => (19, None, None),
+        Instruction::BinaryMultiply => (20, None, None),
+        Instruction::BinaryDivide => (21, None, None),
+        Instruction::BinaryReminder => (22, None, None),
+        Instruction::BinaryPower => (23, None, None),
+        Instruction::BinaryLessThan => (24, None, None),
+        Instruction::BinaryLessThanEqual => (25, None, None),
+        Instruction::BinaryGreaterThan => (26, None, None),
+        Instruction::BinaryGreaterThanEqual => (27, None, None),
+        Instruction::BinaryEqual => (28, None, None),
+        Instruction::BinaryNotEqual => (29, None, None),
+        Instruction::BinaryLogicalAnd => (30, None, None),
+        Instruction::BinaryLogicalOr => (31, None, None),
+        Instruction::BinaryLogicalXor => (32, None, None),
+        Instruction::Contains => (33, None, None),
+        Instruction::BuildSet(count) => (34, Some(count), None),
+        Instruction::BuildArray(count) => (35, Some(count), None),
+        Instruction::SetIndex => (36, None, None),
+        Instruction::Push => (37, None, None),
+        Instruction::UnaryMinus => (38, None, None),
+        Instruction::UnaryNot => (39, None, None),
+        Instruction::Call(idx, argc) => (40, Some(idx), Some(argc)),
+        Instruction::TailCall(idx, argc) => (41, Some(idx), Some(argc)),
+        Instruction::CallHost(idx, argc) => (42, Some(idx), Some(argc)),
+        Instruction::CallNative(idx, argc) => (43, Some(idx), Some(argc)),
+        Instruction::Return => (44, None, None),
+        Instruction::GetIter => (45, None, None),
+        Instruction::IterNext(target) => (46, Some(target), None),
+        Instruction::GetIndex => (47, None, None),
+        Instruction::Slice => (48, None, None),
+    };
+    out.push(opcode);
+    if let Some(operand) = operand {
+        out.extend_from_slice(&operand.to_le_bytes());
+    }
+    if let Some(argc) = argc {
+        out.push(argc);
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, offset: 0 }
+    }
+
+    fn remaining(&self) -> usize {
+        self.bytes.len() - self.offset
+    }
+
+    fn take(&mut self, len: usize) -> DecodeResult<&'a [u8]> {
+        if len > self.remaining() {
+            return Err(DecodeError::UnexpectedEof(self.offset));
+        }
+        let bytes = &self.bytes[self.offset..self.offset + len];
+        self.offset += len;
+        Ok(bytes)
+    }
+
+    fn array<const N: usize>(&mut self) -> DecodeResult<[u8; N]> {
+        let mut array = [0; N];
+        array.copy_from_slice(self.take(N)?);
+        Ok(array)
+    }
+
+    fn u8(&mut self) -> DecodeResult<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> DecodeResult<u16> {
+        Ok(u16::from_le_bytes(self.array()?))
+    }
+
+    fn u32(&mut self) -> DecodeResult<usize> {
+        let int = u32::from_le_bytes(self.array()?);
+        usize::try_from(int).map_err(|_| DecodeError::InvalidInteger(self.offset - 4))
+    }
+
+    /// Number of items that follow, each taking at least `item_size` bytes,
+    /// so lengths are rejected before anything is allocated for them.
+    fn len(&mut self, item_size: usize) -> DecodeResult<usize> {
+        let offset = self.offset;
+        let len = self.u32()?;
+        if len.saturating_mul(item_size) > self.remaining() {
+            return Err(DecodeError::LengthOutOfBounds { len, offset });
+        }
+        Ok(len)
+    }
+
+    fn str(&mut self) -> DecodeResult<&'a str> {
+        let len = self.len(1)?;
+        let offset = self.offset;
+        str::from_utf8(self.take(len)?).map_err(|_| DecodeError::InvalidUtf8(offset))
+    }
+
+    fn block(&mut self, is_main: bool) -> DecodeResult<CodeBlock> {
+        let mut block = CodeBlock::default();
+        let len = self.len(1)?;
+        block.instructions = (0..len)
+            .map(|_| self.instruction())
+            .collect::<DecodeResult<_>>()?;
+        if is_main {
+            let len = self.len(1)?;
+            block.values = Rc::new(
+                (0..len)
+                    .map(|_| self.value())
+                    .collect::<DecodeResult<_>>()?,
+            );
+            // Name length, arity and the lengths of the function's block
+            let len = self.len(4 + 1 + 3 * 4)?;
+            block.functions = (0..len)
+                .map(|_| self.function())
+                .collect::<DecodeResult<_>>()?;
+        }
+        let len = self.len(3 * 4)?;
+        for _ in 0..len {
+            let pc = self.u32()?;
+            let span = (self.u32()?, self.u32()?);
+            block.spans.insert(pc, span);
+        }
+        let len = self.len(4 + 2 + 2 * 4)?;
+        block.locals = (0..len)
+            .map(|_| self.local())
+            .collect::<DecodeResult<_>>()?;
+        Ok(block)
+    }
+
+    fn function(&mut self) -> DecodeResult<Rc<Function>> {
+        let name = self.str()?.to_string();
+        let arity = self.u8()?;
+        let code = self.block(false)?;
+        Ok(Rc::new(Function { name, arity, code }))
+    }
+
+    fn local(&mut self) -> DecodeResult<LocalInfo> {
+        Ok(LocalInfo {
+            name: self.str()?.to_string(),
+            slot: self.u16()?,
+            scope_start: self.u32()?,
+            scope_end: self.u32()?,
+        })
+    }
+
+    fn value(&mut self) -> DecodeResult<Value> {
+        let offset = self.offset;
+        let value = match self.u8()? {
+            0 => Value::Integer(i64::from_le_bytes(self.array()?)),
+            1 => Value::Float(f64::from_bits(u64::from_le_bytes(self.array()?))),
+            2 => Value::True,
+            3 => Value::False,
+            4 => Value::Null,
+            5 => Value::String(self.str()?.to_string()),
+            #[cfg(feature = "bigint")]
+            6 => {
+                let digits_offset = self.offset;
+                let digits = self.str()?;
+                let big = match digits.strip_prefix('-') {
+                    Some(magnitude) => BigInt::parse_decimal(magnitude).map(|big| -&big),
+                    None => BigInt::parse_decimal(digits),
+                };
+                big.map(Value::from)
+                    .ok_or(DecodeError::InvalidInteger(digits_offset))?
+            }
+            tag => return Err(DecodeError::UnknownValueTag { tag, offset }),
+        };
+        Ok(value)
+    }
+
+    fn instruction(&mut self) -> DecodeResult<Instruction> {
+        let offset = self.offset;
+        let instruction = match self.u8()? {
+            0 => Instruction::StoreSymbol(self.u16()?),
+            1 => Instruction::LoadSymbol(self.u16()?),
+            2 => Instruction::StoreLocal(self.u16()?),
+            3 => Instruction::LoadLocal(self.u16()?),
+            4 => Instruction::LoadValue(self.u16()?),
+            5 => Instruction::Pop,
+            6 => Instruction::PopN(self.u16()?),
+            7 => Instruction::Dup,
+            8 => Instruction::Display,
+            9 => Instruction::Jump(self.u16()?),
+            10 => Instruction::JumpIfTrue(self.u16()?),
+            11 => Instruction::JumpIfFalse(self.u16()?),
+            12 => Instruction::JumpUnlessLessThan(self.u16()?),
+            13 => Instruction::JumpUnlessLessThanEqual(self.u16()?),
+            14 => Instruction::JumpUnlessGreaterThan(self.u16()?),
+            15 => Instruction::JumpUnlessGreaterThanEqual(self.u16()?),
+            16 => Instruction::JumpUnlessEqual(self.u16()?),
+            17 => Instruction::JumpUnlessNotEqual(self.u16()?),
+            18 => Instruction::BinaryAdd,
+            19 => Instruction::BinarySubtract,
+            20 => Instruction::BinaryMultiply,
+            21 => Instruction::BinaryDivide,
+            22 => Instruction::BinaryReminder,
+            23 => Instruction::BinaryPower,
+            24 => Instruction::BinaryLessThan,
+            25 => Instruction::BinaryLessThanEqual,
+            26 => Instruction::BinaryGreaterThan,
+            27 => Instruction::BinaryGreaterThanEqual,
+            28 => Instruction::BinaryEqual,
+            29 => Instruction::BinaryNotEqual,
+            30 => Instruction::BinaryLogicalAnd,
+            31 => Instruction::BinaryLogicalOr,
+            32 => Instruction::BinaryLogicalXor,
+            33 => Instruction::Contains,
+            34 => Instruction::BuildSet(self.u16()?),
+            35 => Instruction::BuildArray(self.u16()?),
+            36 => Instruction::SetIndex,
+            37 => Instruction::Push,
+            38 => Instruction::UnaryMinus,
+            39 => Instruction::UnaryNot,
+            40 => Instruction::Call(self.u16()?, self.u8()?),
+            41 => Instruction::TailCall(self.u16()?, self.u8()?),
+            42 => Instruction::CallHost(self.u16()?, self.u8()?),
+            43 => Instruction::CallNative(self.u16()?, self.u8()?),
+            44 => Instruction::Return,
+            45 => Instruction::GetIter,
+            46 => Instruction::IterNext(self.u16()?),
+            47 => Instruction::GetIndex,
+            48 => Instruction::Slice,
+            opcode => return Err(DecodeError::UnknownOpcode { opcode, offset }),
+        };
+        Ok(instruction)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{compiler::compile_program, parser, vm::VM};
+
+    use super::{write_instruction, CodeBlock, DecodeError, Instruction, Reader};
+
+    const OPCODES: u8 = 49;
+
+    fn compile(input: &str) -> CodeBlock {
+        compile_program(&parser::parse(input).unwrap()).unwrap()
+    }
+
+    fn run(code_block: &CodeBlock) -> String {
+        let mut output = Vec::new();
+        VM::new_with_output(&mut output).run(code_block).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn round_trip() {
+        let program = "fn fib(n) { if n < 2 { return n; } return fib(n - 1) + fib(n - 2); }
+            var s = set{1, 2};
+            for i in [1.5, -0.0] { print i; }
+            print fib(10); print 2 in s;";
+        let code_block = compile(program);
+        let decoded = CodeBlock::from_bytes(&code_block.to_bytes()).unwrap();
+        assert_eq!(decoded.to_string(), code_block.to_string());
+        assert_eq!(decoded.values, code_block.values);
+        assert_eq!(decoded.spans, code_block.spans);
+        assert_eq!(decoded.locals, code_block.locals);
+        assert_eq!(
+            decoded.functions[0].code.locals,
+            code_block.functions[0].code.locals
+        );
+        assert_eq!(run(&decoded), run(&code_block));
+        assert_eq!(decoded.to_bytes(), code_block.to_bytes());
+    }
+
+    #[test]
+    #[cfg(feature = "bigint")]
+    fn bigint_round_trip() {
+        let code_block = compile("print 99999999999999999999; print -99999999999999999999;");
+        let decoded = CodeBlock::from_bytes(&code_block.to_bytes()).unwrap();
+        assert_eq!(decoded.values, code_block.values);
+        assert_eq!(
+            run(&decoded),
+            "99999999999999999999\n-99999999999999999999\n"
+        );
+    }
+
+    #[test]
+    fn every_opcode_round_trips() {
+        // Opcode followed by enough operand bytes for any instruction
+        for opcode in 0..=u8::MAX {
+            let bytes = [opcode, 1, 2, 3];
+            let mut reader = Reader::new(&bytes);
+            match reader.instruction() {
+                Ok(instruction) => {
+                    assert!(opcode < OPCODES, "{instruction}");
+                    let mut out = Vec::new();
+                    write_instruction(&mut out, instruction);
+                    assert_eq!(out, bytes[..reader.offset]);
+                }
+                Err(error) => {
+                    assert!(opcode >= OPCODES);
+                    assert_eq!(error, DecodeError::UnknownOpcode { opcode, offset: 0 });
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn malformed_inputs() {
+        let valid = compile("fn f(x) { return x + 1; } print f(2.5);").to_bytes();
+        let header = |rest: &[u8]| [&b"ALYC\x01"[..], rest].concat();
+        // Empty constants, functions, spans and locals after the instructions
+        let block = |instructions: &[u8]| header(&[instructions, &[0; 16]].concat());
+        let corpus: Vec<(Vec<u8>, DecodeError)> = vec![
+            (Vec::new(), DecodeError::BadMagic),
+            (b"ALY".to_vec(), DecodeError::BadMagic),
+            (b"ALYX\x01".to_vec(), DecodeError::BadMagic),
+            (b"ALYC".to_vec(), DecodeError::UnexpectedEof(4)),
+            (b"ALYC\x02".to_vec(), DecodeError::UnsupportedVersion(2)),
+            (header(&[]), DecodeError::UnexpectedEof(5)),
+            (header(&[1, 0]), DecodeError::UnexpectedEof(5)),
+            // Huge length prefixes are rejected before allocating
+            (
+                header(&[0xff, 0xff, 0xff, 0xff]),
+                DecodeError::LengthOutOfBounds {
+                    len: u32::MAX as usize,
+                    offset: 5,
+                },
+            ),
+            (
+                header(&[0, 0, 0, 0, 0xff, 0xff, 0xff, 0x0f]),
+                DecodeError::LengthOutOfBounds {
+                    len: 0x0fff_ffff,
+                    offset: 9,
+                },
+            ),
+            // Truncated operand
+            (header(&[1, 0, 0, 0, 4, 0]), DecodeError::UnexpectedEof(10)),
+            (
+                header(&[1, 0, 0, 0, 200, 0, 0, 0]),
+                DecodeError::UnknownOpcode {
+                    opcode: 200,
+                    offset: 9,
+                },
+            ),
+            (
+                header(&[0, 0, 0, 0, 1, 0, 0, 0, 9]),
+                DecodeError::UnknownValueTag { tag: 9, offset: 13 },
+            ),
+            (
+                header(&[0, 0, 0, 0, 1, 0, 0, 0, 5, 2, 0, 0, 0, 0xc3, 0x28]),
+                DecodeError::InvalidUtf8(18),
+            ),
+            (
+                header(&[0, 0, 0, 0, 1, 0, 0, 0, 0, 1, 2, 3]),
+                DecodeError::UnexpectedEof(14),
+            ),
+            // Operands indexing past the program
+            (
+                block(&[1, 0, 0, 0, 4, 0, 0]),
+                DecodeError::InvalidOperand {
+                    instruction: "LoadValue(0)".to_string(),
+                    pc: 0,
+                },
+            ),
+            (
+                block(&[2, 0, 0, 0, 5, 9, 5, 0]),
+                DecodeError::InvalidOperand {
+                    instruction: "Jump(5)".to_string(),
+                    pc: 1,
+                },
+            ),
+            ([&valid[..], &[0]].concat(), DecodeError::TrailingBytes(1)),
+        ];
+        for (bytes, error) in corpus {
+            assert_eq!(
+                CodeBlock::from_bytes(&bytes).unwrap_err(),
+                error,
+                "{bytes:?}"
+            );
+        }
+
+        // Every truncation of a valid program fails without panicking
+        for len in 0..valid.len() {
+            assert!(CodeBlock::from_bytes(&valid[..len]).is_err());
+        }
+        // And so does every single byte change that decodes as something else
+        for i in 0..valid.len() {
+            for byte in [0, 1, 0x7f, 0xff] {
+                let mut bytes = valid.clone();
+                bytes[i] = byte;
+                if let Ok(code_block) = CodeBlock::from_bytes(&bytes) {
+                    let mut output = Vec::new();
+                    let _ = VM::new_with_output(&mut output).run(&code_block);
+                }
+            }
+        }
+        assert!(CodeBlock::from_bytes(&valid).is_ok());
+    }
+
+    #[test]
+    fn invalid_calls_are_rejected() {
+        let mut code_block = compile("fn f(x) { return x; } f(1);");
+        let pc = code_block
+            .instructions
+            .iter()
+            .position(|instruction| instruction.to_string() == "Call(0, 1)")
+            .unwrap();
+        for call in [
+            // Arity of the call doesn't match the function
+            Instruction::Call(0, 2),
+            Instruction::TailCall(1, 1),
+            Instruction::CallNative(u16::MAX, 0),
+        ] {
+            code_block.instructions[pc] = call;
+            assert!(matches!(
+                CodeBlock::from_bytes(&code_block.to_bytes()),
+                Err(DecodeError::InvalidOperand { pc: found, .. }) if found == pc
+            ));
+        }
+    }
+}
@@ -1,6 +1,13 @@
-use std::fmt;
+use alloc::{
+    collections::{BTreeMap, BTreeSet},
+    format,
+    rc::Rc,
+    string::String,
+    vec::Vec,
+};
+use core::fmt;
 
-use crate::ast::value::Value;
+use crate::{ast::value::Value, collections::HashMap};
 
 use super::Instruction;
 
@@ -15,6 +22,12 @@ pub enum PrettyInstruction<'a> {
         instruction: Instruction,
         value: &'a Value,
     },
+    /// Instruction loading a constant that isn't in the pool it's looked up in.
+    MissingValue(Instruction),
+    Jump {
+        instruction: Instruction,
+        label: usize,
+    },
 }
 
 impl fmt::Display for PrettyInstruction<'_> {
@@ -28,13 +41,65 @@ impl fmt::Display for PrettyInstruction<'_> {
                 identifier,
             } => write!(f, "{instruction}\t{identifier}"),
             Self::Value { instruction, value } => write!(f, "{instruction}\t{value}"),
+            Self::MissingValue(instruction) => write!(f, "{instruction}\t<missing constant>"),
+            Self::Jump { instruction, label } => write!(f, "{instruction} -> L{label}"),
         }
     }
 }
 
+#[derive(Debug, Default)]
 pub struct CodeBlock {
     pub instructions: Vec<Instruction>,
-    pub values: Vec<Value>,
+    /// Constants of the whole program, empty for function bodies which load
+    /// constants of the code block being run.
+    pub values: Rc<Vec<Value>>,
+    /// Functions declared so far, indexed by `Call` instructions.
+    pub functions: Vec<Rc<Function>>,
+    /// Start and end offsets in the source of instructions whose runtime
+    /// errors point at it, keyed by instruction index.
+    pub spans: BTreeMap<usize, (usize, usize)>,
+    /// Names of local slots ordered by the start of their scope.
+    pub locals: Vec<LocalInfo>,
+}
+
+/// Local variable occupying `slot` for the instructions in
+/// `scope_start..scope_end`, slots are reused by other locals outside of
+/// that range.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LocalInfo {
+    pub name: String,
+    pub slot: u16,
+    pub scope_start: usize,
+    pub scope_end: usize,
+}
+
+/// Compiled function body, its `LoadValue` and `Call` instructions index the
+/// constants and the function table of the code block being run.
+#[derive(Debug)]
+pub struct Function {
+    pub name: String,
+    pub arity: u8,
+    pub code: CodeBlock,
+}
+
+/// Compiled program with its functions looked up by name, used by embedders
+/// to call functions from Rust with `VM::call_named`.
+#[derive(Debug)]
+pub struct Program {
+    pub main: CodeBlock,
+    /// Index of each function in the function table of `main`.
+    pub functions: HashMap<String, u16>,
+}
+
+impl Program {
+    pub fn function(&self, name: &str) -> Option<&Function> {
+        let idx = *self.functions.get(name)?;
+        self.main.functions.get(idx as usize).map(Rc::as_ref)
+    }
+
+    pub fn arity(&self, name: &str) -> Option<usize> {
+        self.function(name).map(|function| function.arity.into())
+    }
 }
 
 impl fmt::Display for CodeBlock {
@@ -47,26 +112,245 @@ impl fmt::Display for CodeBlock {
 }
 
 impl CodeBlock {
-    pub fn disassemble(&self, debug_symbols: &[&String]) -> String {
-        self.instructions
+    /// Name of the local in `slot` at instruction `pc`, `None` if no local
+    /// occupies the slot there.
+    pub fn local_name(&self, slot: u16, pc: usize) -> Option<&str> {
+        self.locals
             .iter()
-            .map(|instruction| match instruction {
-                Instruction::StoreSymbol(idx) => PrettyInstruction::Symbol {
-                    instruction: *instruction,
-                    identifier: debug_symbols[*idx as usize],
-                },
-                Instruction::LoadSymbol(idx) => PrettyInstruction::Symbol {
-                    instruction: *instruction,
-                    identifier: debug_symbols[*idx as usize],
+            .find(|local| local.slot == slot && (local.scope_start..local.scope_end).contains(&pc))
+            .map(|local| local.name.as_str())
+    }
+
+    /// Jump targets in ascending order, the position of a target is the
+    /// number of its label.
+    fn jump_targets(&self) -> Vec<u16> {
+        let targets = self
+            .instructions
+            .iter()
+            .filter_map(Instruction::jump_target)
+            .collect::<BTreeSet<_>>();
+        targets.into_iter().collect()
+    }
+
+    /// Human readable listing of the instructions, jumps are annotated with
+    /// the label of their target and each label is written on its own line
+    /// before the instruction it marks.
+    pub fn disassemble(&self, debug_symbols: &[&String]) -> String {
+        self.disassemble_with(debug_symbols, &self.values)
+    }
+
+    /// `disassemble` looking constants up in `values`, function bodies are
+    /// disassembled with the constants of the program they belong to.
+    pub fn disassemble_with(&self, debug_symbols: &[&String], values: &[Value]) -> String {
+        let targets = self.jump_targets();
+        let label = |target: u16| targets.binary_search(&target).ok();
+
+        let mut out = String::new();
+        for (i, instruction) in self.instructions.iter().enumerate() {
+            if let Some(label) = label(i as u16) {
+                out.push_str(&format!("L{label}:\n"));
+            }
+            let pretty = match instruction {
+                Instruction::StoreSymbol(idx) | Instruction::LoadSymbol(idx) => {
+                    PrettyInstruction::Symbol {
+                        instruction: *instruction,
+                        identifier: debug_symbols[*idx as usize],
+                    }
+                }
+                Instruction::StoreLocal(slot) | Instruction::LoadLocal(slot) => {
+                    match self.local_name(*slot, i) {
+                        Some(identifier) => PrettyInstruction::Symbol {
+                            instruction: *instruction,
+                            identifier,
+                        },
+                        None => PrettyInstruction::Plain(*instruction),
+                    }
+                }
+                Instruction::LoadValue(idx) => match values.get(*idx as usize) {
+                    Some(value) => PrettyInstruction::Value {
+                        instruction: *instruction,
+                        value,
+                    },
+                    None => PrettyInstruction::MissingValue(*instruction),
                 },
-                Instruction::LoadValue(idx) => PrettyInstruction::Value {
-                    instruction: *instruction,
-                    value: &self.values[*idx as usize],
+                _ => match instruction.jump_target().and_then(label) {
+                    Some(label) => PrettyInstruction::Jump {
+                        instruction: *instruction,
+                        label,
+                    },
+                    None => PrettyInstruction::Plain(*instruction),
                 },
-                _ => PrettyInstruction::Plain(*instruction),
-            })
-            .enumerate()
-            .map(|(i, pretty)| format!("{i:>4}\t{pretty}\n"))
-            .collect()
+            };
+            out.push_str(&format!("{i:>4}\t{pretty}\n"));
+        }
+        // Jumps past the last instruction exit the code block
+        if let Some(label) = label(self.instructions.len() as u16) {
+            out.push_str(&format!("L{label}:\n"));
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        compiler::{compile_program, Compile, Compiler},
+        parser,
+    };
+
+    use std::rc::Rc;
+
+    use super::LocalInfo;
+
+    #[test]
+    fn disassemble_labels_jump_targets() {
+        let statements = parser::parse("var i = 0; while i < 10 { i = i + 1; }").unwrap();
+        let code_block = compile_program(&statements).unwrap();
+        let i = String::from("i");
+        assert_eq!(
+            code_block.disassemble(&[&i]),
+            [
+                "   0\tLoadValue(0)\t0",
+                "   1\tStoreSymbol(0)\ti",
+                // Back-edge of the loop
+                "L0:",
+                "   2\tLoadSymbol(0)\ti",
+                "   3\tLoadValue(1)\t10",
+                "   4\tJumpUnlessLessThan(10) -> L1",
+                "   5\tLoadSymbol(0)\ti",
+                "   6\tLoadValue(2)\t1",
+                "   7\tBinaryAdd",
+                "   8\tStoreSymbol(0)\ti",
+                "   9\tJump(2) -> L0",
+                // Loop exit is past the last instruction
+                "L1:",
+                "",
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test]
+    fn disassemble_names_globals() {
+        let mut compiler = Compiler::new();
+        let program = "var zeta = 1; var alpha = 2; var mid = zeta + alpha; mid = alpha;";
+        for statement in parser::parse(program).unwrap() {
+            statement.compile(&mut compiler).unwrap();
+        }
+        let (code_block, debug_symbols) = compiler.finish().unwrap();
+        assert_eq!(debug_symbols, ["zeta", "alpha", "mid"]);
+        assert_eq!(
+            code_block.disassemble(&debug_symbols),
+            [
+                "   0\tLoadValue(0)\t1",
+                "   1\tStoreSymbol(0)\tzeta",
+                "   2\tLoadValue(1)\t2",
+                "   3\tStoreSymbol(1)\talpha",
+                "   4\tLoadSymbol(0)\tzeta",
+                "   5\tLoadSymbol(1)\talpha",
+                "   6\tBinaryAdd",
+                "   7\tStoreSymbol(2)\tmid",
+                "   8\tLoadSymbol(1)\talpha",
+                "   9\tStoreSymbol(2)\tmid",
+                "",
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test]
+    fn disassemble_names_locals() {
+        let statements = parser::parse("{ var a = 1; { var b = a; } var c = 2; }").unwrap();
+        let code_block = compile_program(&statements).unwrap();
+        let local = |name: &str, slot, scope_start, scope_end| LocalInfo {
+            name: name.to_string(),
+            slot,
+            scope_start,
+            scope_end,
+        };
+        assert_eq!(
+            code_block.locals,
+            [
+                local("a", 0, 1, 6),
+                local("b", 1, 3, 4),
+                local("c", 1, 5, 6)
+            ]
+        );
+        // `b` and `c` share a slot
+        assert_eq!(
+            code_block.disassemble(&[]),
+            [
+                "   0\tLoadValue(0)\t1",
+                "   1\tStoreLocal(0)\ta",
+                "   2\tLoadLocal(0)\ta",
+                "   3\tStoreLocal(1)\tb",
+                "   4\tLoadValue(1)\t2",
+                "   5\tStoreLocal(1)\tc",
+                "",
+            ]
+            .join("\n")
+        );
+        assert_eq!(code_block.local_name(1, 3), Some("b"));
+        assert_eq!(code_block.local_name(1, 5), Some("c"));
+        assert_eq!(code_block.local_name(1, 1), None);
+
+        // Parameters are in scope for the whole function
+        let mut compiler = Compiler::new();
+        for statement in parser::parse("fn f(x, y) { var z = x + y; return z; }").unwrap() {
+            statement.compile(&mut compiler).unwrap();
+        }
+        let (code_block, _) = compiler.finish().unwrap();
+        let function = &code_block.functions[0].code;
+        let end = function.instructions.len();
+        assert_eq!(
+            function.locals,
+            [
+                local("x", 0, 0, end),
+                local("y", 1, 0, end),
+                local("z", 2, 3, end)
+            ]
+        );
+    }
+
+    #[test]
+    fn disassemble_function() {
+        let statements = parser::parse("fn f(x) { return x + 1; }").unwrap();
+        let code_block = compile_program(&statements).unwrap();
+        let function = &code_block.functions[0].code;
+        // Constants of function bodies are in the pool of the program
+        assert_eq!(
+            function.disassemble_with(&[], &code_block.values),
+            [
+                "   0\tLoadLocal(0)\tx",
+                "   1\tLoadValue(0)\t1",
+                "   2\tBinaryAdd",
+                "   3\tReturn",
+                "   4\tLoadValue(1)\tnull",
+                "   5\tReturn",
+                "",
+            ]
+            .join("\n")
+        );
+        assert!(function
+            .disassemble(&[])
+            .contains("LoadValue(0)\t<missing constant>"));
+    }
+
+    #[test]
+    fn finishing_shares_constants() {
+        let mut compiler = Compiler::new();
+        parser::parse("print 1;").unwrap()[0]
+            .compile(&mut compiler)
+            .unwrap();
+        let (first, _) = compiler.finish().unwrap();
+        let (second, _) = compiler.finish().unwrap();
+        assert!(Rc::ptr_eq(&first.values, &second.values));
+        drop((first, second));
+        // Constants registered later are added to the same pool
+        parser::parse("print 2;").unwrap()[0]
+            .compile(&mut compiler)
+            .unwrap();
+        let (third, _) = compiler.finish().unwrap();
+        assert_eq!(*third.values, [1.into(), 2.into()]);
     }
 }
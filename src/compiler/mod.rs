@@ -1,16 +1,36 @@
-use std::{collections::HashMap, convert::TryInto, fmt, mem};
+use alloc::{
+    collections::BTreeMap,
+    rc::Rc,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::{convert::TryInto, fmt, mem};
 
 use thiserror::Error;
 
-use crate::ast::{
-    identifier::{Identifier, IdentifierKind},
-    value::Value,
+use crate::{
+    ast::{
+        expression::Expression,
+        identifier::{Identifier, IdentifierKind},
+        statement::Statement,
+        value::Value,
+    },
+    collections::HashMap,
+    parser::Spanned,
+    vm::host::HostFunctions,
 };
 
-use self::{code_block::CodeBlock, symbol_table::SymbolTable};
+use self::{
+    code_block::{CodeBlock, Function, LocalInfo, Program},
+    symbol_table::{Slot, SymbolTable},
+    types::{Check, TypeChecker, TypeError},
+};
 
+pub mod bytecode;
 pub mod code_block;
+pub mod register;
 pub mod symbol_table;
+pub mod types;
 
 pub trait Compile {
     fn compile(&self, compiler: &mut Compiler) -> CompilerResult<()>;
@@ -18,12 +38,25 @@ pub trait Compile {
 
 pub type CompilerResult<T> = Result<T, CompilerError>;
 
+/// Compile a whole program with a fresh `Compiler`, stopping at the first
+/// statement that fails to compile.
+pub fn compile_program(statements: &[Statement]) -> CompilerResult<CodeBlock> {
+    let mut compiler = Compiler::new();
+    for statement in statements {
+        statement.compile(&mut compiler)?;
+    }
+    let (code_block, _) = compiler.finish()?;
+    Ok(code_block)
+}
+
 #[derive(Debug, PartialEq, Eq, Hash)]
 pub enum BlockType {
     Block,
     If,
     For,
     While,
+    Function,
+    IfExpression,
 }
 
 #[derive(Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Clone, Copy)]
@@ -42,12 +75,13 @@ impl From<Label> for usize {
 }
 
 impl Label {
+    /// Jump target addressing the label, every jump target is converted
+    /// through this so programs too long for `u16` targets fail to compile
+    /// instead of wrapping around.
     pub fn target(self) -> Result<u16, CompilerError> {
-        if let Ok(t) = self.0.try_into() {
-            Ok(t)
-        } else {
-            Err(CompilerError::InstructionLimitReached)
-        }
+        self.0
+            .try_into()
+            .map_err(|_| CompilerError::InstructionLimitReached)
     }
 }
 
@@ -66,8 +100,31 @@ impl From<JumpRef> for usize {
 pub struct Compiler {
     symbol_table: SymbolTable,
     instructions: Vec<Instruction>,
+    // Source spans of instructions emitted so far, keyed by their index
+    spans: BTreeMap<usize, (usize, usize)>,
     blocks: Vec<BlockType>,
     unplaced_labels: HashMap<usize, Vec<JumpRef>>,
+    // Jumps of `continue` statements keyed by the index of their loop block
+    continue_jumps: HashMap<usize, Vec<JumpRef>>,
+    keep_last_value: bool,
+    // Index of the `Pop` discarding the latest top-level expression statement
+    last_value_pop: Option<usize>,
+    functions: Vec<Rc<Function>>,
+    // Index and arity of declared functions, a function is declared before
+    // its body is compiled so it can call itself
+    function_indices: HashMap<String, (u16, u8)>,
+    // Index of the function whose body is being compiled
+    function: Option<u16>,
+    // Indices of host functions in the registry given to the VM
+    host_indices: HashMap<String, u16>,
+    // Checks top-level statements before they are compiled in strict mode
+    checker: Option<TypeChecker>,
+    // Assignments to undeclared identifiers declare a variable
+    implicit_var: bool,
+    // Locals declared in each scope entered, their scope ends when it exits
+    scope_locals: Vec<Vec<LocalInfo>>,
+    // Locals of the code block being compiled whose scope has ended
+    locals: Vec<LocalInfo>,
 }
 
 impl Compiler {
@@ -75,89 +132,415 @@ impl Compiler {
         Self::default()
     }
 
+    /// Create a compiler for the REPL, if the last statement of a code block
+    /// is a top-level expression statement its value is left on the stack
+    /// so the VM returns it for echoing.
+    pub fn new_repl() -> Self {
+        Self {
+            keep_last_value: true,
+            ..Self::default()
+        }
+    }
+
+    /// Resolve calls to functions registered in `hosts` to `CallHost`
+    /// instructions, user functions can't be declared with the same names.
+    pub fn with_host_functions(mut self, hosts: &HostFunctions) -> Self {
+        self.host_indices = hosts.indices().clone();
+        self
+    }
+
+    /// Enable or disable strict mode, in strict mode every top-level statement
+    /// is type checked before it's compiled. Mixing booleans with arithmetic,
+    /// comparing values of different types and non-boolean conditions are
+    /// rejected instead of being left to the VM.
+    pub fn set_strict(&mut self, strict: bool) {
+        self.checker = strict.then(TypeChecker::new_strict);
+    }
+
+    /// Enable or disable implicit declarations, when enabled assigning to an
+    /// undeclared identifier declares it as a variable in the current scope
+    /// instead of failing with `CompilerError::UndefinedIdentifer`. Compound
+    /// assignments still need the identifier to be declared.
+    pub fn set_implicit_var(&mut self, implicit: bool) {
+        self.implicit_var = implicit;
+    }
+
+    pub fn implicit_var(&self) -> bool {
+        self.implicit_var
+    }
+
+    /// Type check `statement` in strict mode if it's a top-level statement,
+    /// nested statements are checked as part of the enclosing one.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first type error found in `statement`.
+    pub fn check_strict(&mut self, statement: &Statement) -> CompilerResult<()> {
+        let top_level = self.blocks.is_empty() && self.symbol_table.is_global_scope();
+        let Some(checker) = self.checker.as_mut().filter(|_| top_level) else {
+            return Ok(());
+        };
+        statement.check(checker);
+        match checker.take_errors().into_iter().next() {
+            Some(error) => Err(CompilerError::Type(error)),
+            None => Ok(()),
+        }
+    }
+
     pub fn emit(&mut self, insruction: Instruction) {
         self.instructions.push(insruction);
     }
 
-    pub fn register(&mut self, identifier: Identifier) -> CompilerResult<u16> {
-        self.symbol_table.register(identifier)
+    /// Emit `instruction` recording `span` as the source location of its
+    /// runtime errors.
+    pub fn emit_spanned<T>(&mut self, instruction: Instruction, span: &Spanned<T>) {
+        self.spans
+            .insert(self.instructions.len(), (span.start, span.end));
+        self.emit(instruction);
     }
 
-    pub fn register_var(&mut self, ident: &str) -> CompilerResult<u16> {
-        self.symbol_table.register(Identifier {
-            ident: ident.to_string(),
-            kind: IdentifierKind::Variable,
-        })
+    /// Register `identifier` in the innermost scope, locals are recorded in
+    /// the debug info of the code block from the next instruction on.
+    pub fn register(&mut self, identifier: Identifier) -> CompilerResult<Slot> {
+        let name = identifier.ident.clone();
+        let slot = self.symbol_table.register(identifier)?;
+        if let (Slot::Local(slot), Some(scope)) = (slot, self.scope_locals.last_mut()) {
+            let start = self.instructions.len();
+            scope.push(LocalInfo {
+                name,
+                slot,
+                scope_start: start,
+                scope_end: start,
+            });
+        }
+        Ok(slot)
     }
 
-    pub fn register_const(&mut self, ident: &str) -> CompilerResult<u16> {
-        self.symbol_table.register(Identifier {
-            ident: ident.to_string(),
-            kind: IdentifierKind::Constant,
-        })
+    pub fn register_var(&mut self, ident: &str) -> CompilerResult<Slot> {
+        self.register(Identifier::new_var(ident.to_string()))
     }
 
-    pub fn get_identifier(&self, ident: &str) -> Option<(IdentifierKind, u16)> {
+    pub fn register_const(&mut self, ident: &str) -> CompilerResult<Slot> {
+        self.register(Identifier::new_const(ident.to_string()))
+    }
+
+    pub fn get_identifier(&self, ident: &str) -> Option<(IdentifierKind, Slot)> {
         self.symbol_table.get(ident)
     }
 
+    /// Replace loads of constant `ident` declared in the innermost scope with
+    /// `value`, only literals should be inlined since they can't have side
+    /// effects.
+    pub fn inline_constant(&mut self, ident: &str, value: Value) {
+        self.symbol_table.inline(ident, value)
+    }
+
+    /// Literal to load instead of `ident` if it's an inlined constant.
+    pub fn get_inlined(&self, ident: &str) -> Option<&Value> {
+        self.symbol_table.get_inlined(ident)
+    }
+
+    /// Check whether `ident` can be the target of an assignment, returns `None`
+    /// if `ident` hasn't been declared.
+    pub fn is_assignable(&self, ident: &str) -> Option<bool> {
+        self.get_identifier(ident)
+            .map(|(kind, _)| kind == IdentifierKind::Variable)
+    }
+
     pub fn register_value(&mut self, value: Value) -> Result<u16, CompilerError> {
         self.symbol_table.register_value(value)
     }
 
-    pub fn finish(&mut self) -> (CodeBlock, Vec<&'_ String>) {
+    /// Index and arity of the function named `name`, `None` if it hasn't been
+    /// declared.
+    pub fn get_function(&self, name: &str) -> Option<(u16, u8)> {
+        self.function_indices.get(name).copied()
+    }
+
+    /// Index of the host function named `name` in the registry, `None` if
+    /// no such function has been registered.
+    pub fn get_host_function(&self, name: &str) -> Option<u16> {
+        self.host_indices.get(name).copied()
+    }
+
+    /// Compile a function declaration into its own code block and add it to
+    /// the function table. Parameters are the first locals of the function's
+    /// frame, and `null` is returned when execution reaches the end of the
+    /// body.
+    pub fn compile_function(
+        &mut self,
+        name: &str,
+        params: &[String],
+        body: &[Statement],
+    ) -> CompilerResult<()> {
+        if self.function_indices.contains_key(name) || self.host_indices.contains_key(name) {
+            return Err(CompilerError::Redefinition(name.to_string()));
+        }
+        let arity = params
+            .len()
+            .try_into()
+            .map_err(|_| CompilerError::ParameterLimitReached)?;
+        let index = self
+            .functions
+            .len()
+            .try_into()
+            .map_err(|_| CompilerError::FunctionLimitReached)?;
+        self.function_indices
+            .insert(name.to_string(), (index, arity));
+
+        let instructions = mem::take(&mut self.instructions);
+        let spans = mem::take(&mut self.spans);
+        let debug_locals = mem::take(&mut self.locals);
+        let locals = self.symbol_table.enter_frame();
+        self.scope_locals.push(Vec::new());
+        let enclosing = self.function.replace(index);
+        self.enter_function();
+        let result = self.compile_function_body(params, body);
+        self.function = enclosing;
+        self.symbol_table.exit_frame(locals);
+        self.end_scope_locals();
+        let code = CodeBlock {
+            locals: self.take_locals(debug_locals),
+            instructions: mem::replace(&mut self.instructions, instructions),
+            // Constants are looked up in the program-wide pool
+            values: Rc::default(),
+            functions: Vec::new(),
+            spans: mem::replace(&mut self.spans, spans),
+        };
+
+        match result {
+            Ok(()) => {
+                self.functions.push(Rc::new(Function {
+                    name: name.to_string(),
+                    arity,
+                    code,
+                }));
+                Ok(())
+            }
+            Err(err) => {
+                self.function_indices.remove(name);
+                Err(err)
+            }
+        }
+    }
+
+    fn compile_function_body(
+        &mut self,
+        params: &[String],
+        body: &[Statement],
+    ) -> CompilerResult<()> {
+        for param in params {
+            self.register_var(param)?;
+        }
+        for statement in body {
+            statement.compile(self)?;
+        }
+        Value::Null.compile(self)?;
+        self.emit(Instruction::Return);
+        self.exit_function()
+    }
+
+    /// Index of the function whose body is being compiled, `None` outside of
+    /// function bodies.
+    pub fn current_function(&self) -> Option<u16> {
+        self.function
+    }
+
+    /// Check whether code being compiled is inside of a function body.
+    pub fn in_function(&self) -> bool {
+        self.blocks.contains(&BlockType::Function)
+    }
+
+    /// Take the instructions emitted so far as a `CodeBlock`.
+    ///
+    /// # Errors
+    ///
+    /// Every block must have been exited and every jump targeted, otherwise
+    /// the code block would contain jumps to instruction 0. That is a bug in
+    /// the compiler, so debug builds panic and release builds return
+    /// `CompilerError::InternalError`.
+    pub fn finish(&mut self) -> CompilerResult<(CodeBlock, Vec<&'_ String>)> {
+        let finished = self.check_finished();
+        debug_assert!(finished.is_ok(), "{finished:?}");
+        finished?;
+
+        if self.keep_last_value
+            && self.last_value_pop.take() == self.instructions.len().checked_sub(1)
+        {
+            self.instructions.pop();
+        }
         let instructions = mem::take(&mut self.instructions);
+        let locals = self.take_locals(Vec::new());
         let (values, debug_symbols) = self.symbol_table.finish();
-        (
+        Ok((
             CodeBlock {
                 instructions,
                 values,
+                functions: self.functions.clone(),
+                spans: mem::take(&mut self.spans),
+                locals,
             },
             debug_symbols,
-        )
+        ))
+    }
+
+    /// Compile `expr` into a code block that leaves its value on the stack,
+    /// so running it returns the value of the expression. Statements
+    /// compiled since the last `finish` run before the expression, and
+    /// instructions emitted so far are discarded if `expr` fails to compile.
+    pub fn compile_expression(&mut self, expr: &Expression) -> CompilerResult<CodeBlock> {
+        if let Err(error) = expr.compile(self) {
+            self.reset();
+            return Err(error);
+        }
+        self.finish().map(|(code_block, _)| code_block)
+    }
+
+    /// Take the instructions emitted so far as the main code block of a
+    /// `Program`, together with every function declared so far.
+    pub fn finish_program(&mut self) -> CompilerResult<Program> {
+        let (main, _) = self.finish()?;
+        let functions = self
+            .function_indices
+            .iter()
+            .map(|(name, (idx, _))| (name.clone(), *idx))
+            .collect();
+        Ok(Program { main, functions })
+    }
+
+    fn check_finished(&self) -> CompilerResult<()> {
+        if !self.blocks.is_empty() {
+            return Err(CompilerError::InternalError("block wasn't exited"));
+        }
+        if !self.unplaced_labels.is_empty() || !self.continue_jumps.is_empty() {
+            return Err(CompilerError::InternalError("jump wasn't targeted"));
+        }
+        Ok(())
+    }
+
+    /// Discard instructions emitted since the last `finish` together with
+    /// blocks left open, used to recover after a statement fails to compile
+    /// half way. Declared identifiers stay registered.
+    pub fn reset(&mut self) {
+        self.instructions.clear();
+        self.spans.clear();
+        self.blocks.clear();
+        self.unplaced_labels.clear();
+        self.continue_jumps.clear();
+        self.locals.clear();
+        self.last_value_pop = None;
     }
 
     fn enter_block(&mut self, block_type: BlockType) {
         self.blocks.push(block_type)
     }
 
-    fn exit_block(&mut self, expected: BlockType) {
+    fn exit_block(&mut self, expected: BlockType) -> CompilerResult<()> {
         let got = self.blocks.pop().unwrap();
         debug_assert_eq!(expected, got);
 
         let block_idx = self.blocks.len();
         if let Some(registered) = self.unplaced_labels.remove(&block_idx) {
             for jump in registered {
-                self.target_jump(jump);
+                self.target_jump(jump)?;
             }
         }
+        Ok(())
+    }
+
+    /// Enter a lexical scope, identifiers declared until the matching
+    /// `exit_scope` are locals.
+    pub fn enter_scope(&mut self) {
+        self.symbol_table.enter_scope();
+        self.scope_locals.push(Vec::new());
+    }
+
+    pub fn exit_scope(&mut self) {
+        self.symbol_table.exit_scope();
+        self.end_scope_locals();
+    }
+
+    /// End the scope of locals declared in the innermost scope at the next
+    /// instruction.
+    fn end_scope_locals(&mut self) {
+        let end = self.instructions.len();
+        let scope = self.scope_locals.pop().unwrap_or_default();
+        self.locals.extend(scope.into_iter().map(|local| LocalInfo {
+            scope_end: end,
+            ..local
+        }));
+    }
+
+    /// Take the locals of the code block being compiled ordered by the start
+    /// of their scope, replacing them with `locals`.
+    fn take_locals(&mut self, locals: Vec<LocalInfo>) -> Vec<LocalInfo> {
+        let mut locals = mem::replace(&mut self.locals, locals);
+        locals.sort_by_key(|local| local.scope_start);
+        locals
+    }
+
+    /// Compile `statements` in a new scope, the scope is exited even if
+    /// compilation fails.
+    pub fn compile_block(&mut self, statements: &[Statement]) -> CompilerResult<()> {
+        self.enter_scope();
+        let result = statements
+            .iter()
+            .try_for_each(|statement| statement.compile(self));
+        self.exit_scope();
+        result
     }
 
     pub fn enter_if(&mut self) {
         self.enter_block(BlockType::If);
     }
 
-    pub fn exit_if(&mut self) {
-        self.exit_block(BlockType::If);
+    pub fn exit_if(&mut self) -> CompilerResult<()> {
+        self.exit_block(BlockType::If)
+    }
+
+    pub fn enter_if_expression(&mut self) {
+        self.enter_block(BlockType::IfExpression);
+    }
+
+    pub fn exit_if_expression(&mut self) -> CompilerResult<()> {
+        self.exit_block(BlockType::IfExpression)
     }
 
     pub fn enter_while(&mut self) {
         self.enter_block(BlockType::While);
     }
 
-    pub fn exit_while(&mut self) {
-        self.exit_block(BlockType::While);
+    pub fn exit_while(&mut self) -> CompilerResult<()> {
+        self.exit_block(BlockType::While)
+    }
+
+    pub fn enter_for(&mut self) {
+        self.enter_block(BlockType::For);
+    }
+
+    pub fn exit_for(&mut self) -> CompilerResult<()> {
+        self.exit_block(BlockType::For)
+    }
+
+    pub fn enter_function(&mut self) {
+        self.enter_block(BlockType::Function);
+    }
+
+    pub fn exit_function(&mut self) -> CompilerResult<()> {
+        self.exit_block(BlockType::Function)
     }
 
     pub fn emit_jump(&mut self, jump: Instruction) -> JumpRef {
-        match jump {
-            Instruction::Jump(_) | Instruction::JumpIfTrue(_) | Instruction::JumpIfFalse(_) => {
-                let idx = self.instructions.len();
-                self.instructions.push(jump);
-                JumpRef { idx }
-            }
-            _ => unreachable!(),
-        }
+        debug_assert!(jump.jump_target().is_some());
+        let idx = self.instructions.len();
+        self.instructions.push(jump);
+        JumpRef { idx }
+    }
+
+    pub fn emit_spanned_jump<T>(&mut self, jump: Instruction, span: &Spanned<T>) -> JumpRef {
+        self.spans
+            .insert(self.instructions.len(), (span.start, span.end));
+        self.emit_jump(jump)
     }
 
     pub fn emit_untargeted_jump(&mut self) -> JumpRef {
@@ -176,48 +559,100 @@ impl Compiler {
         self.instructions.len().into()
     }
 
-    pub fn target_jump(&mut self, jump: JumpRef) {
+    /// Net number of values pushed to the stack by the instructions emitted
+    /// since `label` was placed, counted along the straight-line path.
+    pub fn stack_effect_since(&self, label: Label) -> i32 {
+        let start: usize = label.into();
+        self.instructions[start..]
+            .iter()
+            .map(Instruction::stack_effect)
+            .sum()
+    }
+
+    /// Emit `Pop` if the instructions emitted since `label` left a value on
+    /// the stack, statements must not change the stack depth. Top-level pops
+    /// are remembered so `finish` can keep the value in REPL mode.
+    pub fn pop_unused_value(&mut self, label: Label) {
+        if self.stack_effect_since(label) > 0 {
+            if self.blocks.is_empty() && self.symbol_table.is_global_scope() {
+                self.last_value_pop = Some(self.instructions.len());
+            }
+            self.emit(Instruction::Pop);
+        }
+    }
+
+    /// Remove every instruction emitted since `label` was placed.
+    pub fn discard_from(&mut self, label: Label) {
+        self.instructions.truncate(label.into());
+        self.spans.split_off(&label.into());
+    }
+
+    pub fn target_jump(&mut self, jump: JumpRef) -> CompilerResult<()> {
         let idx: usize = jump.into();
-        let target = self.current();
-        let jump = match self.instructions[idx] {
-            Instruction::Jump(_) => Instruction::Jump(target),
-            Instruction::JumpIfTrue(_) => Instruction::JumpIfTrue(target),
-            Instruction::JumpIfFalse(_) => Instruction::JumpIfFalse(target),
-            _ => unreachable!(),
-        };
-        self.instructions[idx] = jump;
+        let target = self.place_label().target()?;
+        self.instructions[idx] = self.instructions[idx].with_target(target);
+        Ok(())
     }
 
+    /// Target `jump` to the exit of the innermost enclosing block of
+    /// `block_type`, outer blocks of the same type are left untouched.
     pub fn target_jump_on_exit(&mut self, block_type: BlockType, jump: JumpRef) {
-        for (i, current) in self.blocks.iter().enumerate().rev() {
-            if *current == block_type {
-                if let Some(vec) = self.unplaced_labels.get_mut(&i) {
-                    vec.push(jump);
-                } else {
-                    let labels = vec![jump];
-                    self.unplaced_labels.insert(i, labels);
-                }
-            }
+        if let Some(i) = self
+            .blocks
+            .iter()
+            .rposition(|current| *current == block_type)
+        {
+            self.unplaced_labels.entry(i).or_default().push(jump);
         }
     }
 
-    pub fn target_jump_on_loop_exit(&mut self, jump: JumpRef) -> Option<()> {
+    /// Index of the innermost enclosing loop block, loops outside of the
+    /// current function aren't visible so `None` is returned when a function
+    /// boundary is reached first.
+    fn innermost_loop(&self) -> Option<usize> {
         for (i, current) in self.blocks.iter().enumerate().rev() {
-            if *current == BlockType::While || *current == BlockType::For {
-                if let Some(vec) = self.unplaced_labels.get_mut(&i) {
-                    vec.push(jump);
-                } else {
-                    let labels = vec![jump];
-                    self.unplaced_labels.insert(i, labels);
-                }
-                return Some(());
+            match current {
+                // Values of the enclosing expression would be left on the
+                // stack when jumping out of an `if` expression
+                BlockType::Function | BlockType::IfExpression => return None,
+                BlockType::While | BlockType::For => return Some(i),
+                _ => {}
             }
         }
         None
     }
 
-    fn current(&self) -> u16 {
-        self.instructions.len().try_into().unwrap()
+    /// Target `jump` to the exit of the innermost enclosing loop, `None` if
+    /// there is no enclosing loop.
+    pub fn target_jump_on_loop_exit(&mut self, jump: JumpRef) -> Option<()> {
+        let i = self.innermost_loop()?;
+        self.unplaced_labels.entry(i).or_default().push(jump);
+        Some(())
+    }
+
+    /// Target `jump` to where the innermost enclosing loop continues with its
+    /// next iteration, `None` if there is no enclosing loop. The loop places
+    /// the target with `target_continue_jumps`.
+    pub fn target_jump_on_loop_continue(&mut self, jump: JumpRef) -> Option<()> {
+        let i = self.innermost_loop()?;
+        self.continue_jumps.entry(i).or_default().push(jump);
+        Some(())
+    }
+
+    /// Target `continue` jumps of the innermost block to the next instruction,
+    /// must be called by loops while their block is the innermost one.
+    pub fn target_continue_jumps(&mut self) -> CompilerResult<()> {
+        let block_idx = self.blocks.len() - 1;
+        debug_assert!(matches!(
+            self.blocks[block_idx],
+            BlockType::While | BlockType::For
+        ));
+        if let Some(registered) = self.continue_jumps.remove(&block_idx) {
+            for jump in registered {
+                self.target_jump(jump)?;
+            }
+        }
+        Ok(())
     }
 }
 
@@ -233,26 +668,65 @@ pub enum CompilerError {
     AssignmentToConst,
     #[error("instruction limit has been reached")]
     InstructionLimitReached,
+    #[error("constant limit has been reached")]
+    ConstantLimitReached,
+    #[error("too many elements in a collection literal")]
+    CollectionLimitReached,
+    #[error("register limit has been reached")]
+    RegisterLimitReached,
+    #[error("expression isn't supported by the register backend")]
+    UnsupportedByRegisters,
     #[error("illegal break statement")]
     BreakOutsideLoop,
     #[error("illegal continue statement")]
     ContinueOutsideLoop,
     #[error("illegal return statement")]
     ReturnOutsideFunction,
+    #[error("`if` expression must have an `else` branch")]
+    MissingElse,
+    #[error("function limit has been reached")]
+    FunctionLimitReached,
+    #[error("too many function parameters")]
+    ParameterLimitReached,
+    #[error("function `{name}` takes {expected} arguments but {found} were given")]
+    ArityMismatch {
+        name: String,
+        expected: u8,
+        found: usize,
+    },
+    #[error(transparent)]
+    Type(#[from] TypeError),
+    #[error("internal compiler error: {0}")]
+    InternalError(&'static str),
 }
 
 #[derive(Debug, Clone, Copy)]
 pub enum Instruction {
     StoreSymbol(u16),
     LoadSymbol(u16),
+    // Frame-relative slots of block-scoped variables
+    StoreLocal(u16),
+    LoadLocal(u16),
     LoadValue(u16),
     Pop,
-    // Display Instruction to be removed
+    // Pops the given number of values at once
+    PopN(u16),
+    // Pushes a copy of the value on top of the stack
+    Dup,
+    // Pops a value and writes it followed by a newline to the VM's output,
+    // `print` statements compile to it
     Display,
     // Jump Instructions
     Jump(u16),
     JumpIfTrue(u16),
     JumpIfFalse(u16),
+    // Fused compare-and-branch, pops both operands and jumps if the comparison is false
+    JumpUnlessLessThan(u16),
+    JumpUnlessLessThanEqual(u16),
+    JumpUnlessGreaterThan(u16),
+    JumpUnlessGreaterThanEqual(u16),
+    JumpUnlessEqual(u16),
+    JumpUnlessNotEqual(u16),
     // Binary Operator Instructions
     BinaryAdd,
     BinarySubtract,
@@ -264,14 +738,46 @@ pub enum Instruction {
     BinaryLessThanEqual,
     BinaryGreaterThan,
     BinaryGreaterThanEqual,
+    // Equality is type-aware, see `Value::strict_eq`
     BinaryEqual,
     BinaryNotEqual,
     BinaryLogicalAnd,
     BinaryLogicalOr,
     BinaryLogicalXor,
+    // Pops container then item and pushes whether the container has the item
+    Contains,
+    // Collections
+    BuildSet(u16),
+    BuildArray(u16),
+    // Pops index then array and pushes the element, negative indices count
+    // from the end of the array
+    GetIndex,
+    // Pops value, index then array and replaces the element in place
+    SetIndex,
+    // Pops end, start then array or string and pushes a copy of the range,
+    // `null` bounds are the start and end
+    Slice,
+    // Pops value then array, appends the value in place and pushes `null`
+    Push,
     // Unary Operators
     UnaryMinus,
     UnaryNot,
+    // Functions, calls pop their arguments and push the returned value
+    Call(u16, u8),
+    // Calls a function reusing the current call's frame, the callee returns
+    // directly to the current caller
+    TailCall(u16, u8),
+    // Calls a function of the VM's host function registry
+    CallHost(u16, u8),
+    // Calls a builtin native function by its index in `native::NATIVES`
+    CallNative(u16, u8),
+    Return,
+    // Pops an iterable and pushes the array iterated over and the index of
+    // its next element
+    GetIter,
+    // Pushes the next element and advances the index below it, jumps if the
+    // array has been exhausted
+    IterNext(u16),
 }
 
 impl fmt::Display for Instruction {
@@ -279,11 +785,32 @@ impl fmt::Display for Instruction {
         match self {
             Instruction::StoreSymbol(idx) => write!(f, "StoreSymbol({idx})"),
             Instruction::LoadSymbol(idx) => write!(f, "LoadSymbol({idx})"),
+            Instruction::StoreLocal(idx) => write!(f, "StoreLocal({idx})"),
+            Instruction::LoadLocal(idx) => write!(f, "LoadLocal({idx})"),
             Instruction::LoadValue(idx) => write!(f, "LoadValue({idx})"),
             Instruction::Jump(idx) => write!(f, "Jump({idx})"),
             Instruction::JumpIfTrue(idx) => write!(f, "JumpIfTrue({idx})"),
             Instruction::JumpIfFalse(idx) => write!(f, "JumpIfFalse({idx})"),
+            Instruction::BuildSet(count) => write!(f, "BuildSet({count})"),
+            Instruction::BuildArray(count) => write!(f, "BuildArray({count})"),
+            Instruction::Call(idx, argc) => write!(f, "Call({idx}, {argc})"),
+            Instruction::TailCall(idx, argc) => write!(f, "TailCall({idx}, {argc})"),
+            Instruction::CallHost(idx, argc) => write!(f, "CallHost({idx}, {argc})"),
+            Instruction::CallNative(idx, argc) => write!(f, "CallNative({idx}, {argc})"),
+            Instruction::IterNext(idx) => write!(f, "IterNext({idx})"),
+            Instruction::PopN(count) => write!(f, "PopN({count})"),
+            Instruction::JumpUnlessLessThan(idx) => write!(f, "JumpUnlessLessThan({idx})"),
+            Instruction::JumpUnlessLessThanEqual(idx) => {
+                write!(f, "JumpUnlessLessThanEqual({idx})")
+            }
+            Instruction::JumpUnlessGreaterThan(idx) => write!(f, "JumpUnlessGreaterThan({idx})"),
+            Instruction::JumpUnlessGreaterThanEqual(idx) => {
+                write!(f, "JumpUnlessGreaterThanEqual({idx})")
+            }
+            Instruction::JumpUnlessEqual(idx) => write!(f, "JumpUnlessEqual({idx})"),
+            Instruction::JumpUnlessNotEqual(idx) => write!(f, "JumpUnlessNotEqual({idx})"),
             Instruction::Pop
+            | Instruction::Dup
             | Instruction::Display
             | Instruction::BinaryAdd
             | Instruction::BinarySubtract
@@ -300,8 +827,15 @@ impl fmt::Display for Instruction {
             | Instruction::BinaryLogicalAnd
             | Instruction::BinaryLogicalOr
             | Instruction::BinaryLogicalXor
+            | Instruction::Contains
             | Instruction::UnaryMinus
-            | Instruction::UnaryNot => write!(f, "{self:?}"),
+            | Instruction::UnaryNot
+            | Instruction::GetIndex
+            | Instruction::SetIndex
+            | Instruction::Slice
+            | Instruction::Push
+            | Instruction::GetIter
+            | Instruction::Return => write!(f, "{self:?}"),
         }
     }
 }
@@ -310,13 +844,108 @@ impl Instruction {
     const UNPLACED_JUMP: Instruction = Instruction::Jump(0);
     const UNPLACED_JUMP_IF_TRUE: Instruction = Instruction::JumpIfTrue(0);
     const UNPLACED_JUMP_IF_FALSE: Instruction = Instruction::JumpIfFalse(0);
+
+    /// Number of values pushed minus the number of values popped by the
+    /// instruction, jumps are counted as if they weren't taken.
+    pub fn stack_effect(&self) -> i32 {
+        match *self {
+            Instruction::LoadSymbol(_)
+            | Instruction::LoadLocal(_)
+            | Instruction::LoadValue(_)
+            | Instruction::Dup => 1,
+            Instruction::StoreSymbol(_)
+            | Instruction::StoreLocal(_)
+            | Instruction::Pop
+            | Instruction::Display
+            | Instruction::JumpIfTrue(_)
+            | Instruction::JumpIfFalse(_)
+            | Instruction::Return => -1,
+            Instruction::Jump(_) | Instruction::UnaryMinus | Instruction::UnaryNot => 0,
+            Instruction::JumpUnlessLessThan(_)
+            | Instruction::JumpUnlessLessThanEqual(_)
+            | Instruction::JumpUnlessGreaterThan(_)
+            | Instruction::JumpUnlessGreaterThanEqual(_)
+            | Instruction::JumpUnlessEqual(_)
+            | Instruction::JumpUnlessNotEqual(_) => -2,
+            Instruction::BinaryAdd
+            | Instruction::BinarySubtract
+            | Instruction::BinaryMultiply
+            | Instruction::BinaryDivide
+            | Instruction::BinaryReminder
+            | Instruction::BinaryPower
+            | Instruction::BinaryLessThan
+            | Instruction::BinaryLessThanEqual
+            | Instruction::BinaryGreaterThan
+            | Instruction::BinaryGreaterThanEqual
+            | Instruction::BinaryEqual
+            | Instruction::BinaryNotEqual
+            | Instruction::BinaryLogicalAnd
+            | Instruction::BinaryLogicalOr
+            | Instruction::BinaryLogicalXor
+            | Instruction::Contains => -1,
+            Instruction::BuildSet(count) | Instruction::BuildArray(count) => 1 - i32::from(count),
+            Instruction::GetIndex => -1,
+            Instruction::SetIndex => -3,
+            Instruction::Slice => -2,
+            Instruction::Push => -1,
+            Instruction::Call(_, argc)
+            | Instruction::CallHost(_, argc)
+            | Instruction::CallNative(_, argc) => 1 - i32::from(argc),
+            Instruction::TailCall(_, argc) => -i32::from(argc),
+            Instruction::PopN(count) => -i32::from(count),
+            Instruction::GetIter | Instruction::IterNext(_) => 1,
+        }
+    }
+
+    /// Target of a jump instruction, `None` if instruction isn't a jump.
+    pub fn jump_target(&self) -> Option<u16> {
+        match *self {
+            Instruction::Jump(target)
+            | Instruction::JumpIfTrue(target)
+            | Instruction::JumpIfFalse(target)
+            | Instruction::JumpUnlessLessThan(target)
+            | Instruction::JumpUnlessLessThanEqual(target)
+            | Instruction::JumpUnlessGreaterThan(target)
+            | Instruction::JumpUnlessGreaterThanEqual(target)
+            | Instruction::JumpUnlessEqual(target)
+            | Instruction::JumpUnlessNotEqual(target)
+            | Instruction::IterNext(target) => Some(target),
+            _ => None,
+        }
+    }
+
+    /// Same jump instruction with its target replaced.
+    fn with_target(self, target: u16) -> Instruction {
+        match self {
+            Instruction::Jump(_) => Instruction::Jump(target),
+            Instruction::JumpIfTrue(_) => Instruction::JumpIfTrue(target),
+            Instruction::JumpIfFalse(_) => Instruction::JumpIfFalse(target),
+            Instruction::JumpUnlessLessThan(_) => Instruction::JumpUnlessLessThan(target),
+            Instruction::JumpUnlessLessThanEqual(_) => Instruction::JumpUnlessLessThanEqual(target),
+            Instruction::JumpUnlessGreaterThan(_) => Instruction::JumpUnlessGreaterThan(target),
+            Instruction::JumpUnlessGreaterThanEqual(_) => {
+                Instruction::JumpUnlessGreaterThanEqual(target)
+            }
+            Instruction::JumpUnlessEqual(_) => Instruction::JumpUnlessEqual(target),
+            Instruction::JumpUnlessNotEqual(_) => Instruction::JumpUnlessNotEqual(target),
+            Instruction::IterNext(_) => Instruction::IterNext(target),
+            _ => unreachable!(),
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::parser;
+    use crate::{
+        ast::{
+            statement::{BreakStatement, ContinueStatement},
+            value::Value,
+        },
+        parser,
+        vm::VM,
+    };
 
-    use super::{Compile, Compiler, CompilerResult};
+    use super::{compile_program, Compile, Compiler, CompilerError, CompilerResult, Instruction};
 
     fn compile(input: &str) -> CompilerResult<()> {
         let mut compiler = Compiler::new();
@@ -345,6 +974,260 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn compile_whole_program() -> CompilerResult<()> {
+        let statements = parser::parse("var x = 1; while x < 10 { x = x * 2; } print x;").unwrap();
+        let code_block = compile_program(&statements)?;
+        assert!(!code_block.instructions.is_empty());
+
+        let statements = parser::parse("print x;").unwrap();
+        assert!(matches!(
+            compile_program(&statements),
+            Err(CompilerError::UndefinedIdentifer(_))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn fused_comparison_conditions() -> CompilerResult<()> {
+        let statements = parser::parse("var i = 0; while i < 10 { i = i + 1; }").unwrap();
+        let code_block = compile_program(&statements)?;
+        let instructions = code_block
+            .instructions
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            instructions,
+            [
+                "LoadValue(0)",
+                "StoreSymbol(0)",
+                "LoadSymbol(0)",
+                "LoadValue(1)",
+                "JumpUnlessLessThan(10)",
+                "LoadSymbol(0)",
+                "LoadValue(2)",
+                "BinaryAdd",
+                "StoreSymbol(0)",
+                "Jump(2)",
+            ]
+        );
+
+        // Conditions that aren't comparisons still produce a value to test
+        let statements = parser::parse("var x = true; if x { print x; }").unwrap();
+        let code_block = compile_program(&statements)?;
+        assert!(matches!(
+            code_block.instructions[3],
+            Instruction::JumpIfFalse(_)
+        ));
+        Ok(())
+    }
+
+    fn disassemble(input: &str) -> CompilerResult<Vec<String>> {
+        let statements = parser::parse(input).unwrap();
+        let code_block = compile_program(&statements)?;
+        Ok(code_block
+            .instructions
+            .iter()
+            .map(ToString::to_string)
+            .collect())
+    }
+
+    #[test]
+    fn break_inside_if_targets_loop_exit() -> CompilerResult<()> {
+        let instructions = disassemble("var c = true; while true { if c { break; } }")?;
+        assert_eq!(
+            instructions,
+            [
+                "LoadValue(0)",
+                "StoreSymbol(0)",
+                // `true` is loaded from the same constant
+                "LoadValue(0)",
+                // loop condition and `break` both leave the loop
+                "JumpIfFalse(9)",
+                "LoadSymbol(0)",
+                // `if` condition and exit both continue after the `if`
+                "JumpIfFalse(8)",
+                "Jump(9)",
+                "Jump(8)",
+                "Jump(2)",
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn nested_if_exits_innermost_block() -> CompilerResult<()> {
+        let instructions = disassemble("if true { if false { print 1; } print 2; }")?;
+        assert_eq!(
+            instructions,
+            [
+                "LoadValue(0)",
+                "JumpIfFalse(10)",
+                "LoadValue(1)",
+                "JumpIfFalse(7)",
+                "LoadValue(2)",
+                "Display",
+                "Jump(7)",
+                "LoadValue(3)",
+                "Display",
+                "Jump(10)",
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn loop_control_outside_loop() {
+        assert!(matches!(
+            compile("break;"),
+            Err(CompilerError::BreakOutsideLoop)
+        ));
+        assert!(matches!(
+            compile("if true { continue; }"),
+            Err(CompilerError::ContinueOutsideLoop)
+        ));
+    }
+
+    #[test]
+    fn sequential_blocks_reuse_local_slots() -> CompilerResult<()> {
+        let instructions = disassemble("{ var x = 1; print x; } { const y = 2; print y; }")?;
+        assert_eq!(
+            instructions,
+            [
+                "LoadValue(0)",
+                "StoreLocal(0)",
+                "LoadLocal(0)",
+                "Display",
+                "LoadValue(1)",
+                "StoreLocal(0)",
+                // `y` is inlined from the constant it was initialized with
+                "LoadValue(1)",
+                "Display",
+            ]
+        );
+
+        let instructions = disassemble("if true { var a = 1; { var b = a; } var c = a; }")?;
+        assert!(instructions.contains(&"StoreLocal(1)".to_string()));
+        assert!(!instructions.contains(&"StoreLocal(2)".to_string()));
+        Ok(())
+    }
+
+    #[test]
+    fn block_scoping() {
+        assert!(compile("{ var x = 1; } { var x = 2; }").is_ok());
+        assert!(compile("var x = 1; { var x = 2; }").is_ok());
+        assert!(matches!(
+            compile("{ var x = 1; } print x;"),
+            Err(CompilerError::UndefinedIdentifer(_))
+        ));
+        assert!(matches!(
+            compile("{ var x = 1; var x = 2; }"),
+            Err(CompilerError::Redefinition(_))
+        ));
+    }
+
+    #[test]
+    fn assignable_identifiers() -> CompilerResult<()> {
+        let mut compiler = Compiler::new();
+        let statements = parser::parse("const x = 10; var y = 12;").unwrap();
+        for statement in &statements {
+            statement.compile(&mut compiler)?;
+        }
+        assert_eq!(compiler.is_assignable("x"), Some(false));
+        assert_eq!(compiler.is_assignable("y"), Some(true));
+        assert_eq!(compiler.is_assignable("z"), None);
+        Ok(())
+    }
+
+    #[test]
+    fn loop_control_stops_at_function_boundary() {
+        let mut compiler = Compiler::new();
+        compiler.enter_while();
+        compiler.enter_function();
+        assert!(matches!(
+            BreakStatement.compile(&mut compiler),
+            Err(CompilerError::BreakOutsideLoop)
+        ));
+        assert!(matches!(
+            ContinueStatement.compile(&mut compiler),
+            Err(CompilerError::ContinueOutsideLoop)
+        ));
+        compiler.exit_function().unwrap();
+        assert!(BreakStatement.compile(&mut compiler).is_ok());
+        compiler.exit_while().unwrap();
+    }
+
+    #[test]
+    fn failed_declaration_emits_nothing() {
+        let mut compiler = Compiler::new();
+        let statements = parser::parse("const x = 1; const x = 2;").unwrap();
+        assert!(statements[0].compile(&mut compiler).is_ok());
+        assert!(matches!(
+            statements[1].compile(&mut compiler),
+            Err(CompilerError::Redefinition(_))
+        ));
+        let (code_block, _) = compiler.finish().unwrap();
+        assert_eq!(code_block.instructions.len(), 2);
+    }
+
+    #[test]
+    fn finished_compiler_has_no_pending_jumps() -> CompilerResult<()> {
+        let mut compiler = Compiler::new();
+        let program = "var x = 1; while x < 9 { if x == 3 { break; } else { x = x + 1; } }";
+        for statement in parser::parse(program).unwrap() {
+            statement.compile(&mut compiler)?;
+        }
+        assert!(compiler.blocks.is_empty());
+        assert!(compiler.unplaced_labels.is_empty());
+        assert!(compiler.finish().is_ok());
+        Ok(())
+    }
+
+    #[test]
+    fn dangling_jumps_are_detected() {
+        // Simulate a statement forgetting to exit its block
+        let mut compiler = Compiler::new();
+        compiler.enter_while();
+        let jump = compiler.emit_untargeted_jump();
+        compiler.target_jump_on_loop_exit(jump);
+        assert!(matches!(
+            compiler.check_finished(),
+            Err(CompilerError::InternalError(_))
+        ));
+
+        compiler.blocks.clear();
+        assert!(matches!(
+            compiler.check_finished(),
+            Err(CompilerError::InternalError(_))
+        ));
+
+        compiler.reset();
+        assert!(compiler.check_finished().is_ok());
+        assert!(compiler.instructions.is_empty());
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "InternalError")]
+    fn finish_asserts_in_debug_builds() {
+        let mut compiler = Compiler::new();
+        compiler.enter_if();
+        let _ = compiler.finish();
+    }
+
+    #[test]
+    fn failed_statements_can_be_discarded() -> CompilerResult<()> {
+        let mut compiler = Compiler::new();
+        let statements = parser::parse("while true { print y; } print 1;").unwrap();
+        assert!(statements[0].compile(&mut compiler).is_err());
+        compiler.reset();
+        statements[1].compile(&mut compiler)?;
+        let (code_block, _) = compiler.finish()?;
+        assert_eq!(code_block.instructions.len(), 2);
+        Ok(())
+    }
+
     #[test]
     fn wont_compile_statements() {
         assert!(compile("const x = 5; x = 5;").is_err());
@@ -353,4 +1236,391 @@ mod tests {
         assert!(compile("const x = x;").is_err());
         assert!(compile("var x = x;").is_err());
     }
+
+    #[test]
+    fn expression_statements_pop_their_value() -> CompilerResult<()> {
+        assert_eq!(disassemble("5;")?, ["LoadValue(0)", "Pop"]);
+        assert_eq!(
+            disassemble("set{1, 2};")?,
+            ["LoadValue(0)", "LoadValue(1)", "BuildSet(2)", "Pop"]
+        );
+
+        // Expressions that consume their own value don't get a `Pop`
+        let mut compiler = Compiler::new();
+        let start = compiler.place_label();
+        compiler.emit(Instruction::LoadValue(0));
+        compiler.emit(Instruction::StoreSymbol(0));
+        compiler.pop_unused_value(start);
+        assert!(matches!(
+            compiler.instructions.last(),
+            Some(Instruction::StoreSymbol(0))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn literal_constants_are_inlined() -> CompilerResult<()> {
+        assert_eq!(
+            disassemble("const PI = 3.5; var r = 2; print PI * r;")?,
+            [
+                "LoadValue(0)",
+                "StoreSymbol(0)",
+                "LoadValue(1)",
+                "StoreSymbol(1)",
+                "LoadValue(0)",
+                "LoadSymbol(1)",
+                "BinaryMultiply",
+                "Display"
+            ]
+        );
+        // Variables and locals shadowing a constant are loaded
+        assert_eq!(
+            disassemble("var x = 1; print x;")?,
+            ["LoadValue(0)", "StoreSymbol(0)", "LoadSymbol(0)", "Display"]
+        );
+        assert_eq!(
+            disassemble("const x = 1; { var x = 2; print x; }")?[4..],
+            ["LoadLocal(0)", "Display"]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn constants_bound_to_expressions_are_loaded() -> CompilerResult<()> {
+        let instructions = disassemble("fn f() { print 1; return 2; } const x = f(); print x;")?;
+        assert_eq!(instructions[..2], ["Call(0, 0)", "StoreSymbol(0)"]);
+        assert_eq!(instructions[2..], ["LoadSymbol(0)", "Display"]);
+        assert_eq!(
+            disassemble("const x = 1 + 2; print x;")?[4..],
+            ["LoadSymbol(0)", "Display"]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn unused_call_results_are_popped() -> CompilerResult<()> {
+        // The function's implicit `return null` registers the first constant
+        let function = "fn f(a) { return a; } var x;";
+        assert_eq!(
+            disassemble(&format!("{function} f(1);"))?,
+            ["LoadValue(1)", "Call(0, 1)", "Pop"]
+        );
+        assert_eq!(
+            disassemble(&format!("{function} x = f(1);"))?,
+            ["LoadValue(1)", "Call(0, 1)", "StoreSymbol(0)"]
+        );
+        assert_eq!(
+            disassemble(&format!("{function} append([], f(x));"))?,
+            [
+                "BuildArray(0)",
+                "LoadSymbol(0)",
+                "Call(0, 1)",
+                "Push",
+                "Pop"
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn implicit_var_declarations() -> CompilerResult<()> {
+        assert!(matches!(
+            compile("x = 5;"),
+            Err(CompilerError::UndefinedIdentifer(name)) if name == "x"
+        ));
+
+        let compile_implicit = |input: &str| {
+            let mut compiler = Compiler::new();
+            compiler.set_implicit_var(true);
+            for statement in parser::parse(input).unwrap() {
+                statement.compile(&mut compiler)?;
+            }
+            compiler.finish().map(|(code_block, _)| code_block)
+        };
+        let code_block = compile_implicit("x = 5; x = x + 1; print x; { y = x; print y; }")?;
+        let mut output = Vec::new();
+        VM::new_with_output(&mut output).run(&code_block).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), "6\n6\n");
+
+        // Reading an undeclared identifier is still an error
+        for program in ["x = x;", "x += 1;", "x[0] = 1;", "print x;"] {
+            assert!(
+                matches!(
+                    compile_implicit(program),
+                    Err(CompilerError::UndefinedIdentifer(_))
+                ),
+                "{program}"
+            );
+        }
+        assert!(matches!(
+            compile_implicit("const c = 1; c = 2;"),
+            Err(CompilerError::AssignmentToConst)
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn strict_mode_rejects_coercions() -> CompilerResult<()> {
+        let compile_strict = |input: &str| {
+            let mut compiler = Compiler::new();
+            compiler.set_strict(true);
+            for statement in parser::parse(input).unwrap() {
+                statement.compile(&mut compiler)?;
+            }
+            Ok(())
+        };
+        compile("if 5 { } while 0 { } print true + 1; print 1 == true;")?;
+        for program in [
+            "if 5 { }",
+            "while 1.5 { }",
+            "print true + 1;",
+            "print 1 == true;",
+        ] {
+            assert!(
+                matches!(compile_strict(program), Err(CompilerError::Type(_))),
+                "{program}"
+            );
+        }
+        compile_strict("var x = 1; if x < 2.5 { print x == 1.0; } while false { }")?;
+        Ok(())
+    }
+
+    #[test]
+    fn comparison_chains_keep_middle_operand() -> CompilerResult<()> {
+        assert_eq!(
+            disassemble("var a; var b; var c; print a < b < c;")?,
+            [
+                "LoadSymbol(0)",
+                "LoadSymbol(1)",
+                "Dup",
+                "StoreLocal(0)",
+                "BinaryLessThan",
+                "LoadLocal(0)",
+                "LoadSymbol(2)",
+                "BinaryLessThan",
+                "BinaryLogicalAnd",
+                "Display"
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn if_expressions_require_else() -> CompilerResult<()> {
+        compile("const x = if true { 1 } else { 2 };")?;
+        assert!(matches!(
+            compile("const x = if true { 1 };"),
+            Err(CompilerError::MissingElse)
+        ));
+        assert!(matches!(
+            compile("print if true { 1 } elif false { 2 };"),
+            Err(CompilerError::MissingElse)
+        ));
+        // Jumping out of an expression would leave its operands on the stack
+        assert!(matches!(
+            compile("while true { print 1 + if true { break; } else { 2 }; }"),
+            Err(CompilerError::BreakOutsideLoop)
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn self_tail_calls_reuse_frame() -> CompilerResult<()> {
+        let program = "
+            fn count(n) { if n > 0 { return count(n - 1); } return n; }
+            fn fib(n) { if n < 2 { return n; } return fib(n - 1) + fib(n - 2); }
+            fn g(n) { return count(n); }
+        ";
+        let code_block = compile_program(&parser::parse(program).unwrap())?;
+        let function = |idx: usize| -> Vec<String> {
+            let instructions = &code_block.functions[idx].code.instructions;
+            instructions.iter().map(ToString::to_string).collect()
+        };
+        assert!(function(0).contains(&"TailCall(0, 1)".to_string()));
+        // Calls in other expressions and calls of other functions aren't
+        // tail calls
+        assert!(!function(1).iter().any(|i| i.starts_with("TailCall")));
+        assert_eq!(function(2)[..2], ["LoadLocal(0)", "Call(0, 1)"]);
+        Ok(())
+    }
+
+    #[test]
+    fn statements_have_no_net_stack_effect() -> CompilerResult<()> {
+        let programs = [
+            "5; 1 + 2 * 3; 1 in set{1, 2};",
+            "var x = 1; const y = x; x = x + y; print x;",
+            "var x = 1; if x < 2 { print 1; } else if x { 2; } else { var z; }",
+            "var i = 0; while i < 10 { i = i + 1; if i == 5 { break; } continue; }",
+            "{ var x = 1; { var y = x; } }",
+            "{ var x = 1; { x + 1 } x }",
+            "for (var i = 0; i < 3; i += 1) { i; continue; } for (;;) { break; }",
+            "var i = 0; i++; { var j = i; j--; } for (; i < 3; i++) { }",
+            "for x in [1, 2] { x; if x { continue; } break; } for y in reversed([]) { }",
+        ];
+        for program in programs {
+            let mut compiler = Compiler::new();
+            for statement in parser::parse(program).unwrap() {
+                let start = compiler.place_label();
+                statement.compile(&mut compiler)?;
+                assert_eq!(compiler.stack_effect_since(start), 0, "{statement}");
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn scope_cleanup() -> CompilerResult<()> {
+        // Locals live in frame slots rather than on the stack, exiting their
+        // scope frees the slots without emitting instructions
+        assert_eq!(
+            disassemble("{ var a = 1; var b = 2; var c = 3; }")?,
+            [
+                "LoadValue(0)",
+                "StoreLocal(0)",
+                "LoadValue(1)",
+                "StoreLocal(1)",
+                "LoadValue(2)",
+                "StoreLocal(2)"
+            ]
+        );
+        // The iterated array and the index of a `for` loop are dropped at once
+        let code = disassemble("for x in [] { }")?;
+        assert_eq!(code.last().unwrap(), "PopN(2)");
+        assert!(!code.contains(&String::from("Pop")));
+        Ok(())
+    }
+
+    #[test]
+    fn jump_targets_past_instruction_limit() {
+        // Each `print x;` is two instructions so the bodies below need jump
+        // targets beyond `u16::MAX`
+        let body = "print x;".repeat(usize::from(u16::MAX) / 2 + 1);
+        for program in [
+            format!("var x = 1; if x {{ {body} }}"),
+            format!("var x = 1; if x {{ }} else {{ {body} }}"),
+            format!("var x = 1; while x {{ {body} }}"),
+            format!("var x = 1; while x {{ {body} break; }}"),
+            format!("var x = 1; {body} while x {{ }}"),
+        ] {
+            let statements = parser::parse(&program).unwrap();
+            assert!(matches!(
+                compile_program(&statements),
+                Err(CompilerError::InstructionLimitReached)
+            ));
+        }
+
+        // Straight-line code has no jump targets to overflow
+        let statements = parser::parse(&format!("var x = 1; {body}")).unwrap();
+        assert!(compile_program(&statements).is_ok());
+    }
+
+    #[test]
+    fn constant_limit() -> CompilerResult<()> {
+        // Constant indices are `u16` so `u16::MAX + 1` constants fit
+        let mut compiler = Compiler::new();
+        for i in 0..=u16::MAX {
+            assert_eq!(compiler.register_value(Value::Integer(i.into()))?, i);
+        }
+        assert!(matches!(
+            compiler.register_value(Value::Null),
+            Err(CompilerError::ConstantLimitReached)
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn programs_past_constant_limit() {
+        let program = (0..=i64::from(u16::MAX))
+            .map(|i| format!("print {i};"))
+            .collect::<String>();
+        let statements = parser::parse(&program).unwrap();
+        let code_block = compile_program(&statements).unwrap();
+        assert_eq!(code_block.values.len(), usize::from(u16::MAX) + 1);
+
+        // The 65,537th constant would alias index 0 if it were truncated
+        let statements = parser::parse(&format!("{program} print -1;")).unwrap();
+        assert!(matches!(
+            compile_program(&statements),
+            Err(CompilerError::ConstantLimitReached)
+        ));
+    }
+
+    #[test]
+    fn compile_expression() -> CompilerResult<()> {
+        let mut compiler = Compiler::new();
+        let expr = parser::parse_expression_str("2 + 3 * 4").unwrap();
+        let code_block = compiler.compile_expression(&expr)?;
+        assert!(code_block
+            .instructions
+            .iter()
+            .all(|instruction| !matches!(instruction, Instruction::Pop | Instruction::Display)));
+        let mut output = Vec::new();
+        let value = VM::new_with_output(&mut output).run(&code_block).unwrap();
+        assert_eq!(value, Some(Value::Integer(14)));
+
+        // Failed expressions leave nothing behind for the next one
+        let expr = parser::parse_expression_str("1 + y").unwrap();
+        assert!(matches!(
+            compiler.compile_expression(&expr),
+            Err(CompilerError::UndefinedIdentifer(name)) if name == "y"
+        ));
+        let expr = parser::parse_expression_str("[1, 2]").unwrap();
+        let code_block = compiler.compile_expression(&expr)?;
+        assert_eq!(code_block.instructions.len(), 3);
+        Ok(())
+    }
+
+    #[test]
+    fn functions_share_constants() -> CompilerResult<()> {
+        let program = "fn f() { return 42; } fn g() { return 42; } print f() + 42;";
+        let code_block = compile_program(&parser::parse(program).unwrap())?;
+        assert_eq!(*code_block.values, [Value::Integer(42), Value::Null]);
+        for function in &code_block.functions {
+            assert!(function.code.values.is_empty());
+            assert_eq!(function.code.instructions[0].to_string(), "LoadValue(0)");
+        }
+
+        // Equal constants of different types or signs are kept apart
+        let mut compiler = Compiler::new();
+        let constants = [
+            Value::Integer(0),
+            Value::Float(0.0),
+            Value::Float(-0.0),
+            Value::Integer(0),
+        ];
+        let indices = constants
+            .into_iter()
+            .map(|value| compiler.register_value(value))
+            .collect::<CompilerResult<Vec<_>>>()?;
+        assert_eq!(indices, [0, 1, 2, 0]);
+        Ok(())
+    }
+
+    #[test]
+    fn function_errors() {
+        assert!(matches!(
+            compile("return 1;"),
+            Err(CompilerError::ReturnOutsideFunction)
+        ));
+        assert!(matches!(
+            compile("if true { return; }"),
+            Err(CompilerError::ReturnOutsideFunction)
+        ));
+        assert!(matches!(
+            compile("fn f(x) { return x; } f(1, 2);"),
+            Err(CompilerError::ArityMismatch {
+                expected: 1,
+                found: 2,
+                ..
+            })
+        ));
+        assert!(matches!(
+            compile("fn f() {} fn f() {}"),
+            Err(CompilerError::Redefinition(name)) if name == "f"
+        ));
+        assert!(matches!(
+            compile("g();"),
+            Err(CompilerError::UndefinedIdentifer(name)) if name == "g"
+        ));
+    }
 }
@@ -0,0 +1,264 @@
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::ast::expression::{
+    binary::BinaryOperator,
+    unary::{UnaryExpression, UnaryOperator},
+    BinaryExpression, Expression,
+};
+
+use super::{symbol_table::Slot, Compiler, CompilerError, CompilerResult};
+
+/// Source of a register instruction's operand, symbols and values are read
+/// directly so loading them doesn't require separate instructions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operand {
+    Register(u8),
+    Symbol(u16),
+    Local(u16),
+    Value(u16),
+}
+
+impl fmt::Display for Operand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Operand::Register(register) => write!(f, "r{register}"),
+            Operand::Symbol(idx) => write!(f, "s{idx}"),
+            Operand::Local(idx) => write!(f, "l{idx}"),
+            Operand::Value(idx) => write!(f, "v{idx}"),
+        }
+    }
+}
+
+/// Instructions of the experimental register based instruction set, each
+/// instruction writes its result into `dst` register.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegisterInstruction {
+    Move {
+        dst: u8,
+        src: Operand,
+    },
+    Binary {
+        operator: BinaryOperator,
+        dst: u8,
+        lhs: Operand,
+        rhs: Operand,
+    },
+    Unary {
+        operator: UnaryOperator,
+        dst: u8,
+        operand: Operand,
+    },
+}
+
+impl fmt::Display for RegisterInstruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RegisterInstruction::Move { dst, src } => write!(f, "r{dst} = {src}"),
+            RegisterInstruction::Binary {
+                operator,
+                dst,
+                lhs,
+                rhs,
+            } => write!(f, "r{dst} = {lhs} {operator} {rhs}"),
+            RegisterInstruction::Unary {
+                operator,
+                dst,
+                operand,
+            } => write!(f, "r{dst} = {operator} {operand}"),
+        }
+    }
+}
+
+/// Register code for a single expression, value of the expression is placed
+/// in `result` register.
+#[derive(Debug)]
+pub struct RegisterCode {
+    pub instructions: Vec<RegisterInstruction>,
+    pub result: u8,
+    /// Number of registers required to evaluate the expression.
+    pub registers: u8,
+}
+
+/// Allocates virtual registers for expression temporaries, registers are
+/// released as soon as an instruction consumes them so they are reused.
+#[derive(Debug, Default)]
+struct RegisterAllocator {
+    free: Vec<u8>,
+    count: u8,
+}
+
+impl RegisterAllocator {
+    fn allocate(&mut self) -> CompilerResult<u8> {
+        if let Some(register) = self.free.pop() {
+            return Ok(register);
+        }
+        let register = self.count;
+        self.count = self
+            .count
+            .checked_add(1)
+            .ok_or(CompilerError::RegisterLimitReached)?;
+        Ok(register)
+    }
+
+    fn release(&mut self, operand: Operand) {
+        if let Operand::Register(register) = operand {
+            self.free.push(register);
+        }
+    }
+}
+
+struct RegisterCompiler<'a> {
+    compiler: &'a mut Compiler,
+    allocator: RegisterAllocator,
+    instructions: Vec<RegisterInstruction>,
+}
+
+impl RegisterCompiler<'_> {
+    fn compile(&mut self, expression: &Expression) -> CompilerResult<Operand> {
+        match expression {
+            Expression::Value(value) => {
+                let idx = self.compiler.register_value(value.clone())?;
+                Ok(Operand::Value(idx))
+            }
+            Expression::Identifier(identifier) => {
+                match self.compiler.get_identifier(&identifier.ident) {
+                    Some((_, Slot::Global(idx))) => Ok(Operand::Symbol(idx)),
+                    Some((_, Slot::Local(idx))) => Ok(Operand::Local(idx)),
+                    None => Err(CompilerError::UndefinedIdentifer(identifier.ident.clone())),
+                }
+            }
+            Expression::Binary(binary) => self.compile_binary(binary),
+            Expression::Unary(unary) => self.compile_unary(unary),
+            Expression::Comparison(_)
+            | Expression::Set(_)
+            | Expression::Array(_)
+            | Expression::Call(_)
+            | Expression::If(_)
+            | Expression::Index(_)
+            | Expression::Slice(_) => Err(CompilerError::UnsupportedByRegisters),
+        }
+    }
+
+    fn compile_binary(&mut self, binary: &BinaryExpression) -> CompilerResult<Operand> {
+        let lhs = self.compile(&binary.left)?;
+        let rhs = self.compile(&binary.right)?;
+        self.allocator.release(lhs);
+        self.allocator.release(rhs);
+        let dst = self.allocator.allocate()?;
+        self.instructions.push(RegisterInstruction::Binary {
+            operator: *binary.operator,
+            dst,
+            lhs,
+            rhs,
+        });
+        Ok(Operand::Register(dst))
+    }
+
+    fn compile_unary(&mut self, unary: &UnaryExpression) -> CompilerResult<Operand> {
+        let operand = self.compile(&unary.expression)?;
+        if unary.operator == UnaryOperator::Plus {
+            return Ok(operand);
+        }
+        self.allocator.release(operand);
+        let dst = self.allocator.allocate()?;
+        self.instructions.push(RegisterInstruction::Unary {
+            operator: unary.operator,
+            dst,
+            operand,
+        });
+        Ok(Operand::Register(dst))
+    }
+}
+
+impl Compiler {
+    /// Compile straight-line `expression` for the experimental register based
+    /// instruction set, the default stack based backend is unaffected.
+    pub fn compile_registers(&mut self, expression: &Expression) -> CompilerResult<RegisterCode> {
+        let mut register_compiler = RegisterCompiler {
+            compiler: self,
+            allocator: RegisterAllocator::default(),
+            instructions: Vec::new(),
+        };
+        let result = match register_compiler.compile(expression)? {
+            Operand::Register(register) => register,
+            src => {
+                let dst = register_compiler.allocator.allocate()?;
+                register_compiler
+                    .instructions
+                    .push(RegisterInstruction::Move { dst, src });
+                dst
+            }
+        };
+        Ok(RegisterCode {
+            instructions: register_compiler.instructions,
+            result,
+            registers: register_compiler.allocator.count,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        ast::expression::Expression,
+        compiler::{Compile, Compiler},
+        parser::{self, Rule},
+    };
+
+    use super::RegisterCode;
+
+    fn compile_both(input: &str) -> (usize, RegisterCode) {
+        let expression = parser::parse_rule::<Expression>(Rule::expression, input).unwrap();
+
+        let mut compiler = Compiler::new();
+        compiler.register_var("a").unwrap();
+        compiler.register_var("b").unwrap();
+        expression.compile(&mut compiler).unwrap();
+        let (stack, _) = compiler.finish().unwrap();
+
+        let mut compiler = Compiler::new();
+        compiler.register_var("a").unwrap();
+        compiler.register_var("b").unwrap();
+        let registers = compiler.compile_registers(&expression).unwrap();
+        (stack.instructions.len(), registers)
+    }
+
+    #[test]
+    fn test_register_instruction_counts() {
+        let cases = [
+            ("a", 1, 1),
+            ("a + b", 3, 1),
+            ("a * b + 2", 5, 2),
+            ("(a + 1) * (b - 2) / 3", 9, 4),
+            ("not a + b * 3 ** 2", 8, 4),
+        ];
+        for (input, stack_count, register_count) in cases {
+            let (stack, registers) = compile_both(input);
+            assert_eq!(stack, stack_count, "{input}");
+            assert_eq!(registers.instructions.len(), register_count, "{input}");
+            assert!(registers.instructions.len() <= stack);
+        }
+    }
+
+    #[test]
+    fn test_registers_are_reused() {
+        let (_, code) = compile_both("(a + 1) * (b - 2) / 3");
+        let listing = code
+            .instructions
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            listing,
+            [
+                "r0 = s0 + v0",
+                "r1 = s1 - v1",
+                "r1 = r0 * r1",
+                "r1 = r1 / v2"
+            ]
+        );
+        assert_eq!(code.registers, 2);
+        assert_eq!(code.result, 1);
+    }
+}
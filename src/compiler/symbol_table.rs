@@ -1,16 +1,71 @@
-use std::{collections::HashMap, convert::TryInto, mem};
+use alloc::{rc::Rc, string::String, vec::Vec};
+use core::{convert::TryInto, mem};
 
-use crate::ast::{
-    identifier::{Identifier, IdentifierKind},
-    value::Value,
+use crate::{
+    ast::{
+        identifier::{Identifier, IdentifierKind},
+        value::{HashableValue, Value},
+    },
+    collections::HashMap,
 };
 
-use super::{CompilerError, CompilerResult};
+use super::{CompilerError, CompilerResult, Instruction};
+
+/// Storage location of an identifier, globals live in the symbol table for
+/// the whole program while locals occupy a frame-relative slot that is reused
+/// once their block exits.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Slot {
+    Global(u16),
+    Local(u16),
+}
+
+impl Slot {
+    pub fn load(self) -> Instruction {
+        match self {
+            Slot::Global(idx) => Instruction::LoadSymbol(idx),
+            Slot::Local(idx) => Instruction::LoadLocal(idx),
+        }
+    }
+
+    pub fn store(self) -> Instruction {
+        match self {
+            Slot::Global(idx) => Instruction::StoreSymbol(idx),
+            Slot::Local(idx) => Instruction::StoreLocal(idx),
+        }
+    }
+}
+
+/// Registered identifier, constants bound to a literal keep the literal so
+/// loads can be replaced with it.
+#[derive(Debug)]
+struct Symbol {
+    kind: IdentifierKind,
+    idx: u16,
+    inlined: Option<Value>,
+}
+
+impl Symbol {
+    fn new(identifier: &Identifier, idx: u16) -> Self {
+        Self {
+            kind: identifier.kind,
+            idx,
+            inlined: None,
+        }
+    }
+}
 
 #[derive(Debug, Default)]
 pub struct SymbolTable {
-    table: HashMap<String, (IdentifierKind, u16)>,
-    values: Vec<Value>,
+    table: HashMap<String, Symbol>,
+    scopes: Vec<HashMap<String, Symbol>>,
+    locals: u16,
+    /// Constants of the whole program, shared by the main code block and
+    /// every function. Code blocks share the pool rather than copying it, so
+    /// finishing a code block doesn't copy constants registered earlier.
+    values: Rc<Vec<Value>>,
+    /// Index of each hashable constant in `values`.
+    constants: HashMap<HashableValue, u16>,
 }
 
 impl SymbolTable {
@@ -18,27 +73,118 @@ impl SymbolTable {
         Self::default()
     }
 
-    pub fn register(&mut self, identifier: Identifier) -> CompilerResult<u16> {
+    /// Register `identifier` in the innermost scope, identifiers declared
+    /// outside of any block are globals.
+    pub fn register(&mut self, identifier: Identifier) -> CompilerResult<Slot> {
         if self.contains(&identifier.ident) {
             return Err(CompilerError::Redefinition(identifier.ident));
         }
 
-        let idx = self.next_identifier()?;
-        self.table.insert(identifier.ident, (identifier.kind, idx));
-        Ok(idx)
+        let next_local = self.locals.checked_add(1);
+        match self.scopes.last_mut() {
+            Some(scope) => {
+                let idx = self.locals;
+                self.locals = next_local.ok_or(CompilerError::VariableLimitReached)?;
+                scope.insert(identifier.ident.clone(), Symbol::new(&identifier, idx));
+                Ok(Slot::Local(idx))
+            }
+            None => {
+                let idx = self.next_identifier()?;
+                self.table
+                    .insert(identifier.ident.clone(), Symbol::new(&identifier, idx));
+                Ok(Slot::Global(idx))
+            }
+        }
+    }
+
+    /// Look up `ident` from the innermost scope outwards, so locals shadow
+    /// identifiers of enclosing scopes.
+    pub fn get(&self, ident: &str) -> Option<(IdentifierKind, Slot)> {
+        self.lookup(ident).map(|(symbol, slot)| (symbol.kind, slot))
     }
 
-    pub fn get(&self, ident: &str) -> Option<(IdentifierKind, u16)> {
-        self.table.get(ident).copied()
+    fn lookup(&self, ident: &str) -> Option<(&Symbol, Slot)> {
+        for scope in self.scopes.iter().rev() {
+            if let Some(symbol) = scope.get(ident) {
+                return Some((symbol, Slot::Local(symbol.idx)));
+            }
+        }
+        self.table
+            .get(ident)
+            .map(|symbol| (symbol, Slot::Global(symbol.idx)))
     }
 
+    /// Record that constant `ident` of the innermost scope is bound to
+    /// `value`, later loads of `ident` can use `value` instead.
+    pub fn inline(&mut self, ident: &str, value: Value) {
+        let symbol = match self.scopes.last_mut() {
+            Some(scope) => scope.get_mut(ident),
+            None => self.table.get_mut(ident),
+        };
+        if let Some(symbol) = symbol {
+            debug_assert_eq!(symbol.kind, IdentifierKind::Constant);
+            symbol.inlined = Some(value);
+        }
+    }
+
+    /// Literal bound to the constant `ident` resolves to, `None` for
+    /// variables and constants that weren't inlined.
+    pub fn get_inlined(&self, ident: &str) -> Option<&Value> {
+        self.lookup(ident)
+            .and_then(|(symbol, _)| symbol.inlined.as_ref())
+    }
+
+    /// Check whether `identifier` is declared in the innermost scope.
     pub fn contains(&self, identifier: &str) -> bool {
-        self.table.contains_key(identifier)
+        match self.scopes.last() {
+            Some(scope) => scope.contains_key(identifier),
+            None => self.table.contains_key(identifier),
+        }
+    }
+
+    /// Check whether identifiers registered now would be globals.
+    pub fn is_global_scope(&self) -> bool {
+        self.scopes.is_empty()
+    }
+
+    pub fn enter_scope(&mut self) {
+        self.scopes.push(HashMap::new());
+    }
+
+    /// Exit the innermost scope, its local slots are freed for reuse.
+    pub fn exit_scope(&mut self) {
+        let scope = self.scopes.pop().unwrap();
+        self.locals -= scope.len() as u16;
     }
 
+    /// Enter the outermost scope of a function, its locals are numbered from
+    /// zero. Returns the number of locals in use outside the function, which
+    /// must be passed to the matching `exit_frame`.
+    pub fn enter_frame(&mut self) -> u16 {
+        self.enter_scope();
+        mem::take(&mut self.locals)
+    }
+
+    pub fn exit_frame(&mut self, locals: u16) {
+        self.scopes.pop().unwrap();
+        self.locals = locals;
+    }
+
+    /// Index of `value` in the constant pool, constants that are already in
+    /// the pool are reused.
     pub fn register_value(&mut self, value: Value) -> Result<u16, CompilerError> {
+        let key = HashableValue::try_from(value.clone()).ok();
+        if let Some(&index) = key.as_ref().and_then(|key| self.constants.get(key)) {
+            // Hashing doesn't tell `0.0` from `-0.0` or NaNs apart
+            if identical(&self.values[index as usize], &value) {
+                return Ok(index);
+            }
+        }
         let index = self.next_constant()?;
-        self.values.push(value);
+        Rc::make_mut(&mut self.values).push(value);
+        if let Some(key) = key {
+            self.constants.entry(key).or_insert(index);
+        }
         Ok(index)
     }
 
@@ -54,12 +200,12 @@ impl SymbolTable {
         let count = self.values.len();
         match count.try_into() {
             Ok(index) => Ok(index),
-            Err(_) => Err(CompilerError::VariableLimitReached),
+            Err(_) => Err(CompilerError::ConstantLimitReached),
         }
     }
 
     pub fn get_symbol(&self, index: u16) -> Option<&String> {
-        let result = self.table.iter().find(|(_, (_, idx))| *idx == index);
+        let result = self.table.iter().find(|(_, symbol)| symbol.idx == index);
 
         match result {
             Some((identifier, _)) => Some(identifier),
@@ -71,9 +217,21 @@ impl SymbolTable {
         self.values.get(index as usize)
     }
 
-    pub fn finish(&mut self) -> (Vec<Value>, Vec<&'_ String>) {
-        let values = mem::take(&mut self.values);
-        let debug_symbols: Vec<_> = self.table.keys().collect();
+    /// Constants registered so far together with the names of globals, the
+    /// name of the global with index `i` is at position `i`. Constants stay
+    /// registered since functions declared so far refer to them.
+    pub fn finish(&mut self) -> (Rc<Vec<Value>>, Vec<&'_ String>) {
+        let values = Rc::clone(&self.values);
+        let mut globals: Vec<_> = self.table.iter().collect();
+        globals.sort_unstable_by_key(|(_, symbol)| symbol.idx);
+        let debug_symbols = globals.into_iter().map(|(ident, _)| ident).collect();
         (values, debug_symbols)
     }
 }
+
+fn identical(lhs: &Value, rhs: &Value) -> bool {
+    match (lhs, rhs) {
+        (Value::Float(lhs), Value::Float(rhs)) => lhs.to_bits() == rhs.to_bits(),
+        (lhs, rhs) => lhs == rhs,
+    }
+}
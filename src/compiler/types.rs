@@ -0,0 +1,381 @@
+use alloc::{
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use core::{fmt, mem};
+
+use thiserror::Error;
+
+use crate::{
+    ast::{
+        expression::{binary::BinaryOperator, unary::UnaryOperator},
+        statement::Statement,
+    },
+    collections::HashMap,
+};
+
+/// Coarse static type of an expression, `Unknown` is assigned whenever the
+/// type can't be determined and never produces an error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Type {
+    Int,
+    Float,
+    Bool,
+    String,
+    Unknown,
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Type::Int => write!(f, "int"),
+            Type::Float => write!(f, "float"),
+            Type::Bool => write!(f, "bool"),
+            Type::String => write!(f, "string"),
+            Type::Unknown => write!(f, "unknown"),
+        }
+    }
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum TypeError {
+    #[error("unsupported operand types for `{0}`: `{1}` and `{2}`")]
+    UnsupportedOperands(BinaryOperator, Type, Type),
+    #[error("unsupported operand type for `{0}`: `{1}`")]
+    UnsupportedOperand(UnaryOperator, Type),
+    #[error("condition must be `bool` but found `{0}`")]
+    NonBooleanCondition(Type),
+    #[error("expected `{expected}` but found `{found}`")]
+    Mismatch { expected: Type, found: Type },
+}
+
+/// Assign a type to an expression, errors are reported to `checker` and
+/// the expression is typed `Unknown` so they don't cascade.
+pub trait Infer {
+    fn infer(&self, checker: &mut TypeChecker) -> Type;
+}
+
+/// Check a statement reporting any type errors to `checker`.
+pub trait Check {
+    fn check(&self, checker: &mut TypeChecker);
+}
+
+/// Infer types of all expressions in `statements`, returns every type error
+/// found rather than stopping at the first one.
+pub fn infer_types(statements: &[Statement]) -> Result<(), Vec<TypeError>> {
+    let mut checker = TypeChecker::new();
+    for statement in statements {
+        statement.check(&mut checker);
+    }
+    checker.finish()
+}
+
+/// Type of a declared identifier, identifiers with a type annotation keep
+/// their type and values of any other type are errors.
+#[derive(Debug, Clone, Copy)]
+struct Binding {
+    ty: Type,
+    annotated: bool,
+}
+
+#[derive(Debug)]
+pub struct TypeChecker {
+    scopes: Vec<HashMap<String, Binding>>,
+    errors: Vec<TypeError>,
+    strict: bool,
+}
+
+impl Default for TypeChecker {
+    fn default() -> Self {
+        Self {
+            scopes: vec![HashMap::new()],
+            errors: Vec::new(),
+            strict: false,
+        }
+    }
+}
+
+impl TypeChecker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a checker that also rejects comparing values of different
+    /// types for equality, integers and floats can still be compared.
+    pub fn new_strict() -> Self {
+        Self {
+            strict: true,
+            ..Self::default()
+        }
+    }
+
+    pub fn report(&mut self, error: TypeError) {
+        self.errors.push(error);
+    }
+
+    pub fn declare(&mut self, ident: &str, ty: Type) {
+        self.bind(
+            ident,
+            Binding {
+                ty,
+                annotated: false,
+            },
+        );
+    }
+
+    /// Declare `ident` with an explicit type `annotation`, `initial` is the
+    /// type of its initializer if it has one.
+    pub fn declare_annotated(&mut self, ident: &str, annotation: Type, initial: Option<Type>) {
+        if let Some(initial) = initial {
+            self.expect_type(annotation, initial);
+        }
+        let binding = Binding {
+            ty: annotation,
+            annotated: true,
+        };
+        self.bind(ident, binding);
+    }
+
+    fn bind(&mut self, ident: &str, binding: Binding) {
+        let scope = self
+            .scopes
+            .last_mut()
+            .expect("global scope is never exited");
+        scope.insert(ident.to_string(), binding);
+    }
+
+    /// Report a value of type `found` where `expected` is required, unless
+    /// either of them is `Unknown`.
+    pub fn expect_type(&mut self, expected: Type, found: Type) {
+        if expected != found && expected != Type::Unknown && found != Type::Unknown {
+            self.report(TypeError::Mismatch { expected, found });
+        }
+    }
+
+    /// Record an assignment to `ident`, a variable assigned values of
+    /// different types is `Unknown` from then on unless its type is
+    /// annotated, in which case the value must have the annotated type.
+    pub fn assign(&mut self, ident: &str, ty: Type) {
+        let Some(current) = self.scopes.iter_mut().rev().find_map(|s| s.get_mut(ident)) else {
+            return;
+        };
+        let binding = *current;
+        if binding.annotated {
+            self.expect_type(binding.ty, ty);
+        } else if binding.ty != ty {
+            current.ty = Type::Unknown;
+        }
+    }
+
+    /// Type of `ident` in the innermost scope declaring it, undeclared
+    /// identifiers are `Unknown` since they are reported by the compiler.
+    pub fn lookup(&self, ident: &str) -> Type {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(ident).map(|binding| binding.ty))
+            .unwrap_or(Type::Unknown)
+    }
+
+    /// Type of applying `operator` to operands of type `left` and `right`,
+    /// unsupported operands are reported unless either of them is `Unknown`.
+    pub fn infer_binary(&mut self, operator: BinaryOperator, left: Type, right: Type) -> Type {
+        if self.strict && matches!(operator, BinaryOperator::Equal | BinaryOperator::NotEqual) {
+            return self.infer_strict_equality(operator, left, right);
+        }
+        match operator.result_type(left, right) {
+            Some(ty) => ty,
+            None if left == Type::Unknown || right == Type::Unknown => Type::Unknown,
+            None => {
+                self.report(TypeError::UnsupportedOperands(operator, left, right));
+                Type::Unknown
+            }
+        }
+    }
+
+    fn infer_strict_equality(&mut self, operator: BinaryOperator, left: Type, right: Type) -> Type {
+        let numeric = |ty| matches!(ty, Type::Int | Type::Float);
+        let comparable = left == right
+            || left == Type::Unknown
+            || right == Type::Unknown
+            || (numeric(left) && numeric(right));
+        if !comparable {
+            self.report(TypeError::UnsupportedOperands(operator, left, right));
+        }
+        Type::Bool
+    }
+
+    /// Require `condition` of an `if` or `while` to be a boolean.
+    pub fn check_condition(&mut self, ty: Type) {
+        if !matches!(ty, Type::Bool | Type::Unknown) {
+            self.report(TypeError::NonBooleanCondition(ty));
+        }
+    }
+
+    pub fn enter_scope(&mut self) {
+        self.scopes.push(HashMap::new());
+    }
+
+    pub fn exit_scope(&mut self) {
+        self.scopes.pop();
+    }
+
+    pub fn check_block(&mut self, statements: &[Statement]) {
+        self.enter_scope();
+        for statement in statements {
+            statement.check(self);
+        }
+        self.exit_scope();
+    }
+
+    /// Remove and return errors reported so far.
+    pub fn take_errors(&mut self) -> Vec<TypeError> {
+        mem::take(&mut self.errors)
+    }
+
+    pub fn finish(self) -> Result<(), Vec<TypeError>> {
+        if self.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(self.errors)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        ast::expression::{binary::BinaryOperator, unary::UnaryOperator},
+        parser::parse,
+    };
+
+    use super::{infer_types, Check, Type, TypeChecker, TypeError};
+
+    fn check(input: &str) -> Result<(), Vec<TypeError>> {
+        infer_types(&parse(input).unwrap())
+    }
+
+    #[test]
+    fn well_typed_programs() {
+        check("var x = 1 + 2.5 * 3; print x / 2;").unwrap();
+        check("var b = 1 < 2; if b == true and b { print not false; }").unwrap();
+        check("var i = 0; while i < 10 { i = i + 1; }").unwrap();
+        check("print 1 in set{1, 2}; print 1 == true; print not (1 < 2);").unwrap();
+    }
+
+    #[test]
+    fn reports_every_error() {
+        let errors = check("print true + 1; var x = 5; print not x;").unwrap_err();
+        assert_eq!(
+            errors,
+            vec![
+                TypeError::UnsupportedOperands(BinaryOperator::Add, Type::Bool, Type::Int),
+                TypeError::UnsupportedOperand(UnaryOperator::Not, Type::Int),
+            ]
+        );
+    }
+
+    #[test]
+    fn non_boolean_conditions() {
+        let errors = check("if 1 { print 1; } while 2.5 { break; }").unwrap_err();
+        assert_eq!(
+            errors,
+            vec![
+                TypeError::NonBooleanCondition(Type::Int),
+                TypeError::NonBooleanCondition(Type::Float),
+            ]
+        );
+    }
+
+    #[test]
+    fn unknown_suppresses_errors() {
+        // Uninitialized, undeclared and reassigned variables are all unknown
+        check("var x; print x + 1; print y + 1;").unwrap();
+        check("var x = 1; x = true; print not x;").unwrap();
+        // Errors don't cascade into the enclosing expression
+        let errors = check("print (true + 1) * false;").unwrap_err();
+        assert_eq!(errors.len(), 1);
+    }
+
+    #[test]
+    fn block_scoping() {
+        check("var x = true; { var x = 1; print x + 1; } print not x;").unwrap();
+        let errors = check("var x = 1; { var x = true; } print not x;").unwrap_err();
+        assert_eq!(
+            errors,
+            vec![TypeError::UnsupportedOperand(UnaryOperator::Not, Type::Int)]
+        );
+    }
+
+    #[test]
+    fn strict_equality() {
+        let mut checker = TypeChecker::new_strict();
+        for statement in parse("print 1 == 1.5; print true != false; var x; print x == 1;").unwrap()
+        {
+            statement.check(&mut checker);
+        }
+        assert!(checker.take_errors().is_empty());
+
+        for statement in parse("print 1 == true; print 2.5 != false;").unwrap() {
+            statement.check(&mut checker);
+        }
+        assert_eq!(
+            checker.take_errors(),
+            vec![
+                TypeError::UnsupportedOperands(BinaryOperator::Equal, Type::Int, Type::Bool),
+                TypeError::UnsupportedOperands(BinaryOperator::NotEqual, Type::Float, Type::Bool),
+            ]
+        );
+    }
+
+    #[test]
+    fn string_comparisons() {
+        let mut checker = TypeChecker::new();
+        let less_than = BinaryOperator::LessThan;
+        assert_eq!(
+            checker.infer_binary(less_than, Type::String, Type::String),
+            Type::Bool
+        );
+        assert_eq!(
+            checker.infer_binary(less_than, Type::Unknown, Type::String),
+            Type::Bool
+        );
+        assert!(checker.take_errors().is_empty());
+        checker.infer_binary(less_than, Type::String, Type::Int);
+        assert_eq!(
+            checker.take_errors(),
+            vec![TypeError::UnsupportedOperands(
+                less_than,
+                Type::String,
+                Type::Int
+            )]
+        );
+    }
+
+    #[test]
+    fn type_annotations() {
+        check("var x: int = 5; print x + 1; x = 6;").unwrap();
+        check("var f: float; const s: bool = true; var p: Point = 1;").unwrap();
+        check("fn f(x: bool) { print not x; }").unwrap();
+        let errors = check("var y: bool = 3;").unwrap_err();
+        assert_eq!(
+            errors,
+            vec![TypeError::Mismatch {
+                expected: Type::Bool,
+                found: Type::Int
+            }]
+        );
+        let errors = check("var x: int; x = 1.5; fn f(n: int) { print not n; }").unwrap_err();
+        assert_eq!(
+            errors,
+            vec![
+                TypeError::Mismatch {
+                    expected: Type::Int,
+                    found: Type::Float
+                },
+                TypeError::UnsupportedOperand(UnaryOperator::Not, Type::Int),
+            ]
+        );
+    }
+}
@@ -0,0 +1,92 @@
+use pest::error::LineColLocation;
+use thiserror::Error;
+
+use crate::{
+    compiler::CompilerError,
+    parser::{self, ParserError},
+    vm::RuntimeError,
+};
+
+/// Error of any stage of running source code, from parsing it to executing
+/// the compiled code.
+#[derive(Error, Debug)]
+pub enum AlloyError {
+    #[error("parse error: {}", .0.kind())]
+    Parser(#[from] ParserError),
+    #[error("compile error: {0}")]
+    Compiler(#[from] CompilerError),
+    #[error("runtime error: {0}")]
+    Runtime(#[from] RuntimeError),
+}
+
+impl AlloyError {
+    /// Line and column in `source` where the error starts, compiler errors
+    /// and runtime errors of instructions without a recorded span have none.
+    pub fn line_col(&self, source: &str) -> Option<(usize, usize)> {
+        match self {
+            AlloyError::Parser(error) => match *error.location() {
+                LineColLocation::Pos(start) | LineColLocation::Span(start, _) => Some(start),
+            },
+            AlloyError::Compiler(_) => None,
+            AlloyError::Runtime(error) => error
+                .span()
+                .and_then(|(start, _)| parser::line_col(source, start)),
+        }
+    }
+
+    /// Render the error prefixed with where it starts in `source`, if known.
+    pub fn report(&self, source: &str) -> String {
+        match self.line_col(source) {
+            Some((line, column)) => format!("{line}:{column}: {self}"),
+            None => self.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        compiler::{compile_program, CompilerError},
+        parser,
+        vm::VM,
+    };
+
+    use super::AlloyError;
+
+    fn run(source: &str) -> Result<(), AlloyError> {
+        let statements = parser::parse(source)?;
+        let code_block = compile_program(&statements)?;
+        let mut output = Vec::new();
+        VM::new_with_output(&mut output).run(&code_block)?;
+        Ok(())
+    }
+
+    #[test]
+    fn report() {
+        let source = "print 1;\nvar = 1;";
+        let error = run(source).unwrap_err();
+        assert!(matches!(error, AlloyError::Parser(_)));
+        assert_eq!(error.line_col(source), Some((2, 5)));
+        assert!(error
+            .report(source)
+            .starts_with("2:5: parse error: unexpected `=`"));
+
+        let source = "print x;";
+        let error = run(source).unwrap_err();
+        assert!(matches!(
+            error,
+            AlloyError::Compiler(CompilerError::UndefinedIdentifer(_))
+        ));
+        assert_eq!(
+            error.report(source),
+            format!(
+                "compile error: {}",
+                CompilerError::UndefinedIdentifer("x".to_string())
+            )
+        );
+
+        let source = "var x = 1;\nprint x / 0;";
+        let error = run(source).unwrap_err();
+        assert_eq!(error.report(source), "2:9: runtime error: division by zero");
+    }
+}
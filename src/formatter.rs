@@ -0,0 +1,88 @@
+use alloc::string::{String, ToString};
+
+use crate::parser::{self, ParserError};
+
+/// Parse `source` and write it back with canonical formatting. Each statement
+/// is written on its own line, blocks are indented by four spaces, operators
+/// are surrounded by single spaces and `else` is kept on the same line as the
+/// closing brace of the preceding block.
+///
+/// # Examples
+///
+/// ```
+/// use alloy::formatter::format;
+///
+/// let formatted = format("if x<5{print x*2;} else {print x;}").unwrap();
+/// assert_eq!(formatted, "if x < 5 {\n    print x * 2;\n} else {\n    print x;\n}\n");
+/// ```
+///
+/// # Errors
+///
+/// This function will return an error if `source` can't be parsed.
+pub fn format(source: &str) -> Result<String, ParserError> {
+    let statements = parser::parse(source)?;
+    let mut formatted = String::new();
+    for statement in &statements {
+        formatted.push_str(&statement.to_string());
+        formatted.push('\n');
+    }
+    Ok(formatted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::format;
+
+    fn assert_format(input: &str, expected: &str) {
+        let formatted = format(input).unwrap();
+        assert_eq!(formatted, expected);
+        assert_eq!(format(&formatted).unwrap(), formatted);
+    }
+
+    #[test]
+    fn test_format_simple_statements() {
+        assert_format("print   1+2 ;", "print 1 + 2;\n");
+        assert_format("var x; x=5;", "var x;\nx = 5;\n");
+        assert_format("const x=1.;", "const x = 1.0;\n");
+        assert_format("1*(2+3);", "1 * (2 + 3);\n");
+        assert_format("(1*2)+3;", "1 * 2 + 3;\n");
+        assert_format("1-(2-3);", "1 - (2 - 3);\n");
+        assert_format("2**3**4;", "2 ** 3 ** 4;\n");
+        assert_format("-(1+2);", "-(1 + 2);\n");
+        assert_format("not true and false;", "not true and false;\n");
+        assert_format("(not true) and false;", "(not true) and false;\n");
+    }
+
+    #[test]
+    fn test_format_blocks() {
+        assert_format("{}", "{}\n");
+        assert_format(
+            "{print 1;{print 2;}}",
+            "{\n    print 1;\n    {\n        print 2;\n    }\n}\n",
+        );
+        assert_format(
+            "while x<=10{x=x+1;if x==5{print x;}}",
+            "while x <= 10 {\n    x = x + 1;\n    if x == 5 {\n        print x;\n    }\n}\n",
+        );
+        assert_format(
+            "for i in 10{continue;}",
+            "for i in 10 {\n    continue;\n}\n",
+        );
+    }
+
+    #[test]
+    fn test_format_if_else() {
+        assert_format(
+            "if a{print 1;}\nelse if b{print 2;}\nelse {print 3;}",
+            "if a {\n    print 1;\n} else if b {\n    print 2;\n} else {\n    print 3;\n}\n",
+        );
+    }
+
+    #[test]
+    fn test_format_functions() {
+        assert_format(
+            "fn add(x,y){return x+y;} fn nothing(){return;}",
+            "fn add(x, y) {\n    return x + y;\n}\nfn nothing() {\n    return;\n}\n",
+        );
+    }
+}
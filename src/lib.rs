@@ -1,14 +1,19 @@
-#[deny(missing_debug_implementations)]
+#![cfg_attr(not(feature = "std"), no_std)]
+#![deny(missing_debug_implementations)]
+extern crate alloc;
 extern crate pest;
 #[macro_use]
 extern crate pest_derive;
 #[macro_use]
-extern crate lazy_static;
-extern crate structopt;
-#[macro_use]
 extern crate phf;
 
 pub mod ast;
+pub mod collections;
 pub mod compiler;
+#[cfg(feature = "std")]
+pub mod error;
+pub mod formatter;
+mod math;
 pub mod object;
 pub mod parser;
+pub mod vm;
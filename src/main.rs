@@ -1,9 +1,12 @@
 use alloy::{
-    ast::statement::Statement,
     compiler::{Compile, Compiler},
+    error::AlloyError,
     parser,
+    vm::VM,
 };
 
+use std::time::{Duration, Instant};
+
 use rustyline::error::ReadlineError;
 use structopt::StructOpt;
 
@@ -13,34 +16,77 @@ struct Alloy {
     /// Verbose mode
     #[structopt(short, long)]
     verbose: bool,
+    /// Print time spent parsing, compiling and running each input to stderr
+    #[structopt(long)]
+    time: bool,
+    /// Type check each input and reject implicit coercions between types
+    #[structopt(long)]
+    strict: bool,
+    /// Declare variables on their first assignment instead of rejecting
+    /// assignments to undeclared identifiers
+    #[structopt(long)]
+    implicit_var: bool,
+}
+
+/// Wall-clock time spent in each stage of evaluating an input.
+#[derive(Debug, Default)]
+struct Timings {
+    parse: Duration,
+    compile: Duration,
+    run: Duration,
+}
+
+/// Call `f` adding the time it took to `elapsed`.
+fn timed<T>(elapsed: &mut Duration, f: impl FnOnce() -> T) -> T {
+    let start = Instant::now();
+    let result = f();
+    *elapsed += start.elapsed();
+    result
 }
 
 impl Alloy {
-    pub fn consume(&self, compiler: &mut Compiler, line: &str) {
+    pub fn consume(&self, compiler: &mut Compiler, vm: &mut VM, line: &str) {
         if line.is_empty() {
             return;
         }
-        match parser::parse(line) {
-            Ok(statements) => {
-                self.compile(compiler, statements);
-            }
-            Err(err) => eprintln!("{err:?}"),
+        let mut timings = Timings::default();
+        if let Err(error) = self.evaluate(compiler, vm, line, &mut timings) {
+            eprintln!("{}", error.report(line));
+            compiler.reset();
+        }
+        if self.time {
+            eprintln!(
+                "parse: {:?}, compile: {:?}, run: {:?}",
+                timings.parse, timings.compile, timings.run
+            );
         }
     }
 
-    pub fn compile(&self, compiler: &mut Compiler, statements: Vec<Statement>) {
+    /// Parse, compile and run `source`, echoing the value of a trailing
+    /// expression.
+    fn evaluate(
+        &self,
+        compiler: &mut Compiler,
+        vm: &mut VM,
+        source: &str,
+        timings: &mut Timings,
+    ) -> Result<(), AlloyError> {
+        let statements = timed(&mut timings.parse, || parser::parse_repl(source))?;
         for statement in statements {
             if self.verbose {
                 println!("{:?}", statement);
             }
-            if let Err(error) = statement.compile(compiler) {
-                eprintln!("{error}");
-                return;
-            }
+            timed(&mut timings.compile, || statement.compile(compiler))?;
+        }
+        let (code_block, debug_symbols) = timed(&mut timings.compile, || compiler.finish())?;
+        if self.verbose {
+            let dis = code_block.disassemble(&debug_symbols);
+            println!("{dis}");
+        }
+        if let Some(value) = timed(&mut timings.run, || vm.run(&code_block))? {
+            println!("{value}");
         }
-        let (code_block, debug_symbols) = compiler.finish();
-        let dis = code_block.disassemble(&debug_symbols);
-        println!("{dis}");
+        Ok(())
     }
 }
 
@@ -48,14 +94,17 @@ fn main() {
     let alloy = Alloy::from_args();
 
     let mut editor = rustyline::Editor::<()>::new();
-    let mut compiler = Compiler::new();
+    let mut compiler = Compiler::new_repl();
+    compiler.set_strict(alloy.strict);
+    compiler.set_implicit_var(alloy.implicit_var);
+    let mut vm = VM::new();
 
     println!("Alloylang REPL");
     loop {
         let readline = editor.readline(">>> ");
         match readline {
             Ok(line) if line == "exit" => break,
-            Ok(line) => alloy.consume(&mut compiler, line.as_str()),
+            Ok(line) => alloy.consume(&mut compiler, &mut vm, line.as_str()),
             Err(ReadlineError::Eof) => break,
             Err(ReadlineError::Interrupted) => break,
             Err(err) => eprintln!("Unexpected error encountered {err}."),
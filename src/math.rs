@@ -0,0 +1,30 @@
+//! Float functions of `std`, or of `libm` when building without it.
+
+macro_rules! float_functions {
+    ($($name:ident => $libm:ident($($arg:ident),*);)*) => {
+        $(
+            #[cfg(feature = "std")]
+            pub fn $name(x: f64, $($arg: f64),*) -> f64 {
+                x.$name($($arg),*)
+            }
+
+            #[cfg(not(feature = "std"))]
+            pub fn $name(x: f64, $($arg: f64),*) -> f64 {
+                libm::$libm(x, $($arg),*)
+            }
+        )*
+    };
+}
+
+float_functions! {
+    trunc => trunc();
+    floor => floor();
+    ceil => ceil();
+    round => round();
+    sqrt => sqrt();
+    powf => pow(y);
+}
+
+pub fn fract(x: f64) -> f64 {
+    x - trunc(x)
+}
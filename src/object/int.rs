@@ -1,5 +1,6 @@
 use super::{AlloyObj, AlloyType};
 
+#[derive(Debug)]
 #[repr(C)]
 pub struct AlloyInt {
     ty: AlloyType,
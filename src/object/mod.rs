@@ -1,4 +1,5 @@
-use std::{mem, ptr::NonNull};
+use alloc::boxed::Box;
+use core::{mem, ptr::NonNull};
 
 pub use crate::object::{boolean::AlloyBool, float::AlloyFloat, int::AlloyInt};
 
@@ -6,7 +7,7 @@ mod boolean;
 mod float;
 mod int;
 
-#[derive(Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum AlloyType {
     Int,
     Float,
@@ -30,19 +31,25 @@ where
     unsafe { NonNull::new_unchecked(obj_ptr as *mut AlloyType) }
 }
 
+/// Deallocate an object created by `create`.
+///
+/// # Safety
+///
+/// `obj_ptr` must have been returned by `create` and must not be used after
+/// this call.
 pub unsafe fn destroy(obj_ptr: AlloyObjPtr) {
     match obj_ptr.as_ref() {
         AlloyType::Int => {
             let int_ptr = obj_ptr.as_ptr() as *mut AlloyInt;
-            Box::from_raw(int_ptr);
+            drop(Box::from_raw(int_ptr));
         }
         AlloyType::Float => {
             let float_ptr = obj_ptr.as_ptr() as *mut AlloyFloat;
-            Box::from_raw(float_ptr);
+            drop(Box::from_raw(float_ptr));
         }
         AlloyType::Bool => {
             let bool_ptr = obj_ptr.as_ptr() as *mut AlloyBool;
-            Box::from_raw(bool_ptr);
+            drop(Box::from_raw(bool_ptr));
         }
     }
 }
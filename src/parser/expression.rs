@@ -1,10 +1,13 @@
-use std::fmt;
+use alloc::{boxed::Box, string::String, vec::Vec};
+use core::fmt;
 
 use nom::{
     branch::alt,
-    character::complete::multispace0,
-    combinator::{map, opt, peek},
+    character::complete::{char, multispace0},
+    combinator::{cut, map, opt, peek},
     error::context,
+    multi::separated_list0,
+    sequence::{delimited, pair, preceded, terminated},
 };
 
 use crate::ast::value::Value;
@@ -14,7 +17,7 @@ use super::{
     literal::parse_value,
     map_spanned,
     operator::{parse_operator, parse_unary_operator, Operator},
-    Input, Spanned, SpannedResult,
+    Input, ParserResult, Spanned, SpannedResult,
 };
 
 #[derive(Debug, Clone, PartialEq)]
@@ -30,6 +33,14 @@ pub enum Expr {
         op: Spanned<Operator>,
         operand: Box<Spanned<Expr>>,
     },
+    Index {
+        target: Box<Spanned<Expr>>,
+        index: Box<Spanned<Expr>>,
+    },
+    Call {
+        callee: Box<Spanned<Expr>>,
+        args: Vec<Spanned<Expr>>,
+    },
 }
 
 impl fmt::Display for Expr {
@@ -39,18 +50,27 @@ impl fmt::Display for Expr {
             Expr::Value(value) => write!(f, "{value}"),
             Expr::Binary { op, lhs, rhs } => write!(f, "({lhs} {op} {rhs})"),
             Expr::Unary { op, operand } => write!(f, "({op} {operand})"),
+            Expr::Index { target, index } => write!(f, "{target}[{index}]"),
+            Expr::Call { callee, args } => {
+                write!(f, "{callee}(")?;
+                for (i, arg) in args.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{arg}")?;
+                }
+                write!(f, ")")
+            }
         }
     }
 }
 
 fn parse_identifer_atom(input: Input<'_>) -> SpannedResult<'_, Expr> {
-    map(parse_identifier, |a| {
-        map_spanned(a, |v| Expr::Identifier(v))
-    })(input)
+    map(parse_identifier, |a| map_spanned(a, Expr::Identifier))(input)
 }
 
 fn parse_value_atom(input: Input<'_>) -> SpannedResult<'_, Expr> {
-    map(parse_value, |a| map_spanned(a, |v| Expr::Value(v)))(input)
+    map(parse_value, |a| map_spanned(a, Expr::Value))(input)
 }
 
 fn parse_atom(input: Input<'_>) -> SpannedResult<'_, Expr> {
@@ -75,6 +95,29 @@ fn parse_prefix_expression(input: Input<'_>) -> SpannedResult<'_, Expr> {
     Ok((input, spanned))
 }
 
+/// Postfix indexing and calls bind tighter than every prefix and infix
+/// operator, so `-a[0]` is `-(a[0])`.
+const POSTFIX_BP: u8 = 13;
+
+fn parse_index(input: Input<'_>) -> SpannedResult<'_, Expr> {
+    // Once `[` is consumed the input can only be an index
+    preceded(
+        pair(char('['), multispace0),
+        cut(terminated(parse_expression, pair(multispace0, char(']')))),
+    )(input)
+}
+
+fn parse_call_args(input: Input<'_>) -> ParserResult<'_, Vec<Spanned<Expr>>> {
+    let separator = delimited(multispace0, char(','), multispace0);
+    preceded(
+        pair(char('('), multispace0),
+        cut(terminated(
+            separated_list0(separator, parse_expression),
+            pair(multispace0, char(')')),
+        )),
+    )(input)
+}
+
 pub fn parse_expression(input: Input<'_>) -> SpannedResult<'_, Expr> {
     parse_expression_bp(input, 0)
 }
@@ -85,6 +128,36 @@ fn parse_expression_bp(input: Input<'_>, min_bp: u8) -> SpannedResult<'_, Expr>
         let (next_input, _whitespace) = multispace0(input)?;
         input = next_input;
 
+        // Postfix operations are applied left to right, so `a[0][1]` indexes `a[0]`
+        if POSTFIX_BP >= min_bp {
+            let (next_input, index) = opt(parse_index)(input)?;
+            input = next_input;
+            if let Some(index) = index {
+                expr = Spanned {
+                    start: expr.start,
+                    end: input.position,
+                    ast: Expr::Index {
+                        target: Box::new(expr),
+                        index: Box::new(index),
+                    },
+                };
+                continue;
+            }
+            let (next_input, args) = opt(parse_call_args)(input)?;
+            input = next_input;
+            if let Some(args) = args {
+                expr = Spanned {
+                    start: expr.start,
+                    end: input.position,
+                    ast: Expr::Call {
+                        callee: Box::new(expr),
+                        args,
+                    },
+                };
+                continue;
+            }
+        }
+
         // Use `peek` to avoid consuming if binding power of operator is lower than `min_bp`.
         let op = match peek(opt(parse_operator))(input) {
             Ok((next_input, Some(op))) => {
@@ -203,6 +276,67 @@ mod tests {
         assert_expr!("not false and true", "((not false) and true)");
     }
 
+    #[test]
+    fn test_not_precedence() {
+        assert_expr!("not a == b", "(not (a == b))");
+        assert_expr!("not a != b", "(not (a != b))");
+        assert_expr!("not a < b", "(not (a < b))");
+        assert_expr!("not a + b", "(not (a + b))");
+        assert_expr!("not a * b", "(not (a * b))");
+        assert_expr!("not a and b", "((not a) and b)");
+        assert_expr!("not a or b", "((not a) or b)");
+        assert_expr!("not a xor b", "((not a) xor b)");
+        assert_expr!("a and not b", "(a and (not b))");
+        assert_expr!("a == not b", "(a == (not b))");
+        assert_expr!("not not a", "(not (not a))");
+    }
+
+    #[test]
+    fn test_sign_precedence() {
+        assert_expr!("-a * b", "((- a) * b)");
+        assert_expr!("-a + b", "((- a) + b)");
+        assert_expr!("-a ** b", "(- (a ** b))");
+        assert_expr!("a * -b", "(a * (- b))");
+    }
+
+    #[test]
+    fn test_postfix_expressions() {
+        assert_expr!("a[0]", "a[0]");
+        assert_expr!("a[0][1]", "a[0][1]");
+        assert_expr!("f()", "f()");
+        assert_expr!("f()[2]", "f()[2]");
+        assert_expr!("f(1, a[0])(b)", "f(1, a[0])(b)");
+        assert_expr!("a [ i + 1 ] [j]", "a[(i + 1)][j]");
+        assert_expr!("-a[0] * b[1]", "((- a[0]) * b[1])");
+        assert_expr!("a[0] ** 2", "(a[0] ** 2)");
+    }
+
+    #[test]
+    fn test_postfix_nesting() {
+        let (_, expr) = parse_expression("a[0][1]".into()).unwrap();
+        let Expr::Index { target, index } = expr.ast else {
+            panic!("expected index, found {expr}");
+        };
+        assert_eq!(index.ast, Expr::Value(1.into()));
+        assert!(matches!(target.ast, Expr::Index { .. }));
+        assert_eq!((target.start, target.end), (0, 4));
+
+        let (_, expr) = parse_expression("f()[2]".into()).unwrap();
+        let Expr::Index { target, .. } = expr.ast else {
+            panic!("expected index, found {expr}");
+        };
+        assert!(matches!(target.ast, Expr::Call { ref args, .. } if args.is_empty()));
+    }
+
+    #[test]
+    fn test_invalid_postfix_expressions() {
+        parse_expression("a[".into()).unwrap_err();
+        parse_expression("a[]".into()).unwrap_err();
+        parse_expression("a[0".into()).unwrap_err();
+        parse_expression("f(1,)".into()).unwrap_err();
+        parse_expression("f(1".into()).unwrap_err();
+    }
+
     #[test]
     fn test_associativity_of_exponent() {
         assert_expr!("1 ** 2 ** 3", "((1 ** 2) ** 3)");
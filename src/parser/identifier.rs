@@ -1,3 +1,5 @@
+use alloc::{format, string::String};
+
 use nom::{
     bytes::complete::{take_while, take_while1},
     combinator::not,
@@ -1,4 +1,4 @@
-use std::{
+use core::{
     fmt,
     ops::RangeFrom,
     str::{CharIndices, Chars},
@@ -43,7 +43,7 @@ impl<'a> InputTake for Input<'a> {
         };
         let suffix = Self {
             input: suffix,
-            position: count,
+            position: self.position + count,
         };
         (suffix, prefix)
     }
@@ -130,12 +130,12 @@ impl<'a> Input<'a> {
     }
 }
 
-impl<'a> Into<Spanned<&'a str>> for Input<'a> {
-    fn into(self) -> Spanned<&'a str> {
+impl<'a> From<Input<'a>> for Spanned<&'a str> {
+    fn from(input: Input<'a>) -> Self {
         Spanned {
-            ast: self.input,
-            start: self.position,
-            end: self.position + self.input.len(),
+            ast: input.input,
+            start: input.position,
+            end: input.position + input.input.len(),
         }
     }
 }
@@ -1,3 +1,5 @@
+use core::fmt;
+
 use nom::{branch::alt, bytes::complete::tag, error::context};
 
 use super::{Input, ParserResult};
@@ -6,8 +8,10 @@ use super::{Input, ParserResult};
 pub enum Keyword {
     If,
     Else,
+    Elif,
     Print,
     While,
+    Do,
     For,
     Return,
     Var,
@@ -22,11 +26,38 @@ pub enum Keyword {
     Fn,
 }
 
+impl fmt::Display for Keyword {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Keyword::If => write!(f, "if"),
+            Keyword::Else => write!(f, "else"),
+            Keyword::Elif => write!(f, "elif"),
+            Keyword::Print => write!(f, "print"),
+            Keyword::While => write!(f, "while"),
+            Keyword::Do => write!(f, "do"),
+            Keyword::For => write!(f, "for"),
+            Keyword::Return => write!(f, "return"),
+            Keyword::Var => write!(f, "var"),
+            Keyword::Const => write!(f, "const"),
+            Keyword::Continue => write!(f, "continue"),
+            Keyword::Break => write!(f, "break"),
+            Keyword::In => write!(f, "in"),
+            Keyword::And => write!(f, "and"),
+            Keyword::Or => write!(f, "or"),
+            Keyword::Not => write!(f, "not"),
+            Keyword::Xor => write!(f, "xor"),
+            Keyword::Fn => write!(f, "fn"),
+        }
+    }
+}
+
 pub static KEYWORDS: phf::Map<&'static str, Keyword> = phf_map! {
     "if" => Keyword::If,
     "else" => Keyword::Else,
+    "elif" => Keyword::Elif,
     "print" => Keyword::Print,
     "while" => Keyword::While,
+    "do" => Keyword::Do,
     "for" => Keyword::For,
     "return" => Keyword::Return,
     "var" => Keyword::Var,
@@ -53,6 +84,12 @@ pub fn parse_else(input: Input<'_>) -> ParserResult<'_, Keyword> {
     Ok((input, *keyword))
 }
 
+pub fn parse_elif(input: Input<'_>) -> ParserResult<'_, Keyword> {
+    let (input, word) = tag("elif")(input)?;
+    let keyword = KEYWORDS.get(word.input).unwrap();
+    Ok((input, *keyword))
+}
+
 pub fn parse_print(input: Input<'_>) -> ParserResult<'_, Keyword> {
     let (input, word) = tag("print")(input)?;
     let keyword = KEYWORDS.get(word.input).unwrap();
@@ -65,6 +102,12 @@ pub fn parse_while(input: Input<'_>) -> ParserResult<'_, Keyword> {
     Ok((input, *keyword))
 }
 
+pub fn parse_do(input: Input<'_>) -> ParserResult<'_, Keyword> {
+    let (input, word) = tag("do")(input)?;
+    let keyword = KEYWORDS.get(word.input).unwrap();
+    Ok((input, *keyword))
+}
+
 pub fn parse_for(input: Input<'_>) -> ParserResult<'_, Keyword> {
     let (input, word) = tag("for")(input)?;
     let keyword = KEYWORDS.get(word.input).unwrap();
@@ -142,8 +185,10 @@ pub fn parse_keyword(input: Input<'_>) -> ParserResult<'_, Keyword> {
         alt((
             parse_if,
             parse_else,
+            parse_elif,
             parse_print,
             parse_while,
+            parse_do,
             parse_for,
             parse_return,
             parse_var,
@@ -159,3 +204,39 @@ pub fn parse_keyword(input: Input<'_>) -> ParserResult<'_, Keyword> {
         )),
     )(input)
 }
+
+#[cfg(test)]
+mod tests {
+    use pest::Parser;
+
+    use crate::parser::{identifier::parse_identifier, AlloyParser, Input, Rule};
+
+    use super::{parse_keyword, KEYWORDS};
+
+    #[test]
+    fn test_keyword_parsing() {
+        for (s, keyword) in KEYWORDS.entries() {
+            let (input, parsed) = parse_keyword(Input::new(s)).unwrap();
+            assert_eq!(input, "");
+            assert_eq!(parsed, *keyword);
+        }
+    }
+
+    #[test]
+    fn test_keywords_are_reserved() {
+        // New keywords have to be added to both parsers, otherwise they're
+        // still valid identifiers
+        for s in KEYWORDS.keys() {
+            assert!(parse_identifier(Input::new(s)).is_err(), "{s}");
+            assert!(AlloyParser::parse(Rule::identifier, s).is_err(), "{s}");
+            assert!(AlloyParser::parse(Rule::keyword, s).is_ok(), "{s}");
+        }
+    }
+
+    #[test]
+    fn test_keyword_display() {
+        for (s, keyword) in KEYWORDS.entries() {
+            assert_eq!(keyword.to_string(), *s);
+        }
+    }
+}
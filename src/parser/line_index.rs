@@ -0,0 +1,86 @@
+use alloc::vec::Vec;
+
+use pest::error::LineColLocation;
+
+/// Byte offsets of the start of each line of a source, used to convert byte
+/// offsets such as `Input::position` or the bounds of a `Spanned` into the
+/// line and column reported by errors.
+#[derive(Debug, Clone)]
+pub struct LineIndex<'a> {
+    source: &'a str,
+    line_starts: Vec<usize>,
+}
+
+impl<'a> LineIndex<'a> {
+    pub fn new(source: &'a str) -> Self {
+        let line_starts = core::iter::once(0)
+            .chain(source.match_indices('\n').map(|(idx, _)| idx + 1))
+            .collect();
+        Self {
+            source,
+            line_starts,
+        }
+    }
+
+    pub fn source(&self) -> &'a str {
+        self.source
+    }
+
+    /// Line and column of byte `offset` counting from 1, columns count
+    /// characters like pest does. `None` if `offset` is out of bounds or not
+    /// at a character boundary, the end of the source is in bounds.
+    pub fn locate(&self, offset: usize) -> Option<(usize, usize)> {
+        if !self.source.is_char_boundary(offset) {
+            return None;
+        }
+        // First line always starts at 0, so there is at least one start
+        let line = self.line_starts.partition_point(|&start| start <= offset);
+        let start = self.line_starts[line - 1];
+        let column = self.source[start..offset].chars().count() + 1;
+        Some((line, column))
+    }
+
+    /// Location of the source between byte offsets `start` and `end`.
+    pub fn span(&self, start: usize, end: usize) -> Option<LineColLocation> {
+        Some(LineColLocation::Span(
+            self.locate(start)?,
+            self.locate(end)?,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pest::{error::LineColLocation, Position};
+
+    use super::LineIndex;
+
+    #[test]
+    fn locate() {
+        let source = "var x = 1;\n\nprint x;\n  é + 1";
+        let index = LineIndex::new(source);
+        assert_eq!(index.locate(0), Some((1, 1)));
+        assert_eq!(index.locate(4), Some((1, 5)));
+        // Newlines end the line they're on
+        assert_eq!(index.locate(10), Some((1, 11)));
+        assert_eq!(index.locate(11), Some((2, 1)));
+        assert_eq!(index.locate(12), Some((3, 1)));
+        // Last line has no newline, columns count characters
+        let last = source.rfind('+').unwrap();
+        assert_eq!(index.locate(last), Some((4, 5)));
+        assert_eq!(index.locate(source.len()), Some((4, 8)));
+        assert_eq!(index.locate(source.len() + 1), None);
+        assert_eq!(index.locate(last - 2), None);
+        assert_eq!(
+            index.span(4, last),
+            Some(LineColLocation::Span((1, 5), (4, 5)))
+        );
+
+        for offset in (0..=source.len()).filter(|&offset| source.is_char_boundary(offset)) {
+            let position = Position::new(source, offset).unwrap();
+            assert_eq!(index.locate(offset), Some(position.line_col()));
+        }
+        assert_eq!(LineIndex::new("").locate(0), Some((1, 1)));
+        assert_eq!(LineIndex::new("\n").locate(1), Some((2, 1)));
+    }
+}
@@ -1,13 +1,15 @@
-use std::fmt;
+use alloc::string::String;
+use core::fmt;
 
 use nom::{
     branch::alt,
-    bytes::complete::{tag, take_while, take_while1},
-    character::complete::{char, none_of},
-    combinator::opt,
-    error::context,
+    bytes::complete::{tag, take_while},
+    character::complete::{char, none_of, one_of},
+    combinator::{map, opt},
+    error::{context, ErrorKind, ParseError, VerboseError},
     multi::many0,
-    sequence::{delimited, preceded, separated_pair},
+    sequence::{delimited, pair, preceded, tuple},
+    InputTake,
 };
 
 use crate::ast::value::Value;
@@ -105,7 +107,7 @@ pub fn parse_whitespace(input: Input<'_>) -> ParserResult<'_, Input<'_>> {
 /// assert_eq!(sign, Sign::Negative);
 /// ```
 ///
-pub fn parse_sign(input: Input<'_>) -> SpannedResult<Sign> {
+pub fn parse_sign(input: Input<'_>) -> SpannedResult<'_, Sign> {
     let start = input.position;
     let (next_input, sign) = context("sign", alt((tag("+"), tag("-"))))(input)?;
     let sign = if sign == "+" {
@@ -149,15 +151,17 @@ pub fn parse_sign(input: Input<'_>) -> SpannedResult<Sign> {
 ///
 /// This function will return an error if given input doesn't contain digits of given radix.
 pub fn parse_digits(input: Input<'_>, radix: u32) -> ParserResult<'_, i64> {
-    let (input, digits) = context(
-        "digits",
-        take_while1(|c: char| c.is_digit(radix) || c == '_'),
-    )(input)?;
-    if digits.input.starts_with('_') {
-        todo!("parse_digits: handle underscores");
+    let start = input.clone();
+    // Underscores separate digits so the first character must be a digit
+    let (input, digits) = context("digits", |input| separated_digits(input, radix))(input)?;
+    match i64::from_str_radix(&digits.input.replace('_', ""), radix) {
+        Ok(number) => Ok((input, number)),
+        // Digits are valid so the only failure is the number being too large
+        Err(_) => Err(nom::Err::Failure(VerboseError::from_error_kind(
+            start,
+            ErrorKind::TooLarge,
+        ))),
     }
-    let number = i64::from_str_radix(&digits.input.replace("_", ""), radix).unwrap();
-    Ok((input, number))
 }
 
 /// Parse decimal integer into `i64` and convert it to `Value::Integer`.
@@ -341,52 +345,60 @@ pub fn parse_integer(input: Input<'_>) -> SpannedResult<'_, Value> {
     )(input)
 }
 
-/// Simple wrapper around `parse_digits` with radix 10.
-fn parse_decimal_digits(input: Input<'_>) -> ParserResult<'_, i64> {
-    parse_digits(input, 10)
-}
-
-/// Parse digits of floating point number whole part (before decimal point)
-/// of the number is optional.
-fn parse_float_optional(input: Input<'_>) -> ParserResult<'_, (i64, i64)> {
-    let (input, (whole, fractional)) =
-        separated_pair(opt(parse_decimal_digits), tag("."), parse_decimal_digits)(input)?;
-    let whole = whole.unwrap_or_default();
-    Ok((input, (whole, fractional)))
-}
-
-/// Parse digits of floating point number fractional part (after decimal point)
-/// of the number is optional.
-fn parse_float_dot_optional(input: Input<'_>) -> ParserResult<'_, (i64, i64)> {
-    let (input, (whole, fractional)) =
-        separated_pair(parse_decimal_digits, tag("."), opt(parse_decimal_digits))(input)?;
-    let fractional = fractional.unwrap_or_default();
-    Ok((input, (whole, fractional)))
+/// Recognize decimal digits optionally separated by underscores.
+fn parse_decimal_digits(input: Input<'_>) -> ParserResult<'_, Input<'_>> {
+    separated_digits(input, 10)
+}
+
+/// Recognize digits of `radix` where single underscores may separate two
+/// digits, an underscore that isn't followed by a digit is left unparsed.
+fn separated_digits(input: Input<'_>, radix: u32) -> ParserResult<'_, Input<'_>> {
+    let mut end = 0;
+    let mut after_digit = false;
+    for (idx, c) in input.input.char_indices() {
+        if c.is_digit(radix) {
+            end = idx + c.len_utf8();
+            after_digit = true;
+        } else if c == '_' && after_digit {
+            after_digit = false;
+        } else {
+            break;
+        }
+    }
+    if end == 0 {
+        return Err(nom::Err::Error(VerboseError::from_error_kind(
+            input,
+            ErrorKind::Digit,
+        )));
+    }
+    Ok(input.take_split(end))
 }
 
-/// Scale down a floating point number by power 10 until it's between 0 and 1.
-fn fractional_part(mut float: f64) -> f64 {
-    while float > 1.0 {
-        float /= 10.0;
-    }
-    float
+/// Recognize digits of floating point number, either the whole part (before
+/// decimal point) or the fractional part (after decimal point) can be omitted.
+fn parse_mantissa(input: Input<'_>) -> ParserResult<'_, (Option<Input<'_>>, Option<Input<'_>>)> {
+    alt((
+        map(preceded(tag("."), parse_decimal_digits), |fractional| {
+            (None, Some(fractional))
+        }),
+        map(
+            tuple((parse_decimal_digits, tag("."), opt(parse_decimal_digits))),
+            |(whole, _, fractional)| (Some(whole), fractional),
+        ),
+    ))(input)
 }
 
-/// Return a signed floating point number from a whole and fractional part.
-fn float_from_parts(sign: Sign, whole: i64, fractional: i64) -> Value {
-    let float = whole as f64 + fractional_part(fractional as f64);
-    let float = match sign {
-        Sign::Positive => float,
-        Sign::Negative => -float,
-    };
-    Value::Float(float)
+/// Recognize exponent of a floating point number such as `e3` or `E-2`.
+fn parse_exponent(input: Input<'_>) -> ParserResult<'_, (Option<char>, Input<'_>)> {
+    preceded(one_of("eE"), pair(opt(one_of("+-")), parse_decimal_digits))(input)
 }
 
 /// Parse floating point number into `f64` and convert it to `Value::Float`.
 /// Floating point numbers can omit either whole part (before decimal point)
 /// or fractional part (after decimal point) but not both. If whole part of
 /// the number is omitted, it is assumed to be 0, same goes for fractional part.
-/// So, for example, `1.` is parsed as `1.0` and `.1` is parsed as `0.1`.
+/// So, for example, `1.` is parsed as `1.0` and `.1` is parsed as `0.1`. An
+/// exponent such as `e3` or `E-2` may follow the digits.
 ///
 /// # Examples
 ///
@@ -416,6 +428,14 @@ fn float_from_parts(sign: Sign, whole: i64, fractional: i64) -> Value {
 /// let (input, float) = parse_float("5_000.600_600".into()).unwrap();
 /// assert_eq!(input, "");
 /// assert_eq!(float, Value::Float(5000.6006));
+///
+/// let (input, float) = parse_float("3.05".into()).unwrap();
+/// assert_eq!(input, "");
+/// assert_eq!(float, Value::Float(3.05));
+///
+/// let (input, float) = parse_float("1.5e3".into()).unwrap();
+/// assert_eq!(input, "");
+/// assert_eq!(float, Value::Float(1500.0));
 /// ```
 ///
 /// # Errors
@@ -430,11 +450,23 @@ pub fn parse_float(input: Input<'_>) -> SpannedResult<'_, Value> {
         Sign::default()
     };
     let (input, _) = parse_whitespace(input)?;
-    let (input, (whole, fractional)) = context(
-        "float",
-        alt((parse_float_optional, parse_float_dot_optional)),
-    )(input)?;
-    let float = float_from_parts(sign, whole, fractional);
+    let (input, (whole, fractional)) = context("float", parse_mantissa)(input)?;
+    let (input, exponent) = context("float exponent", opt(parse_exponent))(input)?;
+
+    // Digits are validated above so the float can be read back by Rust
+    let mut literal = String::from(match sign {
+        Sign::Positive => "",
+        Sign::Negative => "-",
+    });
+    literal.push_str(whole.map_or("0", |whole| whole.input));
+    literal.push('.');
+    literal.push_str(fractional.map_or("0", |fractional| fractional.input));
+    if let Some((exponent_sign, exponent)) = exponent {
+        literal.push('e');
+        literal.extend(exponent_sign);
+        literal.push_str(exponent.input);
+    }
+    let float = Value::Float(literal.replace('_', "").parse().unwrap());
     let spanned = Spanned {
         ast: float,
         start,
@@ -708,7 +740,20 @@ mod tests {
         parser::literal::{parse_sign, Sign},
     };
 
-    use super::{parse_bool, parse_escaped};
+    use super::{parse_bool, parse_digits, parse_escaped};
+
+    #[test]
+    fn test_digit_separators() {
+        let (rest, number) = parse_digits("1_000".into(), 10).unwrap();
+        assert_eq!((rest.input, number), ("", 1000));
+        // Underscores that don't separate two digits are left unparsed
+        for (input, expected, left) in [("1__0", 1, "__0"), ("12_", 0x12, "_"), ("f_f_", 255, "_")]
+        {
+            let (rest, number) = parse_digits(input.into(), 16).unwrap();
+            assert_eq!((rest.input, number), (left, expected), "{input}");
+        }
+        assert!(parse_digits("_1".into(), 10).is_err());
+    }
 
     #[test]
     fn test_boolean() {
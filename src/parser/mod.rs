@@ -1,37 +1,138 @@
-use std::num::{ParseFloatError, ParseIntError};
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use core::{
+    fmt,
+    num::{ParseFloatError, ParseIntError},
+};
 
-use nom::{self, error::VerboseError, IResult};
+use nom::{
+    self,
+    error::{VerboseError, VerboseErrorKind},
+    IResult,
+};
 use pest::{
-    error::LineColLocation,
+    error::{ErrorVariant, InputLocation, LineColLocation},
     iterators::{Pair, Pairs},
     Parser, Span,
 };
 use thiserror::Error;
 
-use crate::ast::statement::Statement;
+use crate::ast::{
+    expression::Expression,
+    statement::{ExpressionStatement, Statement},
+};
 
-pub use self::{input::Input, spanned::Spanned};
+pub use self::{input::Input, line_index::LineIndex, spanned::Spanned};
 
 pub mod expression;
 pub mod identifier;
 mod input;
 pub mod keyword;
+mod line_index;
 pub mod literal;
 pub mod operator;
 mod spanned;
 
-#[derive(Parser)]
+#[derive(Parser, Debug)]
 #[grammar = "parser/alloy.pest"]
 pub struct AlloyParser;
 
+/// Broad class of a `ParserError`. Incomplete input may become valid when
+/// more input follows, such as a REPL line continuing on the next line, while
+/// syntax and semantic errors can't be fixed by appending input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorCategory {
+    Incomplete,
+    Syntax,
+    Semantic,
+}
+
+/// Syntactically valid literal that can't be represented as a value.
 #[derive(Error, Debug)]
-pub enum ParserErrorKind {
+pub enum LiteralError {
     #[error(transparent)]
-    ParseIntError(#[from] ParseIntError),
+    Integer(#[from] ParseIntError),
     #[error(transparent)]
-    ParseFloatError(#[from] ParseFloatError),
-    #[error("WIP")]
-    WIP,
+    Float(#[from] ParseFloatError),
+}
+
+#[derive(Error, Debug)]
+pub enum ParserErrorKind {
+    #[error("unexpected end of input, expected {}", format_rules(.expected))]
+    UnexpectedEof { expected: Vec<Rule> },
+    #[error("unexpected `{found}`, expected {}", format_rules(.expected))]
+    UnexpectedToken { expected: Vec<Rule>, found: String },
+    #[error(transparent)]
+    LiteralError(#[from] LiteralError),
+    #[error("unexpected `{0:?}`")]
+    UnexpectedRule(Rule),
+    #[error("duplicate parameter `{0}`")]
+    DuplicateParameter(String),
+    #[error("integer literal `{literal}` is too large")]
+    IntegerLiteralOverflow { literal: String },
+}
+
+impl From<ParseIntError> for ParserErrorKind {
+    fn from(error: ParseIntError) -> Self {
+        Self::LiteralError(error.into())
+    }
+}
+
+impl From<ParseFloatError> for ParserErrorKind {
+    fn from(error: ParseFloatError) -> Self {
+        Self::LiteralError(error.into())
+    }
+}
+
+impl ParserErrorKind {
+    pub fn category(&self) -> ErrorCategory {
+        match self {
+            ParserErrorKind::UnexpectedEof { .. } => ErrorCategory::Incomplete,
+            ParserErrorKind::UnexpectedToken { .. } | ParserErrorKind::UnexpectedRule(_) => {
+                ErrorCategory::Syntax
+            }
+            ParserErrorKind::LiteralError(_)
+            | ParserErrorKind::DuplicateParameter(_)
+            | ParserErrorKind::IntegerLiteralOverflow { .. } => ErrorCategory::Semantic,
+        }
+    }
+}
+
+fn format_rules(rules: &[Rule]) -> String {
+    if rules.is_empty() {
+        return "valid input".to_string();
+    }
+    rules
+        .iter()
+        .map(|rule| format!("`{rule:?}`"))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Error for input `rest` that couldn't be parsed, reporting the word or
+/// character it starts with or the end of input if only whitespace remains.
+fn unexpected_input(rest: &str, expected: Vec<Rule>) -> ParserErrorKind {
+    let rest = rest.trim_start();
+    match rest.chars().next() {
+        None => ParserErrorKind::UnexpectedEof { expected },
+        Some(first) if first.is_ascii_alphanumeric() || first == '_' => {
+            let end = rest
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(rest.len());
+            ParserErrorKind::UnexpectedToken {
+                expected,
+                found: rest[..end].to_string(),
+            }
+        }
+        Some(first) => ParserErrorKind::UnexpectedToken {
+            expected,
+            found: first.to_string(),
+        },
+    }
 }
 
 type ParserResult<'a, T> = IResult<Input<'a>, T, VerboseError<Input<'a>>>;
@@ -43,32 +144,129 @@ pub struct ParserError {
     location: LineColLocation,
 }
 
-impl From<pest::error::Error<Rule>> for ParserError {
-    fn from(e: pest::error::Error<Rule>) -> Self {
+impl ParserError {
+    /// Classify a grammar error, errors where only whitespace remains after
+    /// the error position are reported as unexpected end of input.
+    fn from_pest(error: pest::error::Error<Rule>, input: &str) -> Self {
+        Self::from_pest_at(error, &LineIndex::new(input), 0)
+    }
+
+    /// Classify an error of parsing the source of `index` from byte `offset`
+    /// on, positions of `error` are relative to `offset`.
+    fn from_pest_at(error: pest::error::Error<Rule>, index: &LineIndex, offset: usize) -> Self {
+        let input = index.source();
+        let (position, location) = match error.location {
+            InputLocation::Pos(position) => {
+                let position = offset + position;
+                (position, index.locate(position).map(LineColLocation::Pos))
+            }
+            InputLocation::Span((start, end)) => {
+                (offset + start, index.span(offset + start, offset + end))
+            }
+        };
+        let expected = match error.variant {
+            ErrorVariant::ParsingError { positives, .. } => positives,
+            ErrorVariant::CustomError { .. } => Vec::new(),
+        };
         Self {
-            kind: ParserErrorKind::WIP,
-            location: e.line_col,
+            kind: unexpected_input(&input[position..], expected),
+            location: location.unwrap_or(error.line_col),
         }
     }
-}
 
-impl ParserError {
+    /// Convert an error of the nom parser run on `source`, located the same
+    /// way as grammar errors. Integer literals that are too large are
+    /// reported as such, other errors as unexpected input.
+    pub fn from_nom(error: nom::Err<VerboseError<Input<'_>>>, source: &str) -> Self {
+        let index = LineIndex::new(source);
+        let (position, too_large) = match &error {
+            nom::Err::Error(error) | nom::Err::Failure(error) => match error.errors.first() {
+                Some((input, kind)) => (
+                    input.position,
+                    *kind == VerboseErrorKind::Nom(nom::error::ErrorKind::TooLarge),
+                ),
+                None => (source.len(), false),
+            },
+            nom::Err::Incomplete(_) => (source.len(), false),
+        };
+        // Point at the unexpected input rather than whitespace before it
+        let rest = source.get(position..).unwrap_or_default();
+        let position = source.len() - rest.trim_start().len();
+        let kind = match unexpected_input(rest, Vec::new()) {
+            ParserErrorKind::UnexpectedToken { found, .. } if too_large => {
+                ParserErrorKind::IntegerLiteralOverflow { literal: found }
+            }
+            kind => kind,
+        };
+        let location = index.locate(position).unwrap_or((1, 1));
+        Self {
+            kind,
+            location: LineColLocation::Pos(location),
+        }
+    }
+
+    pub fn category(&self) -> ErrorCategory {
+        self.kind.category()
+    }
+
+    pub fn kind(&self) -> &ParserErrorKind {
+        &self.kind
+    }
+
+    pub fn location(&self) -> &LineColLocation {
+        &self.location
+    }
+
     pub fn for_pair<T: Into<ParserErrorKind>>(pair: Pair<Rule>, kind: T) -> Self {
         Self::for_span(pair.as_span(), kind)
     }
 
     pub fn for_span<T: Into<ParserErrorKind>>(span: Span, kind: T) -> Self {
-        let start = span.start();
-        let end = span.end();
+        let start = span.start_pos().line_col();
+        let end = span.end_pos().line_col();
         Self {
             kind: kind.into(),
-            location: LineColLocation::Pos((start, end)),
+            location: LineColLocation::Span(start, end),
         }
     }
 }
 
+/// Errors are prefixed with the line and column where they start.
+impl fmt::Display for ParserError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (line, column) = match self.location {
+            LineColLocation::Pos(start) | LineColLocation::Span(start, _) => start,
+        };
+        write!(f, "{line}:{column}: {}", self.kind)
+    }
+}
+
+impl core::error::Error for ParserError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        Some(&self.kind)
+    }
+}
+
 pub type ParseResult<T> = Result<T, ParserError>;
 
+/// Return an error if `pair` wasn't produced by `rule`, this guards `Parse`
+/// implementations from being handed a pair of the wrong kind.
+pub fn expect_rule(pair: &Pair<Rule>, rule: Rule) -> ParseResult<()> {
+    if pair.as_rule() == rule {
+        Ok(())
+    } else {
+        Err(unexpected_rule(pair))
+    }
+}
+
+/// Create an error for a pair that a `Parse` implementation can't handle.
+pub fn unexpected_rule(pair: &Pair<Rule>) -> ParserError {
+    ParserError::for_span(
+        pair.as_span(),
+        ParserErrorKind::UnexpectedRule(pair.as_rule()),
+    )
+}
+
 pub trait Parse<'a>: Sized {
     fn parse(pair: Pair<'a, Rule>) -> Result<Self, ParserError>;
 }
@@ -76,14 +274,14 @@ pub trait Parse<'a>: Sized {
 pub fn parse_rule<'a, T: Parse<'a>>(rule: Rule, input: &'a str) -> ParseResult<T> {
     match AlloyParser::parse(rule, input) {
         Ok(mut pairs) => T::parse(pairs.next().unwrap()),
-        Err(e) => Err(e.into()),
+        Err(e) => Err(ParserError::from_pest(e, input)),
     }
 }
 
 pub fn parse_statement<'a, T: Parse<'a>>(input: &'a str) -> ParseResult<T> {
     match AlloyParser::parse(Rule::program, input) {
         Ok(mut pairs) => T::parse(pairs.next().unwrap()),
-        Err(e) => Err(e.into()),
+        Err(e) => Err(ParserError::from_pest(e, input)),
     }
 }
 
@@ -106,13 +304,127 @@ pub fn parse_pairs(pairs: Pairs<Rule>) -> Result<Vec<Statement>, ParserError> {
 pub fn parse(input: &str) -> Result<Vec<Statement>, ParserError> {
     match AlloyParser::parse(Rule::program, input) {
         Ok(pairs) => parse_pairs(pairs),
-        Err(e) => Err(ParserError {
-            kind: ParserErrorKind::WIP,
-            location: e.line_col,
-        }),
+        Err(e) => Err(ParserError::from_pest(e, input)),
     }
 }
 
+/// Parse `input` like `parse` but continue after errors, returning the
+/// statements that could be parsed together with every error. Input that
+/// isn't a statement is skipped up to the next `;`, `}` or block.
+///
+/// # Examples
+///
+/// ```
+/// use alloy::parser::parse_recovering;
+///
+/// let (statements, errors) = parse_recovering("var x = ; print 1; print ;");
+/// assert_eq!(statements.len(), 1);
+/// assert_eq!(errors.len(), 2);
+/// ```
+pub fn parse_recovering(input: &str) -> (Vec<Statement>, Vec<ParserError>) {
+    let pairs = match AlloyParser::parse(Rule::recovering_program, input) {
+        Ok(pairs) => pairs,
+        Err(e) => return (Vec::new(), vec![ParserError::from_pest(e, input)]),
+    };
+    let index = LineIndex::new(input);
+    let mut statements = Vec::new();
+    let mut errors = Vec::new();
+    // Skipped input before the end of the latest error belongs to it
+    let mut recovered = (0, 0);
+    for pair in pairs {
+        match pair.as_rule() {
+            Rule::EOI => break,
+            Rule::invalid_statement => {
+                if pair.as_span().start_pos().line_col() < recovered {
+                    continue;
+                }
+                let error = invalid_statement_error(pair, &index);
+                recovered = match error.location {
+                    LineColLocation::Pos(end) | LineColLocation::Span(_, end) => end,
+                };
+                errors.push(error);
+            }
+            _ => match Statement::parse(pair) {
+                Ok(statement) => statements.push(statement),
+                Err(error) => errors.push(error),
+            },
+        }
+    }
+    (statements, errors)
+}
+
+/// Error of parsing a program starting at skipped input. Parsing stops at
+/// the first statement that fails, so only the input from the skipped
+/// statement up to its error is parsed again.
+fn invalid_statement_error(pair: Pair<Rule>, index: &LineIndex) -> ParserError {
+    let start = pair.as_span().start();
+    match AlloyParser::parse(Rule::program, &index.source()[start..]) {
+        Err(e) => ParserError::from_pest_at(e, index, start),
+        Ok(_) => unexpected_rule(&pair),
+    }
+}
+
+/// Parse `input` as a single expression, the whole input must be consumed.
+///
+/// # Examples
+///
+/// ```
+/// use alloy::{ast::expression::Expression, parser::parse_expression_str};
+///
+/// let expression = parse_expression_str("1 + 2 * 3").unwrap();
+/// assert!(matches!(expression, Expression::Binary(_)));
+/// assert_eq!(expression.to_string(), "1 + 2 * 3");
+/// ```
+///
+/// # Errors
+///
+/// This function will return an error if `input` isn't exactly one expression.
+pub fn parse_expression_str(input: &str) -> ParseResult<Expression> {
+    parse_rule(Rule::expression_input, input)
+}
+
+/// Parse `input` as a single statement, the whole input must be consumed.
+///
+/// # Examples
+///
+/// ```
+/// use alloy::{ast::statement::Statement, parser::parse_statement_str};
+///
+/// let statement = parse_statement_str("print 1 + 2 * 3;").unwrap();
+/// assert!(matches!(statement, Statement::Print(_)));
+/// assert_eq!(statement.to_string(), "print 1 + 2 * 3;");
+/// ```
+///
+/// # Errors
+///
+/// This function will return an error if `input` isn't exactly one statement.
+pub fn parse_statement_str(input: &str) -> ParseResult<Statement> {
+    parse_rule(Rule::statement_input, input)
+}
+
+/// Parse a line of REPL input like `parse`, except that a single expression
+/// without the trailing `;` is accepted as an expression statement.
+///
+/// # Examples
+///
+/// ```
+/// use alloy::parser::{parse, parse_repl};
+///
+/// assert_eq!(parse_repl("1 + 1").unwrap()[0].to_string(), "1 + 1;");
+/// parse("1 + 1").unwrap_err();
+/// ```
+///
+/// # Errors
+///
+/// This function will return the error of parsing `input` as a program if
+/// it isn't a single expression either.
+pub fn parse_repl(input: &str) -> ParseResult<Vec<Statement>> {
+    parse(input).or_else(|error| match parse_expression_str(input) {
+        Ok(expression) => Ok(vec![ExpressionStatement::from(expression).into()]),
+        Err(_) => Err(error),
+    })
+}
+
 pub fn map_spanned<T, U, F>(Spanned { ast, start, end }: Spanned<T>, f: F) -> Spanned<U>
 where
     F: FnOnce(T) -> U,
@@ -123,3 +435,211 @@ where
         end,
     }
 }
+
+/// Line and column of byte `offset` in `input` counting from 1, `None` if
+/// `offset` is out of bounds or not at a character boundary.
+pub fn line_col(input: &str, offset: usize) -> Option<(usize, usize)> {
+    LineIndex::new(input).locate(offset)
+}
+
+#[cfg(test)]
+mod tests {
+    use pest::error::LineColLocation;
+
+    use super::{
+        expression, parse, parse_expression_str, parse_recovering, parse_repl, parse_statement_str,
+        ErrorCategory, ParserError, ParserErrorKind,
+    };
+
+    fn category(input: &str) -> ErrorCategory {
+        parse(input).unwrap_err().category()
+    }
+
+    #[test]
+    fn test_incomplete_input() {
+        for input in [
+            "print 1",
+            "if true {",
+            "var x = ",
+            "while x < 2 { print x; \n",
+        ] {
+            let error = parse(input).unwrap_err();
+            assert!(
+                matches!(error.kind(), ParserErrorKind::UnexpectedEof { .. }),
+                "{input}: {error:?}"
+            );
+            assert_eq!(error.category(), ErrorCategory::Incomplete);
+        }
+    }
+
+    #[test]
+    fn test_unexpected_token() {
+        let error = parse("print 1 2;").unwrap_err();
+        match error.kind() {
+            ParserErrorKind::UnexpectedToken { found, .. } => assert_eq!(found, "2"),
+            kind => panic!("unexpected {kind:?}"),
+        }
+        assert_eq!(error.location(), &LineColLocation::Pos((1, 9)));
+
+        let error = parse("var x = 1; const = 2;").unwrap_err();
+        match error.kind() {
+            ParserErrorKind::UnexpectedToken { found, .. } => assert_eq!(found, "="),
+            kind => panic!("unexpected {kind:?}"),
+        }
+        assert_eq!(category("print );"), ErrorCategory::Syntax);
+    }
+
+    #[test]
+    fn test_assignment_in_condition() {
+        // Assignment is a statement, so a condition can't assign by mistake
+        // for a comparison. If assignments become expressions conditions need
+        // a lint instead.
+        for (input, column) in [
+            ("if x = 5 {}", 6),
+            ("if x == 1 {} elif x = 5 {}", 21),
+            ("while x = 5 {}", 9),
+            ("print if x = 5 { 1 } else { 2 };", 12),
+            ("if a[0] = 5 {}", 9),
+        ] {
+            let error = parse(input).unwrap_err();
+            match error.kind() {
+                ParserErrorKind::UnexpectedToken { found, .. } => assert_eq!(found, "=", "{input}"),
+                kind => panic!("{input}: unexpected {kind:?}"),
+            }
+            assert_eq!(
+                error.location(),
+                &LineColLocation::Pos((1, column)),
+                "{input}"
+            );
+        }
+        parse("var x = 1; if x == 5 { x = 6; }").unwrap();
+    }
+
+    #[test]
+    fn test_nom_errors() {
+        let nom_error = |source: &str| {
+            let error = expression::parse_expression(source.into()).unwrap_err();
+            ParserError::from_nom(error, source)
+        };
+        let error = nom_error("a[\n  ");
+        assert!(matches!(
+            error.kind(),
+            ParserErrorKind::UnexpectedEof { .. }
+        ));
+        assert_eq!(error.location(), &LineColLocation::Pos((2, 3)));
+
+        let error = nom_error("x +\n\n 99999999999999999999");
+        assert!(matches!(
+            error.kind(),
+            ParserErrorKind::IntegerLiteralOverflow { literal } if literal == "99999999999999999999"
+        ));
+        assert_eq!(error.location(), &LineColLocation::Pos((3, 2)));
+
+        // Both parsers locate an unexpected token on the last line alike
+        let source = "1 +\n  ]";
+        let error = nom_error(source);
+        assert!(matches!(
+            error.kind(),
+            ParserErrorKind::UnexpectedToken { found, .. } if found == "]"
+        ));
+        assert_eq!(
+            error.location(),
+            parse(&format!("print {source};")).unwrap_err().location()
+        );
+    }
+
+    #[test]
+    fn test_semantic_errors() {
+        // Hexadecimal literals aren't promoted to big integers
+        let error = parse("print 0x1_0000_0000_0000_0000;").unwrap_err();
+        assert!(matches!(
+            error.kind(),
+            ParserErrorKind::IntegerLiteralOverflow { .. }
+        ));
+        assert_eq!(error.category(), ErrorCategory::Semantic);
+        assert_eq!(error.location(), &LineColLocation::Span((1, 7), (1, 30)));
+        assert_eq!(category("fn f(x, x) {}"), ErrorCategory::Semantic);
+    }
+
+    #[test]
+    fn test_parse_str_helpers() {
+        assert_eq!(
+            parse_expression_str(" (1 + 2) * x ").unwrap().to_string(),
+            "(1 + 2) * x"
+        );
+        parse_expression_str("1 + 2;").unwrap_err();
+        parse_expression_str("1 2").unwrap_err();
+        parse_expression_str("").unwrap_err();
+
+        assert_eq!(
+            parse_statement_str("var x = 1;").unwrap().to_string(),
+            "var x = 1;"
+        );
+        parse_statement_str("fn f() {}").unwrap();
+        parse_statement_str("print 1; print 2;").unwrap_err();
+        parse_statement_str("1 + 2").unwrap_err();
+    }
+
+    #[test]
+    fn test_empty_programs() {
+        for input in ["", "   \n  ", "\t\r\n", "// comment", "// one\n  // two\n"] {
+            assert!(parse(input).unwrap().is_empty(), "{input:?}");
+        }
+        assert_eq!(parse("print 1; // comment\r\nprint 2;").unwrap().len(), 2);
+        assert_eq!(parse("print 1 // comment\n;").unwrap().len(), 1);
+        // Division isn't mistaken for a comment
+        assert_eq!(parse_expression_str("4 / 2").unwrap().to_string(), "4 / 2");
+    }
+
+    #[test]
+    fn test_parse_repl() {
+        let statements = parse_repl(" x * (1 + 2) ").unwrap();
+        assert_eq!(statements.len(), 1);
+        assert_eq!(statements[0].to_string(), "x * (1 + 2);");
+        assert_eq!(parse_repl("var x = 1; x;").unwrap().len(), 2);
+
+        // Only a single expression may omit the `;`
+        let error = parse_repl("print 1").unwrap_err();
+        assert_eq!(error.category(), ErrorCategory::Incomplete);
+        parse_repl("var x = 1; x").unwrap_err();
+        parse_repl("1 2").unwrap_err();
+    }
+
+    fn error_lines(input: &str) -> Vec<usize> {
+        let (_, errors) = parse_recovering(input);
+        errors
+            .iter()
+            .map(|error| match error.location() {
+                LineColLocation::Pos((line, _)) | LineColLocation::Span((line, _), _) => *line,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_recovering() {
+        let input = "var x = 1;\nvar y = ;\nprint x;\nif x { print 1 }\nprint x + y;";
+        let (statements, errors) = parse_recovering(input);
+        let statements: Vec<_> = statements.iter().map(ToString::to_string).collect();
+        assert_eq!(statements, ["var x = 1;", "print x;", "print x + y;"]);
+        assert_eq!(errors.len(), 2);
+        assert_eq!(error_lines(input), [2, 4]);
+        assert!(matches!(
+            errors[0].kind(),
+            ParserErrorKind::UnexpectedToken { found, .. } if found == ";"
+        ));
+
+        // Valid programs parse the same as with `parse`
+        let (statements, errors) = parse_recovering("var x = 1; { x; } fn f() { }");
+        assert_eq!(statements.len(), 3);
+        assert!(errors.is_empty());
+        assert!(parse_recovering("").0.is_empty());
+
+        // Skipped input belonging to an error isn't reported again
+        assert_eq!(error_lines("print set{1} + ;\nprint 1;\n}"), [1, 3]);
+        assert_eq!(error_lines("print 1"), [1]);
+        // Errors of statements that parse are reported too
+        assert_eq!(error_lines("print 1 print;\nfn f(a, a) { }"), [1, 2]);
+        // Stray terminators and unterminated blocks are skipped on their own
+        assert_eq!(error_lines(";\n}\nprint 1;\n{ print"), [1, 2, 4]);
+    }
+}
@@ -1,4 +1,4 @@
-use std::fmt;
+use core::fmt;
 
 use nom::{branch::alt, bytes::complete::tag, combinator::map, error::context};
 
@@ -50,6 +50,11 @@ impl Operator {
         unsafe { self.infix_bp().unwrap_unchecked() }
     }
 
+    /// Prefix operator precedence used in Pratt parser. Like Python, `not` binds
+    /// looser than comparison and arithmetic but tighter than logical operators,
+    /// so `not a == b` is `not (a == b)` while `not a and b` is `(not a) and b`.
+    /// `+` and `-` bind tighter than multiplication but looser than `**`, so
+    /// `-a ** b` is `-(a ** b)`.
     #[inline]
     pub fn prefix_bp(&self) -> Option<((), u8)> {
         let bp = match self {
@@ -272,13 +277,19 @@ mod tests {
         }
     }
 
-    /// This segment can use `int_abs_diff` feature #89492
     /// Assert that infix binding power difference is 1 for every infix operator.
+    #[test]
+    fn test_operator_display() {
+        for (s, op) in OPERATORS.entries() {
+            assert_eq!(op.to_string(), *s);
+        }
+    }
+
     #[test]
     fn test_operator_precendence() {
         for op in OPERATORS.values() {
             if let Some((l_bp, r_bp)) = op.infix_bp() {
-                assert_eq!(i32::abs((l_bp - r_bp).into()), 1);
+                assert_eq!(l_bp.abs_diff(r_bp), 1);
             }
         }
     }
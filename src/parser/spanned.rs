@@ -1,4 +1,4 @@
-use std::{fmt, ops::Deref};
+use core::{fmt, ops::Deref};
 
 /// `Spanned<T>` is a wrapper around `T` that holds start and
 /// end positions of the AST node in the source code.
@@ -0,0 +1,72 @@
+use alloc::{
+    boxed::Box,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::{convert::TryInto, fmt};
+
+use crate::{
+    ast::value::Value,
+    collections::HashMap,
+    compiler::{CompilerError, CompilerResult},
+};
+
+use super::RuntimeResult;
+
+/// Rust function exposed to Alloy scripts by an embedder, arguments are
+/// passed in order they appear in the call.
+pub type HostFunction = Box<dyn Fn(&[Value]) -> RuntimeResult<Value>>;
+
+/// Registry of host functions keyed by name. The compiler resolves calls to
+/// registered names into `CallHost` instructions indexing the registry, so
+/// the VM executing the code must be given the same registry.
+#[derive(Default)]
+pub struct HostFunctions {
+    indices: HashMap<String, u16>,
+    functions: Vec<HostFunction>,
+}
+
+impl HostFunctions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register `function` as `name` and return its index, names can only
+    /// be registered once.
+    pub fn register<F>(&mut self, name: &str, function: F) -> CompilerResult<u16>
+    where
+        F: Fn(&[Value]) -> RuntimeResult<Value> + 'static,
+    {
+        if self.indices.contains_key(name) {
+            return Err(CompilerError::Redefinition(name.to_string()));
+        }
+        let index = self
+            .functions
+            .len()
+            .try_into()
+            .map_err(|_| CompilerError::FunctionLimitReached)?;
+        self.indices.insert(name.to_string(), index);
+        self.functions.push(Box::new(function));
+        Ok(index)
+    }
+
+    /// Index of the host function registered as `name`.
+    pub fn index(&self, name: &str) -> Option<u16> {
+        self.indices.get(name).copied()
+    }
+
+    /// Names of registered host functions and their indices.
+    pub fn indices(&self) -> &HashMap<String, u16> {
+        &self.indices
+    }
+
+    pub fn get(&self, index: u16) -> Option<&HostFunction> {
+        self.functions.get(usize::from(index))
+    }
+}
+
+impl fmt::Debug for HostFunctions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.indices.keys()).finish()
+    }
+}
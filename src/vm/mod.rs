@@ -0,0 +1,1674 @@
+#[cfg(feature = "std")]
+use alloc::vec::Vec;
+use alloc::{boxed::Box, string::String};
+#[cfg(feature = "std")]
+use core::{convert::TryFrom, fmt};
+#[cfg(feature = "std")]
+use std::io::{self, Write};
+
+use thiserror::Error;
+
+use crate::ast::value::ValueError;
+#[cfg(feature = "std")]
+use crate::{
+    ast::value::{HashableValue, IntOverflow, Value},
+    compiler::{
+        code_block::{CodeBlock, Program},
+        Instruction,
+    },
+};
+
+#[cfg(feature = "std")]
+use self::host::HostFunctions;
+
+pub mod host;
+pub mod native;
+
+pub type RuntimeResult<T> = Result<T, RuntimeError>;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum RuntimeError {
+    #[error("expected `{expected}` but found `{found}`")]
+    TypeError {
+        expected: &'static str,
+        found: &'static str,
+    },
+    #[error("expected {expected} arguments but found {found}")]
+    ArityMismatch { expected: usize, found: usize },
+    #[error("expected at least {minimum} arguments but found {found}")]
+    TooFewArguments { minimum: usize, found: usize },
+    #[error("cannot convert `{value}` to `{target}`")]
+    InvalidConversion { value: String, target: &'static str },
+    #[error("invalid format string `{0}`")]
+    InvalidFormat(String),
+    #[error("`{function}` isn't defined for `{value}`")]
+    DomainError {
+        function: &'static str,
+        value: String,
+    },
+    #[error(transparent)]
+    Value(#[from] ValueError),
+    #[error("stack underflow")]
+    StackUnderflow,
+    #[error("value `{0}` is out of bounds")]
+    InvalidValue(u16),
+    #[error("function `{0}` is out of bounds")]
+    InvalidFunction(u16),
+    #[error("host function `{0}` is out of bounds")]
+    InvalidHostFunction(u16),
+    #[error("function `{0}` isn't defined")]
+    UndefinedFunction(String),
+    #[error("tail call outside of a function")]
+    TailCallOutsideFunction,
+    /// Error of an instruction compiled from the source between byte offsets
+    /// `start` and `end`, such as the operator of a binary expression.
+    #[error("{error}")]
+    Spanned {
+        error: Box<RuntimeError>,
+        start: usize,
+        end: usize,
+    },
+    #[cfg(feature = "std")]
+    #[error("failed to write output: {0}")]
+    Output(io::ErrorKind),
+}
+
+impl RuntimeError {
+    /// Source span of the instruction that failed, if it was recorded.
+    pub fn span(&self) -> Option<(usize, usize)> {
+        match self {
+            Self::Spanned { start, end, .. } => Some((*start, *end)),
+            _ => None,
+        }
+    }
+
+    /// Error without its source span.
+    pub fn into_inner(self) -> Self {
+        match self {
+            Self::Spanned { error, .. } => *error,
+            error => error,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<io::Error> for RuntimeError {
+    fn from(error: io::Error) -> Self {
+        Self::Output(error.kind())
+    }
+}
+
+/// State of the caller saved by `Call` and restored by `Return`.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy)]
+struct CallFrame {
+    // Function the caller is executing, `None` for the top-level code block
+    function: Option<u16>,
+    return_pc: usize,
+    // Stack height once the arguments have been popped
+    base: usize,
+    // Start of the caller's slots in `locals`
+    locals: usize,
+}
+
+/// Stack based virtual machine executing compiled `CodeBlock`s. Symbols
+/// outlive a single run so consecutive code blocks from the same `Compiler`
+/// can share variables.
+#[cfg(feature = "std")]
+pub struct VM<'a> {
+    stack: Vec<Value>,
+    symbols: Vec<Value>,
+    locals: Vec<Value>,
+    // Start of the current frame's slots in `locals`
+    frame: usize,
+    frames: Vec<CallFrame>,
+    hosts: HostFunctions,
+    overflow: IntOverflow,
+    output: Box<dyn Write + 'a>,
+    trace: Option<Box<dyn Write + 'a>>,
+}
+
+#[cfg(feature = "std")]
+impl fmt::Debug for VM<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("VM")
+            .field("stack", &self.stack)
+            .field("symbols", &self.symbols)
+            .field("locals", &self.locals)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "std")]
+impl Default for VM<'static> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "std")]
+impl VM<'static> {
+    /// Create a VM that prints to stdout.
+    pub fn new() -> Self {
+        Self::with_writer(io::stdout())
+    }
+}
+
+#[cfg(feature = "std")]
+impl<'a> VM<'a> {
+    /// Create a VM that prints to `output` instead of stdout.
+    pub fn new_with_output(output: &'a mut dyn Write) -> Self {
+        Self::with_writer(output)
+    }
+
+    fn with_writer(output: impl Write + 'a) -> Self {
+        Self {
+            stack: Vec::new(),
+            symbols: Vec::new(),
+            locals: Vec::new(),
+            frame: 0,
+            frames: Vec::new(),
+            hosts: HostFunctions::new(),
+            overflow: IntOverflow::Error,
+            output: Box::new(output),
+            trace: None,
+        }
+    }
+
+    /// Execute `CallHost` instructions with functions of `hosts`, which must
+    /// be the registry given to the compiler.
+    pub fn with_host_functions(mut self, hosts: HostFunctions) -> Self {
+        self.hosts = hosts;
+        self
+    }
+
+    /// Treat integer overflow of `+`, `-` and `*` as `overflow` says instead
+    /// of failing.
+    pub fn with_int_overflow(mut self, overflow: IntOverflow) -> Self {
+        self.overflow = overflow;
+        self
+    }
+
+    /// Write a line with the pc, the instruction and the stack to `trace`
+    /// before executing each instruction.
+    pub fn with_trace(mut self, trace: &'a mut dyn Write) -> Self {
+        self.trace = Some(Box::new(trace));
+        self
+    }
+
+    fn trace_step(&mut self, pc: usize, instruction: &Instruction) -> RuntimeResult<()> {
+        if let Some(trace) = &mut self.trace {
+            let stack = self
+                .stack
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>();
+            let instruction = instruction.to_string();
+            writeln!(trace, "{pc:04} {instruction:<24} [{}]", stack.join(", "))?;
+        }
+        Ok(())
+    }
+
+    /// Execute `code` until its last instruction, output is flushed when
+    /// execution finishes. Returns the value left on the stack, if any, which
+    /// is the last expression's value for code compiled in REPL mode.
+    /// Executing `Return` in the top-level code block ends execution with the
+    /// returned value.
+    pub fn run(&mut self, code: &CodeBlock) -> RuntimeResult<Option<Value>> {
+        // Frames of a previous run that failed inside a function are stale
+        self.frames.clear();
+        self.frame = 0;
+        self.execute(code, None)
+    }
+
+    /// Call the function named `name` of `program` with `args` and return
+    /// its value. Globals are shared with previous runs, so the main code
+    /// block should be run first if the function uses any.
+    pub fn call_named(
+        &mut self,
+        program: &Program,
+        name: &str,
+        args: &[Value],
+    ) -> RuntimeResult<Value> {
+        let (idx, function) = program
+            .functions
+            .get(name)
+            .zip(program.function(name))
+            .ok_or_else(|| RuntimeError::UndefinedFunction(name.to_string()))?;
+        if args.len() != usize::from(function.arity) {
+            return Err(RuntimeError::ArityMismatch {
+                expected: function.arity.into(),
+                found: args.len(),
+            });
+        }
+        self.frames.clear();
+        self.frame = self.locals.len();
+        self.locals.extend_from_slice(args);
+        let value = self.execute(&program.main, Some(*idx))?;
+        Ok(value.unwrap_or_default())
+    }
+
+    /// Execute the top-level code block of `code` if `function` is `None`,
+    /// otherwise the function with that index whose arguments have already
+    /// been stored as locals of the current frame.
+    fn execute(
+        &mut self,
+        code: &CodeBlock,
+        mut function: Option<u16>,
+    ) -> RuntimeResult<Option<Value>> {
+        let mut current = match function {
+            Some(idx) => {
+                &code
+                    .functions
+                    .get(idx as usize)
+                    .ok_or(RuntimeError::InvalidFunction(idx))?
+                    .code
+            }
+            None => code,
+        };
+        let mut pc = 0;
+        if let Err(error) = self.dispatch(code, &mut current, &mut function, &mut pc) {
+            // `pc` has moved past the failing instruction, unless writing the
+            // trace of the first instruction failed
+            let span = pc.checked_sub(1).and_then(|pc| current.spans.get(&pc));
+            return Err(match span {
+                Some(&(start, end)) => RuntimeError::Spanned {
+                    error: Box::new(error),
+                    start,
+                    end,
+                },
+                None => error,
+            });
+        }
+        self.output.flush()?;
+        if let Some(trace) = &mut self.trace {
+            trace.flush()?;
+        }
+        Ok(self.stack.pop())
+    }
+
+    /// Execute instructions of `current` from `pc` until the end of the top
+    /// level code block or a `Return` from it. On error `current` and `pc`
+    /// are left just past the instruction that failed.
+    fn dispatch<'c>(
+        &mut self,
+        code: &'c CodeBlock,
+        current: &mut &'c CodeBlock,
+        function: &mut Option<u16>,
+        pc: &mut usize,
+    ) -> RuntimeResult<()> {
+        while let Some(instruction) = current.instructions.get(*pc) {
+            if self.trace.is_some() {
+                self.trace_step(*pc, instruction)?;
+            }
+            *pc += 1;
+            match *instruction {
+                Instruction::StoreSymbol(idx) => {
+                    let value = self.pop()?;
+                    store(&mut self.symbols, idx as usize, value);
+                }
+                Instruction::LoadSymbol(idx) => {
+                    // Variables declared without an initializer are `null`
+                    let value = self.symbols.get(idx as usize).cloned().unwrap_or_default();
+                    self.stack.push(value);
+                }
+                Instruction::StoreLocal(idx) => {
+                    let value = self.pop()?;
+                    store(&mut self.locals, self.frame + idx as usize, value);
+                }
+                Instruction::LoadLocal(idx) => {
+                    let value = self
+                        .locals
+                        .get(self.frame + idx as usize)
+                        .cloned()
+                        .unwrap_or_default();
+                    self.stack.push(value);
+                }
+                Instruction::LoadValue(idx) => match code.values.get(idx as usize) {
+                    Some(value) => self.stack.push(value.clone()),
+                    None => return Err(RuntimeError::InvalidValue(idx)),
+                },
+                Instruction::Pop => {
+                    self.pop()?;
+                }
+                Instruction::PopN(count) => {
+                    let len = self.stack.len();
+                    let count = count as usize;
+                    if len < count {
+                        return Err(RuntimeError::StackUnderflow);
+                    }
+                    self.stack.truncate(len - count);
+                }
+                Instruction::Dup => {
+                    let value = self.stack.last().ok_or(RuntimeError::StackUnderflow)?;
+                    self.stack.push(value.clone());
+                }
+                Instruction::Display => {
+                    let value = self.pop()?;
+                    writeln!(self.output, "{value}")?;
+                }
+                Instruction::Jump(target) => *pc = target as usize,
+                Instruction::JumpIfTrue(target) => {
+                    if self.pop()?.is_truthy() {
+                        *pc = target as usize;
+                    }
+                }
+                Instruction::JumpIfFalse(target) => {
+                    if !self.pop()?.is_truthy() {
+                        *pc = target as usize;
+                    }
+                }
+                Instruction::JumpUnlessLessThan(target) => {
+                    self.jump_unless(Value::less_than, target, pc)?
+                }
+                Instruction::JumpUnlessLessThanEqual(target) => {
+                    self.jump_unless(Value::less_than_equal, target, pc)?
+                }
+                Instruction::JumpUnlessGreaterThan(target) => {
+                    self.jump_unless(Value::greater_than, target, pc)?
+                }
+                Instruction::JumpUnlessGreaterThanEqual(target) => {
+                    self.jump_unless(Value::greater_than_equal, target, pc)?
+                }
+                Instruction::JumpUnlessEqual(target) => {
+                    self.jump_unless(|lhs, rhs| Ok(lhs.equal(rhs)), target, pc)?
+                }
+                Instruction::JumpUnlessNotEqual(target) => {
+                    self.jump_unless(|lhs, rhs| Ok(lhs.not_equal(rhs)), target, pc)?
+                }
+                Instruction::BinaryAdd => match self.overflow {
+                    IntOverflow::Error => self.binary(Value::add)?,
+                    IntOverflow::Promote => self.binary(Value::add_promote)?,
+                },
+                Instruction::BinarySubtract => match self.overflow {
+                    IntOverflow::Error => self.binary(Value::subtract)?,
+                    IntOverflow::Promote => self.binary(Value::subtract_promote)?,
+                },
+                Instruction::BinaryMultiply => match self.overflow {
+                    IntOverflow::Error => self.binary(Value::multiply)?,
+                    IntOverflow::Promote => self.binary(Value::multiply_promote)?,
+                },
+                Instruction::BinaryDivide => self.binary(Value::divide)?,
+                Instruction::BinaryReminder => self.binary(Value::reminder)?,
+                Instruction::BinaryPower => self.binary(Value::power)?,
+                Instruction::BinaryLessThan => self.binary(Value::less_than)?,
+                Instruction::BinaryLessThanEqual => self.binary(Value::less_than_equal)?,
+                Instruction::BinaryGreaterThan => self.binary(Value::greater_than)?,
+                Instruction::BinaryGreaterThanEqual => self.binary(Value::greater_than_equal)?,
+                Instruction::BinaryEqual => self.binary(|lhs, rhs| Ok(lhs.equal(rhs)))?,
+                Instruction::BinaryNotEqual => self.binary(|lhs, rhs| Ok(lhs.not_equal(rhs)))?,
+                Instruction::BinaryLogicalAnd => self.binary(|lhs, rhs| Ok(lhs.and(rhs)))?,
+                Instruction::BinaryLogicalOr => self.binary(|lhs, rhs| Ok(lhs.or(rhs)))?,
+                Instruction::BinaryLogicalXor => self.binary(|lhs, rhs| Ok(lhs.xor(rhs)))?,
+                Instruction::Contains => self.binary(|item, container| container.contains(item))?,
+                Instruction::BuildSet(count) => {
+                    let start = self
+                        .stack
+                        .len()
+                        .checked_sub(count as usize)
+                        .ok_or(RuntimeError::StackUnderflow)?;
+                    let set = self
+                        .stack
+                        .drain(start..)
+                        .map(HashableValue::try_from)
+                        .collect::<Result<_, _>>()?;
+                    self.stack.push(Value::Set(set));
+                }
+                Instruction::BuildArray(count) => {
+                    let start = self
+                        .stack
+                        .len()
+                        .checked_sub(count as usize)
+                        .ok_or(RuntimeError::StackUnderflow)?;
+                    let array = self.stack.split_off(start);
+                    self.stack.push(Value::array(array));
+                }
+                Instruction::UnaryMinus => {
+                    let value = self.pop()?.negate()?;
+                    self.stack.push(value);
+                }
+                Instruction::UnaryNot => {
+                    let value = self.pop()?.not();
+                    self.stack.push(value);
+                }
+                Instruction::GetIndex => {
+                    let index = self.pop()?;
+                    let element = self.pop()?.get_index(&index)?;
+                    self.stack.push(element);
+                }
+                Instruction::SetIndex => {
+                    let value = self.pop()?;
+                    let index = self.pop()?;
+                    self.pop()?.set_index(&index, value)?;
+                }
+                Instruction::Slice => {
+                    let end = self.pop()?;
+                    let start = self.pop()?;
+                    let slice = self.pop()?.slice(&start, &end)?;
+                    self.stack.push(slice);
+                }
+                Instruction::Push => {
+                    let value = self.pop()?;
+                    self.pop()?.push(value)?;
+                    self.stack.push(Value::Null);
+                }
+                Instruction::Call(idx, argc) => {
+                    let callee = code
+                        .functions
+                        .get(idx as usize)
+                        .ok_or(RuntimeError::InvalidFunction(idx))?;
+                    if callee.arity != argc {
+                        return Err(RuntimeError::ArityMismatch {
+                            expected: callee.arity.into(),
+                            found: argc.into(),
+                        });
+                    }
+                    let base = self
+                        .stack
+                        .len()
+                        .checked_sub(argc as usize)
+                        .ok_or(RuntimeError::StackUnderflow)?;
+                    self.frames.push(CallFrame {
+                        function: *function,
+                        return_pc: *pc,
+                        base,
+                        locals: self.frame,
+                    });
+                    // Arguments are the first locals of the callee
+                    self.frame = self.locals.len();
+                    self.locals.extend(self.stack.drain(base..));
+                    *function = Some(idx);
+                    *current = &callee.code;
+                    *pc = 0;
+                }
+                Instruction::TailCall(idx, argc) => {
+                    let callee = code
+                        .functions
+                        .get(idx as usize)
+                        .ok_or(RuntimeError::InvalidFunction(idx))?;
+                    if callee.arity != argc {
+                        return Err(RuntimeError::ArityMismatch {
+                            expected: callee.arity.into(),
+                            found: argc.into(),
+                        });
+                    }
+                    let frame = self
+                        .frames
+                        .last()
+                        .ok_or(RuntimeError::TailCallOutsideFunction)?;
+                    let start = self
+                        .stack
+                        .len()
+                        .checked_sub(argc as usize)
+                        .ok_or(RuntimeError::StackUnderflow)?;
+                    // Replace the current call's locals and temporaries with
+                    // the arguments, the caller's frame is left as is
+                    self.locals.truncate(self.frame);
+                    self.locals.extend(self.stack.drain(start..));
+                    self.stack.truncate(frame.base);
+                    *function = Some(idx);
+                    *current = &callee.code;
+                    *pc = 0;
+                }
+                Instruction::CallHost(idx, argc) => {
+                    let host = self
+                        .hosts
+                        .get(idx)
+                        .ok_or(RuntimeError::InvalidHostFunction(idx))?;
+                    let base = self
+                        .stack
+                        .len()
+                        .checked_sub(argc as usize)
+                        .ok_or(RuntimeError::StackUnderflow)?;
+                    let value = host(&self.stack[base..])?;
+                    self.stack.truncate(base);
+                    self.stack.push(value);
+                }
+                Instruction::CallNative(idx, argc) => {
+                    let native =
+                        native::get_by_index(idx).ok_or(RuntimeError::InvalidFunction(idx))?;
+                    let base = self
+                        .stack
+                        .len()
+                        .checked_sub(argc as usize)
+                        .ok_or(RuntimeError::StackUnderflow)?;
+                    let value = native(&self.stack[base..])?;
+                    self.stack.truncate(base);
+                    self.stack.push(value);
+                }
+                Instruction::GetIter => {
+                    let iterable = self.pop()?.iterable()?;
+                    self.stack.push(iterable);
+                    self.stack.push(Value::Integer(0));
+                }
+                Instruction::IterNext(target) => self.iter_next(target, pc)?,
+                Instruction::Return => {
+                    let value = self.pop()?;
+                    let Some(frame) = self.frames.pop() else {
+                        self.locals.truncate(self.frame);
+                        self.stack.push(value);
+                        return Ok(());
+                    };
+                    self.stack.truncate(frame.base);
+                    self.locals.truncate(self.frame);
+                    self.stack.push(value);
+                    self.frame = frame.locals;
+                    *function = frame.function;
+                    *current = match *function {
+                        Some(idx) => &code.functions[idx as usize].code,
+                        None => code,
+                    };
+                    *pc = frame.return_pc;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn pop(&mut self) -> RuntimeResult<Value> {
+        self.stack.pop().ok_or(RuntimeError::StackUnderflow)
+    }
+
+    fn jump_unless<F>(&mut self, comparison: F, target: u16, pc: &mut usize) -> RuntimeResult<()>
+    where
+        F: FnOnce(&Value, &Value) -> Result<Value, ValueError>,
+    {
+        let rhs = self.pop()?;
+        let lhs = self.pop()?;
+        if !comparison(&lhs, &rhs)?.is_truthy() {
+            *pc = target as usize;
+        }
+        Ok(())
+    }
+
+    /// Push the next element of the array below the index on top of the
+    /// stack and advance the index, or jump to `target` if there isn't one.
+    fn iter_next(&mut self, target: u16, pc: &mut usize) -> RuntimeResult<()> {
+        let [array, index] = self
+            .stack
+            .last_chunk_mut::<2>()
+            .ok_or(RuntimeError::StackUnderflow)?;
+        let (Value::Array(array), Value::Integer(index)) = (&*array, index) else {
+            return Err(RuntimeError::TypeError {
+                expected: "iterator",
+                found: array.type_name(),
+            });
+        };
+        let next = usize::try_from(*index)
+            .ok()
+            .and_then(|i| array.borrow().get(i).cloned());
+        match next {
+            Some(value) => {
+                *index += 1;
+                self.stack.push(value);
+            }
+            None => *pc = target as usize,
+        }
+        Ok(())
+    }
+
+    fn binary<F>(&mut self, operation: F) -> RuntimeResult<()>
+    where
+        F: FnOnce(&Value, &Value) -> Result<Value, ValueError>,
+    {
+        let rhs = self.pop()?;
+        let lhs = self.pop()?;
+        self.stack.push(operation(&lhs, &rhs)?);
+        Ok(())
+    }
+}
+
+/// Store `value` at `idx`, growing `slots` with `null`s if necessary.
+#[cfg(feature = "std")]
+fn store(slots: &mut Vec<Value>, idx: usize, value: Value) {
+    if idx >= slots.len() {
+        slots.resize(idx + 1, Value::Null);
+    }
+    slots[idx] = value;
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use crate::{
+        ast::value::{Value, ValueError},
+        compiler::{
+            code_block::CodeBlock, compile_program, Compile, Compiler, CompilerError, Instruction,
+        },
+        parser,
+    };
+
+    use super::{host::HostFunctions, RuntimeError, RuntimeResult, VM};
+
+    fn run(input: &str) -> RuntimeResult<String> {
+        let statements = parser::parse(input).unwrap();
+        let code_block = compile_program(&statements).unwrap();
+        let mut output = Vec::new();
+        VM::new_with_output(&mut output).run(&code_block)?;
+        Ok(String::from_utf8(output).unwrap())
+    }
+
+    #[test]
+    fn test_print() -> RuntimeResult<()> {
+        assert_eq!(run("print 42;")?, "42\n");
+        assert_eq!(run("print 1.5; print true;")?, "1.5\ntrue\n");
+        assert_eq!(run("")?, "");
+        Ok(())
+    }
+
+    #[test]
+    fn test_expressions() -> RuntimeResult<()> {
+        assert_eq!(run("print 2 + 3 * 4;")?, "14\n");
+        assert_eq!(run("print (2 + 3) * 4 ** 2;")?, "80\n");
+        assert_eq!(run("print 7 / 2; print 7 / 2.0;")?, "3\n3.5\n");
+        assert_eq!(run("print 1 == true; print 1 != 1.0;")?, "false\nfalse\n");
+        assert_eq!(run("print not 1 < 2;")?, "false\n");
+        Ok(())
+    }
+
+    #[test]
+    fn test_reminder() -> RuntimeResult<()> {
+        // Two integers give an integer, any float operand gives a float
+        assert_eq!(run("print 5 % 3;")?, "2\n");
+        assert_eq!(run("print 5.0 % 2.0;")?, "1.0\n");
+        assert_eq!(run("print 5 % 2.5; print -7 % 2;")?, "0.0\n-1\n");
+        assert_eq!(run("print 1 + 7 % 4 * 2;")?, "7\n");
+        // Booleans are 0 and 1, so `false % x` is 0 rather than `x`
+        assert_eq!(run("print false % 3; print true % 3;")?, "0\n1\n");
+        assert_eq!(
+            run("print 5 % 0;").map_err(RuntimeError::into_inner),
+            Err(ValueError::DivisionByZero.into())
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_variables_and_loops() -> RuntimeResult<()> {
+        assert_eq!(run("var x; print x;")?, "null\n");
+        assert_eq!(
+            run("const x = 10; var y = x * 2; y = y + x; print y;")?,
+            "30\n"
+        );
+        assert_eq!(
+            run("var i = 0; while i < 3 { print i; i = i + 1; }")?,
+            "0\n1\n2\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_for_in_loops() -> RuntimeResult<()> {
+        assert_eq!(run("for x in [1, 2, 3] { print x; }")?, "1\n2\n3\n");
+        assert_eq!(run("for x in [] { print x; } print 0;")?, "0\n");
+        assert_eq!(
+            run("var a = [1, 2]; for x in a { if x < 4 { append(a, x + 2); } } print a;")?,
+            "[1, 2, 3, 4, 5]\n"
+        );
+        assert_eq!(
+            run("for x in [1, 2, 3, 4] { if x == 2 { continue; } if x == 4 { break; } print x; }")?,
+            "1\n3\n"
+        );
+        assert_eq!(
+            run("for x in [1, 2] { for y in [3, 4] { print x * y; } }")?,
+            "3\n4\n6\n8\n"
+        );
+        assert_eq!(
+            run("fn first(a) { for x in a { return x; } return 0; } print first([5, 6]); print first([]);")?,
+            "5\n0\n"
+        );
+        assert!(matches!(
+            run("for x in 1 { }").map_err(RuntimeError::into_inner),
+            Err(RuntimeError::Value(ValueError::NotIterable("int")))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_reversed_and_enumerate() -> RuntimeResult<()> {
+        assert_eq!(
+            run("for x in reversed([1, 2, 3]) { print x; }")?,
+            "3\n2\n1\n"
+        );
+        assert_eq!(
+            run("for pair in enumerate([4, 5]) { print pair; }")?,
+            "[0, 4]\n[1, 5]\n"
+        );
+        assert_eq!(
+            run("for pair in enumerate(reversed([4, 5])) { print pair; }")?,
+            "[0, 5]\n[1, 4]\n"
+        );
+        assert_eq!(run("print reversed([]); print str(1);")?, "[]\n1\n");
+        // Declared functions shadow natives
+        assert_eq!(
+            run("fn reversed(x) { return x; } print reversed([1, 2]);")?,
+            "[1, 2]\n"
+        );
+        assert!(matches!(
+            run("print reversed(1);"),
+            Err(RuntimeError::Value(ValueError::NotIterable("int")))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_inlined_constants() -> RuntimeResult<()> {
+        assert_eq!(
+            run("const PI = 3.5; var r = 2; print PI * r * r;")?,
+            "14.0\n"
+        );
+        assert_eq!(
+            run("const n = 3; fn f() { return n; } { const n = 4; print n + f(); }")?,
+            "7\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_annotated_declarations() -> RuntimeResult<()> {
+        assert_eq!(run("var x: int = 5; print x;")?, "5\n");
+        assert_eq!(
+            run("var i: int, f: float, b: bool, s: string; print i; print f; print b; print s;")?,
+            "0\n0.0\nfalse\n\n"
+        );
+        assert_eq!(run("{ var x: int; print x; }")?, "0\n");
+        assert_eq!(
+            run("fn twice(x: int) { return x * 2; } print twice(4);")?,
+            "8\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_operator_error_spans() {
+        let span = |input| {
+            let error = run(input).unwrap_err();
+            assert!(matches!(error.clone().into_inner(), RuntimeError::Value(_)));
+            error
+                .span()
+                .and_then(|(start, _)| parser::line_col(input, start))
+        };
+        assert_eq!(span("print 1 + set{};"), Some((1, 9)));
+        assert_eq!(span("print 1 * (2 + set{});"), Some((1, 14)));
+        assert_eq!(span("var x = 1;\nprint x\n  ** true;"), Some((3, 3)));
+        // Fused compare-and-branch instructions point at the comparison
+        assert_eq!(span("if true < 1 { print 1; }"), Some((1, 9)));
+        assert_eq!(
+            span("fn f(x) {\n  return x - set{};\n}\nprint f(1);"),
+            Some((2, 12))
+        );
+        // Errors of unspanned instructions don't have a span
+        assert_eq!(run("print set{set{}};").unwrap_err().span(), None);
+    }
+
+    #[test]
+    fn test_runtime_errors() {
+        assert!(matches!(
+            run("print 1 / 0;").map_err(RuntimeError::into_inner),
+            Err(RuntimeError::Value(_))
+        ));
+        assert!(matches!(
+            run("print 1 + set{};").map_err(RuntimeError::into_inner),
+            Err(RuntimeError::Value(_))
+        ));
+    }
+
+    #[test]
+    fn test_comparison_conditions() -> RuntimeResult<()> {
+        let cases = [
+            ("<", "0\n1\n"),
+            ("<=", "0\n1\n2\n"),
+            (">", "3\n4\n"),
+            (">=", "2\n3\n4\n"),
+            ("==", "2\n"),
+            ("!=", "0\n1\n3\n4\n"),
+        ];
+        for (operator, expected) in cases {
+            let input =
+                format!("var i = 0; while i < 5 {{ if i {operator} 2 {{ print i; }} i = i + 1; }}");
+            assert_eq!(run(&input)?, expected, "{operator}");
+        }
+        assert_eq!(
+            run("if 1.0 == 1 { print 1; } if 1 == true { print 2; }")?,
+            "1\n"
+        );
+        assert_eq!(
+            run("if 0 { print 1; } if 2 > 1 and true { print 2; }")?,
+            "2\n"
+        );
+        assert!(matches!(
+            run("if true < 1 { print 1; }").map_err(RuntimeError::into_inner),
+            Err(RuntimeError::Value(_))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_break_inside_if() -> RuntimeResult<()> {
+        let input = "var i = 0; while true { if i == 3 { break; } print i; i = i + 1; } print 10;";
+        assert_eq!(run(input)?, "0\n1\n2\n10\n");
+        Ok(())
+    }
+
+    #[test]
+    fn test_c_style_for() -> RuntimeResult<()> {
+        assert_eq!(
+            run("for (var i = 0; i < 3; i += 1) { print i; }")?,
+            "0\n1\n2\n"
+        );
+        assert_eq!(run("for (;;) { break; } print 1;")?, "1\n");
+        // `continue` runs the step before the next iteration
+        assert_eq!(
+            run("for (var i = 0; i < 5; i += 1) { if i == 2 { continue; } print i; }")?,
+            "0\n1\n3\n4\n"
+        );
+        // Loop variable is scoped to the loop
+        assert_eq!(
+            run("var i = 10; for (var i = 0; i < 1; i += 1) { print i; } print i;")?,
+            "0\n10\n"
+        );
+        assert_eq!(
+            run("for (var i = 3; i > 0; i--) { print i; }")?,
+            "3\n2\n1\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_increments() -> RuntimeResult<()> {
+        assert_eq!(run("var i = 0; i++; i++; i--; print i;")?, "1\n");
+        assert_eq!(run("var x = 1.5; x++; print x;")?, "2.5\n");
+        assert_eq!(run("{ var i = 5; i--; print i; }")?, "4\n");
+        Ok(())
+    }
+
+    #[test]
+    fn test_do_while() -> RuntimeResult<()> {
+        // Body runs once even though the condition is false from the start
+        assert_eq!(run("do { print 1; } while false;")?, "1\n");
+        assert_eq!(
+            run("var i = 0; do { print i; i += 1; } while i < 3;")?,
+            "0\n1\n2\n"
+        );
+        assert_eq!(
+            run("var i = 0; do { i += 1; if i == 2 { continue; } if i == 4 { break; } print i; } while i < 10;")?,
+            "1\n3\n"
+        );
+        // `continue` evaluates the condition before the next iteration
+        assert_eq!(run("do { print 1; continue; } while false;")?, "1\n");
+        Ok(())
+    }
+
+    #[test]
+    fn test_continue() -> RuntimeResult<()> {
+        let input = "var i = 0; while i < 5 { i += 1; if i == 2 { continue; } print i; }";
+        assert_eq!(run(input)?, "1\n3\n4\n5\n");
+        Ok(())
+    }
+
+    #[test]
+    fn test_block_scoped_variables() -> RuntimeResult<()> {
+        assert_eq!(
+            run("var x = 1; { var x = 2; print x; } print x;")?,
+            "2\n1\n"
+        );
+        assert_eq!(
+            run("{ var a = 1; print a; } { var b; print b; }")?,
+            "1\nnull\n"
+        );
+        assert_eq!(
+            run("var i = 0; while i < 2 { var x; print x; x = i; i = i + 1; }")?,
+            "null\nnull\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_arrays() -> RuntimeResult<()> {
+        assert_eq!(
+            run("print [1, [2.5, true], []];")?,
+            "[1, [2.5, true], []]\n"
+        );
+        assert_eq!(
+            run("print [1, 2] == [1, 2]; print [1, 2] == [1, 3]; print [1] != [1, 1];")?,
+            "true\nfalse\ntrue\n"
+        );
+        assert_eq!(run("print [[1], [2]] == [[1], [2.0]];")?, "true\n");
+        assert_eq!(
+            run("print [1, 2] < [1, 3]; print [2] > [1, 5];")?,
+            "true\ntrue\n"
+        );
+        assert_eq!(run("if [1, 2] == [1, 2] { print 1; }")?, "1\n");
+        Ok(())
+    }
+
+    #[test]
+    fn test_sets() -> RuntimeResult<()> {
+        assert_eq!(run("print set{};")?, "set{}\n");
+        assert_eq!(run("print set{3, 1, 2, 1, 1 + 2};")?, "set{1, 2, 3}\n");
+        assert_eq!(
+            run("var s = set{1, 2.5}; print 1 in s; print 2 in s; print 2.5 in s;")?,
+            "true\nfalse\ntrue\n"
+        );
+        assert_eq!(run("if 1 + 1 in set{2} { print 1; }")?, "1\n");
+        assert!(matches!(
+            run("print set{set{}};"),
+            Err(RuntimeError::Value(_))
+        ));
+        assert!(matches!(
+            run("print 1 in 2;").map_err(RuntimeError::into_inner),
+            Err(RuntimeError::Value(_))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_repl_keeps_last_value() -> RuntimeResult<()> {
+        let mut compiler = Compiler::new_repl();
+        let mut output = Vec::new();
+        let mut vm = VM::new_with_output(&mut output);
+        let mut eval = |line: &str| {
+            for statement in parser::parse_repl(line).unwrap() {
+                statement.compile(&mut compiler).unwrap();
+            }
+            let (code_block, _) = compiler.finish().unwrap();
+            vm.run(&code_block)
+        };
+        assert_eq!(eval("1 + 1;")?, Some(Value::Integer(2)));
+        // REPL input may omit the `;` after a single expression
+        assert_eq!(eval("1 + 1")?, Some(Value::Integer(2)));
+        assert_eq!(eval("var x = 5;")?, None);
+        assert_eq!(eval("x * 2;")?, Some(Value::Integer(10)));
+        // Only the value of the last statement is kept
+        assert_eq!(eval("1; 2; print x;")?, None);
+        assert_eq!(eval("1; 2;")?, Some(Value::Integer(2)));
+        assert_eq!(eval("if true { 3; }")?, None);
+        assert_eq!(eval("{ 4; }")?, None);
+        // Blocks ending with an expression without `;` have a value
+        assert_eq!(eval("{ 1; 2; 3 }")?, Some(Value::Integer(3)));
+        assert_eq!(eval("{ 1; 2; 3; }")?, None);
+        assert_eq!(eval("{ var y = x; y + 1 }")?, Some(Value::Integer(6)));
+        assert_eq!(eval("{ 1 } print x;")?, None);
+        // Functions load constants registered by earlier lines
+        assert_eq!(eval("fn answer() { return 42; }")?, None);
+        assert_eq!(eval("answer() + 1")?, Some(Value::Integer(43)));
+
+        // Scripts discard every expression statement's value
+        let code_block = compile_program(&parser::parse("1 + 1;").unwrap()).unwrap();
+        assert_eq!(VM::new_with_output(&mut Vec::new()).run(&code_block)?, None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_trace() -> RuntimeResult<()> {
+        let statements = parser::parse("var x = 1 + 2; print x;").unwrap();
+        let code_block = compile_program(&statements).unwrap();
+        let (mut output, mut trace) = (Vec::new(), Vec::new());
+        VM::new_with_output(&mut output)
+            .with_trace(&mut trace)
+            .run(&code_block)?;
+        assert_eq!(String::from_utf8(output).unwrap(), "3\n");
+        let expected = [
+            "0000 LoadValue(0)             []",
+            "0001 LoadValue(1)             [1]",
+            "0002 BinaryAdd                [1, 2]",
+            "0003 StoreSymbol(0)           [3]",
+            "0004 LoadSymbol(0)            []",
+            "0005 Display                  [3]",
+        ];
+        assert_eq!(
+            String::from_utf8(trace)
+                .unwrap()
+                .lines()
+                .collect::<Vec<_>>(),
+            expected
+        );
+
+        // Errors writing the trace of the first instruction aren't spanned
+        let (mut output, mut trace): (Vec<u8>, &mut [u8]) = (Vec::new(), &mut []);
+        assert_eq!(
+            VM::new_with_output(&mut output)
+                .with_trace(&mut trace)
+                .run(&code_block),
+            Err(RuntimeError::Output(io::ErrorKind::WriteZero))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_function_return() -> RuntimeResult<()> {
+        assert_eq!(
+            run("fn add(x, y) { return x + y; } print add(1, 2);")?,
+            "3\n"
+        );
+        assert_eq!(run("fn f() { print 1; } print f();")?, "1\nnull\n");
+        assert_eq!(run("fn f() { return; print 1; } print f();")?, "null\n");
+        assert_eq!(
+            run("fn f() {\n    return\n        5;\n}\nprint f();")?,
+            "5\n"
+        );
+        assert_eq!(
+            run("fn sign(x) { if x < 0 { return -1; } return 1; } print sign(0 - 5); print sign(5);")?,
+            "-1\n1\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_empty_function_body() -> RuntimeResult<()> {
+        let statements = parser::parse("fn f() {} f(); f();").unwrap();
+        let code_block = compile_program(&statements).unwrap();
+        // Bodies without `return` return `null` when execution reaches the end
+        let function = &code_block.functions[0].code;
+        assert_eq!(
+            function
+                .instructions
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            ["LoadValue(0)", "Return"]
+        );
+        assert!(function.values.is_empty());
+        assert_eq!(*code_block.values, [Value::Null]);
+
+        let mut output = Vec::new();
+        let mut vm = VM::new_with_output(&mut output);
+        assert_eq!(vm.run(&code_block)?, None);
+        assert!(vm.stack.is_empty());
+        assert!(vm.frames.is_empty());
+        assert_eq!(run("fn f() {} print f();")?, "null\n");
+        Ok(())
+    }
+
+    #[test]
+    fn test_nested_calls() -> RuntimeResult<()> {
+        let program = "
+            fn square(x) { return x * x; }
+            fn sum_of_squares(x, y) { var z = square(y); return square(x) + z; }
+            { var a = 3; print sum_of_squares(a, 4) + a; }
+        ";
+        assert_eq!(run(program)?, "28\n");
+        let program = "
+            fn fib(n) { if n < 2 { return n; } return fib(n - 1) + fib(n - 2); }
+            print fib(10);
+        ";
+        assert_eq!(run(program)?, "55\n");
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(not(feature = "bigint"))]
+    fn test_int_overflow_modes() -> RuntimeResult<()> {
+        let statements = parser::parse("print 9223372036854775807 + 1;").unwrap();
+        let code_block = compile_program(&statements).unwrap();
+        let result = VM::new_with_output(&mut Vec::new()).run(&code_block);
+        assert_eq!(
+            result.map_err(RuntimeError::into_inner),
+            Err(RuntimeError::Value(ValueError::Overflow))
+        );
+        let mut output = Vec::new();
+        VM::new_with_output(&mut output)
+            .with_int_overflow(crate::ast::value::IntOverflow::Promote)
+            .run(&code_block)?;
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "9223372036854775808.0\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_comparison_chains() -> RuntimeResult<()> {
+        assert_eq!(run("print 1 < 2 < 3; print 3 < 2 < 1;")?, "true\nfalse\n");
+        assert_eq!(run("print 1 < 3 > 2; print 1 <= 1 < 1;")?, "true\nfalse\n");
+        assert_eq!(run("print 1 < 2 < 3 < 4 >= 4;")?, "true\n");
+        // Middle operands are evaluated once
+        let program = "
+            fn two() { print 0; return 2; }
+            var x = 1 < two() < 3;
+            print x;
+            if 5 > two() > 1 { print 1; }
+        ";
+        assert_eq!(run(program)?, "0\ntrue\n0\n1\n");
+        Ok(())
+    }
+
+    #[test]
+    fn test_shared_and_cloned_arrays() -> RuntimeResult<()> {
+        let program = "
+            var inner = [2];
+            var a = [1, inner];
+            var shared = a;
+            var cloned = clone(a);
+            shared[0] = 5;
+            append(inner, 3);
+            print a; print shared; print cloned;
+        ";
+        assert_eq!(run(program)?, "[5, [2, 3]]\n[5, [2, 3]]\n[1, [2]]\n");
+        assert_eq!(run("print clone(1) + 1;")?, "2\n");
+        Ok(())
+    }
+
+    #[test]
+    fn test_if_expressions() -> RuntimeResult<()> {
+        assert_eq!(run("print if true { 1 } else { 2 };")?, "1\n");
+        assert_eq!(
+            run("const x = if false { 1 } else { 2 }; print x * 10;")?,
+            "20\n"
+        );
+        let program = "
+            fn sign(n) { return if n < 0 { -1 } elif n == 0 { 0 } else { var one = 1; one }; }
+            print [sign(0 - 5), sign(0), sign(5)];
+        ";
+        assert_eq!(run(program)?, "[-1, 0, 1]\n");
+        assert_eq!(run("print 1 + if true { 2 } else { 3 } * 2;")?, "5\n");
+        // Branches without a trailing expression are `null`
+        assert_eq!(run("print if true { print 1; } else { 2 };")?, "1\nnull\n");
+        Ok(())
+    }
+
+    #[test]
+    fn test_display() -> RuntimeResult<()> {
+        let statements = parser::parse("print 1; print [1.5, true];").unwrap();
+        let code_block = compile_program(&statements).unwrap();
+        let mut output = Vec::new();
+        let mut vm = VM::new_with_output(&mut output);
+        assert_eq!(vm.run(&code_block)?, None);
+        assert!(vm.stack.is_empty());
+        drop(vm);
+        assert_eq!(String::from_utf8(output).unwrap(), "1\n[1.5, true]\n");
+
+        let code_block = CodeBlock {
+            instructions: vec![Instruction::Display],
+            ..CodeBlock::default()
+        };
+        let result = VM::new_with_output(&mut Vec::new()).run(&code_block);
+        assert!(matches!(result, Err(RuntimeError::StackUnderflow)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_truthy_conditions() -> RuntimeResult<()> {
+        // Conditions jump on truthiness rather than on being `false`
+        assert_eq!(run("if 0 {} else { print 1; }")?, "1\n");
+        assert_eq!(
+            run("if 0.0 { print 1; } elif [] { print 2; } else { print 3; }")?,
+            "3\n"
+        );
+        assert_eq!(run("if [0] { print 1; }")?, "1\n");
+        assert_eq!(
+            run("var n = 3; while n { print n; n = n - 1; }")?,
+            "3\n2\n1\n"
+        );
+
+        for (jump, value, taken) in [
+            (
+                Instruction::JumpIfFalse(4),
+                Value::from(String::new()),
+                true,
+            ),
+            (
+                Instruction::JumpIfFalse(4),
+                Value::from("a".to_string()),
+                false,
+            ),
+            (Instruction::JumpIfFalse(4), Value::Null, true),
+            (Instruction::JumpIfTrue(4), Value::Integer(-1), true),
+            (
+                Instruction::JumpIfTrue(4),
+                Value::from(String::new()),
+                false,
+            ),
+        ] {
+            let code_block = CodeBlock {
+                instructions: vec![
+                    Instruction::LoadValue(0),
+                    jump,
+                    Instruction::LoadValue(1),
+                    Instruction::Return,
+                    Instruction::LoadValue(2),
+                ],
+                values: vec![value, Value::False, Value::True].into(),
+                ..CodeBlock::default()
+            };
+            let mut output = Vec::new();
+            let result = VM::new_with_output(&mut output).run(&code_block)?;
+            assert_eq!(result, Some(Value::from(taken)), "{jump}");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_tail_calls() -> RuntimeResult<()> {
+        let program = "
+            fn count(n, total) { if n == 0 { return total; } return count(n - 1, total + 1); }
+            print count(100000, 0);
+        ";
+        assert_eq!(run(program)?, "100000\n");
+        // Iterators and locals of the replaced call are discarded
+        let program = "
+            fn f(n) { var x = n; for i in [1, 2] { if x > 0 { return f(x - i); } } return x; }
+            { var a = 1; print f(5) + a; }
+        ";
+        assert_eq!(run(program)?, "1\n");
+        Ok(())
+    }
+
+    #[test]
+    fn test_top_level_return() -> RuntimeResult<()> {
+        let code_block = CodeBlock {
+            instructions: vec![
+                Instruction::LoadValue(0),
+                Instruction::Return,
+                Instruction::LoadValue(1),
+                Instruction::Display,
+            ],
+            values: vec![Value::Integer(7), Value::Integer(8)].into(),
+            ..CodeBlock::default()
+        };
+        let mut output = Vec::new();
+        let value = VM::new_with_output(&mut output).run(&code_block)?;
+        assert_eq!(value, Some(Value::Integer(7)));
+        assert!(output.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_string_comparisons() {
+        let compare = |operator, lhs: &str, rhs: Value| {
+            let code_block = CodeBlock {
+                instructions: vec![
+                    Instruction::LoadValue(0),
+                    Instruction::LoadValue(1),
+                    operator,
+                    Instruction::Return,
+                ],
+                values: vec![Value::from(lhs.to_string()), rhs].into(),
+                ..CodeBlock::default()
+            };
+            let mut output = Vec::new();
+            let result = VM::new_with_output(&mut output).run(&code_block);
+            result
+        };
+        let string = |string: &str| Value::from(string.to_string());
+
+        let result = compare(Instruction::BinaryLessThan, "a", string("b"));
+        assert_eq!(result.unwrap(), Some(Value::True));
+        let result = compare(Instruction::BinaryEqual, "abc", string("abc"));
+        assert_eq!(result.unwrap(), Some(Value::True));
+        let result = compare(Instruction::BinaryGreaterThan, "a", Value::Integer(1));
+        assert!(matches!(
+            result,
+            Err(RuntimeError::Value(ValueError::Incomparable(
+                "string", "int"
+            )))
+        ));
+    }
+
+    #[test]
+    fn test_index_assignment() -> RuntimeResult<()> {
+        assert_eq!(run("var a = [1, 2]; a[0] = 5; print a;")?, "[5, 2]\n");
+        // Copies of an array refer to the same array
+        assert_eq!(
+            run("const a = [1, 2]; var b = a; b[1] = [3]; print a;")?,
+            "[1, [3]]\n"
+        );
+        assert_eq!(
+            run("fn set(arr, i) { arr[i] = 0; } var a = [1, 2]; set(a, 1); print a;")?,
+            "[1, 0]\n"
+        );
+        assert_eq!(
+            run("var a = []; append(a, 1); print append(a, 2); print a;")?,
+            "null\n[1, 2]\n"
+        );
+        // Arrays containing themselves can be printed and compared
+        assert_eq!(
+            run("var a = [1]; a[0] = a; append(a, a); var b = [a]; b[0] = b;
+                 print a; print a == a; print a == b; print a < b;")?,
+            "[[...], [...]]\ntrue\nfalse\nfalse\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_index() -> Result<(), RuntimeError> {
+        assert_eq!(run("print [10, 20, 30][-1] == 30;")?, "true\n");
+        assert_eq!(run("var a = [10, 20, 30]; print a[0] == a[-3];")?, "true\n");
+        assert_eq!(run("print [[1, 2], [3]][0][-1];")?, "2\n");
+        assert_eq!(run("var a = [1, 2]; a[-1] = 5; print a;")?, "[1, 5]\n");
+        assert_eq!(
+            run("print [10][-2];"),
+            Err(ValueError::IndexOutOfBounds { index: -2, len: 1 }.into())
+        );
+        assert_eq!(
+            run("print [10][1];"),
+            Err(ValueError::IndexOutOfBounds { index: 1, len: 1 }.into())
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_slice() -> Result<(), RuntimeError> {
+        assert_eq!(run("print [1, 2, 3, 4][1:3];")?, "[2, 3]\n");
+        assert_eq!(run("var a = [1, 2, 3, 4]; print a[:2];")?, "[1, 2]\n");
+        assert_eq!(run("var a = [1, 2, 3, 4]; print a[1:];")?, "[2, 3, 4]\n");
+        assert_eq!(run("var a = [1, 2, 3, 4]; print a[:];")?, "[1, 2, 3, 4]\n");
+        assert_eq!(run("var a = [1, 2, 3, 4]; print a[-3:-1];")?, "[2, 3]\n");
+        assert_eq!(run("print [1, 2][1:5] == [2];")?, "true\n");
+        assert_eq!(run("var a = [[1, 2]]; print a[0][1:][0];")?, "2\n");
+        assert_eq!(
+            run("print [1][1.5:];"),
+            Err(ValueError::UnsupportedOperands("[:]", "array", "float").into())
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_index_assignment_errors() {
+        assert_eq!(
+            run("var a = [1, 2]; a[2] = 0;"),
+            Err(ValueError::IndexOutOfBounds { index: 2, len: 2 }.into())
+        );
+        assert_eq!(
+            run("var a = []; a[-1] = 0;"),
+            Err(ValueError::IndexOutOfBounds { index: -1, len: 0 }.into())
+        );
+        assert_eq!(
+            run("var a = [1]; a[true] = 0;"),
+            Err(ValueError::UnsupportedOperands("[]", "array", "bool").into())
+        );
+        assert_eq!(
+            run("var a = 1; append(a, 1);"),
+            Err(ValueError::UnsupportedOperand("append", "int").into())
+        );
+    }
+
+    #[test]
+    fn test_call_named() -> RuntimeResult<()> {
+        let statements = parser::parse(
+            "var base = 10; fn add(x, y) { return x + y; } fn add_base(x) { return add(x, base); }",
+        )
+        .unwrap();
+        let mut compiler = Compiler::new();
+        for statement in &statements {
+            statement.compile(&mut compiler).unwrap();
+        }
+        let program = compiler.finish_program().unwrap();
+        assert_eq!(program.arity("add"), Some(2));
+        assert_eq!(program.arity("print"), None);
+
+        let mut output = Vec::new();
+        let mut vm = VM::new_with_output(&mut output);
+        vm.run(&program.main)?;
+        let args = [Value::Integer(1), Value::Integer(2)];
+        assert_eq!(vm.call_named(&program, "add", &args)?, Value::Integer(3));
+        assert_eq!(
+            vm.call_named(&program, "add_base", &args[..1])?,
+            Value::Integer(11)
+        );
+        assert_eq!(
+            vm.call_named(&program, "add", &args[..1]),
+            Err(RuntimeError::ArityMismatch {
+                expected: 2,
+                found: 1
+            })
+        );
+        assert_eq!(
+            vm.call_named(&program, "sub", &args),
+            Err(RuntimeError::UndefinedFunction("sub".to_string()))
+        );
+        Ok(())
+    }
+
+    fn host_functions() -> HostFunctions {
+        let mut hosts = HostFunctions::new();
+        hosts
+            .register("double", |args| match args {
+                [value] => Ok(value.add(value)?),
+                _ => Err(RuntimeError::ArityMismatch {
+                    expected: 1,
+                    found: args.len(),
+                }),
+            })
+            .unwrap();
+        hosts
+    }
+
+    fn run_with_hosts(input: &str) -> RuntimeResult<String> {
+        let statements = parser::parse(input).unwrap();
+        let mut compiler = Compiler::new().with_host_functions(&host_functions());
+        for statement in &statements {
+            statement.compile(&mut compiler).unwrap();
+        }
+        let (code_block, _) = compiler.finish().unwrap();
+        let mut output = Vec::new();
+        VM::new_with_output(&mut output)
+            .with_host_functions(host_functions())
+            .run(&code_block)?;
+        Ok(String::from_utf8(output).unwrap())
+    }
+
+    #[test]
+    fn test_host_function() -> RuntimeResult<()> {
+        assert_eq!(run_with_hosts("print double(21);")?, "42\n");
+        assert_eq!(
+            run_with_hosts("fn quadruple(x) { return double(double(x)); } print quadruple(1.5);")?,
+            "6.0\n"
+        );
+        assert_eq!(run_with_hosts("double(1); print 1;")?, "1\n");
+        assert_eq!(
+            run_with_hosts("print double(1, 2);"),
+            Err(RuntimeError::ArityMismatch {
+                expected: 1,
+                found: 2
+            })
+        );
+        assert!(matches!(
+            run_with_hosts("print double(set{});"),
+            Err(RuntimeError::Value(_))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_host_function_collisions() {
+        let mut hosts = host_functions();
+        assert!(matches!(
+            hosts.register("double", |_| Ok(Value::Null)),
+            Err(CompilerError::Redefinition(name)) if name == "double"
+        ));
+        assert_eq!(hosts.index("double"), Some(0));
+
+        let statements = parser::parse("fn double(x) { return x; }").unwrap();
+        let mut compiler = Compiler::new().with_host_functions(&hosts);
+        assert!(matches!(
+            statements[0].compile(&mut compiler),
+            Err(CompilerError::Redefinition(name)) if name == "double"
+        ));
+        // Without the registry the call is an undefined function
+        assert!(matches!(
+            compile_program(&parser::parse("double(1);").unwrap()),
+            Err(CompilerError::UndefinedIdentifer(name)) if name == "double"
+        ));
+        // A VM without the registry can't run code calling host functions
+        let mut compiler = Compiler::new().with_host_functions(&hosts);
+        for statement in parser::parse("double(1);").unwrap() {
+            statement.compile(&mut compiler).unwrap();
+        }
+        let (code_block, _) = compiler.finish().unwrap();
+        let mut output = Vec::new();
+        assert_eq!(
+            VM::new_with_output(&mut output).run(&code_block),
+            Err(RuntimeError::InvalidHostFunction(0))
+        );
+    }
+
+    #[cfg(feature = "bigint")]
+    #[test]
+    fn test_bigint_arithmetic() -> RuntimeResult<()> {
+        assert_eq!(
+            run("print 99999999999999999999999 + 1;")?,
+            "100000000000000000000000\n"
+        );
+        assert_eq!(
+            run("print 9223372036854775807 + 1; print -9223372036854775807 - 2;")?,
+            "9223372036854775808\n-9223372036854775809\n"
+        );
+        assert_eq!(
+            run("print 4294967296 * 4294967296 * 4294967296;")?,
+            "79228162514264337593543950336\n"
+        );
+        // Results that fit in `i64` are integers again
+        assert_eq!(
+            run("var x = 99999999999999999999999 - 99999999999999999999998; print x == 1;")?,
+            "true\n"
+        );
+        assert_eq!(
+            run("print 99999999999999999999999 / 2;").map_err(RuntimeError::into_inner),
+            Err(ValueError::UnsupportedOperands("/", "bigint", "int").into())
+        );
+        Ok(())
+    }
+
+    /// Number of `Instruction` variants, must be updated together with
+    /// `variant_index`.
+    const INSTRUCTION_VARIANTS: usize = 49;
+
+    /// Position of the variant of `instruction`, the match has no wildcard so
+    /// adding a variant doesn't compile until it's listed here.
+    fn variant_index(instruction: Instruction) -> usize {
+        match instruction {
+            Instruction::StoreSymbol(_) => 0,
+            Instruction::LoadSymbol(_) => 1,
+            Instruction::StoreLocal(_) => 2,
+            Instruction::LoadLocal(_) => 3,
+            Instruction::LoadValue(_) => 4,
+            Instruction::Pop => 5,
+            Instruction::Display => 6,
+            Instruction::Jump(_) => 7,
+            Instruction::JumpIfTrue(_) => 8,
+            Instruction::JumpIfFalse(_) => 9,
+            Instruction::JumpUnlessLessThan(_) => 10,
+            Instruction::JumpUnlessLessThanEqual(_) => 11,
+            Instruction::JumpUnlessGreaterThan(_) => 12,
+            Instruction::JumpUnlessGreaterThanEqual(_) => 13,
+            Instruction::JumpUnlessEqual(_) => 14,
+            Instruction::JumpUnlessNotEqual(_) => 15,
+            Instruction::BinaryAdd => 16,
+            Instruction::BinarySubtract => 17,
+            Instruction::BinaryMultiply => 18,
+            Instruction::BinaryDivide => 19,
+            Instruction::BinaryReminder => 20,
+            Instruction::BinaryPower => 21,
+            Instruction::BinaryLessThan => 22,
+            Instruction::BinaryLessThanEqual => 23,
+            Instruction::BinaryGreaterThan => 24,
+            Instruction::BinaryGreaterThanEqual => 25,
+            Instruction::BinaryEqual => 26,
+            Instruction::BinaryNotEqual => 27,
+            Instruction::BinaryLogicalAnd => 28,
+            Instruction::BinaryLogicalOr => 29,
+            Instruction::BinaryLogicalXor => 30,
+            Instruction::Contains => 31,
+            Instruction::BuildSet(_) => 32,
+            Instruction::BuildArray(_) => 33,
+            Instruction::SetIndex => 34,
+            Instruction::Push => 35,
+            Instruction::UnaryMinus => 36,
+            Instruction::UnaryNot => 37,
+            Instruction::Call(_, _) => 38,
+            Instruction::Return => 39,
+            Instruction::CallHost(_, _) => 40,
+            Instruction::CallNative(_, _) => 41,
+            Instruction::GetIter => 42,
+            Instruction::IterNext(_) => 43,
+            Instruction::TailCall(_, _) => 44,
+            Instruction::Dup => 45,
+            Instruction::PopN(_) => 46,
+            Instruction::GetIndex => 47,
+            Instruction::Slice => 48,
+        }
+    }
+
+    /// One instruction of each variant, jumps target the end of a code block
+    /// holding only the jump.
+    fn every_instruction() -> Vec<Instruction> {
+        vec![
+            Instruction::StoreSymbol(0),
+            Instruction::LoadSymbol(0),
+            Instruction::StoreLocal(0),
+            Instruction::LoadLocal(0),
+            Instruction::LoadValue(0),
+            Instruction::Pop,
+            Instruction::Display,
+            Instruction::Jump(1),
+            Instruction::JumpIfTrue(1),
+            Instruction::JumpIfFalse(1),
+            Instruction::JumpUnlessLessThan(1),
+            Instruction::JumpUnlessLessThanEqual(1),
+            Instruction::JumpUnlessGreaterThan(1),
+            Instruction::JumpUnlessGreaterThanEqual(1),
+            Instruction::JumpUnlessEqual(1),
+            Instruction::JumpUnlessNotEqual(1),
+            Instruction::BinaryAdd,
+            Instruction::BinarySubtract,
+            Instruction::BinaryMultiply,
+            Instruction::BinaryDivide,
+            Instruction::BinaryReminder,
+            Instruction::BinaryPower,
+            Instruction::BinaryLessThan,
+            Instruction::BinaryLessThanEqual,
+            Instruction::BinaryGreaterThan,
+            Instruction::BinaryGreaterThanEqual,
+            Instruction::BinaryEqual,
+            Instruction::BinaryNotEqual,
+            Instruction::BinaryLogicalAnd,
+            Instruction::BinaryLogicalOr,
+            Instruction::BinaryLogicalXor,
+            Instruction::Contains,
+            Instruction::BuildSet(0),
+            Instruction::BuildArray(0),
+            Instruction::SetIndex,
+            Instruction::Push,
+            Instruction::UnaryMinus,
+            Instruction::UnaryNot,
+            Instruction::Call(0, 0),
+            Instruction::Return,
+            Instruction::CallHost(0, 0),
+            Instruction::CallNative(0, 0),
+            Instruction::GetIter,
+            Instruction::IterNext(0),
+            Instruction::TailCall(0, 0),
+            Instruction::Dup,
+            Instruction::PopN(1),
+            Instruction::GetIndex,
+            Instruction::Slice,
+        ]
+    }
+
+    #[test]
+    fn test_every_instruction_is_handled() {
+        let instructions = every_instruction();
+        let mut indices = instructions
+            .iter()
+            .map(|instruction| variant_index(*instruction))
+            .collect::<Vec<_>>();
+        indices.sort_unstable();
+        assert_eq!(indices, (0..INSTRUCTION_VARIANTS).collect::<Vec<_>>());
+
+        let x = String::from("x");
+        for instruction in instructions {
+            assert!(!instruction.to_string().is_empty());
+            instruction.stack_effect();
+            let code_block = CodeBlock {
+                instructions: vec![instruction],
+                values: vec![Value::Null].into(),
+                ..CodeBlock::default()
+            };
+            code_block.disassemble(&[&x]);
+            // Only panics matter, most instructions underflow the empty stack
+            let mut output = Vec::new();
+            let _ = VM::new_with_output(&mut output).run(&code_block);
+        }
+    }
+}
@@ -0,0 +1,754 @@
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use core::cmp::Ordering;
+
+use crate::{
+    ast::value::{FloatToInt, HashableValue, Value},
+    collections::HashSet,
+    math,
+};
+
+use super::{RuntimeError, RuntimeResult};
+
+/// Functions implemented in Rust that are callable from Alloy, arguments are
+/// passed in order they appear in the call.
+pub type NativeFunction = fn(&[Value]) -> RuntimeResult<Value>;
+
+pub static NATIVES: phf::Map<&'static str, NativeFunction> = phf_map! {
+    "hex" => hex,
+    "bin" => bin,
+    "oct" => oct,
+    "int" => int,
+    "float" => float,
+    "bool" => bool,
+    "str" => str,
+    "union" => union,
+    "intersection" => intersection,
+    "min" => min,
+    "max" => max,
+    "append" => append,
+    "format" => format,
+    "reversed" => reversed,
+    "enumerate" => enumerate,
+    "clone" => clone,
+    "abs" => abs,
+    "floor" => floor,
+    "ceil" => ceil,
+    "round" => round,
+    "sqrt" => sqrt,
+    "pow" => pow,
+};
+
+/// Look up native function by its name.
+pub fn get(name: &str) -> Option<NativeFunction> {
+    NATIVES.get(name).copied()
+}
+
+/// Index of the native function named `name`, `CallNative` instructions
+/// refer to natives by their index.
+pub fn index(name: &str) -> Option<u16> {
+    let index = NATIVES.keys().position(|native| *native == name)?;
+    index.try_into().ok()
+}
+
+/// Look up native function by the index returned by `index`.
+pub fn get_by_index(index: u16) -> Option<NativeFunction> {
+    NATIVES.values().nth(index.into()).copied()
+}
+
+/// Return arguments as an array if exactly `N` arguments are passed.
+fn expect_args<const N: usize>(args: &[Value]) -> RuntimeResult<&[Value; N]> {
+    args.try_into().map_err(|_| RuntimeError::ArityMismatch {
+        expected: N,
+        found: args.len(),
+    })
+}
+
+fn expect_integer(value: &Value) -> RuntimeResult<i64> {
+    match value {
+        Value::Integer(int) => Ok(*int),
+        _ => Err(RuntimeError::TypeError {
+            expected: "int",
+            found: value.type_name(),
+        }),
+    }
+}
+
+fn invalid_conversion(value: &Value, target: &'static str) -> RuntimeError {
+    RuntimeError::InvalidConversion {
+        value: value.to_string(),
+        target,
+    }
+}
+
+fn expect_string(value: &Value) -> RuntimeResult<&str> {
+    match value {
+        Value::String(string) => Ok(string),
+        _ => Err(RuntimeError::TypeError {
+            expected: "string",
+            found: value.type_name(),
+        }),
+    }
+}
+
+/// Check that `value` is an integer or a float.
+fn expect_number(value: &Value) -> RuntimeResult<&Value> {
+    match value {
+        Value::Integer(_) | Value::Float(_) => Ok(value),
+        #[cfg(feature = "bigint")]
+        Value::BigInt(_) => Ok(value),
+        _ => Err(RuntimeError::TypeError {
+            expected: "number",
+            found: value.type_name(),
+        }),
+    }
+}
+
+fn expect_set(value: &Value) -> RuntimeResult<&HashSet<HashableValue>> {
+    match value {
+        Value::Set(set) => Ok(set),
+        _ => Err(RuntimeError::TypeError {
+            expected: "set",
+            found: value.type_name(),
+        }),
+    }
+}
+
+/// Format integer argument with given radix prefix, negative numbers are
+/// formatted as their magnitude prefixed with `-`.
+fn format_radix(args: &[Value], format: fn(u64) -> String) -> RuntimeResult<Value> {
+    let [value] = expect_args(args)?;
+    let int = expect_integer(value)?;
+    let sign = if int < 0 { "-" } else { "" };
+    Ok(Value::String(format!(
+        "{sign}{}",
+        format(int.unsigned_abs())
+    )))
+}
+
+/// `hex(n)` formats integer as hexadecimal such as `0xff`.
+pub fn hex(args: &[Value]) -> RuntimeResult<Value> {
+    format_radix(args, |int| format!("{int:#x}"))
+}
+
+/// `bin(n)` formats integer as binary such as `0b101`.
+pub fn bin(args: &[Value]) -> RuntimeResult<Value> {
+    format_radix(args, |int| format!("{int:#b}"))
+}
+
+/// `oct(n)` formats integer as octal such as `0o17`.
+pub fn oct(args: &[Value]) -> RuntimeResult<Value> {
+    format_radix(args, |int| format!("{int:#o}"))
+}
+
+/// `int(x)` converts `x` to an integer, floats are truncated towards zero
+/// and strings are parsed as integer literals.
+pub fn int(args: &[Value]) -> RuntimeResult<Value> {
+    let [value] = expect_args(args)?;
+    match value.try_to_int(FloatToInt::Truncate) {
+        Ok(int) => Ok(Value::Integer(int)),
+        Err(_) => Err(invalid_conversion(value, "int")),
+    }
+}
+
+/// `float(x)` converts `x` to a float, strings are parsed as decimal floats.
+pub fn float(args: &[Value]) -> RuntimeResult<Value> {
+    let [value] = expect_args(args)?;
+    let float = match value {
+        Value::Integer(int) => *int as f64,
+        Value::Float(float) => *float,
+        #[cfg(feature = "bigint")]
+        Value::BigInt(big) => big.to_f64(),
+        Value::True => 1.0,
+        Value::False => 0.0,
+        Value::String(string) => match Value::from_str_radix_aware(string) {
+            Ok(Value::Integer(int)) => int as f64,
+            Ok(Value::Float(float)) => float,
+            _ => return Err(invalid_conversion(value, "float")),
+        },
+        Value::Null | Value::Set(_) | Value::Array(_) => {
+            return Err(invalid_conversion(value, "float"))
+        }
+    };
+    Ok(Value::Float(float))
+}
+
+/// `bool(x)` converts `x` to a boolean using its truthiness.
+pub fn bool(args: &[Value]) -> RuntimeResult<Value> {
+    let [value] = expect_args(args)?;
+    Ok(value.is_truthy().into())
+}
+
+/// `str(x)` converts `x` to its string representation.
+pub fn str(args: &[Value]) -> RuntimeResult<Value> {
+    let [value] = expect_args(args)?;
+    Ok(Value::String(value.to_string()))
+}
+
+/// `union(a, b)` returns a set of elements that are in either set.
+pub fn union(args: &[Value]) -> RuntimeResult<Value> {
+    let [lhs, rhs] = expect_args(args)?;
+    let (lhs, rhs) = (expect_set(lhs)?, expect_set(rhs)?);
+    Ok(Value::Set(lhs.union(rhs).cloned().collect()))
+}
+
+/// `intersection(a, b)` returns a set of elements that are in both sets.
+pub fn intersection(args: &[Value]) -> RuntimeResult<Value> {
+    let [lhs, rhs] = expect_args(args)?;
+    let (lhs, rhs) = (expect_set(lhs)?, expect_set(rhs)?);
+    Ok(Value::Set(lhs.intersection(rhs).cloned().collect()))
+}
+
+/// Value of `args` that `Value::compare` orders as `wanted` relative to every
+/// other argument, ties are resolved in favour of the earlier argument. NaN
+/// is propagated, the first argument that isn't ordered with itself is the
+/// result regardless of where it is.
+fn extremum(args: &[Value], wanted: Ordering) -> RuntimeResult<Value> {
+    let (first, rest) = args.split_first().ok_or(RuntimeError::TooFewArguments {
+        minimum: 1,
+        found: 0,
+    })?;
+    let is_nan = |value: &Value| value.compare(value) != Ok(Some(Ordering::Equal));
+    let mut extremum = first;
+    for value in rest {
+        // Every argument is compared so mismatched types are still errors
+        match value.compare(extremum)? {
+            Some(ordering) if ordering == wanted => extremum = value,
+            None if !is_nan(extremum) => extremum = value,
+            _ => {}
+        }
+    }
+    Ok(extremum.clone())
+}
+
+/// `min(a, b, ...)` returns the smallest of one or more arguments.
+pub fn min(args: &[Value]) -> RuntimeResult<Value> {
+    extremum(args, Ordering::Less)
+}
+
+/// `max(a, b, ...)` returns the largest of one or more arguments.
+pub fn max(args: &[Value]) -> RuntimeResult<Value> {
+    extremum(args, Ordering::Greater)
+}
+
+/// `append(array, x)` appends `x` to `array` in place and returns `null`.
+pub fn append(args: &[Value]) -> RuntimeResult<Value> {
+    let [array, value] = expect_args(args)?;
+    array.push(value.clone())?;
+    Ok(Value::Null)
+}
+
+/// `format(template, ...)` replaces each `{}` in `template` with the next
+/// argument, `{{` and `}}` are literal braces.
+pub fn format(args: &[Value]) -> RuntimeResult<Value> {
+    let (template, values) = args.split_first().ok_or(RuntimeError::TooFewArguments {
+        minimum: 1,
+        found: 0,
+    })?;
+    let template = expect_string(template)?;
+    let invalid = || RuntimeError::InvalidFormat(template.to_string());
+
+    let mut formatted = String::with_capacity(template.len());
+    let mut values = values.iter();
+    let mut placeholders = 0;
+    let mut chars = template.chars();
+    while let Some(c) = chars.next() {
+        match (c, chars.clone().next()) {
+            ('{', Some('{')) | ('}', Some('}')) => {
+                chars.next();
+                formatted.push(c);
+            }
+            ('{', Some('}')) => {
+                chars.next();
+                placeholders += 1;
+                if let Some(value) = values.next() {
+                    formatted.push_str(&value.to_string());
+                }
+            }
+            ('{', _) | ('}', _) => return Err(invalid()),
+            _ => formatted.push(c),
+        }
+    }
+    if placeholders != args.len() - 1 {
+        return Err(RuntimeError::ArityMismatch {
+            expected: placeholders + 1,
+            found: args.len(),
+        });
+    }
+    Ok(Value::String(formatted))
+}
+
+/// Elements of iterable `value` in the order `for` loops visit them.
+fn iterable_elements(value: &Value) -> RuntimeResult<Vec<Value>> {
+    match value.iterable()? {
+        Value::Array(array) => Ok(array.borrow().clone()),
+        _ => unreachable!("iterables are arrays"),
+    }
+}
+
+/// `reversed(x)` returns an array of the elements of iterable `x` in reverse
+/// iteration order.
+pub fn reversed(args: &[Value]) -> RuntimeResult<Value> {
+    let [value] = expect_args(args)?;
+    let mut elements = iterable_elements(value)?;
+    elements.reverse();
+    Ok(Value::array(elements))
+}
+
+/// `enumerate(x)` returns an array of `[index, element]` pairs of iterable
+/// `x` in iteration order.
+pub fn enumerate(args: &[Value]) -> RuntimeResult<Value> {
+    let [value] = expect_args(args)?;
+    let pairs = iterable_elements(value)?
+        .into_iter()
+        .zip(0..)
+        .map(|(element, index)| Value::array(vec![Value::Integer(index), element]))
+        .collect();
+    Ok(Value::array(pairs))
+}
+
+/// `clone(x)` returns a deep copy of `x`, arrays in the copy are independent
+/// of the arrays of `x`.
+pub fn clone(args: &[Value]) -> RuntimeResult<Value> {
+    let [value] = expect_args(args)?;
+    Ok(value.deep_clone())
+}
+
+/// `abs(x)` returns the magnitude of number `x` with the type of `x`.
+pub fn abs(args: &[Value]) -> RuntimeResult<Value> {
+    let [value] = expect_args(args)?;
+    match expect_number(value)? {
+        Value::Float(float) => Ok(Value::Float(float.abs())),
+        int if int.compare(&Value::Integer(0))? == Some(Ordering::Less) => Ok(int.negate()?),
+        int => Ok(int.clone()),
+    }
+}
+
+/// Round number argument with `round`, the result is an integer unless it's
+/// out of range of integers.
+fn round_with(args: &[Value], round: fn(f64) -> f64) -> RuntimeResult<Value> {
+    let [value] = expect_args(args)?;
+    match expect_number(value)? {
+        Value::Float(float) => {
+            let rounded = Value::Float(round(*float));
+            match rounded.try_to_int(FloatToInt::Exact) {
+                Ok(int) => Ok(Value::Integer(int)),
+                Err(_) => Ok(rounded),
+            }
+        }
+        int => Ok(int.clone()),
+    }
+}
+
+/// `floor(x)` returns the largest integer less than or equal to `x`.
+pub fn floor(args: &[Value]) -> RuntimeResult<Value> {
+    round_with(args, math::floor)
+}
+
+/// `ceil(x)` returns the smallest integer greater than or equal to `x`.
+pub fn ceil(args: &[Value]) -> RuntimeResult<Value> {
+    round_with(args, math::ceil)
+}
+
+/// `round(x)` returns the integer nearest to `x`, halfway cases are rounded
+/// away from zero.
+pub fn round(args: &[Value]) -> RuntimeResult<Value> {
+    round_with(args, math::round)
+}
+
+/// `sqrt(x)` returns the square root of non-negative number `x` as a float.
+pub fn sqrt(args: &[Value]) -> RuntimeResult<Value> {
+    let [value] = expect_args(args)?;
+    if expect_number(value)?.compare(&Value::Integer(0))? == Some(Ordering::Less) {
+        return Err(RuntimeError::DomainError {
+            function: "sqrt",
+            value: value.to_string(),
+        });
+    }
+    let Value::Float(float) = float(args)? else {
+        unreachable!("numbers convert to floats")
+    };
+    Ok(Value::Float(math::sqrt(float)))
+}
+
+/// `pow(x, y)` raises `x` to the power of `y` like `x ** y`.
+pub fn pow(args: &[Value]) -> RuntimeResult<Value> {
+    let [base, exponent] = expect_args(args)?;
+    Ok(expect_number(base)?.power(expect_number(exponent)?)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+
+    use crate::{
+        ast::value::{HashableValue, Value, ValueError},
+        vm::RuntimeError,
+    };
+
+    use super::get;
+
+    fn call(name: &str, args: &[Value]) -> Result<Value, RuntimeError> {
+        get(name).unwrap()(args)
+    }
+
+    fn string(s: &str) -> Value {
+        Value::String(s.to_string())
+    }
+
+    #[test]
+    fn test_radix_natives() {
+        assert_eq!(call("hex", &[255.into()]), Ok(string("0xff")));
+        assert_eq!(call("hex", &[0.into()]), Ok(string("0x0")));
+        assert_eq!(call("hex", &[(-255).into()]), Ok(string("-0xff")));
+        assert_eq!(call("bin", &[5.into()]), Ok(string("0b101")));
+        assert_eq!(call("bin", &[(-5).into()]), Ok(string("-0b101")));
+        assert_eq!(call("oct", &[8.into()]), Ok(string("0o10")));
+        assert_eq!(call("oct", &[(-15).into()]), Ok(string("-0o17")));
+        assert_eq!(
+            call("hex", &[i64::MIN.into()]),
+            Ok(string("-0x8000000000000000"))
+        );
+    }
+
+    #[test]
+    fn test_radix_natives_errors() {
+        assert_eq!(
+            call("hex", &[]),
+            Err(RuntimeError::ArityMismatch {
+                expected: 1,
+                found: 0
+            })
+        );
+        assert_eq!(
+            call("bin", &[1.into(), 2.into()]),
+            Err(RuntimeError::ArityMismatch {
+                expected: 1,
+                found: 2
+            })
+        );
+        assert_eq!(
+            call("oct", &[1.5.into()]),
+            Err(RuntimeError::TypeError {
+                expected: "int",
+                found: "float"
+            })
+        );
+        assert!(get("unknown").is_none());
+    }
+
+    #[test]
+    fn test_conversion_natives() {
+        assert_eq!(call("int", &[string("42")]), Ok(42.into()));
+        assert_eq!(call("int", &[string(" -7 ")]), Ok((-7).into()));
+        assert_eq!(call("int", &[3.9.into()]), Ok(3.into()));
+        assert_eq!(call("int", &[(-3.9).into()]), Ok((-3).into()));
+        assert_eq!(call("int", &[true.into()]), Ok(1.into()));
+        assert_eq!(call("float", &[string("3.5")]), Ok(3.5.into()));
+        assert_eq!(call("int", &[string("0xFF")]), Ok(255.into()));
+        assert_eq!(call("int", &[string("1_000")]), Ok(1000.into()));
+        assert_eq!(call("float", &[string("1.5e3")]), Ok(1500.0.into()));
+        assert_eq!(call("float", &[string("0b11")]), Ok(3.0.into()));
+        assert_eq!(call("float", &[2.into()]), Ok(2.0.into()));
+        assert_eq!(call("float", &[false.into()]), Ok(0.0.into()));
+        assert_eq!(call("bool", &[0.into()]), Ok(false.into()));
+        assert_eq!(call("bool", &[string("abc")]), Ok(true.into()));
+        assert_eq!(call("bool", &[Value::Null]), Ok(false.into()));
+        assert_eq!(call("str", &[3.5.into()]), Ok(string("3.5")));
+        assert_eq!(call("str", &[2.0.into()]), Ok(string("2.0")));
+        assert_eq!(call("str", &[42.into()]), Ok(string("42")));
+        assert_eq!(call("str", &[Value::Null]), Ok(string("null")));
+        assert_eq!(call("str", &[string("abc")]), Ok(string("abc")));
+    }
+
+    #[test]
+    fn test_conversion_natives_errors() {
+        let invalid = |value: &str, target| {
+            Err(RuntimeError::InvalidConversion {
+                value: value.to_string(),
+                target,
+            })
+        };
+        assert_eq!(call("int", &[string("abc")]), invalid("abc", "int"));
+        assert_eq!(call("int", &[string("4.2")]), invalid("4.2", "int"));
+        assert_eq!(call("int", &[string("true")]), invalid("true", "int"));
+        assert_eq!(call("int", &[f64::NAN.into()]), invalid("NaN", "int"));
+        assert_eq!(
+            call("int", &[1e20.into()]),
+            invalid("100000000000000000000.0", "int")
+        );
+        assert_eq!(call("int", &[Value::Null]), invalid("null", "int"));
+        assert_eq!(call("float", &[string("abc")]), invalid("abc", "float"));
+        assert_eq!(call("float", &[Value::Null]), invalid("null", "float"));
+        assert_eq!(
+            call("str", &[]),
+            Err(RuntimeError::ArityMismatch {
+                expected: 1,
+                found: 0
+            })
+        );
+    }
+
+    #[test]
+    fn test_set_natives() {
+        let set = |values: &[i64]| {
+            Value::Set(
+                values
+                    .iter()
+                    .map(|int| HashableValue::try_from(Value::Integer(*int)).unwrap())
+                    .collect(),
+            )
+        };
+        assert_eq!(
+            call("union", &[set(&[1, 2]), set(&[2, 3])]),
+            Ok(set(&[1, 2, 3]))
+        );
+        assert_eq!(
+            call("intersection", &[set(&[1, 2]), set(&[2, 3])]),
+            Ok(set(&[2]))
+        );
+        assert_eq!(call("intersection", &[set(&[1]), set(&[])]), Ok(set(&[])));
+        assert_eq!(
+            call("union", &[set(&[1]), 1.into()]),
+            Err(RuntimeError::TypeError {
+                expected: "set",
+                found: "int"
+            })
+        );
+    }
+
+    #[test]
+    fn test_min_max_natives() {
+        assert_eq!(call("max", &[3.into(), 7.into()]), Ok(7.into()));
+        assert_eq!(call("min", &[3.into(), 7.into(), 1.into()]), Ok(1.into()));
+        assert_eq!(
+            call("max", &[3.into(), 7.5.into(), 7.into()]),
+            Ok(7.5.into())
+        );
+        assert_eq!(call("min", &[2.into()]), Ok(2.into()));
+        // Ties keep the first argument
+        assert_eq!(call("min", &[1.0.into(), 1.into()]), Ok(1.0.into()));
+        assert_eq!(
+            call("max", &[]),
+            Err(RuntimeError::TooFewArguments {
+                minimum: 1,
+                found: 0
+            })
+        );
+        assert_eq!(
+            call("max", &[1.into(), Value::Null]),
+            Err(RuntimeError::Value(ValueError::Incomparable("null", "int")))
+        );
+    }
+
+    #[test]
+    fn test_min_max_nan() {
+        let is_nan = |result: Result<Value, RuntimeError>| matches!(result, Ok(Value::Float(f)) if f.is_nan());
+        // NaN is the result whichever argument it is
+        for name in ["min", "max"] {
+            assert!(is_nan(call(name, &[f64::NAN.into(), 1.into()])), "{name}");
+            assert!(is_nan(call(name, &[1.into(), f64::NAN.into()])), "{name}");
+            assert!(
+                is_nan(call(name, &[1.into(), f64::NAN.into(), 0.into()])),
+                "{name}"
+            );
+            assert!(is_nan(call(name, &[f64::NAN.into()])), "{name}");
+            assert_eq!(
+                call(name, &[f64::NAN.into(), Value::Null]),
+                Err(RuntimeError::Value(ValueError::Incomparable(
+                    "null", "float"
+                )))
+            );
+        }
+    }
+
+    #[test]
+    fn test_format_native() {
+        let format = |args: &[Value]| call("format", args);
+        assert_eq!(
+            format(&[string("{} + {} = {}"), 1.into(), 2.into(), 3.into()]),
+            Ok(string("1 + 2 = 3"))
+        );
+        assert_eq!(
+            format(&[string("{}, {}!"), string("Hello"), 2.5.into()]),
+            Ok(string("Hello, 2.5!"))
+        );
+        assert_eq!(
+            format(&[string("no placeholders")]),
+            Ok(string("no placeholders"))
+        );
+        assert_eq!(
+            format(&[string("{{}} {{{}}}"), Value::Null]),
+            Ok(string("{} {null}"))
+        );
+    }
+
+    #[test]
+    fn test_format_native_errors() {
+        let format = |args: &[Value]| call("format", args);
+        assert_eq!(
+            format(&[string("{} {}"), 1.into()]),
+            Err(RuntimeError::ArityMismatch {
+                expected: 3,
+                found: 2
+            })
+        );
+        assert_eq!(
+            format(&[string("{}"), 1.into(), 2.into()]),
+            Err(RuntimeError::ArityMismatch {
+                expected: 2,
+                found: 3
+            })
+        );
+        assert_eq!(
+            format(&[]),
+            Err(RuntimeError::TooFewArguments {
+                minimum: 1,
+                found: 0
+            })
+        );
+        assert_eq!(
+            format(&[1.into()]),
+            Err(RuntimeError::TypeError {
+                expected: "string",
+                found: "int"
+            })
+        );
+        for template in ["{", "}", "{x}", "a } b"] {
+            assert_eq!(
+                format(&[string(template)]),
+                Err(RuntimeError::InvalidFormat(template.to_string()))
+            );
+        }
+    }
+
+    #[test]
+    fn test_math_natives() {
+        assert_eq!(call("abs", &[(-3).into()]), Ok(3.into()));
+        assert_eq!(call("abs", &[3.into()]), Ok(3.into()));
+        assert_eq!(call("abs", &[(-2.5).into()]), Ok(2.5.into()));
+        assert_eq!(call("abs", &[(-0.0).into()]), Ok(0.0.into()));
+
+        assert_eq!(call("floor", &[2.7.into()]), Ok(2.into()));
+        assert_eq!(call("floor", &[(-2.5).into()]), Ok((-3).into()));
+        assert_eq!(call("ceil", &[2.1.into()]), Ok(3.into()));
+        assert_eq!(call("ceil", &[(-2.5).into()]), Ok((-2).into()));
+        assert_eq!(call("round", &[2.5.into()]), Ok(3.into()));
+        assert_eq!(call("round", &[(-2.5).into()]), Ok((-3).into()));
+        assert_eq!(call("round", &[2.4.into()]), Ok(2.into()));
+        assert_eq!(call("floor", &[7.into()]), Ok(7.into()));
+        // Results out of range of integers stay floats
+        assert_eq!(call("floor", &[1e300.into()]), Ok(1e300.into()));
+        assert_eq!(
+            call("ceil", &[f64::INFINITY.into()]),
+            Ok(f64::INFINITY.into())
+        );
+        let Ok(Value::Float(nan)) = call("round", &[f64::NAN.into()]) else {
+            panic!("expected NaN")
+        };
+        assert!(nan.is_nan());
+
+        assert_eq!(call("sqrt", &[16.into()]), Ok(4.0.into()));
+        assert_eq!(call("sqrt", &[2.25.into()]), Ok(1.5.into()));
+        assert_eq!(call("sqrt", &[0.into()]), Ok(0.0.into()));
+
+        assert_eq!(call("pow", &[2.into(), 10.into()]), Ok(1024.into()));
+        assert_eq!(call("pow", &[2.into(), (-1).into()]), Ok(0.5.into()));
+        assert_eq!(call("pow", &[4.0.into(), 0.5.into()]), Ok(2.0.into()));
+    }
+
+    #[test]
+    fn test_math_natives_errors() {
+        let domain_error = |value: &str| {
+            Err(RuntimeError::DomainError {
+                function: "sqrt",
+                value: value.to_string(),
+            })
+        };
+        assert_eq!(call("sqrt", &[(-1).into()]), domain_error("-1"));
+        assert_eq!(call("sqrt", &[(-0.5).into()]), domain_error("-0.5"));
+        for name in ["abs", "floor", "ceil", "round", "sqrt"] {
+            assert_eq!(
+                call(name, &[string("1")]),
+                Err(RuntimeError::TypeError {
+                    expected: "number",
+                    found: "string"
+                }),
+                "{name}"
+            );
+            assert_eq!(
+                call(name, &[1.into(), 2.into()]),
+                Err(RuntimeError::ArityMismatch {
+                    expected: 1,
+                    found: 2
+                }),
+                "{name}"
+            );
+        }
+        assert_eq!(
+            call("pow", &[2.into(), true.into()]),
+            Err(RuntimeError::TypeError {
+                expected: "number",
+                found: "bool"
+            })
+        );
+        assert_eq!(
+            call("pow", &[2.into()]),
+            Err(RuntimeError::ArityMismatch {
+                expected: 2,
+                found: 1
+            })
+        );
+        #[cfg(not(feature = "bigint"))]
+        {
+            assert_eq!(
+                call("abs", &[i64::MIN.into()]),
+                Err(RuntimeError::Value(ValueError::Overflow))
+            );
+            assert_eq!(
+                call("pow", &[2.into(), 64.into()]),
+                Err(RuntimeError::Value(ValueError::Overflow))
+            );
+        }
+    }
+
+    #[test]
+    fn test_iteration_natives() {
+        let array = |values: Vec<Value>| Value::array(values);
+        assert_eq!(
+            call("reversed", &[array(vec![1.into(), 2.into(), 3.into()])]),
+            Ok(array(vec![3.into(), 2.into(), 1.into()]))
+        );
+        assert_eq!(
+            call("reversed", &[string("abc")]),
+            Ok(array(vec![string("c"), string("b"), string("a")]))
+        );
+        assert_eq!(
+            call("enumerate", &[string("ab")]),
+            Ok(array(vec![
+                array(vec![0.into(), string("a")]),
+                array(vec![1.into(), string("b")]),
+            ]))
+        );
+        assert_eq!(call("enumerate", &[array(vec![])]), Ok(array(vec![])));
+        // Reversing doesn't modify the original array
+        let original = array(vec![1.into(), 2.into()]);
+        call("reversed", std::slice::from_ref(&original)).unwrap();
+        assert_eq!(original, array(vec![1.into(), 2.into()]));
+        assert_eq!(
+            call("enumerate", &[Value::Null]),
+            Err(RuntimeError::Value(ValueError::NotIterable("null")))
+        );
+        assert_eq!(
+            call("reversed", &[]),
+            Err(RuntimeError::ArityMismatch {
+                expected: 1,
+                found: 0
+            })
+        );
+    }
+}
@@ -0,0 +1,166 @@
+use alloy::{
+    ast::value::{Value, ValueError},
+    compiler::{Compile, Compiler, CompilerError},
+    error::AlloyError,
+    parser::{self, ParserErrorKind},
+    vm::{RuntimeError, VM},
+};
+
+/// Run `input` through the whole pipeline like the REPL does. Returns the
+/// value of the last statement if it's an expression statement, or the
+/// printed output as a string otherwise.
+fn eval(input: &str) -> Result<Value, AlloyError> {
+    let statements = parser::parse_repl(input)?;
+    let mut compiler = Compiler::new_repl();
+    for statement in &statements {
+        statement.compile(&mut compiler)?;
+    }
+    let (code_block, _) = compiler.finish()?;
+    let mut output = Vec::new();
+    let value = VM::new_with_output(&mut output).run(&code_block)?;
+    Ok(match value {
+        Some(value) => value,
+        None => Value::String(String::from_utf8(output).unwrap()),
+    })
+}
+
+fn output(output: &str) -> Value {
+    Value::String(output.to_string())
+}
+
+#[test]
+fn arithmetic() -> Result<(), AlloyError> {
+    assert_eq!(eval("1 + 2 * 3")?, Value::Integer(7));
+    assert_eq!(eval("(1 + 2) * 3 - 4 / 2")?, Value::Integer(7));
+    assert_eq!(eval("7 - 3 + 2 ** 3")?, Value::Integer(12));
+    assert_eq!(eval("7 / 2.0")?, Value::Float(3.5));
+    assert_eq!(eval("-(2 + 3)")?, Value::Integer(-5));
+    Ok(())
+}
+
+#[test]
+fn comparison_and_logic() -> Result<(), AlloyError> {
+    assert_eq!(eval("1 < 2 and 2 <= 2")?, Value::True);
+    assert_eq!(eval("not (1 == 1.0) or false")?, Value::False);
+    assert_eq!(eval("1 < 2 < 3")?, Value::True);
+    Ok(())
+}
+
+#[test]
+fn variables() -> Result<(), AlloyError> {
+    assert_eq!(
+        eval("var x = 2; const y = 3; x = x * y; x;")?,
+        Value::Integer(6)
+    );
+    assert_eq!(eval("var x; x;")?, Value::Null);
+    Ok(())
+}
+
+#[test]
+fn printed_output() -> Result<(), AlloyError> {
+    assert_eq!(
+        eval("print 1; print 2.5; print true;")?,
+        output("1\n2.5\ntrue\n")
+    );
+    assert_eq!(eval("")?, output(""));
+    Ok(())
+}
+
+#[test]
+fn if_statements() -> Result<(), AlloyError> {
+    let program = "var x = 5; if x > 3 { print 1; } else if x > 1 { print 2; } else { print 3; }";
+    assert_eq!(eval(program)?, output("1\n"));
+    assert_eq!(eval("var x = 0; if x { print 1; }")?, output(""));
+    Ok(())
+}
+
+#[test]
+fn if_expressions() -> Result<(), AlloyError> {
+    assert_eq!(eval("if 1 > 2 { 1 } else { 2 }")?, Value::Integer(2));
+    assert_eq!(
+        eval("var x = 3; { var y = x * 2; y + 1 }")?,
+        Value::Integer(7)
+    );
+    Ok(())
+}
+
+#[test]
+fn while_loops() -> Result<(), AlloyError> {
+    let program = "var i = 0; var sum = 0; while i < 10 { i = i + 1; sum = sum + i; } sum;";
+    assert_eq!(eval(program)?, Value::Integer(55));
+    let program = "var i = 0; while true { i = i + 1; if i == 3 { break; } } i;";
+    assert_eq!(eval(program)?, Value::Integer(3));
+    Ok(())
+}
+
+#[test]
+fn for_loops() -> Result<(), AlloyError> {
+    let program = "var sum = 0; for x in [1, 2, 3] { sum = sum + x; } sum;";
+    assert_eq!(eval(program)?, Value::Integer(6));
+    let program = "for (var i = 0; i < 3; i += 1) { if i == 1 { continue; } print i; }";
+    assert_eq!(eval(program)?, output("0\n2\n"));
+    Ok(())
+}
+
+#[test]
+fn functions() -> Result<(), AlloyError> {
+    assert_eq!(
+        eval("fn add(a, b) { return a + b; } add(2, 3);")?,
+        Value::Integer(5)
+    );
+    assert_eq!(eval("fn f() { } f();")?, Value::Null);
+    let program = "fn fib(n) { if n < 2 { return n; } return fib(n - 1) + fib(n - 2); } fib(15);";
+    assert_eq!(eval(program)?, Value::Integer(610));
+    Ok(())
+}
+
+#[test]
+fn arrays() -> Result<(), AlloyError> {
+    assert_eq!(
+        eval("var a = [1, 2]; a[0] = 5; print a;")?,
+        output("[5, 2]\n")
+    );
+    assert_eq!(eval("2 in set{1, 2}")?, Value::True);
+    Ok(())
+}
+
+#[test]
+fn math_natives() -> Result<(), AlloyError> {
+    assert_eq!(eval("abs(-3) + floor(2.7) + ceil(0.2)")?, Value::Integer(6));
+    assert_eq!(
+        eval("round(sqrt(pow(3, 2) + pow(4, 2)))")?,
+        Value::Integer(5)
+    );
+    assert!(matches!(
+        eval("sqrt(-1)"),
+        Err(AlloyError::Runtime(RuntimeError::DomainError { .. }))
+    ));
+    Ok(())
+}
+
+#[test]
+fn errors_of_each_stage() {
+    assert!(matches!(
+        eval("var = 1;"),
+        Err(AlloyError::Parser(error)) if matches!(error.kind(), ParserErrorKind::UnexpectedToken { .. })
+    ));
+    assert!(matches!(
+        eval("print x;"),
+        Err(AlloyError::Compiler(CompilerError::UndefinedIdentifer(_)))
+    ));
+    let Err(AlloyError::Runtime(error)) = eval("1 / 0") else {
+        panic!("expected runtime error")
+    };
+    assert_eq!(error.span(), Some((2, 3)));
+    assert_eq!(
+        error.into_inner(),
+        RuntimeError::Value(ValueError::DivisionByZero)
+    );
+    let error = eval("var = 1;").unwrap_err();
+    assert!(
+        error
+            .report("var = 1;")
+            .starts_with("1:5: parse error: unexpected `=`"),
+        "{error}"
+    );
+}
@@ -1,5 +1,3 @@
-use alloy_macros;
-
 #[test]
 fn test_binary_expressions() {
     alloy_macros::assert_expr!(3 + 5);